@@ -0,0 +1,378 @@
+//! Application results for Rustle
+//!
+//! This provider indexes the Start Menu shortcuts of the current user and
+//! of all users once at start-up, and fuzzy matches their names and the
+//! file names of their targets against the query. Shortcuts are read to
+//! show the program they start, and one program installed for both the
+//! user and all users is listed once. Launch history boosts the
+//! applications used most.
+
+#![allow(dead_code)]
+
+use crate::error::Result;
+use crate::history::LaunchHistory;
+use crate::lnk::ShellLink;
+use crate::provider::{Provider, ProviderQuery};
+use crate::query::{self, Candidate};
+use crate::search::{name_score, CancelToken, ResultType, SearchResult, Section};
+use crate::utils::{display_name, is_shortcut, normalize_for_search};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use walkdir::WalkDir;
+
+/// Most applications shown for one query
+const MAX_RESULTS: usize = 5;
+
+/// Finds installed applications by their Start Menu shortcuts
+pub struct ApplicationProvider {
+    /// Indexed shortcuts (behind a lock so they can be refreshed)
+    applications: RwLock<Vec<SearchResult>>,
+
+    /// Fuzzy matcher instance
+    matcher: SkimMatcherV2,
+
+    /// Launch history used to boost frequently and recently used apps
+    history: Arc<RwLock<LaunchHistory>>,
+}
+
+impl ApplicationProvider {
+    /// Creates the provider and indexes the Start Menu
+    pub fn new(history: Arc<RwLock<LaunchHistory>>) -> Self {
+        let provider = Self::with_applications(Vec::new(), history);
+        if let Err(e) = provider.refresh() {
+            log::warn!("Failed to index some applications: {}", e);
+        }
+        provider
+    }
+
+    /// Creates the provider over an already known list of applications
+    pub fn with_applications(
+        applications: Vec<SearchResult>,
+        history: Arc<RwLock<LaunchHistory>>,
+    ) -> Self {
+        Self {
+            applications: RwLock::new(applications),
+            matcher: SkimMatcherV2::default().smart_case(),
+            history,
+        }
+    }
+
+    /// Indexes all Start Menu shortcuts again
+    pub fn refresh(&self) -> Result<()> {
+        let mut applications = Vec::new();
+
+        // User Start Menu
+        if let Some(start_menu) = dirs::data_dir() {
+            let user_start = start_menu
+                .parent()
+                .map(|p| p.join("Roaming"))
+                .map(|p| p.join("Microsoft"))
+                .map(|p| p.join("Windows"))
+                .map(|p| p.join("Start Menu"))
+                .map(|p| p.join("Programs"));
+
+            if let Some(path) = user_start {
+                index_directory(&path, &mut applications)?;
+            }
+        }
+
+        // System-wide Start Menu
+        let system_start = PathBuf::from(r"C:\ProgramData\Microsoft\Windows\Start Menu\Programs");
+        if system_start.exists() {
+            index_directory(&system_start, &mut applications)?;
+        }
+        dedupe_by_target(&mut applications);
+
+        log::info!("Indexed {} applications", applications.len());
+        *self.applications.write().unwrap() = applications;
+        Ok(())
+    }
+
+    /// Returns the number of indexed applications
+    pub fn len(&self) -> usize {
+        self.applications.read().unwrap().len()
+    }
+
+    /// Returns true if no applications were found
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Provider for ApplicationProvider {
+    fn id(&self) -> &'static str {
+        "apps"
+    }
+
+    fn title(&self) -> &str {
+        "Applications"
+    }
+
+    fn priority(&self) -> i32 {
+        10
+    }
+
+    fn query(
+        &self,
+        query: &ProviderQuery,
+        _cancel: &CancelToken,
+        emit: &(dyn Fn(Vec<Section>) + Sync),
+    ) -> bool {
+        let query = query.parsed;
+        if query.is_empty() || !query.wants(ResultType::Application) {
+            return true;
+        }
+
+        let text = query.text.as_str();
+        let normalized_query = normalize_for_search(text);
+        let query_lower = text.to_lowercase();
+        let now = query::now_secs();
+        let history = self.history.read().unwrap();
+
+        let mut results: Vec<SearchResult> = Vec::new();
+        for app in self.applications.read().unwrap().iter() {
+            let candidate = Candidate {
+                path: &app.path,
+                result_type: ResultType::Application,
+                size: None,
+                modified: None,
+                target: app
+                    .shortcut
+                    .as_ref()
+                    .and_then(|link| link.target.as_deref()),
+            };
+            if !query.matches(&candidate, now) {
+                continue;
+            }
+
+            let score = if text.is_empty() {
+                Some(0)
+            } else {
+                let target_score = target_name(app).and_then(|target| {
+                    name_score(&self.matcher, &target, &normalized_query, &query_lower)
+                });
+                name_score(&self.matcher, &app.name, &normalized_query, &query_lower)
+                    .max(target_score)
+            };
+            if let Some(score) = score {
+                let mut result = app.clone();
+                result.score = score + history.boost(&app.path, now);
+                results.push(result);
+            }
+        }
+
+        results.sort_unstable_by_key(|r| std::cmp::Reverse(r.score));
+        results.truncate(MAX_RESULTS);
+        emit(vec![self.section(results)]);
+        true
+    }
+}
+
+/// Adds the shortcuts under a Start Menu directory to `applications`
+fn index_directory(path: &Path, applications: &mut Vec<SearchResult>) -> Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+
+    for entry in WalkDir::new(path)
+        .max_depth(5)
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        let path = entry.path();
+        if !is_shortcut(path) {
+            continue;
+        }
+
+        let name = display_name(path);
+        if should_skip_app(&name) {
+            continue;
+        }
+
+        let shortcut = match ShellLink::open(path) {
+            Ok(mut link) => {
+                link.target = link.resolve(path);
+                Some(Box::new(link))
+            }
+            Err(e) => {
+                log::debug!("Failed to read shortcut {}: {}", path.display(), e);
+                None
+            }
+        };
+
+        // Show the program the shortcut starts; shortcuts that only name
+        // it through the shell namespace fall back to their folder
+        let target = shortcut.as_ref().and_then(|link| link.target.as_ref());
+        let description = match target {
+            Some(target) => target.display().to_string(),
+            None => path
+                .parent()
+                .and_then(|p| p.file_name())
+                .and_then(|s| s.to_str())
+                .unwrap_or("")
+                .to_string(),
+        };
+
+        applications.push(SearchResult {
+            name,
+            path: path.to_path_buf(),
+            result_type: ResultType::Application,
+            score: 0,
+            description,
+            provider: "apps",
+            shortcut,
+        });
+    }
+
+    Ok(())
+}
+
+/// Keeps only the first of several shortcuts that start the same target
+/// with the same arguments, such as one in the user's Start Menu and one
+/// in all users'
+///
+/// Shortcuts to one program with different arguments, like browser
+/// profiles, are kept apart.
+fn dedupe_by_target(applications: &mut Vec<SearchResult>) {
+    let mut seen = HashSet::new();
+    applications.retain(|app| {
+        let Some(link) = &app.shortcut else {
+            return true;
+        };
+        let Some(target) = &link.target else {
+            return true;
+        };
+        let key = (
+            target.to_string_lossy().to_lowercase(),
+            link.arguments.clone().unwrap_or_default(),
+        );
+        seen.insert(key)
+    });
+}
+
+/// Returns the file name of a shortcut's target, e.g. `WINWORD.EXE` for
+/// Word
+fn target_name(app: &SearchResult) -> Option<String> {
+    let target = app.shortcut.as_ref()?.target.as_ref()?;
+    Some(target.file_name()?.to_string_lossy().into_owned())
+}
+
+/// Checks if an application should be skipped during indexing
+fn should_skip_app(name: &str) -> bool {
+    let lower = name.to_lowercase();
+
+    lower.contains("uninstall")
+        || lower.contains("remove")
+        || lower.contains("repair")
+        || lower.contains("help")
+        || lower.contains("readme")
+        || lower.contains("manual")
+        || lower.contains("documentation")
+        || lower.contains("license")
+        || lower.contains("website")
+        || lower.contains("url")
+        || lower == "about"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::{run_provider, run_provider_sections};
+
+    fn app(name: &str) -> SearchResult {
+        SearchResult {
+            name: name.to_string(),
+            path: PathBuf::from(format!(r"C:\Start Menu\{}.lnk", name)),
+            result_type: ResultType::Application,
+            score: 0,
+            description: String::new(),
+            provider: "apps",
+            shortcut: None,
+        }
+    }
+
+    #[test]
+    fn test_should_skip_app() {
+        assert!(should_skip_app("Uninstall Chrome"));
+        assert!(should_skip_app("Remove App"));
+        assert!(!should_skip_app("Google Chrome"));
+        assert!(!should_skip_app("Visual Studio Code"));
+    }
+
+    #[test]
+    fn test_query_ranks_and_filters() {
+        let history = Arc::new(RwLock::new(LaunchHistory::new(Default::default())));
+        let provider = ApplicationProvider::with_applications(
+            vec![app("Notepad"), app("Notepad++"), app("Paint")],
+            history,
+        );
+
+        let sections = run_provider_sections(&provider, "notepad");
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].title, "Applications");
+        let names: Vec<&str> = sections[0]
+            .results
+            .iter()
+            .map(|r| r.name.as_str())
+            .collect();
+        assert_eq!(names, ["Notepad", "Notepad++"]);
+    }
+
+    #[test]
+    fn test_indexes_shortcut_targets() {
+        let editor = include_bytes!("../tests/fixtures/lnk/editor.lnk");
+        let dir = tempfile::tempdir().unwrap();
+        let user = dir.path().join("user");
+        let all_users = dir.path().join("all users").join("Tools");
+        std::fs::create_dir_all(&user).unwrap();
+        std::fs::create_dir_all(&all_users).unwrap();
+        std::fs::write(user.join("Notes.lnk"), editor).unwrap();
+        std::fs::write(all_users.join("Notes.lnk"), editor).unwrap();
+        std::fs::write(all_users.join("Broken.lnk"), "not a link").unwrap();
+
+        let mut applications = Vec::new();
+        index_directory(&user, &mut applications).unwrap();
+        index_directory(&dir.path().join("all users"), &mut applications).unwrap();
+        assert_eq!(applications.len(), 3);
+        dedupe_by_target(&mut applications);
+
+        let names: Vec<&str> = applications.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, ["Notes", "Broken"]);
+        assert_eq!(applications[0].path, user.join("Notes.lnk"));
+        assert_eq!(
+            applications[0].description,
+            r"C:\Program Files\Editor\editor.exe"
+        );
+        let link = applications[0].shortcut.as_ref().unwrap();
+        assert_eq!(
+            link.arguments.as_deref(),
+            Some("--new-window \"notes.txt\"")
+        );
+        assert_eq!(
+            link.working_dir.as_deref(),
+            Some(r"%USERPROFILE%\Documents")
+        );
+        assert_eq!(link.description.as_deref(), Some("Edit text files"));
+        assert_eq!(applications[1].description, "Tools");
+        assert!(applications[1].shortcut.is_none());
+    }
+
+    #[test]
+    fn test_matches_target_file_name() {
+        let mut word = app("Word");
+        word.shortcut = Some(Box::new(ShellLink {
+            target: Some(PathBuf::from(
+                "C:/Program Files/Microsoft Office/WINWORD.EXE",
+            )),
+            ..ShellLink::default()
+        }));
+        let history = Arc::new(RwLock::new(LaunchHistory::new(Default::default())));
+        let provider = ApplicationProvider::with_applications(vec![word, app("Paint")], history);
+
+        let results = run_provider(&provider, "winword");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "Word");
+    }
+}
@@ -0,0 +1,621 @@
+//! Browser bookmarks for Rustle
+//!
+//! This provider reads the bookmarks of every browser profile found on
+//! the machine: the `Bookmarks` JSON file of Chromium-based browsers and
+//! the `places.sqlite` database of Firefox. Titles are fuzzy matched
+//! against the query, falling back to URLs and folder paths, and launch
+//! history boosts the pages opened most. Profiles whose file has changed
+//! are read again in the background, at most once a refresh interval.
+
+#![allow(dead_code)]
+
+use crate::browsers::{self, Engine, FileStamp, Profile};
+use crate::config::BookmarksConfig;
+use crate::error::Result;
+use crate::history::LaunchHistory;
+use crate::json::{self, Json};
+use crate::provider::{Action, Provider, ProviderQuery};
+use crate::query;
+use crate::search::{name_score, CancelToken, ResultType, SearchResult, Section};
+use crate::sqlite::Database;
+use crate::utils::normalize_for_search;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Separates the folders of a bookmark's folder path
+const FOLDER_SEPARATOR: &str = " › ";
+
+/// Deepest folder nesting followed in a Firefox profile, which also stops
+/// loops in a damaged database
+const MAX_FOLDER_DEPTH: usize = 64;
+
+/// Shortest time between checks of a profile for changed bookmarks;
+/// Firefox rewrites its database with nearly every page load
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Firefox bookmark entry types
+const FIREFOX_BOOKMARK: i64 = 1;
+
+/// Firefox's built-in root folders, by GUID, and the names its library
+/// shows for them
+const FIREFOX_ROOTS: &[(&str, &str)] = &[
+    ("menu________", "Bookmarks Menu"),
+    ("toolbar_____", "Bookmarks Toolbar"),
+    ("unfiled_____", "Other Bookmarks"),
+    ("mobile______", "Mobile Bookmarks"),
+];
+
+/// GUID of the Firefox folder that holds the whole tree
+const FIREFOX_ROOT: &str = "root________";
+
+/// GUID of the Firefox folder whose entries are tags, not bookmarks
+const FIREFOX_TAGS: &str = "tags________";
+
+/// A bookmarked page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bookmark {
+    pub title: String,
+    pub url: String,
+
+    /// Folders from the root down, joined with ` › `
+    pub folder: String,
+}
+
+/// Returns false for bookmarks that are not pages, such as bookmarklets
+/// and Firefox's saved searches
+fn is_page(url: &str) -> bool {
+    let scheme = url.split(':').next().unwrap_or_default().to_lowercase();
+    !matches!(scheme.as_str(), "javascript" | "place" | "data")
+}
+
+fn join_folder(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}{}{}", parent, FOLDER_SEPARATOR, name)
+    }
+}
+
+/// Reads the bookmarks of a Chromium `Bookmarks` file
+///
+/// Every root folder (bookmarks bar, other and mobile bookmarks) is read;
+/// untitled bookmarks are named after their URL.
+pub fn parse_chromium_bookmarks(text: &str) -> Result<Vec<Bookmark>> {
+    let doc = json::parse(text)?;
+    let mut bookmarks = Vec::new();
+    let roots = doc
+        .get("roots")
+        .and_then(Json::as_object)
+        .unwrap_or_default();
+    for (_, root) in roots {
+        if root.get("children").is_some() {
+            collect_chromium(root, "", &mut bookmarks);
+        }
+    }
+    Ok(bookmarks)
+}
+
+fn collect_chromium(folder: &Json, parent: &str, bookmarks: &mut Vec<Bookmark>) {
+    let name = folder
+        .get("name")
+        .and_then(Json::as_str)
+        .unwrap_or_default();
+    let path = join_folder(parent, name);
+    let children = folder
+        .get("children")
+        .and_then(Json::as_array)
+        .unwrap_or_default();
+
+    for child in children {
+        match child.get("type").and_then(Json::as_str) {
+            Some("folder") => collect_chromium(child, &path, bookmarks),
+            Some("url") => {
+                let Some(url) = child.get("url").and_then(Json::as_str) else {
+                    continue;
+                };
+                if !is_page(url) {
+                    continue;
+                }
+                let title = child.get("name").and_then(Json::as_str).unwrap_or_default();
+                bookmarks.push(Bookmark {
+                    title: if title.is_empty() { url } else { title }.to_string(),
+                    url: url.to_string(),
+                    folder: path.clone(),
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// One row of Firefox's `moz_bookmarks` table
+struct FirefoxEntry {
+    kind: i64,
+    place: Option<i64>,
+    parent: i64,
+    title: String,
+    guid: String,
+}
+
+/// Reads the bookmarks of a Firefox `places.sqlite` database
+///
+/// Tags, which Firefox stores as bookmarks inside the tags folder, and
+/// saved searches are left out. Untitled bookmarks are named after the
+/// page's title, or failing that its URL.
+pub fn parse_firefox_bookmarks(db: &Database) -> Result<Vec<Bookmark>> {
+    let places = db.table("moz_places")?;
+    let places: HashMap<i64, (&str, &str)> = places
+        .iter()
+        .filter_map(|row| {
+            let url = row.text("url")?;
+            Some((
+                row.integer("id")?,
+                (url, row.text("title").unwrap_or_default()),
+            ))
+        })
+        .collect();
+
+    let table = db.table("moz_bookmarks")?;
+    let entries: HashMap<i64, FirefoxEntry> = table
+        .iter()
+        .filter_map(|row| {
+            let entry = FirefoxEntry {
+                kind: row.integer("type")?,
+                place: row.integer("fk"),
+                parent: row.integer("parent").unwrap_or(0),
+                title: row.text("title").unwrap_or_default().to_string(),
+                guid: row.text("guid").unwrap_or_default().to_string(),
+            };
+            Some((row.integer("id")?, entry))
+        })
+        .collect();
+
+    let mut bookmarks = Vec::new();
+    for row in table.iter() {
+        let Some(entry) = row.integer("id").and_then(|id| entries.get(&id)) else {
+            continue;
+        };
+        if entry.kind != FIREFOX_BOOKMARK {
+            continue;
+        }
+        let Some(&(url, page_title)) = entry.place.and_then(|p| places.get(&p)) else {
+            continue;
+        };
+        let Some(folder) = firefox_folder(&entries, entry.parent) else {
+            continue;
+        };
+        if !is_page(url) {
+            continue;
+        }
+
+        let title = [entry.title.as_str(), page_title, url]
+            .into_iter()
+            .find(|t| !t.is_empty())
+            .unwrap_or_default();
+        bookmarks.push(Bookmark {
+            title: title.to_string(),
+            url: url.to_string(),
+            folder,
+        });
+    }
+    Ok(bookmarks)
+}
+
+/// Returns the folder path of a Firefox folder, or None if it lies in the
+/// tags folder or is not attached to the tree
+fn firefox_folder(entries: &HashMap<i64, FirefoxEntry>, mut id: i64) -> Option<String> {
+    let mut names = Vec::new();
+    for _ in 0..MAX_FOLDER_DEPTH {
+        let folder = entries.get(&id)?;
+        match folder.guid.as_str() {
+            FIREFOX_ROOT => {
+                names.reverse();
+                return Some(names.join(FOLDER_SEPARATOR));
+            }
+            FIREFOX_TAGS => return None,
+            guid => {
+                let root = FIREFOX_ROOTS.iter().find(|(g, _)| *g == guid);
+                names.push(root.map_or(folder.title.clone(), |(_, name)| name.to_string()));
+            }
+        }
+        id = folder.parent;
+    }
+    None
+}
+
+/// Reads the bookmarks of one profile
+pub fn read_profile(profile: &Profile) -> Result<Vec<Bookmark>> {
+    match profile.engine {
+        Engine::Chromium => {
+            let text = std::fs::read_to_string(profile.dir.join("Bookmarks"))?;
+            parse_chromium_bookmarks(&text)
+        }
+        Engine::Firefox => {
+            let db = Database::open(&profile.dir.join("places.sqlite"))?;
+            parse_firefox_bookmarks(&db)
+        }
+    }
+}
+
+/// Returns the file a profile keeps its bookmarks in
+fn bookmarks_file(profile: &Profile) -> PathBuf {
+    match profile.engine {
+        Engine::Chromium => profile.dir.join("Bookmarks"),
+        Engine::Firefox => profile.dir.join("places.sqlite"),
+    }
+}
+
+/// The bookmarks of one profile, as of when its file was last read
+struct LoadedProfile {
+    profile: Profile,
+    stamp: Option<FileStamp>,
+    checked: Option<Instant>,
+    bookmarks: Arc<Vec<Bookmark>>,
+}
+
+/// Finds bookmarked pages in every browser profile
+///
+/// Profiles are read again on a background thread, and queries search
+/// whatever was read last.
+pub struct BookmarkProvider {
+    config: BookmarksConfig,
+    profiles: Arc<Mutex<Vec<LoadedProfile>>>,
+
+    /// Thread reading profiles again, if one was started
+    refresh: Mutex<Option<JoinHandle<()>>>,
+
+    matcher: SkimMatcherV2,
+    history: Arc<RwLock<LaunchHistory>>,
+}
+
+impl BookmarkProvider {
+    /// Creates the provider over the profiles of the installed browsers
+    /// and starts reading them
+    pub fn new(config: BookmarksConfig, history: Arc<RwLock<LaunchHistory>>) -> Self {
+        let provider = Self::with_profiles(config, browsers::find_profiles(), history);
+        provider.start_refresh();
+        provider
+    }
+
+    /// Creates the provider over the given profiles, without reading them
+    pub fn with_profiles(
+        config: BookmarksConfig,
+        profiles: Vec<Profile>,
+        history: Arc<RwLock<LaunchHistory>>,
+    ) -> Self {
+        let profiles = profiles
+            .into_iter()
+            .map(|profile| LoadedProfile {
+                profile,
+                stamp: None,
+                checked: None,
+                bookmarks: Arc::default(),
+            })
+            .collect();
+        Self {
+            config,
+            profiles: Arc::new(Mutex::new(profiles)),
+            refresh: Mutex::new(None),
+            matcher: SkimMatcherV2::default().smart_case(),
+            history,
+        }
+    }
+
+    /// Starts reading again, in the background, the profiles not checked
+    /// within the refresh interval
+    ///
+    /// Does nothing while an earlier refresh is still running. Profiles
+    /// whose bookmarks file is unchanged are not read.
+    fn start_refresh(&self) {
+        let mut refresh = self.refresh.lock().unwrap();
+        if refresh.as_ref().is_some_and(|thread| !thread.is_finished()) {
+            return;
+        }
+
+        let due: Vec<(usize, Profile, Option<FileStamp>)> = {
+            let mut profiles = self.profiles.lock().unwrap();
+            profiles
+                .iter_mut()
+                .enumerate()
+                .filter(|(_, loaded)| {
+                    loaded
+                        .checked
+                        .is_none_or(|t| t.elapsed() >= REFRESH_INTERVAL)
+                })
+                .map(|(i, loaded)| {
+                    loaded.checked = Some(Instant::now());
+                    (i, loaded.profile.clone(), loaded.stamp)
+                })
+                .collect()
+        };
+        if due.is_empty() {
+            return;
+        }
+
+        let profiles = self.profiles.clone();
+        *refresh = Some(std::thread::spawn(move || {
+            for (i, profile, old_stamp) in due {
+                let stamp = FileStamp::of(&bookmarks_file(&profile));
+                if old_stamp == Some(stamp) {
+                    continue;
+                }
+                let bookmarks = if stamp.exists() {
+                    read_profile(&profile).unwrap_or_else(|e| {
+                        log::warn!(
+                            "Failed to read bookmarks from {}: {}",
+                            profile.dir.display(),
+                            e
+                        );
+                        Vec::new()
+                    })
+                } else {
+                    Vec::new()
+                };
+
+                let mut profiles = profiles.lock().unwrap();
+                profiles[i].stamp = Some(stamp);
+                profiles[i].bookmarks = Arc::new(bookmarks);
+            }
+        }));
+    }
+
+    /// Waits for a running refresh to finish
+    fn wait_for_refresh(&self) {
+        if let Some(thread) = self.refresh.lock().unwrap().take() {
+            let _ = thread.join();
+        }
+    }
+
+    /// Scores a bookmark by its title, then its URL, then its folders
+    fn score(&self, bookmark: &Bookmark, normalized_query: &str, query_lower: &str) -> Option<i64> {
+        name_score(
+            &self.matcher,
+            &bookmark.title,
+            normalized_query,
+            query_lower,
+        )
+        .or_else(|| {
+            let url = bookmark
+                .url
+                .split_once("://")
+                .map_or(&*bookmark.url, |(_, u)| u);
+            self.matcher.fuzzy_match(url, query_lower).map(|s| s / 2)
+        })
+        .or_else(|| {
+            let path = join_folder(&bookmark.folder, &bookmark.title);
+            self.matcher.fuzzy_match(&path, query_lower).map(|s| s / 3)
+        })
+    }
+}
+
+impl Provider for BookmarkProvider {
+    fn id(&self) -> &'static str {
+        "bookmarks"
+    }
+
+    fn title(&self) -> &str {
+        "Bookmarks"
+    }
+
+    fn priority(&self) -> i32 {
+        12
+    }
+
+    fn query(
+        &self,
+        query: &ProviderQuery,
+        cancel: &CancelToken,
+        emit: &(dyn Fn(Vec<Section>) + Sync),
+    ) -> bool {
+        let query = query.parsed;
+        if query.text.is_empty() || query.has_filters() {
+            return true;
+        }
+
+        let text = query.text.as_str();
+        let normalized_query = normalize_for_search(text);
+        let query_lower = text.to_lowercase();
+        let now = query::now_secs();
+
+        self.start_refresh();
+        let profiles: Vec<(&'static str, Arc<Vec<Bookmark>>)> = self
+            .profiles
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|loaded| (loaded.profile.browser, loaded.bookmarks.clone()))
+            .collect();
+        if cancel.is_cancelled() {
+            return false;
+        }
+
+        let history = self.history.read().unwrap();
+        let mut seen = HashSet::new();
+        let mut results = Vec::new();
+        for (browser, bookmarks) in &profiles {
+            for bookmark in bookmarks.iter() {
+                // The same page is often bookmarked in more than one browser
+                if !seen.insert(bookmark.url.as_str()) {
+                    continue;
+                }
+                let Some(score) = self.score(bookmark, &normalized_query, &query_lower) else {
+                    continue;
+                };
+                let path = PathBuf::from(&bookmark.url);
+                let folder = join_folder(browser, &bookmark.folder);
+                results.push(SearchResult {
+                    name: bookmark.title.clone(),
+                    score: score + history.boost(&path, now),
+                    path,
+                    result_type: ResultType::Web,
+                    description: format!("{} · {}", folder, bookmark.url),
+                    provider: self.id(),
+                    shortcut: None,
+                });
+            }
+        }
+
+        results.sort_unstable_by_key(|r| std::cmp::Reverse(r.score));
+        results.truncate(self.config.max_results);
+        if !results.is_empty() {
+            emit(vec![self.section(results)]);
+        }
+        true
+    }
+
+    /// Opens the page, or copies its URL
+    fn actions(&self, result: &SearchResult) -> Vec<Action> {
+        vec![
+            Action::Open(result.path.clone()),
+            Action::Copy(result.path.to_string_lossy().into_owned()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::run_provider;
+    use std::fs;
+
+    const CHROMIUM_BOOKMARKS: &str = include_str!("../tests/fixtures/browsers/Bookmarks");
+    const FIREFOX_PLACES: &[u8] = include_bytes!("../tests/fixtures/browsers/places.sqlite");
+
+    fn bookmark(title: &str, url: &str, folder: &str) -> Bookmark {
+        Bookmark {
+            title: title.to_string(),
+            url: url.to_string(),
+            folder: folder.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_chromium_bookmarks() {
+        let bookmarks = parse_chromium_bookmarks(CHROMIUM_BOOKMARKS).unwrap();
+        assert_eq!(
+            bookmarks,
+            [
+                bookmark("Wiki", "https://wiki.example.com/", "Bookmarks bar"),
+                bookmark(
+                    "Sprint board",
+                    "https://jira.example.com/board/42",
+                    "Bookmarks bar › Work"
+                ),
+                bookmark(
+                    "Grafana – Production",
+                    "https://grafana.example.com/d/prod",
+                    "Bookmarks bar › Work › Ops"
+                ),
+                bookmark(
+                    "http://printer.local/status",
+                    "http://printer.local/status",
+                    "Other bookmarks"
+                ),
+            ]
+        );
+        assert!(parse_chromium_bookmarks("{\"roots\": ").is_err());
+    }
+
+    #[test]
+    fn test_parse_firefox_bookmarks() {
+        let db = Database::from_bytes(FIREFOX_PLACES.to_vec(), None).unwrap();
+        let bookmarks = parse_firefox_bookmarks(&db).unwrap();
+        assert_eq!(
+            bookmarks,
+            [
+                bookmark(
+                    "Intranet",
+                    "https://intranet.example.com/",
+                    "Bookmarks Toolbar"
+                ),
+                bookmark(
+                    "Build dashboard",
+                    "https://ci.example.com/dashboard",
+                    "Bookmarks Toolbar › Work"
+                ),
+                bookmark(
+                    "API reference – Example",
+                    "https://docs.example.com/api",
+                    "Bookmarks Toolbar › Work › Docs"
+                ),
+                bookmark("Rust Blog", "https://blog.rust-lang.org/", "Bookmarks Menu"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_searches_and_refreshes_profiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let chrome = dir.path().join("Default");
+        let firefox = dir.path().join("abcd.default-release");
+        fs::create_dir_all(&chrome).unwrap();
+        fs::create_dir_all(&firefox).unwrap();
+        fs::write(chrome.join("Bookmarks"), CHROMIUM_BOOKMARKS).unwrap();
+        fs::write(firefox.join("places.sqlite"), FIREFOX_PLACES).unwrap();
+
+        let profiles = vec![
+            Profile {
+                browser: "Chrome",
+                engine: Engine::Chromium,
+                dir: chrome.clone(),
+            },
+            Profile {
+                browser: "Firefox",
+                engine: Engine::Firefox,
+                dir: firefox,
+            },
+        ];
+        let history = Arc::new(RwLock::new(LaunchHistory::new(Default::default())));
+        let provider =
+            BookmarkProvider::with_profiles(BookmarksConfig::default(), profiles, history);
+        provider.start_refresh();
+        provider.wait_for_refresh();
+
+        let found = run_provider(&provider, "sprint");
+        assert_eq!(found[0].name, "Sprint board");
+        assert_eq!(
+            found[0].description,
+            "Chrome › Bookmarks bar › Work · https://jira.example.com/board/42"
+        );
+        assert_eq!(
+            provider.actions(&found[0])[0],
+            Action::Open(PathBuf::from("https://jira.example.com/board/42"))
+        );
+
+        // URLs and folder paths match too
+        assert_eq!(
+            run_provider(&provider, "grafana.example")[0].name,
+            "Grafana – Production"
+        );
+        assert_eq!(
+            run_provider(&provider, "docs api")[0].name,
+            "API reference – Example"
+        );
+        assert_eq!(
+            run_provider(&provider, "build")[0].description,
+            "Firefox › Bookmarks Toolbar › Work · https://ci.example.com/dashboard"
+        );
+        assert!(run_provider(&provider, "dark mode").is_empty());
+        assert!(run_provider(&provider, "sprint type:file").is_empty());
+
+        // Changes to a profile are picked up once it is due to be checked
+        let renamed = CHROMIUM_BOOKMARKS.replace("Sprint board", "Team kanban");
+        fs::write(chrome.join("Bookmarks"), renamed).unwrap();
+        provider.start_refresh();
+        provider.wait_for_refresh();
+        assert!(run_provider(&provider, "kanban").is_empty());
+        for loaded in provider.profiles.lock().unwrap().iter_mut() {
+            loaded.checked = None;
+        }
+        provider.start_refresh();
+        provider.wait_for_refresh();
+        assert_eq!(run_provider(&provider, "kanban")[0].name, "Team kanban");
+        let found = run_provider(&provider, "sprint");
+        assert!(found.iter().all(|r| r.name != "Sprint board"));
+    }
+}
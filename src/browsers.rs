@@ -0,0 +1,170 @@
+//! Browser profile discovery for Rustle
+//!
+//! Bookmarks and browsing history are read straight from the profile
+//! directories of the installed browsers: Chromium-based ones (Chrome,
+//! Edge, Brave, Vivaldi) under the local application data folder, and
+//! Firefox under the roaming one.
+
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Chromium-based browsers and their `User Data` folders, relative to the
+/// local application data folder
+const CHROMIUM_BROWSERS: &[(&str, &str)] = &[
+    ("Chrome", r"Google\Chrome\User Data"),
+    ("Edge", r"Microsoft\Edge\User Data"),
+    ("Brave", r"BraveSoftware\Brave-Browser\User Data"),
+    ("Vivaldi", r"Vivaldi\User Data"),
+];
+
+/// Firefox's profiles folder, relative to the roaming application data
+/// folder
+const FIREFOX_PROFILES: &str = r"Mozilla\Firefox\Profiles";
+
+/// Chromium profile folders that never hold the user's own data
+const SKIPPED_CHROMIUM_PROFILES: &[&str] = &["System Profile", "Guest Profile"];
+
+/// Which family of browsers a profile belongs to, which decides the
+/// format of its files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+    Chromium,
+    Firefox,
+}
+
+/// One browser profile on disk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    /// Browser name shown to the user, such as `Edge`
+    pub browser: &'static str,
+
+    pub engine: Engine,
+
+    /// Profile folder, such as `...\User Data\Default`
+    pub dir: PathBuf,
+}
+
+/// Finds the profiles of every installed browser
+pub fn find_profiles() -> Vec<Profile> {
+    let mut profiles = Vec::new();
+    if let Some(local) = dirs::data_local_dir() {
+        for &(browser, user_data) in CHROMIUM_BROWSERS {
+            profiles.extend(chromium_profiles(browser, &local.join(user_data)));
+        }
+    }
+    if let Some(roaming) = dirs::data_dir() {
+        profiles.extend(firefox_profiles(&roaming.join(FIREFOX_PROFILES)));
+    }
+    profiles
+}
+
+/// Lists the profiles in a Chromium `User Data` folder: `Default`,
+/// `Profile 1` and so on, each recognised by its `Preferences` file
+pub fn chromium_profiles(browser: &'static str, user_data: &Path) -> Vec<Profile> {
+    subdirectories(user_data)
+        .into_iter()
+        .filter(|dir| dir.join("Preferences").is_file())
+        .filter(|dir| {
+            let name = dir.file_name().unwrap_or_default();
+            !SKIPPED_CHROMIUM_PROFILES.iter().any(|s| name == *s)
+        })
+        .map(|dir| Profile {
+            browser,
+            engine: Engine::Chromium,
+            dir,
+        })
+        .collect()
+}
+
+/// Lists the Firefox profiles that have a `places.sqlite` database
+pub fn firefox_profiles(profiles: &Path) -> Vec<Profile> {
+    subdirectories(profiles)
+        .into_iter()
+        .filter(|dir| dir.join("places.sqlite").is_file())
+        .map(|dir| Profile {
+            browser: "Firefox",
+            engine: Engine::Firefox,
+            dir,
+        })
+        .collect()
+}
+
+fn subdirectories(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+/// Modification time and size of a file and of its SQLite `-wal` file,
+/// which change whenever the browser writes to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    file: Option<(SystemTime, u64)>,
+    wal: Option<(SystemTime, u64)>,
+}
+
+impl FileStamp {
+    pub fn of(path: &Path) -> FileStamp {
+        let stamp = |path: &Path| {
+            let metadata = std::fs::metadata(path).ok()?;
+            Some((metadata.modified().ok()?, metadata.len()))
+        };
+        let mut wal = path.as_os_str().to_owned();
+        wal.push("-wal");
+        FileStamp {
+            file: stamp(path),
+            wal: stamp(Path::new(&wal)),
+        }
+    }
+
+    /// Returns true if the file exists
+    pub fn exists(&self) -> bool {
+        self.file.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_finds_profiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let user_data = dir.path().join("User Data");
+        for profile in ["Default", "Profile 2", "System Profile", "Crashpad"] {
+            fs::create_dir_all(user_data.join(profile)).unwrap();
+            if profile != "Crashpad" {
+                fs::write(user_data.join(profile).join("Preferences"), "{}").unwrap();
+            }
+        }
+        let found: Vec<PathBuf> = chromium_profiles("Edge", &user_data)
+            .into_iter()
+            .map(|p| p.dir)
+            .collect();
+        assert_eq!(
+            found,
+            [user_data.join("Default"), user_data.join("Profile 2")]
+        );
+
+        let firefox = dir.path().join("Profiles");
+        fs::create_dir_all(firefox.join("abcd.default-release")).unwrap();
+        fs::create_dir_all(firefox.join("empty.old")).unwrap();
+        fs::write(firefox.join("abcd.default-release/places.sqlite"), "").unwrap();
+        let found = firefox_profiles(&firefox);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].browser, "Firefox");
+        assert_eq!(found[0].engine, Engine::Firefox);
+
+        assert!(chromium_profiles("Chrome", &dir.path().join("missing")).is_empty());
+    }
+}
@@ -0,0 +1,915 @@
+//! Inline calculator for Rustle
+//!
+//! Queries that look like arithmetic, such as `12*(3+4)/5` or
+//! `sqrt(2)^2 + 0xff`, are evaluated and the answer is shown above every
+//! other result; choosing it copies the answer to the clipboard. Integers
+//! stay exact as long as they fit in 128 bits, and everything else falls
+//! back to floating point. Anything that does not parse as an expression
+//! is left to the other providers.
+
+#![allow(dead_code)]
+
+use crate::error::{Result, RustleError};
+use crate::provider::{Action, Provider, ProviderQuery};
+use crate::search::{CancelToken, ResultType, SearchResult, Section};
+use std::fmt;
+use std::path::PathBuf;
+
+/// Significant digits shown for floating point answers
+const SIGNIFICANT_DIGITS: usize = 12;
+
+/// Trigonometric results closer to zero than this are shown as zero, so
+/// `sin(pi)` is 0 rather than a rounding error
+const TRIG_EPSILON: f64 = 1e-12;
+
+/// Functions the calculator knows, with their least and most arguments
+const FUNCTIONS: &[(&str, usize, usize)] = &[
+    ("abs", 1, 1),
+    ("acos", 1, 1),
+    ("asin", 1, 1),
+    ("atan", 1, 1),
+    ("cbrt", 1, 1),
+    ("ceil", 1, 1),
+    ("cos", 1, 1),
+    ("exp", 1, 1),
+    ("floor", 1, 1),
+    ("ln", 1, 1),
+    ("log", 1, 2),
+    ("log2", 1, 1),
+    ("max", 1, usize::MAX),
+    ("min", 1, usize::MAX),
+    ("round", 1, 1),
+    ("sin", 1, 1),
+    ("sqrt", 1, 1),
+    ("tan", 1, 1),
+];
+
+/// A number, kept exact while it is a whole number that fits
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Number {
+    /// An exact whole number
+    Integer(i128),
+
+    /// A floating point number
+    Real(f64),
+}
+
+impl Number {
+    /// Returns the number as a float
+    pub fn to_f64(self) -> f64 {
+        match self {
+            Number::Integer(n) => n as f64,
+            Number::Real(x) => x,
+        }
+    }
+
+    /// Returns true if the number is zero
+    fn is_zero(self) -> bool {
+        self.to_f64() == 0.0
+    }
+
+    /// Wraps a float, rejecting infinities and NaN
+    fn real(x: f64) -> Result<Number> {
+        if x.is_nan() {
+            Err(RustleError::calculation_error("result is undefined"))
+        } else if x.is_infinite() {
+            Err(RustleError::calculation_error("result is too large"))
+        } else {
+            Ok(Number::Real(x))
+        }
+    }
+
+    /// Turns a whole float back into an exact integer if it fits
+    fn whole(x: f64) -> Result<Number> {
+        if x.abs() < i128::MAX as f64 {
+            Ok(Number::Integer(x as i128))
+        } else {
+            Number::real(x)
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Number::Integer(n) => write!(f, "{}", n),
+            Number::Real(0.0) => write!(f, "0"),
+            Number::Real(x) => {
+                // Round away floating point noise such as 0.1 + 0.2
+                let rounded: f64 = format!("{:.*e}", SIGNIFICANT_DIGITS - 1, x)
+                    .parse()
+                    .unwrap_or(x);
+                if (1e-6..1e16).contains(&rounded.abs()) {
+                    write!(f, "{}", rounded)
+                } else {
+                    write!(f, "{:e}", rounded)
+                }
+            }
+        }
+    }
+}
+
+/// Binary operators, in the order they bind from weakest to strongest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Power,
+}
+
+/// A parsed arithmetic expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    /// A literal or constant
+    Number(Number),
+
+    /// Unary minus
+    Negate(Box<Expression>),
+
+    /// A binary operation
+    Binary(Operator, Box<Expression>, Box<Expression>),
+
+    /// `x%`, a hundredth of `x`, or a share of the left side of `+` and `-`
+    Percent(Box<Expression>),
+
+    /// `n!`
+    Factorial(Box<Expression>),
+
+    /// A function call such as `sqrt(2)`
+    Call(&'static str, Vec<Expression>),
+}
+
+impl Expression {
+    /// Parses an expression
+    ///
+    /// Supports `+ - * / ^` with the usual precedence (`**` also means a
+    /// power, `×` and `÷` work too), `mod`, parentheses, postfix `%` and
+    /// `!`, implicit multiplication such as `2pi` or `3(4+5)`, decimal,
+    /// scientific, hex (`0x`), octal (`0o`) and binary (`0b`) literals,
+    /// the constants `pi`, `e` and `tau`, and the functions in `FUNCTIONS`.
+    pub fn parse(input: &str) -> Result<Expression> {
+        let tokens = tokenize(input)?;
+        if tokens.is_empty() {
+            return Err(RustleError::calculation_error("empty expression"));
+        }
+
+        let mut parser = Parser { tokens, pos: 0 };
+        let expression = parser.sum()?;
+        match parser.peek() {
+            None => Ok(expression),
+            Some(Token::Close) => Err(RustleError::calculation_error("unmatched ')'")),
+            Some(token) => Err(RustleError::calculation_error(format!(
+                "unexpected {}",
+                token
+            ))),
+        }
+    }
+
+    /// Returns true if there is nothing to calculate, as in `42` or `pi`
+    pub fn is_trivial(&self) -> bool {
+        match self {
+            Expression::Number(_) => true,
+            Expression::Negate(inner) => matches!(**inner, Expression::Number(_)),
+            _ => false,
+        }
+    }
+
+    /// Works out the value of the expression
+    pub fn evaluate(&self) -> Result<Number> {
+        match self {
+            Expression::Number(n) => Ok(*n),
+            Expression::Negate(inner) => negate(inner.evaluate()?),
+            Expression::Percent(inner) => divide(inner.evaluate()?, Number::Integer(100)),
+            Expression::Factorial(inner) => factorial(inner.evaluate()?),
+            Expression::Binary(op @ (Operator::Add | Operator::Subtract), left, right) => {
+                let left = left.evaluate()?;
+                // `200 + 10%` adds ten percent of 200
+                let right = match &**right {
+                    Expression::Percent(share) => {
+                        divide(multiply(left, share.evaluate()?)?, Number::Integer(100))?
+                    }
+                    right => right.evaluate()?,
+                };
+                if *op == Operator::Add {
+                    add(left, right)
+                } else {
+                    add(left, negate(right)?)
+                }
+            }
+            Expression::Binary(op, left, right) => {
+                let (left, right) = (left.evaluate()?, right.evaluate()?);
+                match op {
+                    Operator::Multiply => multiply(left, right),
+                    Operator::Divide => divide(left, right),
+                    Operator::Modulo => modulo(left, right),
+                    Operator::Power => power(left, right),
+                    Operator::Add | Operator::Subtract => unreachable!(),
+                }
+            }
+            Expression::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(Expression::evaluate)
+                    .collect::<Result<Vec<_>>>()?;
+                call(name, &args)
+            }
+        }
+    }
+}
+
+/// Parses and evaluates `input` in one go
+pub fn evaluate(input: &str) -> Result<Number> {
+    Expression::parse(input)?.evaluate()
+}
+
+/// Parses `input` if it is worth calculating
+///
+/// Returns None for anything that is not an expression, and for plain
+/// numbers and constants, which have nothing to work out. A lone hex,
+/// octal or binary literal still counts, to show it in decimal.
+pub fn calculation(input: &str) -> Option<Expression> {
+    let expression = Expression::parse(input).ok()?;
+    if !expression.is_trivial() {
+        return Some(expression);
+    }
+    let literal = input
+        .trim()
+        .trim_start_matches(['-', '+', '−'])
+        .to_lowercase();
+    ["0x", "0o", "0b"]
+        .iter()
+        .any(|prefix| literal.starts_with(prefix))
+        .then_some(expression)
+}
+
+/// A lexical token of an expression
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(Number),
+    Name(String),
+    Operator(char),
+    Open,
+    Close,
+    Comma,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "number {}", n),
+            Token::Name(name) => write!(f, "'{}'", name),
+            Token::Operator(op) => write!(f, "'{}'", op),
+            Token::Open => write!(f, "'('"),
+            Token::Close => write!(f, "')'"),
+            Token::Comma => write!(f, "','"),
+        }
+    }
+}
+
+/// Splits an expression into tokens
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '0'..='9' | '.' => {
+                let (number, len) = number_literal(&chars[i..])?;
+                tokens.push(Token::Number(number));
+                i += len;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let len = chars[i..]
+                    .iter()
+                    .take_while(|c| c.is_alphanumeric() || **c == '_')
+                    .count();
+                let name: String = chars[i..i + len].iter().collect();
+                tokens.push(Token::Name(name.to_lowercase()));
+                i += len;
+            }
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                tokens.push(Token::Operator('^'));
+                i += 2;
+            }
+            '+' | '-' | '*' | '/' | '^' | '%' | '!' => {
+                tokens.push(Token::Operator(c));
+                i += 1;
+            }
+            '−' => {
+                tokens.push(Token::Operator('-'));
+                i += 1;
+            }
+            '×' | '·' => {
+                tokens.push(Token::Operator('*'));
+                i += 1;
+            }
+            '÷' => {
+                tokens.push(Token::Operator('/'));
+                i += 1;
+            }
+            '(' => {
+                tokens.push(Token::Open);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::Close);
+                i += 1;
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            c => {
+                return Err(RustleError::calculation_error(format!(
+                    "unexpected '{}'",
+                    c
+                )))
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Reads a number literal, returning it and how many characters it used
+fn number_literal(chars: &[char]) -> Result<(Number, usize)> {
+    // Hex, octal and binary literals are always whole numbers
+    if chars[0] == '0' {
+        let radix = match chars.get(1) {
+            Some('x' | 'X') => Some(16),
+            Some('o' | 'O') => Some(8),
+            Some('b' | 'B') => Some(2),
+            _ => None,
+        };
+        if let Some(radix) = radix {
+            let len = chars[2..]
+                .iter()
+                .take_while(|c| c.is_digit(radix) || **c == '_')
+                .count();
+            let digits: String = chars[2..2 + len].iter().filter(|c| **c != '_').collect();
+            if digits.is_empty() {
+                return Err(RustleError::calculation_error(
+                    "missing digits after 0x, 0o or 0b",
+                ));
+            }
+            let value = i128::from_str_radix(&digits, radix)
+                .map_err(|_| RustleError::calculation_error("number is too large"))?;
+            return Ok((Number::Integer(value), 2 + len));
+        }
+    }
+
+    let mut len = chars.iter().take_while(|c| c.is_ascii_digit()).count();
+    let mut is_integer = true;
+    if chars.get(len) == Some(&'.') {
+        is_integer = false;
+        len += 1;
+        len += chars[len..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .count();
+    }
+
+    // An exponent needs digits after it, otherwise `2e` means 2 times e
+    if matches!(chars.get(len), Some('e' | 'E')) {
+        let sign = usize::from(matches!(chars.get(len + 1), Some('+' | '-')));
+        let digits = chars[len + 1 + sign..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .count();
+        if digits > 0 {
+            is_integer = false;
+            len += 1 + sign + digits;
+        }
+    }
+
+    let text: String = chars[..len].iter().collect();
+    if text == "." {
+        return Err(RustleError::calculation_error("unexpected '.'"));
+    }
+    if is_integer {
+        if let Ok(value) = text.parse::<i128>() {
+            return Ok((Number::Integer(value), len));
+        }
+    }
+    let value: f64 = text
+        .parse()
+        .map_err(|_| RustleError::calculation_error(format!("invalid number '{}'", text)))?;
+    Ok((Number::real(value)?, len))
+}
+
+/// Recursive descent parser over the tokens of an expression
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// Consumes the next token if it is the operator `op`
+    fn eat_operator(&mut self, op: char) -> bool {
+        if self.peek() == Some(&Token::Operator(op)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// sum := product (('+' | '-') product)*
+    fn sum(&mut self) -> Result<Expression> {
+        let mut left = self.product()?;
+        loop {
+            let op = if self.eat_operator('+') {
+                Operator::Add
+            } else if self.eat_operator('-') {
+                Operator::Subtract
+            } else {
+                return Ok(left);
+            };
+            let right = self.product()?;
+            left = Expression::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    /// product := unary (('*' | '/' | 'mod' | implicit) unary)*
+    fn product(&mut self) -> Result<Expression> {
+        let mut left = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Operator('*')) => Operator::Multiply,
+                Some(Token::Operator('/')) => Operator::Divide,
+                Some(Token::Name(name)) if name == "mod" => Operator::Modulo,
+                // `2pi` and `3(4+5)` multiply
+                Some(Token::Name(_) | Token::Open) => {
+                    let right = self.unary()?;
+                    left = Expression::Binary(Operator::Multiply, Box::new(left), Box::new(right));
+                    continue;
+                }
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            let right = self.unary()?;
+            left = Expression::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    /// unary := ('-' | '+') unary | power
+    fn unary(&mut self) -> Result<Expression> {
+        if self.eat_operator('-') {
+            Ok(Expression::Negate(Box::new(self.unary()?)))
+        } else if self.eat_operator('+') {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+
+    /// power := postfix ('^' unary)?, so powers group from the right and
+    /// `-2^2` is -4
+    fn power(&mut self) -> Result<Expression> {
+        let base = self.postfix()?;
+        if self.eat_operator('^') {
+            let exponent = self.unary()?;
+            Ok(Expression::Binary(
+                Operator::Power,
+                Box::new(base),
+                Box::new(exponent),
+            ))
+        } else {
+            Ok(base)
+        }
+    }
+
+    /// postfix := primary ('%' | '!')*
+    fn postfix(&mut self) -> Result<Expression> {
+        let mut expression = self.primary()?;
+        loop {
+            if self.eat_operator('%') {
+                expression = Expression::Percent(Box::new(expression));
+            } else if self.eat_operator('!') {
+                expression = Expression::Factorial(Box::new(expression));
+            } else {
+                return Ok(expression);
+            }
+        }
+    }
+
+    /// primary := number | constant | function '(' arguments ')' | '(' sum ')'
+    fn primary(&mut self) -> Result<Expression> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expression::Number(n)),
+            Some(Token::Open) => {
+                let inner = self.sum()?;
+                self.expect_close()?;
+                Ok(inner)
+            }
+            Some(Token::Name(name)) => self.name(&name),
+            Some(token) => Err(RustleError::calculation_error(format!(
+                "unexpected {}",
+                token
+            ))),
+            None => Err(RustleError::calculation_error("expression is incomplete")),
+        }
+    }
+
+    /// Parses what follows a name: nothing for a constant, arguments for
+    /// a function
+    fn name(&mut self, name: &str) -> Result<Expression> {
+        let constant = match name {
+            "pi" | "π" => Some(std::f64::consts::PI),
+            "tau" | "τ" => Some(std::f64::consts::TAU),
+            "e" => Some(std::f64::consts::E),
+            _ => None,
+        };
+        if let Some(value) = constant {
+            return Ok(Expression::Number(Number::Real(value)));
+        }
+
+        let Some(&(function, min_args, max_args)) = FUNCTIONS.iter().find(|f| f.0 == name) else {
+            return Err(RustleError::calculation_error(format!(
+                "unknown name '{}'",
+                name
+            )));
+        };
+
+        // `sqrt 16` works without parentheses for a single argument
+        if self.peek() != Some(&Token::Open) {
+            let argument = self.power()?;
+            return Ok(Expression::Call(function, vec![argument]));
+        }
+        self.pos += 1;
+
+        let mut args = vec![self.sum()?];
+        while self.peek() == Some(&Token::Comma) {
+            self.pos += 1;
+            args.push(self.sum()?);
+        }
+        self.expect_close()?;
+
+        if args.len() < min_args || args.len() > max_args {
+            return Err(RustleError::calculation_error(format!(
+                "{} takes {} argument{}",
+                function,
+                if min_args == max_args {
+                    min_args.to_string()
+                } else {
+                    format!("{} or {}", min_args, max_args)
+                },
+                if max_args == 1 { "" } else { "s" }
+            )));
+        }
+        Ok(Expression::Call(function, args))
+    }
+
+    fn expect_close(&mut self) -> Result<()> {
+        match self.next() {
+            Some(Token::Close) => Ok(()),
+            _ => Err(RustleError::calculation_error("missing ')'")),
+        }
+    }
+}
+
+fn add(a: Number, b: Number) -> Result<Number> {
+    match (a, b) {
+        (Number::Integer(x), Number::Integer(y)) if x.checked_add(y).is_some() => {
+            Ok(Number::Integer(x + y))
+        }
+        _ => Number::real(a.to_f64() + b.to_f64()),
+    }
+}
+
+fn negate(a: Number) -> Result<Number> {
+    match a {
+        Number::Integer(x) if x != i128::MIN => Ok(Number::Integer(-x)),
+        _ => Number::real(-a.to_f64()),
+    }
+}
+
+fn multiply(a: Number, b: Number) -> Result<Number> {
+    match (a, b) {
+        (Number::Integer(x), Number::Integer(y)) if x.checked_mul(y).is_some() => {
+            Ok(Number::Integer(x * y))
+        }
+        _ => Number::real(a.to_f64() * b.to_f64()),
+    }
+}
+
+/// Divides, staying exact when the division leaves no remainder
+fn divide(a: Number, b: Number) -> Result<Number> {
+    if b.is_zero() {
+        return Err(RustleError::calculation_error("division by zero"));
+    }
+    match (a, b) {
+        (Number::Integer(x), Number::Integer(y)) if x.checked_rem(y) == Some(0) => {
+            Ok(Number::Integer(x / y))
+        }
+        _ => Number::real(a.to_f64() / b.to_f64()),
+    }
+}
+
+/// Remainder of a division, with the sign of the divisor's absolute value
+fn modulo(a: Number, b: Number) -> Result<Number> {
+    if b.is_zero() {
+        return Err(RustleError::calculation_error("division by zero"));
+    }
+    match (a, b) {
+        (Number::Integer(x), Number::Integer(y)) => {
+            Ok(Number::Integer(x.checked_rem_euclid(y).unwrap_or(0)))
+        }
+        _ => Number::real(a.to_f64().rem_euclid(b.to_f64())),
+    }
+}
+
+fn power(base: Number, exponent: Number) -> Result<Number> {
+    if let (Number::Integer(x), Number::Integer(y)) = (base, exponent) {
+        if let Ok(y) = u32::try_from(y) {
+            if let Some(value) = x.checked_pow(y) {
+                return Ok(Number::Integer(value));
+            }
+        } else if y < 0 && x == 0 {
+            return Err(RustleError::calculation_error("division by zero"));
+        }
+    }
+    Number::real(base.to_f64().powf(exponent.to_f64()))
+}
+
+fn factorial(n: Number) -> Result<Number> {
+    let n = match n {
+        Number::Integer(n) if n >= 0 => n,
+        Number::Real(x) if x >= 0.0 && x.fract() == 0.0 => x as i128,
+        _ => {
+            return Err(RustleError::calculation_error(
+                "factorial needs a whole number of at least 0",
+            ))
+        }
+    };
+
+    let mut exact: Option<i128> = Some(1);
+    let mut approximate = 1.0f64;
+    for i in 2..=n {
+        exact = exact.and_then(|value| value.checked_mul(i));
+        approximate *= i as f64;
+        if exact.is_none() && approximate.is_infinite() {
+            break;
+        }
+    }
+    match exact {
+        Some(value) => Ok(Number::Integer(value)),
+        None => Number::real(approximate),
+    }
+}
+
+/// Calls one of the functions in `FUNCTIONS` with checked arguments
+fn call(name: &str, args: &[Number]) -> Result<Number> {
+    let x = args[0];
+    let f = x.to_f64();
+    let trig = |value: f64| {
+        if value.abs() < TRIG_EPSILON {
+            Number::real(0.0)
+        } else {
+            Number::real(value)
+        }
+    };
+    let logarithm = |value: f64, log: fn(f64) -> f64| {
+        if value <= 0.0 {
+            Err(RustleError::calculation_error(
+                "logarithm of a number that is not positive",
+            ))
+        } else {
+            Number::real(log(value))
+        }
+    };
+
+    match name {
+        "abs" => match x {
+            Number::Integer(n) if n != i128::MIN => Ok(Number::Integer(n.abs())),
+            _ => Number::real(f.abs()),
+        },
+        "sqrt" => {
+            if f < 0.0 {
+                return Err(RustleError::calculation_error(
+                    "square root of a negative number",
+                ));
+            }
+            // Perfect squares stay exact
+            if let Number::Integer(n) = x {
+                let root = (n as f64).sqrt().round() as i128;
+                if root.checked_mul(root) == Some(n) {
+                    return Ok(Number::Integer(root));
+                }
+            }
+            Number::real(f.sqrt())
+        }
+        "cbrt" => Number::real(f.cbrt()),
+        "floor" => Number::whole(f.floor()),
+        "ceil" => Number::whole(f.ceil()),
+        "round" => Number::whole(f.round()),
+        "exp" => Number::real(f.exp()),
+        "ln" => logarithm(f, f64::ln),
+        "log2" => logarithm(f, f64::log2),
+        "log" if args.len() == 2 => {
+            let base = args[1].to_f64();
+            if base <= 0.0 || base == 1.0 {
+                return Err(RustleError::calculation_error(
+                    "logarithm base must be positive and not 1",
+                ));
+            }
+            logarithm(f, |v| v.ln()).and_then(|n| Number::real(n.to_f64() / base.ln()))
+        }
+        "log" => logarithm(f, f64::log10),
+        "sin" => trig(f.sin()),
+        "cos" => trig(f.cos()),
+        "tan" => trig(f.tan()),
+        "asin" | "acos" if !(-1.0..=1.0).contains(&f) => Err(RustleError::calculation_error(
+            format!("{} needs a number between -1 and 1", name),
+        )),
+        "asin" => Number::real(f.asin()),
+        "acos" => Number::real(f.acos()),
+        "atan" => Number::real(f.atan()),
+        "min" => Ok(args
+            .iter()
+            .copied()
+            .fold(x, |a, b| if b.to_f64() < a.to_f64() { b } else { a })),
+        "max" => Ok(args
+            .iter()
+            .copied()
+            .fold(x, |a, b| if b.to_f64() > a.to_f64() { b } else { a })),
+        _ => Err(RustleError::calculation_error(format!(
+            "unknown function '{}'",
+            name
+        ))),
+    }
+}
+
+/// Shows the answer to arithmetic typed into the search box
+pub struct CalculatorProvider;
+
+impl Provider for CalculatorProvider {
+    fn id(&self) -> &'static str {
+        "calculator"
+    }
+
+    fn title(&self) -> &str {
+        "Calculator"
+    }
+
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    /// Expressions are read as typed
+    fn wants_filters(&self) -> bool {
+        false
+    }
+
+    fn query(
+        &self,
+        query: &ProviderQuery,
+        _cancel: &CancelToken,
+        emit: &(dyn Fn(Vec<Section>) + Sync),
+    ) -> bool {
+        // Anything that is not an expression is left to the other providers
+        let text = query.text.trim();
+        let Some(expression) = calculation(text) else {
+            return true;
+        };
+
+        let (name, description) = match expression.evaluate() {
+            Ok(value) => (value.to_string(), format!("{} =", text)),
+            Err(e) => (e.to_string(), text.to_string()),
+        };
+        emit(vec![self.section(vec![SearchResult {
+            name,
+            path: PathBuf::new(),
+            result_type: ResultType::Answer,
+            score: 0,
+            description,
+            provider: self.id(),
+            shortcut: None,
+        }])]);
+        true
+    }
+
+    /// Copies answers; error messages have nothing to copy
+    fn actions(&self, result: &SearchResult) -> Vec<Action> {
+        if result.name.parse::<f64>().is_ok() {
+            vec![Action::Copy(result.name.clone())]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::run_provider;
+
+    fn calc(input: &str) -> String {
+        evaluate(input).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_precedence_and_parentheses() {
+        assert_eq!(calc("12*(3+4)/5"), "16.8");
+        assert_eq!(calc("2 + 3 * 4"), "14");
+        assert_eq!(calc("(2 + 3) * 4"), "20");
+        assert_eq!(calc("2^3^2"), "512");
+        assert_eq!(calc("2**10"), "1024");
+        assert_eq!(calc("-2^2"), "-4");
+        assert_eq!(calc("2^-1"), "0.5");
+        assert_eq!(calc("10 - 4 - 3"), "3");
+        assert_eq!(calc("17 mod 5"), "2");
+        assert_eq!(calc("6 ÷ 4 × 2"), "3");
+        assert_eq!(calc("2pi"), calc("2 * pi"));
+        assert_eq!(calc("3(4+5)"), "27");
+        assert_eq!(calc("5!"), "120");
+    }
+
+    #[test]
+    fn test_exact_integers() {
+        assert_eq!(calc("2^100"), "1267650600228229401496703205376");
+        assert_eq!(evaluate("12/4").unwrap(), Number::Integer(3));
+        assert_eq!(evaluate("sqrt(144)").unwrap(), Number::Integer(12));
+        assert_eq!(calc("25!"), "15511210043330985984000000");
+        // Past 128 bits the answer is approximate
+        assert_eq!(calc("2^200"), "1.60693804426e60");
+        assert_eq!(calc("0.1 + 0.2"), "0.3");
+    }
+
+    #[test]
+    fn test_percentages_literals_functions_and_constants() {
+        assert_eq!(calc("50%"), "0.5");
+        assert_eq!(calc("200 + 10%"), "220");
+        assert_eq!(calc("200 - 10%"), "180");
+        assert_eq!(calc("200 * 10%"), "20");
+        assert_eq!(calc("0xFF + 0b1010"), "265");
+        assert_eq!(calc("0o17"), "15");
+        assert_eq!(calc("1.5e3"), "1500");
+        assert_eq!(calc("sqrt(2)^2"), "2");
+        assert_eq!(calc("sin(pi)"), "0");
+        assert_eq!(calc("cos(0)"), "1");
+        assert_eq!(calc("log(1000)"), "3");
+        assert_eq!(calc("log(8, 2)"), "3");
+        assert_eq!(calc("ln(e)"), "1");
+        assert_eq!(calc("max(3, 9, 4)"), "9");
+        assert_eq!(calc("sqrt 16"), "4");
+        assert_eq!(calc("tau / pi"), "2");
+    }
+
+    #[test]
+    fn test_errors_are_clear() {
+        let message = |input: &str| evaluate(input).unwrap_err().to_string();
+        assert!(message("1/0").contains("division by zero"));
+        assert!(message("5 mod 0").contains("division by zero"));
+        assert!(message("sqrt(-1)").contains("square root of a negative number"));
+        assert!(message("log(0)").contains("not positive"));
+        assert!(message("(1+2").contains("missing ')'"));
+        assert!(message("1+2)").contains("unmatched ')'"));
+        assert!(message("2 +").contains("incomplete"));
+        assert!(message("foo(2)").contains("unknown name 'foo'"));
+        assert!(message("sqrt(1, 2)").contains("sqrt takes 1 argument"));
+        assert!(message("1.5!").contains("factorial"));
+        assert!(message("10^400").contains("too large"));
+    }
+
+    #[test]
+    fn test_non_math_falls_through() {
+        let is_calculation = |input: &str| calculation(input).is_some();
+        assert!(is_calculation("12*(3+4)/5"));
+        assert!(is_calculation("0xff"));
+        assert!(is_calculation("sqrt 2"));
+        assert!(!is_calculation("notepad"));
+        assert!(!is_calculation("7zip"));
+        assert!(!is_calculation("2048"));
+        assert!(!is_calculation("pi"));
+        assert!(!is_calculation("report 2024.docx"));
+        assert!(!is_calculation(""));
+    }
+
+    #[test]
+    fn test_provider_offers_copy() {
+        let results = run_provider(&CalculatorProvider, "12*(3+4)/5");
+        let answer = &results[0];
+        assert_eq!(answer.name, "16.8");
+        assert_eq!(
+            CalculatorProvider.actions(answer),
+            [Action::Copy("16.8".to_string())]
+        );
+    }
+}
@@ -1,17 +1,49 @@
 //! Clipboard operations for Rustle
 //!
-//! Provides copy and paste functionality using the Windows Clipboard API.
+//! Provides copy and paste functionality using the Windows Clipboard API,
+//! and reads what other applications copy for the clipboard history.
 
+use crate::cliphistory::ClipboardChange;
+use crate::utils::to_wide_string;
+use std::path::Path;
 use std::ptr;
-use windows::Win32::Foundation::HWND;
+use std::time::{Duration, Instant};
+use windows::core::{PCWSTR, PWSTR};
+use windows::Win32::Foundation::{CloseHandle, HGLOBAL, HWND};
 use windows::Win32::System::DataExchange::{
-    CloseClipboard, EmptyClipboard, GetClipboardData, OpenClipboard, SetClipboardData,
+    CloseClipboard, EmptyClipboard, GetClipboardData, GetClipboardOwner,
+    IsClipboardFormatAvailable, OpenClipboard, RegisterClipboardFormatW, SetClipboardData,
 };
 use windows::Win32::System::Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
+use windows::Win32::System::Threading::{
+    OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYBD_EVENT_FLAGS, KEYEVENTF_KEYUP,
+    VIRTUAL_KEY, VK_CONTROL, VK_V,
+};
+use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
 
 /// Standard clipboard format for Unicode text
 const CF_UNICODETEXT: u32 = 13;
 
+/// Formats that password managers and similar apps add to what they copy
+/// to ask clipboard monitors to leave it alone
+const PRIVATE_MARKER_FORMATS: &[&str] = &[
+    "ExcludeClipboardContentFromMonitorProcessing",
+    "Clipboard Viewer Ignore",
+];
+
+/// Format holding a DWORD; zero asks for the copy to stay out of any
+/// clipboard history
+const CAN_INCLUDE_IN_HISTORY_FORMAT: &str = "CanIncludeInClipboardHistory";
+
+/// How long to wait for the window pasted into to get focus back
+const PASTE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How often to check whether that window has focus yet
+const PASTE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
 /// Copies text to the Windows clipboard
 ///
 /// # Arguments
@@ -96,38 +128,163 @@ pub fn paste_from_clipboard(hwnd: Option<HWND>) -> Result<String, String> {
             return Err("Failed to open clipboard".to_string());
         }
 
-        // Get clipboard data
-        let hmem = match GetClipboardData(CF_UNICODETEXT) {
-            Ok(h) => h,
-            Err(_) => {
-                let _ = CloseClipboard();
-                return Err("No text in clipboard".to_string());
-            }
-        };
+        let text = read_text();
+        let _ = CloseClipboard();
+        let text = text?;
 
-        // Lock and read data
-        let ptr = GlobalLock(windows::Win32::Foundation::HGLOBAL(hmem.0));
-        if ptr.is_null() {
-            let _ = CloseClipboard();
-            return Err("Failed to lock clipboard memory".to_string());
-        }
+        log::debug!("Pasted from clipboard: {}", text);
+        Ok(text)
+    }
+}
 
-        // Find null terminator and read the string
-        let wide_ptr = ptr as *const u16;
-        let mut len = 0;
-        while *wide_ptr.add(len) != 0 {
-            len += 1;
-        }
+/// Reads the text of the clipboard, which must be open
+unsafe fn read_text() -> Result<String, String> {
+    let hmem = GetClipboardData(CF_UNICODETEXT).map_err(|_| "No text in clipboard".to_string())?;
+
+    // Lock and read data
+    let ptr = GlobalLock(HGLOBAL(hmem.0));
+    if ptr.is_null() {
+        return Err("Failed to lock clipboard memory".to_string());
+    }
 
-        let slice = std::slice::from_raw_parts(wide_ptr, len);
-        let text = String::from_utf16_lossy(slice);
+    // Find null terminator and read the string
+    let wide_ptr = ptr as *const u16;
+    let mut len = 0;
+    while *wide_ptr.add(len) != 0 {
+        len += 1;
+    }
+
+    let slice = std::slice::from_raw_parts(wide_ptr, len);
+    let text = String::from_utf16_lossy(slice);
+
+    let _ = GlobalUnlock(HGLOBAL(hmem.0));
+    Ok(text)
+}
+
+/// Reads what was just copied, for the clipboard history
+///
+/// Called when the clipboard changes. Reports which program copied it and
+/// whether that program marked the copy as private.
+pub fn read_change(hwnd: Option<HWND>) -> Result<ClipboardChange, String> {
+    unsafe {
+        let source = clipboard_owner_name();
 
-        let _ = GlobalUnlock(windows::Win32::Foundation::HGLOBAL(hmem.0));
+        let hwnd = hwnd.unwrap_or(HWND::default());
+        if OpenClipboard(hwnd).is_err() {
+            return Err("Failed to open clipboard".to_string());
+        }
+        let private = is_marked_private();
+        let text = if private { None } else { read_text().ok() };
         let _ = CloseClipboard();
 
-        log::debug!("Pasted from clipboard: {}", text);
-        Ok(text)
+        Ok(ClipboardChange {
+            text,
+            source,
+            private,
+        })
+    }
+}
+
+/// Returns true if the copy carries one of the private markers; the
+/// clipboard must be open
+unsafe fn is_marked_private() -> bool {
+    let format = |name: &str| RegisterClipboardFormatW(PCWSTR(to_wide_string(name).as_ptr()));
+
+    for name in PRIVATE_MARKER_FORMATS {
+        let marker = format(name);
+        if marker != 0 && IsClipboardFormatAvailable(marker).is_ok() {
+            return true;
+        }
+    }
+
+    let can_include = format(CAN_INCLUDE_IN_HISTORY_FORMAT);
+    if can_include != 0 && IsClipboardFormatAvailable(can_include).is_ok() {
+        if let Ok(hmem) = GetClipboardData(can_include) {
+            let ptr = GlobalLock(HGLOBAL(hmem.0));
+            if !ptr.is_null() {
+                let allowed = *(ptr as *const u32);
+                let _ = GlobalUnlock(HGLOBAL(hmem.0));
+                return allowed == 0;
+            }
+        }
+    }
+    false
+}
+
+/// Returns the executable name of the program that owns the clipboard
+unsafe fn clipboard_owner_name() -> Option<String> {
+    let owner = GetClipboardOwner().ok()?;
+    let mut process_id = 0u32;
+    GetWindowThreadProcessId(owner, Some(&mut process_id));
+    if process_id == 0 {
+        return None;
     }
+
+    let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id).ok()?;
+    let mut buffer = [0u16; 1024];
+    let mut len = buffer.len() as u32;
+    let queried = QueryFullProcessImageNameW(
+        process,
+        PROCESS_NAME_WIN32,
+        PWSTR(buffer.as_mut_ptr()),
+        &mut len,
+    );
+    let _ = CloseHandle(process);
+    queried.ok()?;
+
+    let path = String::from_utf16_lossy(&buffer[..len as usize]);
+    Path::new(&path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+}
+
+/// Presses Ctrl+V in `target`, pasting what was just copied
+///
+/// The search window hands focus back to `target` as it hides, which
+/// takes effect asynchronously. The keys are only sent once `target` is
+/// the foreground window, so they never land in the search window or
+/// anywhere else; if it has not got focus within a second, nothing is
+/// pasted.
+pub fn paste_into(target: HWND) {
+    let target = target.0 as isize;
+    std::thread::spawn(move || {
+        let target = HWND(target as *mut _);
+        let deadline = Instant::now() + PASTE_TIMEOUT;
+        while unsafe { GetForegroundWindow() } != target {
+            if Instant::now() >= deadline {
+                log::warn!("Not pasting: the previous window did not get focus back");
+                return;
+            }
+            std::thread::sleep(PASTE_POLL_INTERVAL);
+        }
+
+        let key = |vk: VIRTUAL_KEY, flags: KEYBD_EVENT_FLAGS| INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 {
+                ki: KEYBDINPUT {
+                    wVk: vk,
+                    wScan: 0,
+                    dwFlags: flags,
+                    time: 0,
+                    dwExtraInfo: 0,
+                },
+            },
+        };
+        let inputs = [
+            key(VK_CONTROL, KEYBD_EVENT_FLAGS(0)),
+            key(VK_V, KEYBD_EVENT_FLAGS(0)),
+            key(VK_V, KEYEVENTF_KEYUP),
+            key(VK_CONTROL, KEYEVENTF_KEYUP),
+        ];
+        let sent = unsafe { SendInput(&inputs, std::mem::size_of::<INPUT>() as i32) };
+        if sent as usize != inputs.len() {
+            log::warn!(
+                "Failed to paste: only {} of {} key events were sent",
+                sent,
+                inputs.len()
+            );
+        }
+    });
 }
 
 #[cfg(test)]
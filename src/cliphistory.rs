@@ -0,0 +1,674 @@
+//! Clipboard history for Rustle
+//!
+//! The search window listens for clipboard changes and records copied text
+//! here, with when it was copied and which program copied it. Copying the
+//! same text again moves it back to the top instead of adding it twice.
+//! Pinned entries stay at the top and survive both the size limit and
+//! clearing. Copies that password managers mark as private, or that come
+//! from excluded programs, are never recorded. The history is stored as a
+//! small text file under `Config::data_dir()`, and queries starting with
+//! the clipboard prefix (`clip ` by default) search it.
+
+#![allow(dead_code)]
+
+use crate::config::{ClipboardConfig, Config};
+use crate::error::{Result, RustleError};
+use crate::provider::{Action, Provider, ProviderQuery, Trigger};
+use crate::query;
+use crate::search::{CancelToken, ResultType, SearchResult, Section};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// First line of every clipboard history file
+const HISTORY_HEADER: &str = "rustle-clipboard 1";
+
+/// File name of the clipboard history inside the data directory
+const HISTORY_FILE_NAME: &str = "clipboard_history.tsv";
+
+/// Most entries shown for one query
+const MAX_RESULTS: usize = 20;
+
+/// Longest preview of an entry, in characters
+const MAX_PREVIEW_CHARS: usize = 100;
+
+/// Scheme of the pseudo-paths that identify entries in search results
+const ENTRY_SCHEME: &str = "clipboard:";
+
+/// What changed on the clipboard, as read by `clipboard::read_change`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardChange {
+    /// Copied text, if there is any
+    pub text: Option<String>,
+
+    /// Executable name of the program that copied it, if known
+    pub source: Option<String>,
+
+    /// Whether the program marked the copy as private
+    pub private: bool,
+}
+
+/// One remembered copy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipEntry {
+    /// Stable identifier, unique within the history
+    pub id: u64,
+
+    pub text: String,
+
+    /// Executable name of the program that copied it last
+    pub source: String,
+
+    /// Time of the last copy in seconds since the Unix epoch
+    pub copied: u64,
+
+    pub pinned: bool,
+}
+
+/// Remembered copies, most recent first
+#[derive(Debug, Clone)]
+pub struct ClipHistory {
+    entries: Vec<ClipEntry>,
+    next_id: u64,
+    max_entries: usize,
+}
+
+impl ClipHistory {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            entries: Vec::new(),
+            next_id: 1,
+            max_entries,
+        }
+    }
+
+    /// Returns the default location of the clipboard history file
+    pub fn default_path() -> Option<PathBuf> {
+        Config::data_dir().map(|p| p.join(HISTORY_FILE_NAME))
+    }
+
+    /// Loads the history, starting empty if it is missing or unreadable
+    pub fn load_or_default(path: &Path, max_entries: usize) -> Self {
+        match Self::load(path, max_entries) {
+            Ok(history) => {
+                log::info!("Loaded clipboard history with {} entries", history.len());
+                history
+            }
+            Err(e) => {
+                if path.exists() {
+                    log::warn!("Ignoring clipboard history at {}: {}", path.display(), e);
+                }
+                Self::new(max_entries)
+            }
+        }
+    }
+
+    /// Loads the history from disk
+    pub fn load(path: &Path, max_entries: usize) -> Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut lines = reader.lines();
+
+        match lines.next() {
+            Some(Ok(header)) if header == HISTORY_HEADER => {}
+            _ => return Err(RustleError::history_error("not a Rustle clipboard history")),
+        }
+
+        let mut history = Self::new(max_entries);
+        for line in lines {
+            let line = line?;
+            // Lines are `id \t copied \t pinned \t source \t text`, with the
+            // text escaped so it fits on one line
+            let mut fields = line.splitn(5, '\t');
+            let parsed = (|| {
+                let id = fields.next()?.parse().ok()?;
+                let copied = fields.next()?.parse().ok()?;
+                let pinned = fields.next()? == "1";
+                let source = fields.next()?.to_string();
+                let text = unescape(fields.next()?);
+                Some(ClipEntry {
+                    id,
+                    text,
+                    source,
+                    copied,
+                    pinned,
+                })
+            })();
+
+            match parsed {
+                Some(entry) => {
+                    history.next_id = history.next_id.max(entry.id + 1);
+                    history.entries.push(entry);
+                }
+                None => log::debug!("Skipping malformed clipboard history line"),
+            }
+        }
+
+        history.entries.sort_by_key(|e| std::cmp::Reverse(e.copied));
+        history.prune();
+        Ok(history)
+    }
+
+    /// Saves the history to disk, through a temporary file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let tmp_path = path.with_extension("tmp");
+        {
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            writeln!(writer, "{}", HISTORY_HEADER)?;
+            for entry in &self.entries {
+                writeln!(
+                    writer,
+                    "{}\t{}\t{}\t{}\t{}",
+                    entry.id,
+                    entry.copied,
+                    if entry.pinned { 1 } else { 0 },
+                    entry.source.replace(['\t', '\n', '\r'], " "),
+                    escape(&entry.text)
+                )?;
+            }
+            writer.flush()?;
+        }
+
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Records a copy of `text` at time `now`
+    ///
+    /// Text copied before moves back to the top, keeping its pin. Returns
+    /// false if the text is blank.
+    pub fn record(&mut self, text: &str, source: &str, now: u64) -> bool {
+        if text.trim().is_empty() {
+            return false;
+        }
+
+        let mut entry = match self.entries.iter().position(|e| e.text == text) {
+            Some(i) => self.entries.remove(i),
+            None => {
+                self.next_id += 1;
+                ClipEntry {
+                    id: self.next_id - 1,
+                    text: text.to_string(),
+                    source: String::new(),
+                    copied: now,
+                    pinned: false,
+                }
+            }
+        };
+        entry.source = source.to_string();
+        entry.copied = now;
+        self.entries.insert(0, entry);
+        self.prune();
+        true
+    }
+
+    /// Returns the entry with the given identifier
+    pub fn get(&self, id: u64) -> Option<&ClipEntry> {
+        self.entries.iter().find(|e| e.id == id)
+    }
+
+    /// Pins or unpins an entry, returning false if there is no such entry
+    pub fn set_pinned(&mut self, id: u64, pinned: bool) -> bool {
+        match self.entries.iter_mut().find(|e| e.id == id) {
+            Some(entry) => {
+                entry.pinned = pinned;
+                self.prune();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forgets an entry, returning false if there is no such entry
+    pub fn remove(&mut self, id: u64) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|e| e.id != id);
+        self.entries.len() != before
+    }
+
+    /// Forgets every entry that is not pinned
+    pub fn clear(&mut self) {
+        self.entries.retain(|e| e.pinned);
+    }
+
+    /// Returns entries fuzzy-matching `text`, best first
+    ///
+    /// Pinned entries come first, then the best matches; equal matches keep
+    /// their order, most recent first. An empty `text` returns every entry.
+    pub fn search(&self, text: &str, matcher: &SkimMatcherV2) -> Vec<&ClipEntry> {
+        let mut found: Vec<(i64, &ClipEntry)> = self
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                let score = if text.is_empty() {
+                    0
+                } else {
+                    matcher.fuzzy_match(&entry.text, text)?
+                };
+                Some((score, entry))
+            })
+            .collect();
+        found.sort_by_key(|(score, entry)| (!entry.pinned, std::cmp::Reverse(*score)));
+        found.into_iter().map(|(_, entry)| entry).collect()
+    }
+
+    pub fn entries(&self) -> &[ClipEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drops the oldest unpinned entries beyond the size limit
+    fn prune(&mut self) {
+        let mut unpinned = 0;
+        let max_entries = self.max_entries;
+        self.entries.retain(|entry| {
+            if entry.pinned {
+                return true;
+            }
+            unpinned += 1;
+            unpinned <= max_entries
+        });
+    }
+}
+
+/// Escapes backslashes, tabs and line breaks so text fits on one line
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reverses `escape`
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Returns the first non-blank line of `text`, shortened for display
+///
+/// Whitespace runs are collapsed, and a note is added when more lines
+/// follow.
+pub fn preview(text: &str) -> String {
+    let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+    let first = lines.next().unwrap_or_default();
+    let mut preview = first.split_whitespace().collect::<Vec<_>>().join(" ");
+    if preview.chars().count() > MAX_PREVIEW_CHARS {
+        preview = preview.chars().take(MAX_PREVIEW_CHARS - 1).collect();
+        preview.push('…');
+    }
+
+    let more = lines.count();
+    match more {
+        0 => preview,
+        1 => format!("{} (+1 line)", preview),
+        n => format!("{} (+{} lines)", preview, n),
+    }
+}
+
+/// Describes how long ago `then` was, e.g. `5 min ago`
+pub fn format_age(then: u64, now: u64) -> String {
+    let elapsed = now.saturating_sub(then);
+    match elapsed {
+        0..=59 => "just now".to_string(),
+        60..=3_599 => format!("{} min ago", elapsed / 60),
+        3_600..=86_399 => format!("{} h ago", elapsed / 3_600),
+        86_400..=172_799 => "yesterday".to_string(),
+        _ => format!("{} days ago", elapsed / 86_400),
+    }
+}
+
+/// Searches and pastes back remembered copies
+pub struct ClipboardProvider {
+    config: ClipboardConfig,
+
+    history: Mutex<ClipHistory>,
+
+    /// Where `history` is saved; None to keep it in memory only
+    history_path: Option<PathBuf>,
+
+    matcher: SkimMatcherV2,
+}
+
+impl ClipboardProvider {
+    /// Creates the provider with the saved clipboard history
+    pub fn new(config: ClipboardConfig) -> Self {
+        let history_path = ClipHistory::default_path();
+        let history = match &history_path {
+            Some(path) => ClipHistory::load_or_default(path, config.max_entries),
+            None => ClipHistory::new(config.max_entries),
+        };
+        Self::with_history(config, history, history_path)
+    }
+
+    /// Creates the provider over an already loaded clipboard history
+    pub fn with_history(
+        config: ClipboardConfig,
+        history: ClipHistory,
+        history_path: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            config,
+            history: Mutex::new(history),
+            history_path,
+            matcher: SkimMatcherV2::default().smart_case(),
+        }
+    }
+
+    /// Records a clipboard change at time `now`
+    ///
+    /// Returns false if it was left out: private copies, copies from
+    /// excluded programs, blank text and text over the size limit are not
+    /// recorded.
+    pub fn record(&self, change: ClipboardChange, now: u64) -> bool {
+        if !self.config.enabled {
+            return false;
+        }
+        if change.private {
+            log::debug!("Not recording a copy marked as private");
+            return false;
+        }
+        let source = change.source.unwrap_or_default();
+        let excluded = self
+            .config
+            .excluded_sources
+            .iter()
+            .any(|s| s.eq_ignore_ascii_case(&source));
+        if excluded {
+            log::debug!("Not recording a copy from {}", source);
+            return false;
+        }
+        let Some(text) = change.text else {
+            return false;
+        };
+        if text.len() > self.config.max_entry_bytes {
+            return false;
+        }
+
+        self.update(|history| history.record(&text, &source, now))
+    }
+
+    /// Applies `change` to the history, saving it if anything changed
+    fn update(&self, change: impl FnOnce(&mut ClipHistory) -> bool) -> bool {
+        let mut history = self.history.lock().unwrap();
+        let changed = change(&mut history);
+        if changed {
+            if let Some(path) = &self.history_path {
+                if let Err(e) = history.save(path) {
+                    log::warn!("Failed to save clipboard history: {}", e);
+                }
+            }
+        }
+        changed
+    }
+
+    /// Returns the entry a search result stands for
+    fn entry(&self, result: &SearchResult) -> Option<ClipEntry> {
+        let id = result
+            .path
+            .to_str()?
+            .strip_prefix(ENTRY_SCHEME)?
+            .parse()
+            .ok()?;
+        self.history.lock().unwrap().get(id).cloned()
+    }
+}
+
+impl Provider for ClipboardProvider {
+    fn id(&self) -> &'static str {
+        "clipboard"
+    }
+
+    fn title(&self) -> &str {
+        "Clipboard"
+    }
+
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    fn trigger(&self) -> Trigger {
+        Trigger::Prefix(self.config.prefix.clone())
+    }
+
+    /// Clipboard entries are searched for the text as typed
+    fn wants_filters(&self) -> bool {
+        false
+    }
+
+    fn query(
+        &self,
+        query: &ProviderQuery,
+        _cancel: &CancelToken,
+        emit: &(dyn Fn(Vec<Section>) + Sync),
+    ) -> bool {
+        let text = query.text.trim();
+        let now = query::now_secs();
+        let history = self.history.lock().unwrap();
+
+        let results: Vec<SearchResult> = history
+            .search(text, &self.matcher)
+            .into_iter()
+            .take(MAX_RESULTS)
+            .map(|entry| {
+                let mut description = match entry.source.as_str() {
+                    "" => format_age(entry.copied, now),
+                    source => format!("{} · {}", source, format_age(entry.copied, now)),
+                };
+                if entry.pinned {
+                    description = format!("Pinned · {}", description);
+                }
+                SearchResult {
+                    name: preview(&entry.text),
+                    path: PathBuf::from(format!("{}{}", ENTRY_SCHEME, entry.id)),
+                    result_type: ResultType::Text,
+                    score: 0,
+                    description,
+                    provider: self.id(),
+                    shortcut: None,
+                }
+            })
+            .collect();
+        drop(history);
+
+        emit(vec![self.section(results)]);
+        true
+    }
+
+    /// Pastes the entry back by default
+    fn actions(&self, result: &SearchResult) -> Vec<Action> {
+        let Some(entry) = self.entry(result) else {
+            return Vec::new();
+        };
+        vec![
+            Action::Paste(entry.text.clone()),
+            Action::Copy(entry.text),
+            if entry.pinned {
+                Action::Unpin
+            } else {
+                Action::Pin
+            },
+            Action::Remove,
+            Action::ClearHistory,
+        ]
+    }
+
+    fn run_action(&self, result: &SearchResult, action: &Action) -> Result<()> {
+        let id = self.entry(result).map(|e| e.id);
+        match (action, id) {
+            (Action::Pin, Some(id)) => {
+                self.update(|history| history.set_pinned(id, true));
+            }
+            (Action::Unpin, Some(id)) => {
+                self.update(|history| history.set_pinned(id, false));
+            }
+            (Action::Remove, Some(id)) => {
+                self.update(|history| history.remove(id));
+            }
+            (Action::ClearHistory, _) => {
+                self.update(|history| {
+                    history.clear();
+                    true
+                });
+            }
+            _ => action.run()?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::run_provider;
+
+    const NOW: u64 = 1_792_238_400;
+
+    #[test]
+    fn test_record_dedup_and_pinning() {
+        let mut history = ClipHistory::new(3);
+        assert!(history.record("first", "notepad.exe", NOW));
+        assert!(history.record("second", "Code.exe", NOW + 1));
+        assert!(!history.record("  \n", "Code.exe", NOW + 2));
+        assert!(history.record("first", "msedge.exe", NOW + 3));
+
+        let texts: Vec<&str> = history.entries().iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(texts, ["first", "second"]);
+        assert_eq!(history.entries()[0].source, "msedge.exe");
+        assert_eq!(history.entries()[0].id, 1);
+
+        // Pinned entries survive the size limit and clearing
+        assert!(history.set_pinned(2, true));
+        for (i, text) in ["a", "b", "c", "d"].iter().enumerate() {
+            history.record(text, "", NOW + 10 + i as u64);
+        }
+        let texts: Vec<&str> = history.entries().iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(texts, ["d", "c", "b", "second"]);
+
+        let matcher = SkimMatcherV2::default().smart_case();
+        let found: Vec<&str> = history
+            .search("", &matcher)
+            .iter()
+            .map(|e| e.text.as_str())
+            .collect();
+        assert_eq!(found, ["second", "d", "c", "b"]);
+
+        history.clear();
+        assert_eq!(history.len(), 1);
+        assert!(history.remove(2));
+        assert!(history.is_empty());
+        assert!(!history.set_pinned(2, true));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let mut history = ClipHistory::new(10);
+        history.record("line one\n\tindented \\ backslash\r\n", "Code.exe", NOW);
+        history.record("plain", "notepad.exe", NOW + 5);
+        history.set_pinned(1, true);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(HISTORY_FILE_NAME);
+        history.save(&path).unwrap();
+
+        let mut loaded = ClipHistory::load(&path, 10).unwrap();
+        assert_eq!(loaded.entries(), history.entries());
+        loaded.record("new", "", NOW + 10);
+        assert_eq!(loaded.entries()[0].id, 3);
+
+        std::fs::write(&path, "not a history\n").unwrap();
+        assert!(ClipHistory::load(&path, 10).is_err());
+        assert!(ClipHistory::load_or_default(&path, 10).is_empty());
+    }
+
+    #[test]
+    fn test_preview_and_age() {
+        assert_eq!(
+            preview("\n  hello   world \nsecond\nthird"),
+            "hello world (+2 lines)"
+        );
+        assert_eq!(preview("one\n\ntwo"), "one (+1 line)");
+        let long = "x".repeat(150);
+        assert_eq!(preview(&long).chars().count(), MAX_PREVIEW_CHARS);
+
+        assert_eq!(format_age(NOW - 5, NOW), "just now");
+        assert_eq!(format_age(NOW - 300, NOW), "5 min ago");
+        assert_eq!(format_age(NOW - 7_200, NOW), "2 h ago");
+        assert_eq!(format_age(NOW - 90_000, NOW), "yesterday");
+        assert_eq!(format_age(NOW - 864_000, NOW), "10 days ago");
+    }
+
+    fn change(text: &str, source: &str, private: bool) -> ClipboardChange {
+        ClipboardChange {
+            text: Some(text.to_string()),
+            source: Some(source.to_string()),
+            private,
+        }
+    }
+
+    #[test]
+    fn test_provider() {
+        let config = ClipboardConfig {
+            max_entry_bytes: 20,
+            ..ClipboardConfig::default()
+        };
+        let provider = ClipboardProvider::with_history(config, ClipHistory::new(10), None);
+
+        assert!(!provider.record(change("hunter2", "chrome.exe", true), NOW));
+        assert!(!provider.record(change("s3cret", "keepassxc.exe", false), NOW));
+        assert!(!provider.record(change(&"x".repeat(21), "Code.exe", false), NOW));
+        assert!(provider.record(change("invoice 4711", "OUTLOOK.EXE", false), NOW));
+        assert!(provider.record(change("cargo build", "WindowsTerminal.exe", false), NOW));
+
+        let results = run_provider(&provider, "clip invoice");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "invoice 4711");
+        assert!(results[0].description.starts_with("OUTLOOK.EXE · "));
+
+        let result = &results[0];
+        let actions = provider.actions(result);
+        assert_eq!(actions[0], Action::Paste("invoice 4711".to_string()));
+        assert_eq!(actions[2], Action::Pin);
+
+        provider.run_action(result, &Action::Pin).unwrap();
+        assert_eq!(provider.actions(result)[2], Action::Unpin);
+        provider.run_action(result, &Action::ClearHistory).unwrap();
+        assert_eq!(provider.history.lock().unwrap().len(), 1);
+        provider.run_action(result, &Action::Remove).unwrap();
+        assert!(provider.actions(result).is_empty());
+    }
+}
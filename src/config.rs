@@ -49,6 +49,295 @@ pub struct SearchConfig {
 
     /// Maximum depth for directory traversal
     pub max_depth: usize,
+
+    /// Ranking boost from launch history
+    pub frecency: FrecencyConfig,
+
+    /// Which files and folders to leave out of the index
+    pub ignore: IgnoreConfig,
+
+    /// Searching inside files
+    pub content: ContentSearchConfig,
+
+    /// Persistent index of the words inside files, used by content search
+    pub full_text: FullTextConfig,
+
+    /// Web search shortcuts and URL detection
+    pub web: WebSearchConfig,
+
+    /// Running shell commands from the search box
+    pub shell: ShellConfig,
+
+    /// Clipboard history
+    pub clipboard: ClipboardConfig,
+
+    /// Canned text snippets
+    pub snippets: SnippetsConfig,
+
+    /// Browser bookmarks
+    pub bookmarks: BookmarksConfig,
+
+    /// Pages visited in the browser
+    pub browser_history: BrowserHistoryConfig,
+
+    /// Documents and folders opened recently
+    pub recent: RecentConfig,
+}
+
+/// Full-text content search configuration
+///
+/// Queries starting with `prefix` search the text inside indexed files
+/// instead of their names, e.g. `/quarterly report ext:docx`.
+#[derive(Debug, Clone)]
+pub struct ContentSearchConfig {
+    /// Whether content search is available at all
+    pub enabled: bool,
+
+    /// Query prefix that switches to content search
+    pub prefix: String,
+
+    /// Files larger than this many bytes are not searched
+    pub max_file_size: u64,
+
+    /// Maximum number of matching files to show
+    pub max_results: usize,
+}
+
+/// Web search configuration
+///
+/// A query starting with a shortcut's keyword, such as `gh tokio`, offers
+/// to search that site for the rest of the query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebSearchConfig {
+    /// Whether keyword shortcuts are available
+    pub enabled: bool,
+
+    /// Keyword shortcuts, matched case-insensitively
+    pub shortcuts: Vec<WebShortcut>,
+
+    /// Whether URLs and domain-like input such as `docs.rs/serde` are
+    /// offered to be opened
+    pub detect_urls: bool,
+}
+
+/// A keyword that searches a site
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebShortcut {
+    /// First word of the query, e.g. `g`
+    pub keyword: String,
+
+    /// Site name shown in "Search Google for ..."
+    pub name: String,
+
+    /// URL template; `{query}` is replaced by the URL-encoded search text
+    pub url: String,
+}
+
+impl WebShortcut {
+    pub fn new(keyword: &str, name: &str, url: &str) -> Self {
+        Self {
+            keyword: keyword.to_string(),
+            name: name.to_string(),
+            url: url.to_string(),
+        }
+    }
+}
+
+/// Shell command configuration
+///
+/// Queries starting with `prefix` run the rest of the query as a command,
+/// e.g. `>ipconfig /all`, either in a new terminal window or with the
+/// output shown in the search window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShellConfig {
+    /// Whether shell commands can be run at all
+    pub enabled: bool,
+
+    /// Query prefix that switches to shell commands
+    pub prefix: String,
+
+    /// Shell that runs the commands
+    pub shell: ShellKind,
+
+    /// Seconds after which a command whose output is shown is killed
+    /// (0 lets it run until it exits)
+    pub timeout_secs: u64,
+
+    /// Most output lines kept for a command; older lines are dropped
+    pub max_output_lines: usize,
+
+    /// Number of past commands remembered for completion
+    pub max_history: usize,
+}
+
+/// Clipboard history configuration
+///
+/// Text copied anywhere is remembered, and queries starting with `prefix`
+/// search it, e.g. `clip invoice number`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardConfig {
+    /// Whether copied text is recorded at all
+    pub enabled: bool,
+
+    /// Query prefix that switches to clipboard history
+    pub prefix: String,
+
+    /// Number of entries kept; pinned entries are never dropped
+    pub max_entries: usize,
+
+    /// Copied text longer than this many bytes is not recorded
+    pub max_entry_bytes: usize,
+
+    /// Executable names, such as `KeePass.exe`, whose copies are never
+    /// recorded, for apps that do not mark their copies as private
+    pub excluded_sources: Vec<String>,
+}
+
+/// Snippets configuration
+///
+/// Queries starting with `prefix` search the snippets file, e.g.
+/// `snip refund`, and copy the chosen snippet with its placeholders
+/// filled in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnippetsConfig {
+    /// Whether snippets are available at all
+    pub enabled: bool,
+
+    /// Query prefix that switches to snippets
+    pub prefix: String,
+
+    /// Markdown file the snippets are kept in; None for `snippets.md`
+    /// next to the config file
+    pub file: Option<PathBuf>,
+}
+
+/// Browser bookmarks configuration
+///
+/// Bookmarks of Chrome, Edge, Brave, Vivaldi and Firefox are searched
+/// alongside applications and files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookmarksConfig {
+    /// Whether bookmarks are searched
+    pub enabled: bool,
+
+    /// Maximum number of bookmarks to show
+    pub max_results: usize,
+}
+
+/// Browser history configuration
+///
+/// Off by default. When enabled, pages visited in Chrome, Edge, Brave,
+/// Vivaldi and Firefox are searched alongside applications and files,
+/// except in profiles set never to remember history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrowserHistoryConfig {
+    /// Whether browser history is searched
+    pub enabled: bool,
+
+    /// Maximum number of pages to show
+    pub max_results: usize,
+
+    /// Pages not visited for this many days are left out
+    pub max_age_days: u64,
+
+    /// Sites never shown, matched against the host and its parent
+    /// domains, e.g. `bank.example` also hides `www.bank.example`
+    pub excluded_domains: Vec<String>,
+}
+
+/// Recent items configuration
+///
+/// Documents and folders from the Windows Recent Items folder are
+/// searched alongside applications and files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentConfig {
+    /// Whether recent items are searched
+    pub enabled: bool,
+
+    /// Maximum number of recent items to show
+    pub max_results: usize,
+}
+
+/// A shell that commands are run with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShellKind {
+    /// The Windows command prompt, `cmd.exe`
+    Cmd,
+
+    /// Windows PowerShell, `powershell.exe`
+    PowerShell,
+
+    /// Any other program, given `args` followed by the command,
+    /// e.g. `bash.exe` with `["-c"]`
+    Custom { program: PathBuf, args: Vec<String> },
+}
+
+/// Full-text index configuration
+///
+/// When enabled, content searches look words up in an inverted index
+/// kept next to the file index instead of reading every file, so only
+/// files with one of `extensions` can be found by their contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FullTextConfig {
+    /// Whether the full-text index is built and used
+    pub enabled: bool,
+
+    /// Lowercase extensions (without the dot) of files whose text is indexed
+    pub extensions: Vec<String>,
+
+    /// Files larger than this many bytes are not indexed
+    pub max_file_size: u64,
+
+    /// Indexing stops once this many bytes of text have been indexed
+    pub max_indexed_bytes: u64,
+}
+
+/// Ignore rule configuration
+///
+/// Patterns use gitignore syntax (see `ignore.rs`). For example, to show
+/// the `Release` folders under `D:\Builds` that the defaults hide, add an
+/// override for `D:\Builds` with the pattern `!Release/`.
+#[derive(Debug, Clone)]
+pub struct IgnoreConfig {
+    /// Patterns for files and folders to leave out below every search root
+    pub patterns: Vec<String>,
+
+    /// Patterns for top-level folders of data drives that are not added as
+    /// search roots of their own
+    pub drive_root_patterns: Vec<String>,
+
+    /// Extra patterns for particular directories, applied after `patterns`
+    pub root_overrides: Vec<RootIgnoreOverride>,
+
+    /// Whether `.gitignore`, `.ignore` and `.rustleignore` files found
+    /// while walking are honoured
+    pub use_ignore_files: bool,
+}
+
+/// Ignore patterns that only apply inside one directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootIgnoreOverride {
+    /// Directory the patterns are relative to
+    pub root: PathBuf,
+
+    /// Patterns checked after the global ones, so `!name` re-includes
+    pub patterns: Vec<String>,
+}
+
+/// Launch history (frecency) ranking configuration
+#[derive(Debug, Clone)]
+pub struct FrecencyConfig {
+    /// Whether launch history affects ranking at all
+    pub enabled: bool,
+
+    /// How strongly launch history boosts a result's score
+    pub weight: f64,
+
+    /// Days after which a launch counts for half as much
+    pub half_life_days: f64,
+
+    /// Maximum number of launched paths to remember
+    pub max_entries: usize,
 }
 
 /// UI appearance configuration
@@ -124,6 +413,232 @@ impl Default for SearchConfig {
             search_paths,
             file_extensions: Vec::new(), // All extensions
             max_depth: 5,
+            frecency: FrecencyConfig::default(),
+            ignore: IgnoreConfig::default(),
+            content: ContentSearchConfig::default(),
+            full_text: FullTextConfig::default(),
+            web: WebSearchConfig::default(),
+            shell: ShellConfig::default(),
+            clipboard: ClipboardConfig::default(),
+            snippets: SnippetsConfig::default(),
+            bookmarks: BookmarksConfig::default(),
+            browser_history: BrowserHistoryConfig::default(),
+            recent: RecentConfig::default(),
+        }
+    }
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            prefix: "clip ".to_string(),
+            max_entries: 200,
+            max_entry_bytes: 64 * 1024,
+            excluded_sources: [
+                "KeePass.exe",
+                "KeePassXC.exe",
+                "1Password.exe",
+                "Bitwarden.exe",
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+        }
+    }
+}
+
+impl Default for SnippetsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            prefix: "snip ".to_string(),
+            file: None,
+        }
+    }
+}
+
+impl Default for BookmarksConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_results: 5,
+        }
+    }
+}
+
+impl Default for BrowserHistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_results: 5,
+            max_age_days: 90,
+            excluded_domains: Vec::new(),
+        }
+    }
+}
+
+impl Default for RecentConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_results: 5,
+        }
+    }
+}
+
+impl Default for ShellConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            prefix: ">".to_string(),
+            shell: ShellKind::Cmd,
+            timeout_secs: 30,
+            max_output_lines: 500,
+            max_history: 200,
+        }
+    }
+}
+
+impl Default for WebSearchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            shortcuts: vec![
+                WebShortcut::new("g", "Google", "https://www.google.com/search?q={query}"),
+                WebShortcut::new("gh", "GitHub", "https://github.com/search?q={query}"),
+                WebShortcut::new("crates", "crates.io", "https://crates.io/search?q={query}"),
+                WebShortcut::new("ddg", "DuckDuckGo", "https://duckduckgo.com/?q={query}"),
+                WebShortcut::new(
+                    "wiki",
+                    "Wikipedia",
+                    "https://en.wikipedia.org/w/index.php?search={query}",
+                ),
+                WebShortcut::new(
+                    "yt",
+                    "YouTube",
+                    "https://www.youtube.com/results?search_query={query}",
+                ),
+            ],
+            detect_urls: true,
+        }
+    }
+}
+
+impl Default for FullTextConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            extensions: DEFAULT_FULL_TEXT_EXTENSIONS
+                .iter()
+                .map(|e| e.to_string())
+                .collect(),
+            max_file_size: 1024 * 1024,
+            max_indexed_bytes: 512 * 1024 * 1024,
+        }
+    }
+}
+
+/// Document and source code formats indexed by default
+const DEFAULT_FULL_TEXT_EXTENSIONS: &[&str] = &[
+    // Documents and notes
+    "txt", "md", "markdown", "rst", "org", "tex", "csv", "log", "pdf", "docx", "xlsx", "pptx",
+    // Configuration and data
+    "json", "toml", "yaml", "yml", "xml", "ini", "cfg", "conf", // Web
+    "html", "htm", "css", "scss", // Source code
+    "rs", "py", "js", "jsx", "ts", "tsx", "c", "h", "cpp", "hpp", "cc", "cs", "java", "kt", "go",
+    "rb", "php", "swift", "lua", "sql", "sh", "ps1", "bat", "cmd",
+];
+
+impl Default for ContentSearchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            prefix: "/".to_string(),
+            max_file_size: 10 * 1024 * 1024,
+            max_results: 20,
+        }
+    }
+}
+
+/// Files and folders left out of the index unless configured otherwise
+const DEFAULT_IGNORE_PATTERNS: &[&str] = &[
+    // Hidden and system entries
+    ".*",
+    "$*",
+    // System and build directories
+    "node_modules",
+    "target",
+    "__pycache__",
+    "appdata",
+    "cache",
+    "temp",
+    "tmp",
+    "system volume information",
+    "windows",
+    "programdata",
+    "recovery",
+    "boot",
+    "perflogs",
+    "msocache",
+    "config.msi",
+    "intel",
+    "amd",
+    "nvidia",
+    "bin",
+    "obj",
+    "debug",
+    "release",
+    "packages",
+    "wpsystem",
+    "windowsapps",
+    "xboxgames",
+];
+
+/// Top-level folders of a drive that are not search roots of their own
+const DEFAULT_DRIVE_ROOT_PATTERNS: &[&str] = &[
+    "$recycle.bin",
+    "system volume information",
+    "recovery",
+    "boot",
+    "perflogs",
+    "msocache",
+    "config.msi",
+    "intel",
+    "amd",
+    "nvidia",
+    "windows",
+    "program files",
+    "program files (x86)",
+    "programdata",
+    "users",
+    "windows.old",
+];
+
+impl Default for IgnoreConfig {
+    fn default() -> Self {
+        Self {
+            patterns: DEFAULT_IGNORE_PATTERNS
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+            drive_root_patterns: DEFAULT_DRIVE_ROOT_PATTERNS
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+            root_overrides: Vec::new(),
+            use_ignore_files: true,
+        }
+    }
+}
+
+impl Default for FrecencyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            weight: 250.0,
+            half_life_days: 14.0,
+            max_entries: 2000,
         }
     }
 }
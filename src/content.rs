@@ -0,0 +1,331 @@
+//! Full-text content search for Rustle
+//!
+//! This module looks for a phrase inside files on disk. Files are read up
+//! to a size limit, checked for binary content and decoded as UTF-8, or as
+//! UTF-16 when a byte order mark says so. Matching is case-insensitive and
+//! reports the first matching line, which the UI shows under the file name.
+//! PDF and Office documents are turned into text by the `extract` module
+//! first.
+
+#![allow(dead_code)]
+
+use crate::extract;
+use crate::search::CancelToken;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Bytes inspected when deciding whether a file is binary
+const BINARY_SNIFF_LEN: u64 = 8 * 1024;
+
+/// Longest line excerpt shown as a result description, in characters
+const MAX_EXCERPT_CHARS: usize = 120;
+
+/// Characters kept before the match when a long line is shortened
+const EXCERPT_LEAD_CHARS: usize = 30;
+
+/// How many lines are scanned between cancellation checks
+const LINES_PER_CANCEL_CHECK: usize = 4096;
+
+/// Extensions of formats that never hold plain text
+const BINARY_EXTENSIONS: &[&str] = &[
+    "exe", "dll", "sys", "msi", "lib", "obj", "pdb", "bin", "iso", "zip", "7z", "rar", "gz", "png",
+    "jpg", "jpeg", "gif", "bmp", "ico", "webp", "mp3", "wav", "flac", "mp4", "mkv", "avi", "mov",
+];
+
+/// Where a phrase was found inside a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentMatch {
+    /// 1-based number of the first matching line
+    pub line_number: usize,
+
+    /// The first matching line, trimmed and shortened around the match
+    pub excerpt: String,
+
+    /// Number of lines containing the phrase
+    pub match_count: usize,
+}
+
+/// Checks whether a file could hold searchable text, judging by its extension
+pub fn is_searchable(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    !BINARY_EXTENSIONS.contains(&extension.as_str())
+}
+
+/// Looks for `phrase_lower` (already lowercased) inside a file
+///
+/// Returns None if the file does not contain the phrase, cannot be read
+/// as text, or the search was cancelled part way through.
+pub fn search_file(
+    path: &Path,
+    phrase_lower: &str,
+    max_size: u64,
+    cancel: &CancelToken,
+) -> Option<ContentMatch> {
+    let text = read_document(path, max_size)?;
+    find_phrase(&text, phrase_lower, cancel)
+}
+
+/// Reads the text of a file, extracting it from documents that need it
+///
+/// PDF and Office files go through their extractor; everything else is
+/// read with [`read_text`].
+pub fn read_document(path: &Path, max_size: u64) -> Option<String> {
+    if extract::has_extractor(path) {
+        extract::extract_file(path, max_size)
+    } else {
+        read_text(path, max_size)
+    }
+}
+
+/// Reads a file as text
+///
+/// Returns None if the file is larger than `max_size`, cannot be read, or
+/// looks binary. Only the first few kilobytes are read before deciding,
+/// so large binaries are rejected cheaply.
+pub fn read_text(path: &Path, max_size: u64) -> Option<String> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    if len > max_size {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(len as usize);
+    (&mut file)
+        .take(BINARY_SNIFF_LEN)
+        .read_to_end(&mut bytes)
+        .ok()?;
+    if detect_bom(&bytes).is_none() && is_binary(&bytes) {
+        return None;
+    }
+
+    file.take(max_size.saturating_sub(bytes.len() as u64))
+        .read_to_end(&mut bytes)
+        .ok()?;
+    decode(&bytes)
+}
+
+/// Text encodings recognised from a byte order mark
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bom {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Bom {
+    fn len(self) -> usize {
+        match self {
+            Bom::Utf8 => 3,
+            Bom::Utf16Le | Bom::Utf16Be => 2,
+        }
+    }
+}
+
+/// Returns the byte order mark at the start of `bytes`, if any
+fn detect_bom(bytes: &[u8]) -> Option<Bom> {
+    match bytes {
+        [0xEF, 0xBB, 0xBF, ..] => Some(Bom::Utf8),
+        [0xFF, 0xFE, ..] => Some(Bom::Utf16Le),
+        [0xFE, 0xFF, ..] => Some(Bom::Utf16Be),
+        _ => None,
+    }
+}
+
+/// Checks whether the start of a file looks binary
+///
+/// Text without a byte order mark never contains NUL bytes, while nearly
+/// every binary format does within its first few kilobytes.
+fn is_binary(bytes: &[u8]) -> bool {
+    bytes
+        .iter()
+        .take(BINARY_SNIFF_LEN as usize)
+        .any(|&b| b == 0)
+}
+
+/// Decodes file contents, honouring a UTF-8 or UTF-16 byte order mark
+///
+/// Files without a mark are read as UTF-8, replacing invalid sequences.
+/// Returns None for binary contents.
+pub fn decode(bytes: &[u8]) -> Option<String> {
+    match detect_bom(bytes) {
+        Some(bom @ Bom::Utf8) => Some(String::from_utf8_lossy(&bytes[bom.len()..]).into_owned()),
+        Some(bom @ Bom::Utf16Le) => Some(decode_utf16(&bytes[bom.len()..], u16::from_le_bytes)),
+        Some(bom @ Bom::Utf16Be) => Some(decode_utf16(&bytes[bom.len()..], u16::from_be_bytes)),
+        None if is_binary(bytes) => None,
+        None => Some(String::from_utf8_lossy(bytes).into_owned()),
+    }
+}
+
+/// Decodes UTF-16 code units, replacing unpaired surrogates
+fn decode_utf16(bytes: &[u8], to_unit: fn([u8; 2]) -> u16) -> String {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| to_unit([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// Finds the lines of `text` containing `phrase_lower`
+///
+/// Returns None if no line matches or `cancel` was cancelled.
+pub fn find_phrase(text: &str, phrase_lower: &str, cancel: &CancelToken) -> Option<ContentMatch> {
+    if phrase_lower.is_empty() {
+        return None;
+    }
+
+    let mut first: Option<ContentMatch> = None;
+    for (i, line) in text.lines().enumerate() {
+        if i % LINES_PER_CANCEL_CHECK == 0 && cancel.is_cancelled() {
+            return None;
+        }
+
+        let line_lower = line.to_lowercase();
+        let Some(pos) = line_lower.find(phrase_lower) else {
+            continue;
+        };
+
+        match &mut first {
+            Some(found) => found.match_count += 1,
+            None => {
+                first = Some(ContentMatch {
+                    line_number: i + 1,
+                    excerpt: excerpt(line, line_lower[..pos].chars().count()),
+                    match_count: 1,
+                })
+            }
+        }
+    }
+    first
+}
+
+/// Returns an excerpt of line `line_number` (1-based) of a file
+///
+/// The excerpt is centred on the first occurrence of `needle_lower` when
+/// the line is long. Returns None if the file cannot be read as text or is
+/// shorter than that. Documents are extracted again, so the line numbers
+/// match those of `search_file` and the full-text index.
+pub fn line_excerpt(
+    path: &Path,
+    line_number: usize,
+    needle_lower: &str,
+    max_size: u64,
+) -> Option<String> {
+    let text = read_document(path, max_size)?;
+    let line = text.lines().nth(line_number.checked_sub(1)?)?;
+    let line_lower = line.to_lowercase();
+    let match_char = line_lower
+        .find(needle_lower)
+        .map_or(0, |pos| line_lower[..pos].chars().count());
+    Some(excerpt(line, match_char))
+}
+
+/// Shortens a line to fit a result description, keeping the match visible
+///
+/// `match_char` is the character offset of the match within the line.
+fn excerpt(line: &str, match_char: usize) -> String {
+    let chars: Vec<char> = line.trim_end().chars().collect();
+    let leading = chars.iter().take_while(|c| c.is_whitespace()).count();
+    let chars = &chars[leading..];
+    let match_char = match_char.saturating_sub(leading);
+
+    if chars.len() <= MAX_EXCERPT_CHARS {
+        return chars.iter().collect();
+    }
+
+    let start = match_char
+        .saturating_sub(EXCERPT_LEAD_CHARS)
+        .min(chars.len() - MAX_EXCERPT_CHARS);
+    let end = start + MAX_EXCERPT_CHARS;
+
+    let mut excerpt = String::new();
+    if start > 0 {
+        excerpt.push('…');
+    }
+    excerpt.extend(&chars[start..end]);
+    if end < chars.len() {
+        excerpt.push('…');
+    }
+    excerpt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_decode_boms() {
+        assert_eq!(decode(b"\xEF\xBB\xBFhello").unwrap(), "hello");
+        assert_eq!(decode(b"\xFF\xFEh\0i\0").unwrap(), "hi");
+        assert_eq!(decode(b"\xFE\xFF\0h\0i").unwrap(), "hi");
+        assert_eq!(decode("plain café".as_bytes()).unwrap(), "plain café");
+        assert!(decode(b"MZ\x90\0\x03\0\0\0").is_none());
+    }
+
+    #[test]
+    fn test_read_text_limits_and_binaries() {
+        let dir = tempfile::tempdir().unwrap();
+        let text = dir.path().join("notes.txt");
+        let binary = dir.path().join("blob.dat");
+        fs::write(&text, b"meeting notes").unwrap();
+        fs::write(&binary, b"\0\x01\x02meeting").unwrap();
+
+        assert_eq!(read_text(&text, 1024).unwrap(), "meeting notes");
+        assert!(read_text(&text, 4).is_none());
+        assert!(read_text(&binary, 1024).is_none());
+    }
+
+    #[test]
+    fn test_find_phrase_reports_first_line() {
+        let text = "Intro\n   The Quarterly Report is due\nquarterly report, again\n";
+        let found = find_phrase(text, "quarterly report", &CancelToken::never()).unwrap();
+
+        assert_eq!(found.line_number, 2);
+        assert_eq!(found.excerpt, "The Quarterly Report is due");
+        assert_eq!(found.match_count, 2);
+        assert!(find_phrase(text, "annual", &CancelToken::never()).is_none());
+    }
+
+    #[test]
+    fn test_excerpt_keeps_match_visible() {
+        let line = format!("{}needle{}", "a".repeat(200), "b".repeat(200));
+        let excerpt = excerpt(&line, 200);
+
+        assert!(excerpt.starts_with('…') && excerpt.ends_with('…'));
+        assert!(excerpt.contains("needle"));
+        assert_eq!(excerpt.chars().count(), MAX_EXCERPT_CHARS + 2);
+    }
+
+    #[test]
+    fn test_is_searchable() {
+        assert!(is_searchable(Path::new("notes.md")));
+        assert!(is_searchable(Path::new("Makefile")));
+        assert!(!is_searchable(Path::new("setup.EXE")));
+        assert!(is_searchable(Path::new("report.pdf")));
+    }
+
+    #[test]
+    fn test_search_file_extracts_documents() {
+        let dir = tempfile::tempdir().unwrap();
+        let docx = dir.path().join("report.docx");
+        fs::write(
+            &docx,
+            include_bytes!("../tests/fixtures/extract/sample.docx"),
+        )
+        .unwrap();
+
+        let found = search_file(&docx, "costs fell", 1 << 20, &CancelToken::never()).unwrap();
+        assert_eq!(found.line_number, 2);
+        assert_eq!(found.excerpt, "Revenue grew by 12% & costs fell");
+        assert_eq!(
+            line_excerpt(&docx, 2, "costs", 1 << 20).unwrap(),
+            found.excerpt
+        );
+    }
+}
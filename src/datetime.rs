@@ -0,0 +1,1084 @@
+//! Date and time answers for Rustle
+//!
+//! Answers queries such as `now in Tokyo`, `3pm PST to CET`,
+//! `today + 45 days`, `days until 2026-12-25` and `1700000000 unix`
+//! inline. Dates use the proleptic Gregorian calendar and time zones come
+//! from the rules embedded in `timezone`, so everything works offline.
+//!
+//! Adding days, weeks, months or years keeps the wall-clock time across
+//! daylight saving changes, while adding hours, minutes or seconds counts
+//! elapsed time.
+
+#![allow(dead_code)]
+
+use crate::error::{Result, RustleError};
+use crate::provider::{Action, Provider, ProviderQuery};
+use crate::query;
+use crate::search::{CancelToken, ResultType, SearchResult, Section};
+use crate::timezone::{local_zone, Zone};
+use std::path::PathBuf;
+
+/// Seconds in a day without a daylight saving change
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Words separating a time from the zone or format to show it in
+const CONNECTORS: &[&str] = &["in", "to", "into", "as", "->"];
+
+const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+/// Returns true for leap years
+pub fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+/// Returns the number of days in a month (1 to 12)
+pub fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Returns the days between 1970-01-01 and a date
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    // Counts from a year starting in March, so leap days come last
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Returns the year, month and day a number of days after 1970-01-01
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    } as u32;
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+/// Returns the day of the week, 0 being Sunday
+pub fn weekday(days: i64) -> u32 {
+    // 1970-01-01 was a Thursday
+    (days + 4).rem_euclid(7) as u32
+}
+
+/// Earliest and latest supported times, the years 1 to 9999
+fn check_range(utc: i64) -> Result<i64> {
+    let earliest = days_from_civil(1, 1, 1) * SECONDS_PER_DAY;
+    let latest = days_from_civil(10_000, 1, 1) * SECONDS_PER_DAY - 1;
+    if (earliest..=latest).contains(&utc) {
+        Ok(utc)
+    } else {
+        Err(RustleError::date_error("outside the years 1 to 9999"))
+    }
+}
+
+/// Splits a query into lowercase words, numbers, ISO dates and signs
+///
+/// Returns None if the query has characters no date query uses.
+fn tokenize(input: &str) -> Option<Vec<String>> {
+    let chars: Vec<char> = input.to_lowercase().chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c.is_whitespace() || c == ',' {
+            i += 1;
+            continue;
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == ':') {
+                i += 1;
+            }
+            // ISO dates, optionally followed by `T` and a time
+            let is_year = i - start == 4 && chars[start..i].iter().all(char::is_ascii_digit);
+            if is_year && chars.get(i) == Some(&'-') {
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '-') {
+                    i += 1;
+                }
+                tokens.push(chars[start..i].iter().collect());
+                if chars.get(i) == Some(&'t') && chars.get(i + 1).is_some_and(char::is_ascii_digit)
+                {
+                    i += 1;
+                }
+                continue;
+            }
+        } else if c.is_alphabetic() {
+            while i < chars.len()
+                && (chars[i].is_alphabetic() || matches!(chars[i], '/' | '_' | '.'))
+            {
+                i += 1;
+            }
+        } else if c == '-' && chars.get(i + 1) == Some(&'>') {
+            i += 2;
+        } else if matches!(c, '+' | '-' | '−') {
+            i += 1;
+            tokens.push(if c == '+' { "+" } else { "-" }.to_string());
+            continue;
+        } else {
+            return None;
+        }
+        let token: String = chars[start..i].iter().collect();
+        tokens.push(token.trim_end_matches('.').to_string());
+    }
+
+    Some(tokens)
+}
+
+/// An amount of time to add
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Seconds(i64),
+    Days(i64),
+    Months(i64),
+}
+
+impl Step {
+    /// Reads a unit such as `days` or `h` for `amount` of it
+    fn parse(amount: i64, unit: &str) -> Option<Step> {
+        let (step, scale): (fn(i64) -> Step, i64) = match unit {
+            "s" | "sec" | "secs" | "second" | "seconds" => (Step::Seconds, 1),
+            "min" | "mins" | "minute" | "minutes" => (Step::Seconds, 60),
+            "h" | "hr" | "hrs" | "hour" | "hours" => (Step::Seconds, 3600),
+            "d" | "day" | "days" => (Step::Days, 1),
+            "w" | "wk" | "wks" | "week" | "weeks" => (Step::Days, 7),
+            "mo" | "month" | "months" => (Step::Months, 1),
+            "y" | "yr" | "yrs" | "year" | "years" => (Step::Months, 12),
+            _ => return None,
+        };
+        amount.checked_mul(scale).map(step)
+    }
+
+    fn negated(self) -> Step {
+        match self {
+            Step::Seconds(n) => Step::Seconds(-n),
+            Step::Days(n) => Step::Days(-n),
+            Step::Months(n) => Step::Months(-n),
+        }
+    }
+}
+
+/// A calendar day
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Day {
+    /// Days after today, so `yesterday` is -1
+    Relative(i64),
+
+    /// A date; without a year the current one is meant
+    Date {
+        year: Option<i64>,
+        month: u32,
+        day: u32,
+    },
+}
+
+/// What a date expression starts from
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Base {
+    Now,
+
+    /// Seconds since the Unix epoch
+    Unix(i64),
+
+    /// A day and time of day in the expression's zone; without a day,
+    /// today is meant
+    Local {
+        day: Option<Day>,
+        time: Option<i64>,
+    },
+}
+
+/// A point in time, such as `tomorrow 3pm PST + 2 hours`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Expression {
+    base: Base,
+
+    /// Zone the base is written in, if not the local one
+    zone: Option<Zone>,
+
+    steps: Vec<Step>,
+}
+
+/// A point in time and how to show it
+struct Moment {
+    utc: i64,
+
+    /// Zone the expression was worked out in
+    zone: Zone,
+
+    /// False for dates without a time of day
+    with_time: bool,
+
+    /// True if seconds are worth showing
+    seconds: bool,
+}
+
+impl Expression {
+    /// Returns true if the expression names a day but no time of day
+    fn is_date_only(&self) -> bool {
+        matches!(self.base, Base::Local { time: None, .. })
+            && !self.steps.iter().any(|s| matches!(s, Step::Seconds(_)))
+    }
+
+    /// Returns true for a time of day alone, such as `3pm`
+    fn is_bare_time(&self) -> bool {
+        matches!(self.base, Base::Local { day: None, .. })
+            && self.zone.is_none()
+            && self.steps.is_empty()
+    }
+
+    /// Fills in the year of a date written without one, picking the
+    /// next occurrence if `forward` and the last one otherwise
+    fn resolve_year(&mut self, now: i64, zone: &Zone, forward: bool) {
+        let Base::Local {
+            day: Some(Day::Date { year, month, day }),
+            ..
+        } = &mut self.base
+        else {
+            return;
+        };
+        if year.is_some() {
+            return;
+        }
+
+        let today = zone.to_local(now).div_euclid(SECONDS_PER_DAY);
+        let (current, _, _) = civil_from_days(today);
+        let date = |year| days_from_civil(year, *month, (*day).min(days_in_month(year, *month)));
+        *year = Some(if forward && date(current) < today {
+            current + 1
+        } else if !forward && date(current) > today {
+            current - 1
+        } else {
+            current
+        });
+    }
+
+    /// Works out the expression, reading times without a zone in `zone`
+    fn evaluate(&self, now: i64, zone: &Zone) -> Result<Moment> {
+        let zone = self.zone.clone().unwrap_or_else(|| zone.clone());
+        let (mut utc, mut with_time, mut seconds) = match &self.base {
+            Base::Now => (now, true, false),
+            Base::Unix(timestamp) => (check_range(*timestamp)?, true, true),
+            Base::Local { day, time } => {
+                let today = zone.to_local(now).div_euclid(SECONDS_PER_DAY);
+                let days = match day {
+                    None => today,
+                    Some(Day::Relative(offset)) => today + offset,
+                    Some(Day::Date { year, month, day }) => {
+                        let year = year.unwrap_or_else(|| civil_from_days(today).0);
+                        if *day > days_in_month(year, *month) {
+                            return Err(RustleError::date_error(format!(
+                                "{} {} has no day {}",
+                                capitalize(MONTHS[*month as usize - 1]),
+                                year,
+                                day
+                            )));
+                        }
+                        days_from_civil(year, *month, *day)
+                    }
+                };
+                let local = days * SECONDS_PER_DAY + time.unwrap_or(0);
+                let seconds = time.is_some_and(|t| t % 60 != 0);
+                (check_range(zone.to_utc(local))?, time.is_some(), seconds)
+            }
+        };
+
+        for step in &self.steps {
+            let overflow = || RustleError::date_error("outside the years 1 to 9999");
+            utc = match *step {
+                Step::Seconds(n) => {
+                    with_time = true;
+                    seconds |= n % 60 != 0;
+                    utc.checked_add(n).ok_or_else(overflow)?
+                }
+                Step::Days(n) => {
+                    let shift = n.checked_mul(SECONDS_PER_DAY).ok_or_else(overflow)?;
+                    let local = zone.to_local(utc).checked_add(shift).ok_or_else(overflow)?;
+                    zone.to_utc(check_range(local)?)
+                }
+                Step::Months(n) => {
+                    let local = zone.to_local(utc);
+                    let (year, month, day) = civil_from_days(local.div_euclid(SECONDS_PER_DAY));
+                    let months = (year * 12 + month as i64 - 1)
+                        .checked_add(n)
+                        .filter(|m| (12..120_000).contains(m))
+                        .ok_or_else(overflow)?;
+                    let (year, month) = (months.div_euclid(12), months.rem_euclid(12) as u32 + 1);
+                    let day = day.min(days_in_month(year, month));
+                    let time = local.rem_euclid(SECONDS_PER_DAY);
+                    zone.to_utc(days_from_civil(year, month, day) * SECONDS_PER_DAY + time)
+                }
+            };
+            check_range(utc)?;
+        }
+
+        Ok(Moment {
+            utc,
+            zone,
+            with_time,
+            seconds,
+        })
+    }
+}
+
+/// Reads date expressions from tokens
+struct Parser<'a> {
+    tokens: &'a [String],
+    pos: usize,
+
+    /// Zone meant by `local` or `here`
+    local: &'a Zone,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).map(String::as_str)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&'a str> {
+        self.tokens.get(self.pos + offset).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.peek()?;
+        self.pos += 1;
+        Some(token)
+    }
+
+    fn done(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    /// Reads a whole date expression
+    fn expression(&mut self) -> Option<Expression> {
+        // `3 days ago`, `2 weeks from tomorrow`, `1 hour before 5pm`
+        if let Some(leading) = self.leading_steps() {
+            let sign = match self.next()? {
+                "ago" => {
+                    return Some(Expression {
+                        base: Base::Now,
+                        zone: None,
+                        steps: leading.into_iter().map(Step::negated).collect(),
+                    });
+                }
+                "from" | "after" => 1,
+                "before" => -1,
+                _ => return None,
+            };
+            let mut expression = self.expression()?;
+            expression.steps.extend(leading.into_iter().map(|step| {
+                if sign < 0 {
+                    step.negated()
+                } else {
+                    step
+                }
+            }));
+            return Some(expression);
+        }
+
+        let base = self.base()?;
+        let zone = self.zone();
+        let mut steps = Vec::new();
+        while let Some(sign) = self.peek().filter(|t| *t == "+" || *t == "-") {
+            self.pos += 1;
+            let amount = number(self.next()?)?;
+            let step = Step::parse(amount, self.next()?)?;
+            steps.push(if sign == "-" { step.negated() } else { step });
+        }
+        Some(Expression { base, zone, steps })
+    }
+
+    /// Reads one or more `<number> <unit>` pairs, or nothing
+    fn leading_steps(&mut self) -> Option<Vec<Step>> {
+        let mut steps = Vec::new();
+        while let (Some(amount), Some(unit)) = (self.peek().and_then(number), self.peek_at(1)) {
+            let Some(step) = Step::parse(amount, unit) else {
+                break;
+            };
+            steps.push(step);
+            self.pos += 2;
+        }
+        (!steps.is_empty()).then_some(steps)
+    }
+
+    /// Reads `now`, a Unix timestamp, or a day and time in either order
+    fn base(&mut self) -> Option<Base> {
+        match (self.peek()?, self.peek_at(1)) {
+            ("now", _) => {
+                self.pos += 1;
+                return Some(Base::Now);
+            }
+            ("unix", Some(timestamp)) | (timestamp, Some("unix")) => {
+                if let Some(timestamp) = number(timestamp) {
+                    self.pos += 2;
+                    return Some(Base::Unix(timestamp));
+                }
+            }
+            _ => {}
+        }
+
+        let mut day = None;
+        let mut time = None;
+        loop {
+            if self.peek() == Some("at") && time.is_none() {
+                self.pos += 1;
+            }
+            if day.is_none() {
+                if let Some(found) = self.day() {
+                    day = Some(found);
+                    continue;
+                }
+            }
+            if time.is_none() {
+                if let Some(found) = self.time() {
+                    time = Some(found);
+                    continue;
+                }
+            }
+            break;
+        }
+
+        if day.is_none() && time.is_none() {
+            return None;
+        }
+        Some(Base::Local { day, time })
+    }
+
+    /// Reads `today`, `2026-12-25`, `25 dec 2026` or `dec 25 2026`
+    fn day(&mut self) -> Option<Day> {
+        let token = self.peek()?;
+        let relative = match token {
+            "today" => Some(0),
+            "tomorrow" => Some(1),
+            "yesterday" => Some(-1),
+            _ => None,
+        };
+        if let Some(offset) = relative {
+            self.pos += 1;
+            return Some(Day::Relative(offset));
+        }
+
+        if let Some((year, month, day)) = iso_date(token) {
+            self.pos += 1;
+            return Some(Day::Date {
+                year: Some(year),
+                month,
+                day,
+            });
+        }
+
+        let (month, day) = match (month(token), self.peek_at(1).and_then(day_of_month)) {
+            (Some(month), Some(day)) => (month, day),
+            _ => match (day_of_month(token), self.peek_at(1).and_then(month)) {
+                (Some(day), Some(month)) => (month, day),
+                _ => return None,
+            },
+        };
+        self.pos += 2;
+
+        // A four-digit number after the day is its year, unless it is a
+        // time such as `1230 pm`
+        let year = self
+            .peek()
+            .filter(|t| t.len() == 4 && !matches!(self.peek_at(1), Some("am" | "pm")))
+            .and_then(number);
+        if year.is_some() {
+            self.pos += 1;
+        }
+        Some(Day::Date { year, month, day })
+    }
+
+    /// Reads `15:30`, `3:30:15 pm`, `3pm`, `noon` or `midnight` as
+    /// seconds after midnight
+    fn time(&mut self) -> Option<i64> {
+        let token = self.peek()?;
+        match token {
+            "noon" | "midday" => {
+                self.pos += 1;
+                return Some(12 * 3600);
+            }
+            "midnight" => {
+                self.pos += 1;
+                return Some(0);
+            }
+            _ => {}
+        }
+
+        let parts: Vec<i64> = token
+            .split(':')
+            .map(|part| number(part).filter(|_| !part.is_empty()))
+            .collect::<Option<_>>()?;
+        let meridiem = match self.peek_at(1).map(|t| t.replace('.', "")) {
+            Some(m) if m == "am" => Some(0),
+            Some(m) if m == "pm" => Some(12),
+            _ => None,
+        };
+        if (parts.len() == 1 && meridiem.is_none()) || parts.len() > 3 {
+            return None;
+        }
+
+        let hour = parts[0];
+        let minute = parts.get(1).copied().unwrap_or(0);
+        let second = parts.get(2).copied().unwrap_or(0);
+        let hour = match meridiem {
+            Some(pm) if (1..=12).contains(&hour) => hour % 12 + pm,
+            Some(_) => return None,
+            None if hour < 24 => hour,
+            None => return None,
+        };
+        if minute >= 60 || second >= 60 {
+            return None;
+        }
+
+        self.pos += if meridiem.is_some() { 2 } else { 1 };
+        Some(hour * 3600 + minute * 60 + second)
+    }
+
+    /// Reads the longest run of words naming a zone, if any
+    fn zone(&mut self) -> Option<Zone> {
+        let end = self.tokens[self.pos..]
+            .iter()
+            .position(|t| t == "+" || t == "-")
+            .map_or(self.tokens.len(), |i| self.pos + i);
+
+        // `utc + 5:30` is an offset, `utc + 5 days` is not
+        let end = match (self.peek(), self.tokens.get(end + 2).map(String::as_str)) {
+            (Some("utc" | "gmt"), unit) if end == self.pos + 1 && end < self.tokens.len() => {
+                let is_step = unit.is_some_and(|u| Step::parse(1, u).is_some());
+                if is_step {
+                    end
+                } else {
+                    (end + 2).min(self.tokens.len())
+                }
+            }
+            _ => end,
+        };
+
+        for stop in (self.pos + 1..=end).rev() {
+            let name = self.tokens[self.pos..stop].join(" ");
+            let zone = match name.as_str() {
+                "local" | "here" => Some(self.local.clone()),
+                _ => Zone::find(&name),
+            };
+            if zone.is_some() {
+                self.pos = stop;
+                return zone;
+            }
+        }
+        None
+    }
+}
+
+/// Parses an unsigned whole number
+fn number(token: &str) -> Option<i64> {
+    if token.is_empty() || !token.chars().all(|c| c.is_ascii_digit()) || token.len() > 15 {
+        return None;
+    }
+    token.parse().ok()
+}
+
+fn day_of_month(token: &str) -> Option<u32> {
+    number(token)
+        .filter(|d| (1..=31).contains(d) && token.len() <= 2)
+        .map(|d| d as u32)
+}
+
+/// Parses a month name or an abbreviation of at least three letters
+fn month(token: &str) -> Option<u32> {
+    if token.len() < 3 {
+        return None;
+    }
+    MONTHS
+        .iter()
+        .position(|name| name.starts_with(token))
+        .map(|i| i as u32 + 1)
+}
+
+/// Parses `2026-12-25`
+fn iso_date(token: &str) -> Option<(i64, u32, u32)> {
+    let mut parts = token.split('-');
+    let year = parts.next().filter(|y| y.len() == 4).and_then(number)?;
+    let month = parts
+        .next()
+        .and_then(number)
+        .filter(|m| (1..=12).contains(m))?;
+    let day = parts
+        .next()
+        .and_then(number)
+        .filter(|d| (1..=31).contains(d))?;
+    parts
+        .next()
+        .is_none()
+        .then_some((year, month as u32, day as u32))
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|c| c.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// Where to show a point in time
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Target {
+    /// The local zone
+    Local,
+    Zone(Zone),
+
+    /// Seconds since the Unix epoch
+    Unix,
+}
+
+/// Units for counting the time until or since a date
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CountUnit {
+    Days,
+    Weeks,
+    Hours,
+}
+
+/// A date query
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Question {
+    /// Show a point in time, as in `3pm PST to CET`
+    Show {
+        expression: Expression,
+        target: Target,
+    },
+
+    /// Count the time to or from a point, as in `days until 2026-12-25`
+    Count {
+        unit: CountUnit,
+        since: bool,
+        expression: Expression,
+    },
+}
+
+impl Question {
+    /// Parses a query, returning None if it is not about dates or times
+    fn parse(tokens: &[String], local: &Zone) -> Option<Question> {
+        let expression = |tokens: &[String]| {
+            let mut parser = Parser {
+                tokens,
+                pos: 0,
+                local,
+            };
+            parser.expression().filter(|_| parser.done())
+        };
+
+        let unit = match tokens.first().map(String::as_str) {
+            Some("days") => Some(CountUnit::Days),
+            Some("weeks") => Some(CountUnit::Weeks),
+            Some("hours") => Some(CountUnit::Hours),
+            _ => None,
+        };
+        if let Some(unit) = unit {
+            let since = match tokens.get(1).map(String::as_str)? {
+                "until" | "till" | "to" => false,
+                "since" => true,
+                _ => return None,
+            };
+            return Some(Question::Count {
+                unit,
+                since,
+                expression: expression(&tokens[2..])?,
+            });
+        }
+
+        let split = (1..tokens.len().saturating_sub(1))
+            .rev()
+            .find(|&i| CONNECTORS.contains(&tokens[i].as_str()));
+        let (left, target) = match split {
+            Some(i) => {
+                let name = tokens[i + 1..].join(" ");
+                let target = match name.as_str() {
+                    "unix" => Target::Unix,
+                    "local" | "here" => Target::Local,
+                    _ => Target::Zone(Zone::find(&name)?),
+                };
+                (&tokens[..i], target)
+            }
+            None => (tokens, Target::Local),
+        };
+
+        let expression = expression(left)?;
+        if target == Target::Local && expression.is_bare_time() {
+            return None;
+        }
+        Some(Question::Show { expression, target })
+    }
+
+    /// Works out the answer at `now`
+    fn answer(&self, now: i64, local: &Zone) -> Result<String> {
+        match self {
+            Question::Show { expression, target } => {
+                // Days are read in the target zone, so `tomorrow in Tokyo`
+                // is tomorrow there
+                let zone = match target {
+                    Target::Zone(zone) if expression.is_date_only() => zone,
+                    _ => local,
+                };
+                let moment = expression.evaluate(now, zone)?;
+                Ok(match target {
+                    Target::Unix => moment.utc.to_string(),
+                    Target::Zone(zone) => format_moment(&moment, zone),
+                    Target::Local if moment.with_time => format_moment(&moment, local),
+                    Target::Local => format_moment(&moment, &moment.zone),
+                })
+            }
+            Question::Count {
+                unit,
+                since,
+                expression,
+            } => {
+                let zone = expression.zone.as_ref().unwrap_or(local);
+                let mut expression = expression.clone();
+                expression.resolve_year(now, zone, !since);
+                let moment = expression.evaluate(now, local)?;
+                let sign = if *since { -1 } else { 1 };
+
+                Ok(match unit {
+                    CountUnit::Days | CountUnit::Weeks => {
+                        let today = moment.zone.to_local(now).div_euclid(SECONDS_PER_DAY);
+                        let day = moment.zone.to_local(moment.utc).div_euclid(SECONDS_PER_DAY);
+                        let days = sign * (day - today);
+                        if *unit == CountUnit::Days {
+                            plural(days, "day")
+                        } else {
+                            format_weeks(days)
+                        }
+                    }
+                    CountUnit::Hours => format_hours(sign * (moment.utc - now)),
+                })
+            }
+        }
+    }
+}
+
+/// Shows a moment in `zone`, as `00:00 CET, Sun 18 Oct 2026`
+fn format_moment(moment: &Moment, zone: &Zone) -> String {
+    let local = zone.to_local(moment.utc);
+    let days = local.div_euclid(SECONDS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+    let date = format!(
+        "{} {} {} {}",
+        WEEKDAYS[weekday(days) as usize],
+        day,
+        capitalize(&MONTHS[month as usize - 1][..3]),
+        year
+    );
+    if !moment.with_time {
+        return date;
+    }
+
+    let time = local.rem_euclid(SECONDS_PER_DAY);
+    let mut clock = format!("{:02}:{:02}", time / 3600, time % 3600 / 60);
+    if moment.seconds {
+        clock += &format!(":{:02}", time % 60);
+    }
+    format!("{} {}, {}", clock, zone.abbreviation_at(moment.utc), date)
+}
+
+fn plural(count: i64, unit: &str) -> String {
+    let s = if count.abs() == 1 { "" } else { "s" };
+    format!("{} {}{}", count, unit, s)
+}
+
+/// Shows days as weeks and days, as `9 weeks 6 days`
+fn format_weeks(days: i64) -> String {
+    let sign = if days < 0 { "-" } else { "" };
+    let (weeks, days) = (days.abs() / 7, days.abs() % 7);
+    if days == 0 {
+        format!("{}{}", sign, plural(weeks, "week"))
+    } else {
+        format!("{}{} {}", sign, plural(weeks, "week"), plural(days, "day"))
+    }
+}
+
+/// Shows seconds as hours and minutes, as `5 hours 30 minutes`
+fn format_hours(seconds: i64) -> String {
+    let sign = if seconds < 0 { "-" } else { "" };
+    let minutes = seconds.abs() / 60;
+    let (hours, minutes) = (minutes / 60, minutes % 60);
+    match (hours, minutes) {
+        (_, 0) => format!("{}{}", sign, plural(hours, "hour")),
+        (0, _) => format!("{}{}", sign, plural(minutes, "minute")),
+        _ => format!(
+            "{}{} {}",
+            sign,
+            plural(hours, "hour"),
+            plural(minutes, "minute")
+        ),
+    }
+}
+
+/// Answers a date query at `now` (seconds since the epoch), with times
+/// written without a zone read in `local`
+///
+/// Returns None if the input is not a date query, and an error for a date
+/// query that cannot be answered, such as `2026-02-30`.
+pub fn evaluate(input: &str, now: i64, local: &Zone) -> Option<Result<String>> {
+    let tokens = tokenize(input)?;
+    let question = Question::parse(&tokens, local)?;
+    Some(question.answer(now, local))
+}
+
+/// Answers date, time and time zone queries
+pub struct DateTimeProvider {
+    /// Zone for times written without one
+    local: Zone,
+}
+
+impl DateTimeProvider {
+    /// Creates the provider for the system time zone
+    pub fn new() -> Self {
+        Self::with_zone(local_zone())
+    }
+
+    /// Creates the provider for a given local zone
+    pub fn with_zone(local: Zone) -> Self {
+        Self { local }
+    }
+}
+
+impl Default for DateTimeProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Provider for DateTimeProvider {
+    fn id(&self) -> &'static str {
+        "datetime"
+    }
+
+    fn title(&self) -> &str {
+        "Date & Time"
+    }
+
+    fn priority(&self) -> i32 {
+        2
+    }
+
+    /// Dates and times are read as typed
+    fn wants_filters(&self) -> bool {
+        false
+    }
+
+    fn query(
+        &self,
+        query: &ProviderQuery,
+        _cancel: &CancelToken,
+        emit: &(dyn Fn(Vec<Section>) + Sync),
+    ) -> bool {
+        let text = query.text.trim();
+        let now = query::now_secs() as i64;
+        let Some(answer) = evaluate(text, now, &self.local) else {
+            return true;
+        };
+
+        let (name, description) = match answer {
+            Ok(answer) => (answer, format!("{} =", text)),
+            Err(e) => (e.to_string(), text.to_string()),
+        };
+        emit(vec![self.section(vec![SearchResult {
+            name,
+            path: PathBuf::new(),
+            result_type: ResultType::Answer,
+            score: 0,
+            description,
+            provider: self.id(),
+            shortcut: None,
+        }])]);
+        true
+    }
+
+    /// Copies answers; error messages have nothing to copy
+    fn actions(&self, result: &SearchResult) -> Vec<Action> {
+        if result.description.ends_with(" =") {
+            vec![Action::Copy(result.name.clone())]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Saturday 2026-10-17 12:00 UTC
+    const NOW: i64 = 1_792_238_400;
+
+    fn answer(input: &str) -> String {
+        answer_in(input, &Zone::utc())
+    }
+
+    fn answer_in(input: &str, local: &Zone) -> String {
+        evaluate(input, NOW, local)
+            .unwrap_or_else(|| panic!("{} is not a date query", input))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_calendar() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(
+            days_from_civil(2026, 10, 17) * SECONDS_PER_DAY + 12 * 3600,
+            NOW
+        );
+        assert_eq!(civil_from_days(days_from_civil(2024, 2, 29)), (2024, 2, 29));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(weekday(days_from_civil(2026, 10, 17)), 6);
+        assert_eq!(days_in_month(2100, 2), 28);
+        assert_eq!(days_in_month(2000, 2), 29);
+    }
+
+    #[test]
+    fn test_examples() {
+        assert_eq!(answer("now in Tokyo"), "21:00 JST, Sat 17 Oct 2026");
+        assert_eq!(answer("3pm PST to CET"), "00:00 CET, Sun 18 Oct 2026");
+        assert_eq!(answer("today + 45 days"), "Tue 1 Dec 2026");
+        assert_eq!(answer("days until 2026-12-25"), "69 days");
+        assert_eq!(answer("1700000000 unix"), "22:13:20 UTC, Tue 14 Nov 2023");
+    }
+
+    #[test]
+    fn test_dates_and_arithmetic() {
+        assert_eq!(answer("tomorrow"), "Sun 18 Oct 2026");
+        assert_eq!(answer("25 dec"), "Fri 25 Dec 2026");
+        assert_eq!(answer("jan 31 2026 + 1 month"), "Sat 28 Feb 2026");
+        assert_eq!(answer("2024-02-29 + 1 year"), "Fri 28 Feb 2025");
+        assert_eq!(answer("3 days ago"), "12:00 UTC, Wed 14 Oct 2026");
+        assert_eq!(answer("2 weeks from tomorrow"), "Sun 1 Nov 2026");
+        assert_eq!(answer("now + 90 min"), "13:30 UTC, Sat 17 Oct 2026");
+        assert_eq!(answer("weeks until dec 25"), "9 weeks 6 days");
+        assert_eq!(answer("days since 2026-01-01"), "289 days");
+        assert_eq!(answer("days until 1 jan"), "76 days");
+        assert_eq!(answer("hours until 5:30pm"), "5 hours 30 minutes");
+        assert_eq!(answer("2026-12-25 to unix"), "1798156800");
+        assert_eq!(
+            answer("now in utc+5:30"),
+            "17:30 UTC+05:30, Sat 17 Oct 2026"
+        );
+    }
+
+    #[test]
+    fn test_zone_conversions() {
+        let new_york = Zone::find("New York").unwrap();
+        assert_eq!(
+            answer("3pm los angeles to paris"),
+            "00:00 CEST, Sun 18 Oct 2026"
+        );
+        assert_eq!(
+            answer("tomorrow 9am new york in london"),
+            "14:00 BST, Sun 18 Oct 2026"
+        );
+        assert_eq!(answer_in("now", &new_york), "08:00 EDT, Sat 17 Oct 2026");
+        assert_eq!(
+            answer_in("noon tokyo", &new_york),
+            "23:00 EDT, Fri 16 Oct 2026"
+        );
+        assert_eq!(answer("tomorrow in Kiritimati"), "Mon 19 Oct 2026");
+    }
+
+    #[test]
+    fn test_daylight_saving_edges() {
+        // Adding a day keeps the wall-clock time; adding hours does not
+        assert_eq!(
+            answer("2026-03-07 12:00 new york + 1 day to utc"),
+            "16:00 UTC, Sun 8 Mar 2026"
+        );
+        assert_eq!(
+            answer("2026-03-07 12:00 new york + 24 hours in new york"),
+            "13:00 EDT, Sun 8 Mar 2026"
+        );
+
+        // Skipped and repeated local times
+        assert_eq!(
+            answer("2026-03-08 2:30am new york to utc"),
+            "07:30 UTC, Sun 8 Mar 2026"
+        );
+        assert_eq!(
+            answer("2026-11-01 1:30am new york to utc"),
+            "05:30 UTC, Sun 1 Nov 2026"
+        );
+        assert_eq!(
+            answer("2026-11-01 1:30am new york + 1 hour in new york"),
+            "01:30 EST, Sun 1 Nov 2026"
+        );
+
+        // Abbreviations are fixed while zone names follow the season
+        assert_eq!(
+            answer("2026-07-01 noon pst to utc"),
+            "20:00 UTC, Wed 1 Jul 2026"
+        );
+        assert_eq!(
+            answer("2026-07-01 noon pt to utc"),
+            "19:00 UTC, Wed 1 Jul 2026"
+        );
+        assert_eq!(
+            answer("2026-04-05 2:30am sydney to utc"),
+            "15:30 UTC, Sat 4 Apr 2026"
+        );
+    }
+
+    #[test]
+    fn test_errors_and_non_dates() {
+        let error = evaluate("2026-02-30", NOW, &Zone::utc())
+            .unwrap()
+            .unwrap_err();
+        assert!(error.to_string().contains("February 2026 has no day 30"));
+        assert!(evaluate("now + 10000 years", NOW, &Zone::utc())
+            .unwrap()
+            .is_err());
+
+        for query in [
+            "notepad",
+            "3pm",
+            "5 km to mi",
+            "2h30m in minutes",
+            "now in atlantis",
+            "report in docs",
+            "may",
+            "25:00 utc",
+            "12*(3+4)",
+        ] {
+            assert!(evaluate(query, NOW, &Zone::utc()).is_none(), "{}", query);
+        }
+    }
+}
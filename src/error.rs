@@ -37,6 +37,54 @@ pub enum RustleError {
     #[error("Search operation failed: {0}")]
     SearchError(String),
 
+    /// File index could not be read or written
+    #[error("File index error: {0}")]
+    IndexError(String),
+
+    /// NTFS Master File Table data could not be parsed
+    #[error("MFT parse error: {0}")]
+    MftParse(String),
+
+    /// Search query contains an invalid filter
+    #[error("Invalid query: {0}")]
+    QueryParse(String),
+
+    /// Launch history could not be read or written
+    #[error("Launch history error: {0}")]
+    HistoryError(String),
+
+    /// Text could not be extracted from a document
+    #[error("Text extraction error: {0}")]
+    ExtractError(String),
+
+    /// Clipboard could not be read or written
+    #[error("Clipboard error: {0}")]
+    ClipboardError(String),
+
+    /// Calculator expression could not be parsed or evaluated
+    #[error("Cannot calculate: {0}")]
+    CalculationError(String),
+
+    /// Unit conversion between incompatible units
+    #[error("Cannot convert: {0}")]
+    ConversionError(String),
+
+    /// Date or time that does not exist or cannot be worked out
+    #[error("Invalid date: {0}")]
+    DateError(String),
+
+    /// Shell command could not be run
+    #[error("Command failed: {0}")]
+    CommandError(String),
+
+    /// Browser profile data that could not be read
+    #[error("Browser data error: {0}")]
+    BrowserData(String),
+
+    /// A shell link (.lnk) that could not be read
+    #[error("Shortcut error: {0}")]
+    Shortcut(String),
+
     /// Failed to access a directory
     #[error("Cannot access directory: {path}")]
     DirectoryAccess {
@@ -90,6 +138,66 @@ impl RustleError {
         Self::SearchError(msg.into())
     }
 
+    /// Creates a new file index error with context
+    pub fn index_error(msg: impl Into<String>) -> Self {
+        Self::IndexError(msg.into())
+    }
+
+    /// Creates a new MFT parse error with context
+    pub fn mft_parse(msg: impl Into<String>) -> Self {
+        Self::MftParse(msg.into())
+    }
+
+    /// Creates a new query parse error with context
+    pub fn query_parse(msg: impl Into<String>) -> Self {
+        Self::QueryParse(msg.into())
+    }
+
+    /// Creates a new launch history error with context
+    pub fn history_error(msg: impl Into<String>) -> Self {
+        Self::HistoryError(msg.into())
+    }
+
+    /// Creates a new text extraction error with context
+    pub fn extract_error(msg: impl Into<String>) -> Self {
+        Self::ExtractError(msg.into())
+    }
+
+    /// Creates a new clipboard error with context
+    pub fn clipboard_error(msg: impl Into<String>) -> Self {
+        Self::ClipboardError(msg.into())
+    }
+
+    /// Creates a new calculation error with context
+    pub fn calculation_error(msg: impl Into<String>) -> Self {
+        Self::CalculationError(msg.into())
+    }
+
+    /// Creates a new unit conversion error with context
+    pub fn conversion_error(msg: impl Into<String>) -> Self {
+        Self::ConversionError(msg.into())
+    }
+
+    /// Creates a new date error with context
+    pub fn date_error(msg: impl Into<String>) -> Self {
+        Self::DateError(msg.into())
+    }
+
+    /// Creates a new shell command error with context
+    pub fn command_error(msg: impl Into<String>) -> Self {
+        Self::CommandError(msg.into())
+    }
+
+    /// Creates a new browser data error with context
+    pub fn browser_data_error(msg: impl Into<String>) -> Self {
+        Self::BrowserData(msg.into())
+    }
+
+    /// Creates a new shortcut error with context
+    pub fn shortcut_error(msg: impl Into<String>) -> Self {
+        Self::Shortcut(msg.into())
+    }
+
     /// Creates a new launch error for the given path
     pub fn launch_error(path: PathBuf, source: std::io::Error) -> Self {
         Self::LaunchError { path, source }
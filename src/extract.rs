@@ -0,0 +1,593 @@
+//! Text extraction from documents for Rustle
+//!
+//! Content search and the full-text index only understand plain text. This
+//! module turns PDF files and Office Open XML packages (.docx, .xlsx and
+//! .pptx) into plain text with one extractor per format, each implementing
+//! [`TextExtractor`]. Paragraphs, table rows and slide text runs become
+//! lines, so line numbers and excerpts keep making sense in results.
+
+#![allow(dead_code)]
+
+use crate::error::{Result, RustleError};
+use crate::pdf;
+use crate::zip::ZipArchive;
+use std::borrow::Cow;
+use std::path::Path;
+
+/// Largest XML part read from an Office package, once decompressed
+const MAX_PART_SIZE: usize = 64 * 1024 * 1024;
+
+/// Pulls plain text out of one document format
+pub trait TextExtractor: Sync {
+    /// Lowercase file extensions handled by this extractor
+    fn extensions(&self) -> &'static [&'static str];
+
+    /// Extracts the text of a document held in memory
+    ///
+    /// Extraction stops once roughly `max_output` bytes of text have been
+    /// produced.
+    fn extract(&self, data: &[u8], max_output: usize) -> Result<String>;
+}
+
+/// Extracts the text drawn by the content streams of a PDF's pages
+pub struct PdfExtractor;
+
+impl TextExtractor for PdfExtractor {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["pdf"]
+    }
+
+    fn extract(&self, data: &[u8], max_output: usize) -> Result<String> {
+        pdf::extract_text(data, max_output)
+    }
+}
+
+/// Extracts the paragraphs of a Word document
+pub struct DocxExtractor;
+
+impl TextExtractor for DocxExtractor {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["docx", "docm"]
+    }
+
+    fn extract(&self, data: &[u8], max_output: usize) -> Result<String> {
+        let archive = ZipArchive::new(data)?;
+        let mut text = TextSink::new(max_output);
+        for part in [
+            "word/document.xml",
+            "word/footnotes.xml",
+            "word/endnotes.xml",
+        ] {
+            if !archive.contains(part) {
+                continue;
+            }
+            let xml = read_part(&archive, part)?;
+            let mut in_run = false;
+            let mut in_text = false;
+            for event in XmlReader::new(&xml) {
+                match event {
+                    XmlEvent::Start { name: "r", .. } => in_run = true,
+                    XmlEvent::End { name: "r" } => in_run = false,
+                    XmlEvent::Start {
+                        name: "t",
+                        empty: false,
+                        ..
+                    } => in_text = true,
+                    XmlEvent::End { name: "t" } => in_text = false,
+                    XmlEvent::Start { name: "tab", .. } if in_run => text.push('\t'),
+                    XmlEvent::Start {
+                        name: "br" | "cr", ..
+                    } if in_run => text.newline(),
+                    XmlEvent::End { name: "p" } => text.newline(),
+                    XmlEvent::Text(t) if in_text => text.push_str(&t),
+                    _ => {}
+                }
+                if text.is_full() {
+                    return Ok(text.finish());
+                }
+            }
+        }
+        Ok(text.finish())
+    }
+}
+
+/// Extracts cell values of every worksheet, one row per line
+pub struct XlsxExtractor;
+
+impl TextExtractor for XlsxExtractor {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["xlsx", "xlsm"]
+    }
+
+    fn extract(&self, data: &[u8], max_output: usize) -> Result<String> {
+        let archive = ZipArchive::new(data)?;
+        let shared = if archive.contains("xl/sharedStrings.xml") {
+            shared_strings(&read_part(&archive, "xl/sharedStrings.xml")?)
+        } else {
+            Vec::new()
+        };
+
+        let mut text = TextSink::new(max_output);
+        for part in numbered_parts(&archive, "xl/worksheets/sheet", ".xml") {
+            let xml = read_part(&archive, &part)?;
+            let mut cell_type = String::new();
+            let mut value = String::new();
+            let mut in_value = false;
+            let mut cells_in_row = 0;
+            for event in XmlReader::new(&xml) {
+                match event {
+                    XmlEvent::Start { name: "row", .. } => cells_in_row = 0,
+                    XmlEvent::End { name: "row" } => text.newline(),
+                    XmlEvent::Start {
+                        name: "c", attrs, ..
+                    } => {
+                        cell_type = attribute(attrs, "t").unwrap_or_default().into_owned();
+                        value.clear();
+                    }
+                    XmlEvent::End { name: "c" } => {
+                        let cell = match cell_type.as_str() {
+                            "s" => value
+                                .trim()
+                                .parse::<usize>()
+                                .ok()
+                                .and_then(|i| shared.get(i))
+                                .map_or("", |s| s.as_str()),
+                            _ => value.as_str(),
+                        };
+                        if !cell.is_empty() {
+                            if cells_in_row > 0 {
+                                text.push('\t');
+                            }
+                            text.push_str(cell);
+                            cells_in_row += 1;
+                        }
+                    }
+                    XmlEvent::Start {
+                        name: "v" | "t",
+                        empty: false,
+                        ..
+                    } => in_value = true,
+                    XmlEvent::End { name: "v" | "t" } => in_value = false,
+                    XmlEvent::Text(t) if in_value => value.push_str(&t),
+                    _ => {}
+                }
+                if text.is_full() {
+                    return Ok(text.finish());
+                }
+            }
+        }
+        Ok(text.finish())
+    }
+}
+
+/// Extracts the text of every slide, in slide order
+pub struct PptxExtractor;
+
+impl TextExtractor for PptxExtractor {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["pptx", "pptm"]
+    }
+
+    fn extract(&self, data: &[u8], max_output: usize) -> Result<String> {
+        let archive = ZipArchive::new(data)?;
+        let mut text = TextSink::new(max_output);
+        for part in numbered_parts(&archive, "ppt/slides/slide", ".xml") {
+            let xml = read_part(&archive, &part)?;
+            let mut in_text = false;
+            for event in XmlReader::new(&xml) {
+                match event {
+                    XmlEvent::Start {
+                        name: "t",
+                        empty: false,
+                        ..
+                    } => in_text = true,
+                    XmlEvent::End { name: "t" } => in_text = false,
+                    XmlEvent::Start { name: "br", .. } | XmlEvent::End { name: "p" } => {
+                        text.newline()
+                    }
+                    XmlEvent::Text(t) if in_text => text.push_str(&t),
+                    _ => {}
+                }
+                if text.is_full() {
+                    return Ok(text.finish());
+                }
+            }
+            text.newline();
+        }
+        Ok(text.finish())
+    }
+}
+
+/// Every registered extractor
+static EXTRACTORS: &[&dyn TextExtractor] = &[
+    &PdfExtractor,
+    &DocxExtractor,
+    &XlsxExtractor,
+    &PptxExtractor,
+];
+
+/// Returns the extractor for a file, judging by its extension
+pub fn extractor_for(path: &Path) -> Option<&'static dyn TextExtractor> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    EXTRACTORS
+        .iter()
+        .copied()
+        .find(|e| e.extensions().contains(&extension.as_str()))
+}
+
+/// Returns true if a file's text is extracted rather than read directly
+pub fn has_extractor(path: &Path) -> bool {
+    extractor_for(path).is_some()
+}
+
+/// Reads a document and extracts its text
+///
+/// Returns None if the file has no extractor, is larger than `max_size`,
+/// or cannot be read or parsed. At most about `max_size` bytes of text are
+/// returned.
+pub fn extract_file(path: &Path, max_size: u64) -> Option<String> {
+    let extractor = extractor_for(path)?;
+    let len = std::fs::metadata(path).ok()?.len();
+    if len > max_size {
+        return None;
+    }
+
+    let data = std::fs::read(path).ok()?;
+    match extractor.extract(&data, max_size as usize) {
+        Ok(text) => Some(text),
+        Err(e) => {
+            log::debug!("Cannot extract text from {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Accumulates extracted text, collapsing blank lines and stopping at a limit
+struct TextSink {
+    text: String,
+    max_len: usize,
+}
+
+impl TextSink {
+    fn new(max_len: usize) -> Self {
+        Self {
+            text: String::new(),
+            max_len,
+        }
+    }
+
+    fn push(&mut self, c: char) {
+        if !self.is_full() {
+            self.text.push(c);
+        }
+    }
+
+    fn push_str(&mut self, s: &str) {
+        if !self.is_full() {
+            self.text.push_str(s);
+        }
+    }
+
+    /// Ends the current line unless it is empty
+    fn newline(&mut self) {
+        if !self.text.is_empty() && !self.text.ends_with('\n') {
+            self.push('\n');
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.text.len() >= self.max_len
+    }
+
+    fn finish(self) -> String {
+        self.text
+    }
+}
+
+/// Reads an XML part of a package as text
+fn read_part(archive: &ZipArchive, name: &str) -> Result<String> {
+    let bytes = archive.read(name, MAX_PART_SIZE)?;
+    crate::content::decode(&bytes)
+        .ok_or_else(|| RustleError::extract_error(format!("{} is not text", name)))
+}
+
+/// Returns parts named `<prefix><number><suffix>`, ordered by number
+fn numbered_parts(archive: &ZipArchive, prefix: &str, suffix: &str) -> Vec<String> {
+    let mut parts: Vec<(u32, String)> = archive
+        .names()
+        .filter_map(|name| {
+            let number = name.strip_prefix(prefix)?.strip_suffix(suffix)?;
+            Some((number.parse().ok()?, name.to_string()))
+        })
+        .collect();
+    parts.sort();
+    parts.into_iter().map(|(_, name)| name).collect()
+}
+
+/// Reads the shared string table of a workbook
+///
+/// Each `si` item may hold plain text or several formatted runs; phonetic
+/// guides (`rPh`) are left out.
+fn shared_strings(xml: &str) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut current = String::new();
+    let mut in_text = false;
+    let mut in_phonetic = false;
+    for event in XmlReader::new(xml) {
+        match event {
+            XmlEvent::Start { name: "si", .. } => current.clear(),
+            XmlEvent::End { name: "si" } => strings.push(std::mem::take(&mut current)),
+            XmlEvent::Start {
+                name: "rPh",
+                empty: false,
+                ..
+            } => in_phonetic = true,
+            XmlEvent::End { name: "rPh" } => in_phonetic = false,
+            XmlEvent::Start {
+                name: "t",
+                empty: false,
+                ..
+            } => in_text = true,
+            XmlEvent::End { name: "t" } => in_text = false,
+            XmlEvent::Text(t) if in_text && !in_phonetic => current.push_str(&t),
+            _ => {}
+        }
+    }
+    strings
+}
+
+/// A piece of an XML document
+#[derive(Debug, PartialEq)]
+enum XmlEvent<'a> {
+    /// An opening tag; `name` has its namespace prefix removed
+    Start {
+        name: &'a str,
+        attrs: &'a str,
+        empty: bool,
+    },
+
+    /// A closing tag
+    End { name: &'a str },
+
+    /// Character data between tags, with entities decoded
+    Text(Cow<'a, str>),
+}
+
+/// A forgiving pull tokenizer for the XML found in Office packages
+///
+/// Declarations, comments and processing instructions are skipped and
+/// namespaces are ignored apart from stripping prefixes.
+struct XmlReader<'a> {
+    xml: &'a str,
+    pos: usize,
+}
+
+impl<'a> XmlReader<'a> {
+    fn new(xml: &'a str) -> Self {
+        Self { xml, pos: 0 }
+    }
+}
+
+impl<'a> Iterator for XmlReader<'a> {
+    type Item = XmlEvent<'a>;
+
+    fn next(&mut self) -> Option<XmlEvent<'a>> {
+        loop {
+            let rest = &self.xml[self.pos..];
+            if rest.is_empty() {
+                return None;
+            }
+
+            if !rest.starts_with('<') {
+                let len = rest.find('<').unwrap_or(rest.len());
+                self.pos += len;
+                return Some(XmlEvent::Text(unescape(&rest[..len])));
+            }
+
+            if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+                let len = cdata.find("]]>").unwrap_or(cdata.len());
+                self.pos += "<![CDATA[".len() + len + "]]>".len().min(cdata.len() - len);
+                return Some(XmlEvent::Text(Cow::Borrowed(&cdata[..len])));
+            }
+
+            let terminator = if rest.starts_with("<!--") {
+                "-->"
+            } else if rest.starts_with("<?") {
+                "?>"
+            } else {
+                ">"
+            };
+            let Some(end) = rest.find(terminator) else {
+                self.pos = self.xml.len();
+                return None;
+            };
+            self.pos += end + terminator.len();
+            if terminator != ">" || rest.starts_with("<!") {
+                continue;
+            }
+
+            let tag = &rest[1..end];
+            if let Some(name) = tag.strip_prefix('/') {
+                return Some(XmlEvent::End {
+                    name: local_name(name.trim()),
+                });
+            }
+
+            let (tag, empty) = match tag.strip_suffix('/') {
+                Some(tag) => (tag, true),
+                None => (tag, false),
+            };
+            let name_len = tag
+                .find(|c: char| c.is_ascii_whitespace())
+                .unwrap_or(tag.len());
+            return Some(XmlEvent::Start {
+                name: local_name(&tag[..name_len]),
+                attrs: &tag[name_len..],
+                empty,
+            });
+        }
+    }
+}
+
+/// Strips the namespace prefix from an element or attribute name
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Looks up an attribute by local name in the attribute text of a tag
+fn attribute<'a>(attrs: &'a str, name: &str) -> Option<Cow<'a, str>> {
+    let mut rest = attrs;
+    loop {
+        let eq = rest.find('=')?;
+        let key = rest[..eq].trim();
+        let after = rest[eq + 1..].trim_start();
+        let quote = after.chars().next()?;
+        if quote != '"' && quote != '\'' {
+            return None;
+        }
+        let value_len = after[1..].find(quote)?;
+        let value = &after[1..1 + value_len];
+        if local_name(key) == name {
+            return Some(unescape(value));
+        }
+        rest = &after[value_len + 2..];
+    }
+}
+
+/// Decodes the predefined XML entities and character references
+fn unescape(text: &str) -> Cow<'_, str> {
+    if !text.contains('&') {
+        return Cow::Borrowed(text);
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';').and_then(|semi| {
+            let c = match &rest[1..semi] {
+                "lt" => '<',
+                "gt" => '>',
+                "amp" => '&',
+                "quot" => '"',
+                "apos" => '\'',
+                reference => {
+                    let code = match reference
+                        .strip_prefix("#x")
+                        .or(reference.strip_prefix("#X"))
+                    {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => reference.strip_prefix('#')?.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, semi + 1))
+        });
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_docx_paragraphs() {
+        let data = include_bytes!("../tests/fixtures/extract/sample.docx");
+        let text = DocxExtractor.extract(data, 1 << 20).unwrap();
+        assert_eq!(
+            text,
+            "Quarterly Report\nRevenue grew by 12% & costs fell\nRegion\tNorth\nSee footnote\nSource: internal figures\n"
+        );
+    }
+
+    #[test]
+    fn test_xlsx_rows() {
+        let data = include_bytes!("../tests/fixtures/extract/sample.xlsx");
+        let text = XlsxExtractor.extract(data, 1 << 20).unwrap();
+        assert_eq!(
+            text,
+            "Region\tRevenue\nNorth\t1200\nSouth\t950\nInline <note>\nSecond sheet\n"
+        );
+    }
+
+    #[test]
+    fn test_pptx_slides_in_order() {
+        let data = include_bytes!("../tests/fixtures/extract/sample.pptx");
+        let text = PptxExtractor.extract(data, 1 << 20).unwrap();
+        assert_eq!(
+            text,
+            "Roadmap 2025\nShip the launcher\nThen the plugins\nSlide two\nSlide ten\n"
+        );
+    }
+
+    #[test]
+    fn test_pdf_pages() {
+        let data = include_bytes!("../tests/fixtures/extract/sample.pdf");
+        let text = PdfExtractor.extract(data, 1 << 20).unwrap();
+        assert_eq!(
+            text,
+            "Quarterly Report\nRevenue grew (a lot)\nSales\u{2019} summary\nCaf\u{e9} menu\nprices in \u{20ac}\n"
+        );
+    }
+
+    #[test]
+    fn test_output_limit_and_garbage() {
+        let data = include_bytes!("../tests/fixtures/extract/sample.docx");
+        assert!(DocxExtractor.extract(data, 8).unwrap().len() < 32);
+        assert!(DocxExtractor.extract(b"not a zip", 1024).is_err());
+        assert!(PdfExtractor.extract(b"not a pdf", 1024).is_err());
+    }
+
+    #[test]
+    fn test_extractor_for() {
+        assert!(extractor_for(Path::new(r"C:\Docs\Report.PDF")).is_some());
+        assert!(extractor_for(Path::new("budget.xlsx")).is_some());
+        assert!(extractor_for(Path::new("notes.txt")).is_none());
+        assert!(extractor_for(Path::new("Makefile")).is_none());
+    }
+
+    #[test]
+    fn test_xml_reader() {
+        let xml = r#"<?xml version="1.0"?><!-- c --><w:p a="1"><w:t xml:space='preserve'>a &amp; b&#x41;</w:t><w:br/></w:p>"#;
+        let events: Vec<XmlEvent> = XmlReader::new(xml).collect();
+        assert_eq!(
+            events,
+            [
+                XmlEvent::Start {
+                    name: "p",
+                    attrs: r#" a="1""#,
+                    empty: false
+                },
+                XmlEvent::Start {
+                    name: "t",
+                    attrs: " xml:space='preserve'",
+                    empty: false
+                },
+                XmlEvent::Text(Cow::Borrowed("a & bA")),
+                XmlEvent::End { name: "t" },
+                XmlEvent::Start {
+                    name: "br",
+                    attrs: "",
+                    empty: true
+                },
+                XmlEvent::End { name: "p" },
+            ]
+        );
+        assert_eq!(attribute(r#" r="A1" t="s""#, "t").unwrap(), "s");
+        assert!(attribute(r#" r="A1""#, "t").is_none());
+    }
+}
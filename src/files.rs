@@ -0,0 +1,813 @@
+//! File and folder results for Rustle
+//!
+//! `FileProvider` matches file and folder names against the persistent
+//! file index (see `index.rs`), searching each root in parallel and
+//! reporting results root by root. It owns the indexes, which are loaded
+//! in the background, and the watcher that keeps them current.
+//! `ContentProvider` answers queries starting with the content search
+//! prefix by looking inside those files instead.
+
+#![allow(dead_code)]
+
+use crate::config::{FullTextConfig, SearchConfig};
+use crate::content;
+use crate::error::Result;
+use crate::fulltext::{FullTextIndex, FullTextQuery};
+use crate::history::LaunchHistory;
+use crate::ignore::{IgnoreRules, IgnoreSet};
+use crate::index::{FileIndex, IndexEntry};
+use crate::provider::{Provider, ProviderQuery, Trigger};
+use crate::query::{self, Candidate, Query};
+use crate::search::{name_score, CancelToken, ResultType, SearchResult, Section};
+use crate::utils::{display_name, normalize_for_search};
+use crate::volume;
+use crate::watcher::{default_watcher, IndexWatcher};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Position of the folders section
+const FOLDERS_PRIORITY: i32 = 20;
+
+/// Position of the files section
+const FILES_PRIORITY: i32 = 30;
+
+/// Most folders shown for one query
+const MAX_FOLDERS: usize = 4;
+
+/// Most files shown for one query
+const MAX_FILES: usize = 5;
+
+/// Finds files and folders by name in the file index
+pub struct FileProvider {
+    /// Configuration for search behavior
+    config: SearchConfig,
+
+    /// Fuzzy matcher instance
+    matcher: SkimMatcherV2,
+
+    /// Additional search paths (beyond config)
+    extra_search_paths: Vec<PathBuf>,
+
+    /// Persistent index of files and folders under the search paths
+    /// (shared with the background watcher that keeps it current)
+    file_index: Arc<RwLock<FileIndex>>,
+
+    /// Words inside documents and source files, for content search
+    full_text: Arc<RwLock<FullTextIndex>>,
+
+    /// Background thread applying file system changes to the index, once
+    /// the indexes are loaded
+    index_watcher: Arc<Mutex<Option<IndexWatcher>>>,
+
+    /// Launch history used to boost frequently and recently used results
+    history: Arc<RwLock<LaunchHistory>>,
+}
+
+impl FileProvider {
+    /// Creates the provider and starts loading (or building) the indexes
+    /// for all search paths in the background
+    ///
+    /// Queries find no files until the file index is ready.
+    pub fn new(config: SearchConfig, history: Arc<RwLock<LaunchHistory>>) -> Self {
+        let mut provider = Self::with_indexes(
+            config,
+            FileIndex::default(),
+            FullTextIndex::default(),
+            history,
+        );
+
+        // Add extra search paths for comprehensive search
+        provider.init_extra_search_paths();
+
+        // Load (or build) the file index for those paths and keep it current
+        provider.start_indexing();
+
+        provider
+    }
+
+    /// Creates the provider over already built indexes, without watching
+    /// anything
+    pub fn with_indexes(
+        config: SearchConfig,
+        file_index: FileIndex,
+        full_text: FullTextIndex,
+        history: Arc<RwLock<LaunchHistory>>,
+    ) -> Self {
+        Self {
+            config,
+            matcher: SkimMatcherV2::default().smart_case(),
+            extra_search_paths: Vec::new(),
+            file_index: Arc::new(RwLock::new(file_index)),
+            full_text: Arc::new(RwLock::new(full_text)),
+            index_watcher: Arc::new(Mutex::new(None)),
+            history,
+        }
+    }
+
+    /// Initialize additional search paths including all available drives
+    fn init_extra_search_paths(&mut self) {
+        // User home directory
+        if let Some(home) = dirs::home_dir() {
+            self.extra_search_paths.push(home);
+        }
+
+        // Pictures
+        if let Some(pics) = dirs::picture_dir() {
+            self.extra_search_paths.push(pics);
+        }
+
+        // Videos
+        if let Some(vids) = dirs::video_dir() {
+            self.extra_search_paths.push(vids);
+        }
+
+        // Music
+        if let Some(music) = dirs::audio_dir() {
+            self.extra_search_paths.push(music);
+        }
+
+        // Common program locations on C drive
+        let program_files = PathBuf::from(r"C:\Program Files");
+        if program_files.exists() {
+            self.extra_search_paths.push(program_files);
+        }
+
+        let program_files_x86 = PathBuf::from(r"C:\Program Files (x86)");
+        if program_files_x86.exists() {
+            self.extra_search_paths.push(program_files_x86);
+        }
+
+        // Use Windows API to get all logical drives (more reliable than checking exists())
+        let available_drives = Self::get_logical_drives();
+        let drive_root_rules = IgnoreRules::new(&self.config.ignore.drive_root_patterns);
+
+        log::info!(
+            "Found {} logical drives: {:?}",
+            available_drives.len(),
+            available_drives
+        );
+
+        for drive_letter in available_drives {
+            let drive = format!("{}:\\", drive_letter);
+            let drive_path = PathBuf::from(&drive);
+
+            // Skip A: and B: (usually floppy drives)
+            if drive_letter == 'A' || drive_letter == 'B' {
+                continue;
+            }
+
+            // Verify drive is accessible by checking if we can read its root
+            if !Self::is_drive_accessible(&drive_path) {
+                log::debug!("Drive {}: is not accessible, skipping", drive_letter);
+                continue;
+            }
+
+            log::info!("Scanning drive {}: ({})", drive_letter, drive);
+
+            // If the MFT can be read, index the whole volume from it instead
+            // of guessing which directories are worth walking
+            if volume::can_read_volume(drive_letter) {
+                self.extra_search_paths.push(drive_path.clone());
+                log::info!("  Drive {}: will be indexed from its MFT", drive_letter);
+                continue;
+            }
+
+            // For non-C drives, scan ALL top-level directories (user's data drive)
+            // For C drive, only scan specific system/user directories
+            if drive_letter != 'C' {
+                // Add the entire drive root - this will search all directories on the drive
+                self.extra_search_paths.push(drive_path.clone());
+                log::info!(
+                    "  Added entire drive {}: for comprehensive search",
+                    drive_letter
+                );
+
+                // Also try to enumerate top-level directories for better organization
+                if let Ok(entries) = std::fs::read_dir(&drive_path) {
+                    let mut top_level_dirs = 0;
+                    for entry in entries.filter_map(|e| e.ok()) {
+                        if let Ok(metadata) = entry.metadata() {
+                            if metadata.is_dir() {
+                                let dir_path = entry.path();
+                                let dir_name =
+                                    dir_path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+
+                                // Skip system directories
+                                if !drive_root_rules.is_ignored(Path::new(dir_name), true) {
+                                    self.extra_search_paths.push(dir_path.clone());
+                                    top_level_dirs += 1;
+                                    log::debug!("  Added top-level dir: {}", dir_path.display());
+                                }
+                            }
+                        }
+                    }
+                    if top_level_dirs > 0 {
+                        log::info!(
+                            "  Found {} top-level directories on drive {}:",
+                            top_level_dirs,
+                            drive_letter
+                        );
+                    }
+                }
+            } else {
+                // For C: drive, only add common user directories (avoid system dirs)
+                let common_dirs = [
+                    "Users",
+                    "Projects",
+                    "Work",
+                    "Development",
+                    "Dev",
+                    "Code",
+                    "Documents",
+                    "Downloads",
+                    "Desktop",
+                    "Games",
+                    "Software",
+                    "Programs",
+                    "Apps",
+                    "Data",
+                ];
+
+                let mut found_dirs_on_drive = 0;
+                for dir_name in common_dirs {
+                    let dir_path = drive_path.join(dir_name);
+                    if dir_path.exists() && dir_path.is_dir() {
+                        self.extra_search_paths.push(dir_path.clone());
+                        found_dirs_on_drive += 1;
+                        log::debug!("  Added: {}", dir_path.display());
+                    }
+                }
+
+                if found_dirs_on_drive > 0 {
+                    log::info!("  Found {} directories on drive C:", found_dirs_on_drive);
+                }
+            }
+        }
+
+        log::info!(
+            "Initialized {} total search paths across all drives",
+            self.extra_search_paths.len()
+        );
+    }
+
+    /// Gets all logical drives using Windows API
+    #[cfg(windows)]
+    fn get_logical_drives() -> Vec<char> {
+        use windows::Win32::Storage::FileSystem::GetLogicalDrives;
+
+        unsafe {
+            let drives = GetLogicalDrives();
+            let mut result = Vec::new();
+
+            // Each bit represents a drive (bit 0 = A:, bit 1 = B:, etc.)
+            for i in 0..26 {
+                if (drives & (1u32 << i)) != 0 {
+                    let letter = (b'A' + i as u8) as char;
+                    result.push(letter);
+                }
+            }
+
+            result
+        }
+    }
+
+    /// Drive letters only exist on Windows
+    #[cfg(not(windows))]
+    fn get_logical_drives() -> Vec<char> {
+        Vec::new()
+    }
+
+    /// Checks if a drive is accessible (not just exists, but can be read)
+    fn is_drive_accessible(drive_path: &Path) -> bool {
+        // Try to read the drive root directory
+        match std::fs::read_dir(drive_path) {
+            Ok(_) => true,
+            Err(e) => {
+                log::debug!("Cannot access {}: {}", drive_path.display(), e);
+                false
+            }
+        }
+    }
+
+    /// Returns the configured and extra search paths without duplicates
+    fn search_roots(&self) -> Vec<PathBuf> {
+        let mut seen_paths: std::collections::HashSet<String> = std::collections::HashSet::new();
+        self.config
+            .search_paths
+            .iter()
+            .chain(self.extra_search_paths.iter())
+            .filter(|path| {
+                let path_str = path.to_string_lossy().to_lowercase();
+                seen_paths.insert(path_str)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Loads (or builds) the indexes on a background thread, publishes
+    /// them, then starts watching the search roots for changes
+    ///
+    /// The watcher is created first, so changes made while the indexes
+    /// load are not missed.
+    fn start_indexing(&self) {
+        let roots = self.search_roots();
+        let ignore = IgnoreSet::from_config(&self.config.ignore);
+        let full_text_config = self.config.full_text.clone();
+        let file_index = self.file_index.clone();
+        let full_text = self.full_text.clone();
+        let index_watcher = self.index_watcher.clone();
+
+        std::thread::spawn(move || {
+            let watcher = default_watcher(&roots, &ignore);
+
+            let index = Self::load_file_index(&roots, ignore);
+            *file_index.write().unwrap() = index;
+
+            if full_text_config.enabled {
+                let index =
+                    Self::load_full_text_index(&file_index.read().unwrap(), &full_text_config);
+                *full_text.write().unwrap() = index;
+            }
+
+            *index_watcher.lock().unwrap() = Some(IndexWatcher::spawn(
+                file_index,
+                full_text_config.enabled.then_some(full_text),
+                watcher,
+                FileIndex::default_path(),
+            ));
+        });
+    }
+
+    /// Loads the persistent file index, building it if needed
+    fn load_file_index(roots: &[PathBuf], ignore: IgnoreSet) -> FileIndex {
+        match FileIndex::default_path() {
+            Some(index_path) => FileIndex::load_or_build(&index_path, roots, ignore),
+            None => FileIndex::build(roots, ignore),
+        }
+    }
+
+    /// Loads the full-text index and brings it up to date with the file index
+    fn load_full_text_index(file_index: &FileIndex, config: &FullTextConfig) -> FullTextIndex {
+        match FullTextIndex::default_path() {
+            Some(path) => FullTextIndex::load_or_build(&path, file_index, config),
+            None => FullTextIndex::build(file_index, config),
+        }
+    }
+
+    /// Searches a slice of the in-memory file index for files and folders
+    /// Uses parallel processing to score all entries simultaneously
+    ///
+    /// Every worker stops picking up entries once `cancel` is cancelled,
+    /// in which case None is returned.
+    fn search_files_and_folders(
+        &self,
+        entries: &[IndexEntry],
+        query: &Query,
+        now: u64,
+        history: &LaunchHistory,
+        cancel: &CancelToken,
+    ) -> Option<Vec<SearchResult>> {
+        let normalized_query = normalize_for_search(&query.text);
+        let query_lower = query.text.to_lowercase();
+        let matches: Vec<SearchResult> = entries
+            .par_iter()
+            .take_any_while(|_| !cancel.is_cancelled())
+            .filter_map(|entry| {
+                let path_str = entry.path.to_string_lossy().to_lowercase();
+                let is_non_c_drive = !path_str.starts_with("c:");
+
+                // Skip very short names directly below a drive root
+                let under_drive_root = entry.path.parent().and_then(Path::parent).is_none();
+                if under_drive_root && entry.name.len() < 2 {
+                    return None;
+                }
+
+                let result_type = if entry.is_dir {
+                    ResultType::Folder
+                } else {
+                    ResultType::File
+                };
+                let candidate = Candidate {
+                    path: &entry.path,
+                    result_type,
+                    size: Some(entry.size),
+                    modified: Some(entry.modified),
+                    target: None,
+                };
+                if !query.matches(&candidate, now) {
+                    return None;
+                }
+
+                // Without free text, rank the most recently modified first
+                let score = if query.text.is_empty() {
+                    (entry.modified / 60) as i64
+                } else {
+                    name_score(&self.matcher, &entry.name, &normalized_query, &query_lower)?
+                };
+
+                // Boost score for files/folders on non-C drives
+                let drive_boost = if is_non_c_drive { 100 } else { 0 };
+
+                let description = entry
+                    .path
+                    .parent()
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or_default();
+
+                Some(SearchResult {
+                    name: entry.name.clone(),
+                    path: entry.path.clone(),
+                    result_type,
+                    score: score + drive_boost + history.boost_by_key(&path_str, now),
+                    description,
+                    provider: "files",
+                    shortcut: None,
+                })
+            })
+            .collect();
+
+        if cancel.is_cancelled() {
+            return None;
+        }
+        Some(matches)
+    }
+
+    /// Rebuilds the file index from disk and saves it
+    pub fn rebuild(&self) -> Result<()> {
+        let roots = self.search_roots();
+        let index = FileIndex::build(&roots, IgnoreSet::from_config(&self.config.ignore));
+        if let Some(index_path) = FileIndex::default_path() {
+            index.save(&index_path)?;
+        }
+
+        if self.config.full_text.enabled {
+            let full_text = FullTextIndex::build(&index, &self.config.full_text);
+            if let Some(full_text_path) = FullTextIndex::default_path() {
+                full_text.save(&full_text_path)?;
+            }
+            *self.full_text.write().unwrap() = full_text;
+        }
+
+        *self.file_index.write().unwrap() = index;
+        Ok(())
+    }
+
+    /// Returns the number of entries in the file index
+    pub fn len(&self) -> usize {
+        self.file_index.read().unwrap().len()
+    }
+
+    /// Returns true if the file index is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Provider for FileProvider {
+    fn id(&self) -> &'static str {
+        "files"
+    }
+
+    fn title(&self) -> &str {
+        "Files"
+    }
+
+    fn priority(&self) -> i32 {
+        FILES_PRIORITY
+    }
+
+    /// Reports the merged and re-ranked folders and files each time
+    /// another search root has been searched
+    fn query(
+        &self,
+        query: &ProviderQuery,
+        cancel: &CancelToken,
+        emit: &(dyn Fn(Vec<Section>) + Sync),
+    ) -> bool {
+        let query = query.parsed;
+        let wants_files = query.wants(ResultType::File) || query.wants(ResultType::Folder);
+        if query.is_empty() || !wants_files || (query.text.len() < 2 && !query.has_filters()) {
+            return true;
+        }
+
+        let now = query::now_secs();
+        let history = self.history.read().unwrap();
+        let file_index = self.file_index.read().unwrap();
+        let entries = file_index.entries();
+        let merged = Mutex::new(FileMatches::default());
+
+        file_index.root_ranges().into_par_iter().for_each(|range| {
+            let Some(matches) =
+                self.search_files_and_folders(&entries[range], query, now, &history, cancel)
+            else {
+                return;
+            };
+
+            // Hold the lock while reporting so updates arrive in order
+            let mut merged = merged.lock().unwrap();
+            merged.merge(matches);
+            if !cancel.is_cancelled() {
+                emit(vec![
+                    Section {
+                        provider: self.id(),
+                        title: "Folders".to_string(),
+                        priority: FOLDERS_PRIORITY,
+                        results: merged.folders.clone(),
+                    },
+                    self.section(merged.files.clone()),
+                ]);
+            }
+        });
+
+        !cancel.is_cancelled()
+    }
+}
+
+/// Folders and files found so far by a name search
+#[derive(Debug, Default)]
+struct FileMatches {
+    folders: Vec<SearchResult>,
+    files: Vec<SearchResult>,
+}
+
+impl FileMatches {
+    /// Merges newly found files and folders
+    ///
+    /// Duplicates (by case-insensitive path) are dropped and only the best
+    /// folders and files are kept, so merging root by root ends with the
+    /// same results as ranking everything at once.
+    fn merge(&mut self, matches: Vec<SearchResult>) {
+        for result in matches {
+            if result.result_type == ResultType::Folder {
+                self.folders.push(result);
+            } else {
+                self.files.push(result);
+            }
+        }
+
+        self.folders
+            .sort_unstable_by_key(|r| std::cmp::Reverse(r.score));
+        self.files
+            .sort_unstable_by_key(|r| std::cmp::Reverse(r.score));
+
+        // Remove duplicates by path (case-insensitive)
+        let mut seen_paths: std::collections::HashSet<String> = std::collections::HashSet::new();
+
+        self.folders.retain(|result| {
+            let path_lower = result.path.to_string_lossy().to_lowercase();
+            seen_paths.insert(path_lower)
+        });
+
+        self.files.retain(|result| {
+            let path_lower = result.path.to_string_lossy().to_lowercase();
+            seen_paths.insert(path_lower)
+        });
+
+        self.folders.truncate(MAX_FOLDERS);
+        self.files.truncate(MAX_FILES);
+    }
+}
+
+/// Finds files by the text inside them
+///
+/// Answers queries starting with the content search prefix. The free text
+/// is looked for as a case-insensitive phrase and the first matching line
+/// becomes the result description. Filters narrow down which files are
+/// read. With the full-text index enabled the phrase is looked up there;
+/// otherwise files are read and updates stream in root by root.
+pub struct ContentProvider {
+    /// The name search, whose indexes and configuration are shared
+    files: Arc<FileProvider>,
+}
+
+impl ContentProvider {
+    /// Creates a content search over the indexes of `files`
+    pub fn new(files: Arc<FileProvider>) -> Self {
+        Self { files }
+    }
+
+    /// Reads the indexed files looking for `phrase`
+    fn search_files(
+        &self,
+        query: &Query,
+        phrase: &str,
+        cancel: &CancelToken,
+        emit: &(dyn Fn(Vec<Section>) + Sync),
+    ) -> bool {
+        let config = &self.files.config.content;
+        let now = query::now_secs();
+        let history = self.files.history.read().unwrap();
+        let file_index = self.files.file_index.read().unwrap();
+        let entries = file_index.entries();
+        let merged = Mutex::new(Vec::new());
+
+        file_index.root_ranges().into_par_iter().for_each(|range| {
+            let matches: Vec<SearchResult> = entries[range]
+                .par_iter()
+                .take_any_while(|_| !cancel.is_cancelled())
+                .filter(|entry| {
+                    !entry.is_dir
+                        && entry.size <= config.max_file_size
+                        && content::is_searchable(&entry.path)
+                })
+                .filter(|entry| {
+                    let candidate = Candidate {
+                        path: &entry.path,
+                        result_type: ResultType::File,
+                        size: Some(entry.size),
+                        modified: Some(entry.modified),
+                        target: None,
+                    };
+                    query.matches(&candidate, now)
+                })
+                .filter_map(|entry| {
+                    let found =
+                        content::search_file(&entry.path, phrase, config.max_file_size, cancel)?;
+                    let score = content_score(&entry.name, phrase, found.match_count);
+
+                    Some(SearchResult {
+                        name: entry.name.clone(),
+                        path: entry.path.clone(),
+                        result_type: ResultType::File,
+                        score: score + history.boost(&entry.path, now),
+                        description: format!("{}: {}", found.line_number, found.excerpt),
+                        provider: self.id(),
+                        shortcut: None,
+                    })
+                })
+                .collect();
+            if cancel.is_cancelled() {
+                return;
+            }
+
+            // Hold the lock while reporting so updates arrive in order
+            let mut merged = merged.lock().unwrap();
+            merged.extend(matches);
+            merged.sort_unstable_by_key(|r: &SearchResult| std::cmp::Reverse(r.score));
+            merged.truncate(config.max_results);
+            if !cancel.is_cancelled() {
+                emit(vec![self.section(merged.clone())]);
+            }
+        });
+
+        !cancel.is_cancelled()
+    }
+
+    /// Answers a content search from the full-text index
+    ///
+    /// Only files of indexed types can be found. The line of the first
+    /// occurrence is read back from disk for each result's description.
+    fn search_full_text(
+        &self,
+        query: &Query,
+        phrase: &str,
+        cancel: &CancelToken,
+        emit: &(dyn Fn(Vec<Section>) + Sync),
+    ) -> bool {
+        let config = &self.files.config;
+        let full_text_query = FullTextQuery::parse(&query.text);
+        let now = query::now_secs();
+        let history = self.files.history.read().unwrap();
+
+        let matches = self
+            .files
+            .full_text
+            .read()
+            .unwrap()
+            .search(&full_text_query);
+        let mut found: Vec<(SearchResult, usize)> = matches
+            .into_iter()
+            .filter(|m| {
+                let candidate = Candidate {
+                    path: &m.document.path,
+                    result_type: ResultType::File,
+                    size: Some(m.document.size),
+                    modified: Some(m.document.modified),
+                    target: None,
+                };
+                query.matches(&candidate, now)
+            })
+            .map(|m| {
+                let name = display_name(&m.document.path);
+                let score = content_score(&name, phrase, m.occurrences)
+                    + history.boost(&m.document.path, now);
+                let result = SearchResult {
+                    name,
+                    path: m.document.path,
+                    result_type: ResultType::File,
+                    score,
+                    description: String::new(),
+                    provider: self.id(),
+                    shortcut: None,
+                };
+                (result, m.line_number)
+            })
+            .collect();
+        found.sort_unstable_by_key(|(r, _)| std::cmp::Reverse(r.score));
+        found.truncate(config.content.max_results);
+
+        let mut results = Vec::with_capacity(found.len());
+        let needle = full_text_query.terms.first().map_or("", String::as_str);
+        for (mut result, line_number) in found {
+            if cancel.is_cancelled() {
+                return false;
+            }
+            let excerpt = content::line_excerpt(
+                &result.path,
+                line_number,
+                needle,
+                config.full_text.max_file_size,
+            );
+            result.description = match excerpt {
+                Some(excerpt) => format!("{}: {}", line_number, excerpt),
+                None => format!("Line {}", line_number),
+            };
+            results.push(result);
+        }
+
+        emit(vec![self.section(results)]);
+        true
+    }
+}
+
+impl Provider for ContentProvider {
+    fn id(&self) -> &'static str {
+        "content"
+    }
+
+    fn title(&self) -> &str {
+        "Files"
+    }
+
+    fn priority(&self) -> i32 {
+        FILES_PRIORITY
+    }
+
+    fn trigger(&self) -> Trigger {
+        Trigger::Prefix(self.files.config.content.prefix.clone())
+    }
+
+    fn query(
+        &self,
+        query: &ProviderQuery,
+        cancel: &CancelToken,
+        emit: &(dyn Fn(Vec<Section>) + Sync),
+    ) -> bool {
+        let query = query.parsed;
+        let phrase = query.text.trim().to_lowercase();
+        if phrase.is_empty() || !query.wants(ResultType::File) {
+            return true;
+        }
+
+        if self.files.config.full_text.enabled {
+            self.search_full_text(query, &phrase, cancel, emit)
+        } else {
+            self.search_files(query, &phrase, cancel, emit)
+        }
+    }
+}
+
+/// Scores a content match: files mentioning the phrase often, or in their
+/// name, rank first
+fn content_score(name: &str, phrase: &str, occurrences: usize) -> i64 {
+    let mut score = occurrences.min(50) as i64 * 10;
+    if name.to_lowercase().contains(phrase) {
+        score += 200;
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::{run_provider, run_provider_sections};
+
+    /// Builds a provider over the given roots without touching system paths
+    fn test_provider(roots: &[PathBuf]) -> FileProvider {
+        let config = SearchConfig::default();
+        let file_index = FileIndex::build(roots, IgnoreSet::default());
+        let full_text = FullTextIndex::build(&file_index, &config.full_text);
+        let history = Arc::new(RwLock::new(LaunchHistory::new(Default::default())));
+        FileProvider::with_indexes(config, file_index, full_text, history)
+    }
+
+    #[test]
+    fn test_folders_and_files_are_separate_sections() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("reports")).unwrap();
+        std::fs::write(dir.path().join("report.txt"), b"").unwrap();
+        let provider = test_provider(&[dir.path().to_path_buf()]);
+
+        let last = run_provider_sections(&provider, "report");
+        assert_eq!(last.len(), 2);
+        assert_eq!(last[0].title, "Folders");
+        assert_eq!(last[0].results[0].name, "reports");
+        assert_eq!(last[1].title, "Files");
+        assert_eq!(last[1].results[0].name, "report.txt");
+
+        // Single characters are too vague to search the file index with
+        assert!(run_provider(&provider, "r").is_empty());
+    }
+}
@@ -0,0 +1,800 @@
+//! Full-text index for Rustle
+//!
+//! This module keeps an inverted index of the words inside documents and
+//! source files: every term maps to a posting list of the files, lines and
+//! word positions where it occurs. Content searches look their words up
+//! here instead of reading every file, which makes phrase (`quarterly
+//! report`) and prefix (`quarter*`) queries fast on large folders.
+//!
+//! The index follows the file index: it is reconciled with it on startup
+//! and updated by the watcher thread as files change. It is stored as a
+//! compact binary file under `Config::data_dir()`.
+
+#![allow(dead_code)]
+
+use crate::config::{Config, FullTextConfig};
+use crate::content;
+use crate::error::{Result, RustleError};
+use crate::index::{
+    read_string, read_strings, read_u32, read_u64, write_string, write_strings, FileIndex,
+    IndexEntry,
+};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+
+/// Magic bytes at the start of every full-text index file
+const FULL_TEXT_MAGIC: &[u8; 8] = b"RSTLFTI\0";
+
+/// Current on-disk format version
+const FULL_TEXT_VERSION: u32 = 1;
+
+/// File name of the full-text index inside the data directory
+const FULL_TEXT_FILE_NAME: &str = "full_text.bin";
+
+/// Terms longer than this (hashes, base64 blobs) are not indexed
+const MAX_TERM_LEN: usize = 64;
+
+/// Documents read in parallel before their terms are added
+const BATCH_SIZE: usize = 256;
+
+/// One occurrence of a term
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Posting {
+    /// Document the term occurs in
+    doc: u32,
+
+    /// 0-based line number
+    line: u32,
+
+    /// 0-based position among the document's terms
+    position: u32,
+}
+
+/// A file whose text is in the index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Document {
+    /// Full path to the file
+    pub path: PathBuf,
+
+    /// Size in bytes when it was indexed
+    pub size: u64,
+
+    /// Modification time in seconds since the Unix epoch when it was indexed
+    pub modified: u64,
+}
+
+/// A parsed full-text query: terms that must appear next to each other
+///
+/// The last term matches any word starting with it when the query ends
+/// with `*`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FullTextQuery {
+    /// Lowercased terms in order
+    pub terms: Vec<String>,
+
+    /// Whether the last term is a prefix
+    pub prefix: bool,
+}
+
+impl FullTextQuery {
+    /// Parses the free text of a content search
+    pub fn parse(text: &str) -> Self {
+        Self {
+            terms: tokenize(text).collect(),
+            prefix: text.trim_end().ends_with('*'),
+        }
+    }
+
+    /// Returns true if there is nothing to look for
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+}
+
+/// A document matching a full-text query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FullTextMatch {
+    /// The matching document
+    pub document: Document,
+
+    /// 1-based line of the first occurrence
+    pub line_number: usize,
+
+    /// How many times the query occurs in the document
+    pub occurrences: usize,
+}
+
+/// Changes that bring part of the full-text index in line with the file index
+#[derive(Debug, Clone, Default)]
+pub struct SyncPlan {
+    /// Documents whose file disappeared or changed
+    stale: Vec<PathBuf>,
+
+    /// Files to read, sorted by path
+    to_add: Vec<IndexEntry>,
+
+    /// Largest file whose text is read
+    max_file_size: u64,
+}
+
+impl SyncPlan {
+    /// Returns the files to read, in batches to be read in parallel
+    pub fn batches(&self) -> std::slice::Chunks<'_, IndexEntry> {
+        self.to_add.chunks(BATCH_SIZE)
+    }
+
+    /// Returns the largest file whose text is read
+    pub fn max_file_size(&self) -> u64 {
+        self.max_file_size
+    }
+}
+
+/// A file's text split into terms, ready to be added to the index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadDocument {
+    entry: IndexEntry,
+
+    /// Length of the text, counted against the indexed size limit
+    bytes: u64,
+
+    /// Terms in order, each with its 0-based line number
+    terms: Vec<(String, u32)>,
+}
+
+/// Reads and tokenizes a batch of files in parallel
+///
+/// Unreadable and binary files come back without terms, so they are kept
+/// in the index and not read again until they change.
+pub fn read_documents(entries: &[IndexEntry], max_file_size: u64) -> Vec<ReadDocument> {
+    entries
+        .par_iter()
+        .map(|entry| {
+            let text = content::read_document(&entry.path, max_file_size).unwrap_or_default();
+            let terms = text
+                .lines()
+                .enumerate()
+                .flat_map(|(line, line_text)| {
+                    tokenize(line_text).map(move |term| (term, line as u32))
+                })
+                .collect();
+            ReadDocument {
+                entry: entry.clone(),
+                bytes: text.len() as u64,
+                terms,
+            }
+        })
+        .collect()
+}
+
+/// Inverted index from terms to the places they occur
+#[derive(Debug, Clone, Default)]
+pub struct FullTextIndex {
+    /// Settings the index was built with
+    config: FullTextConfig,
+
+    /// Documents by id; removed documents leave a hole until compaction
+    docs: Vec<Option<Document>>,
+
+    /// Document ids by path
+    by_path: HashMap<PathBuf, u32>,
+
+    /// Posting lists by term, sorted so prefixes form a contiguous range
+    terms: BTreeMap<String, Vec<Posting>>,
+
+    /// Bytes of text indexed so far
+    indexed_bytes: u64,
+}
+
+impl FullTextIndex {
+    /// Creates an empty index with the given settings
+    pub fn new(config: FullTextConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Builds an index of every eligible file in `file_index`
+    pub fn build(file_index: &FileIndex, config: &FullTextConfig) -> Self {
+        let started = std::time::Instant::now();
+        let mut index = Self::new(config.clone());
+        index.reconcile(file_index.entries(), None);
+        log::info!(
+            "Built full-text index of {} documents and {} terms in {:?}",
+            index.len(),
+            index.terms.len(),
+            started.elapsed()
+        );
+        index
+    }
+
+    /// Returns the default location of the index file
+    pub fn default_path() -> Option<PathBuf> {
+        Config::data_dir().map(|p| p.join(FULL_TEXT_FILE_NAME))
+    }
+
+    /// Loads the index at `path` and brings it up to date, or builds a new one
+    ///
+    /// Only files added or changed since the index was saved are read. The
+    /// index is rebuilt if it is missing, unreadable, or was built with
+    /// different settings.
+    pub fn load_or_build(path: &Path, file_index: &FileIndex, config: &FullTextConfig) -> Self {
+        match Self::load(path) {
+            Ok(mut index) if index.config == *config => {
+                let changes = index.sync(file_index);
+                log::info!(
+                    "Loaded full-text index of {} documents ({} changed)",
+                    index.len(),
+                    changes
+                );
+                if changes > 0 {
+                    if let Err(e) = index.save(path) {
+                        log::warn!("Failed to save full-text index: {}", e);
+                    }
+                }
+                return index;
+            }
+            Ok(_) => log::info!("Full-text settings changed, rebuilding full-text index"),
+            Err(e) => log::info!("Rebuilding full-text index: {}", e),
+        }
+
+        let index = Self::build(file_index, config);
+        if let Err(e) = index.save(path) {
+            log::warn!("Failed to save full-text index: {}", e);
+        }
+        index
+    }
+
+    /// Loads an index from disk
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != FULL_TEXT_MAGIC {
+            return Err(RustleError::index_error("not a Rustle full-text index"));
+        }
+
+        let version = read_u32(&mut reader)?;
+        if version != FULL_TEXT_VERSION {
+            return Err(RustleError::index_error(format!(
+                "unsupported full-text index version {} (expected {})",
+                version, FULL_TEXT_VERSION
+            )));
+        }
+
+        let config = FullTextConfig {
+            enabled: true,
+            extensions: read_strings(&mut reader)?,
+            max_file_size: read_u64(&mut reader)?,
+            max_indexed_bytes: read_u64(&mut reader)?,
+        };
+        let indexed_bytes = read_u64(&mut reader)?;
+
+        let doc_count = read_u32(&mut reader)? as usize;
+        let mut docs = Vec::with_capacity(doc_count.min(1 << 20));
+        let mut by_path = HashMap::with_capacity(doc_count.min(1 << 20));
+        for id in 0..doc_count {
+            let document = Document {
+                path: PathBuf::from(read_string(&mut reader)?),
+                size: read_u64(&mut reader)?,
+                modified: read_u64(&mut reader)?,
+            };
+            by_path.insert(document.path.clone(), id as u32);
+            docs.push(Some(document));
+        }
+
+        let term_count = read_u32(&mut reader)? as usize;
+        let mut terms = BTreeMap::new();
+        for _ in 0..term_count {
+            let term = read_string(&mut reader)?;
+            let posting_count = read_u32(&mut reader)? as usize;
+            let mut postings = Vec::with_capacity(posting_count.min(1 << 20));
+            for _ in 0..posting_count {
+                let posting = Posting {
+                    doc: read_u32(&mut reader)?,
+                    line: read_u32(&mut reader)?,
+                    position: read_u32(&mut reader)?,
+                };
+                if posting.doc as usize >= doc_count {
+                    return Err(RustleError::index_error("posting for unknown document"));
+                }
+                postings.push(posting);
+            }
+            terms.insert(term, postings);
+        }
+
+        Ok(Self {
+            config,
+            docs,
+            by_path,
+            terms,
+            indexed_bytes,
+        })
+    }
+
+    /// Saves the index to disk
+    ///
+    /// Removed documents are left out and the remaining ids renumbered.
+    /// Writes to a temporary file first and renames it into place, like
+    /// the file index does.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let (live, new_ids) = self.live_ids();
+        let tmp_path = path.with_extension("tmp");
+        {
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            writer.write_all(FULL_TEXT_MAGIC)?;
+            writer.write_all(&FULL_TEXT_VERSION.to_le_bytes())?;
+
+            write_strings(&mut writer, &self.config.extensions)?;
+            writer.write_all(&self.config.max_file_size.to_le_bytes())?;
+            writer.write_all(&self.config.max_indexed_bytes.to_le_bytes())?;
+            writer.write_all(&self.indexed_bytes.to_le_bytes())?;
+
+            writer.write_all(&(live.len() as u32).to_le_bytes())?;
+            for document in &live {
+                write_string(&mut writer, &document.path.to_string_lossy())?;
+                writer.write_all(&document.size.to_le_bytes())?;
+                writer.write_all(&document.modified.to_le_bytes())?;
+            }
+
+            let terms: Vec<(&String, Vec<Posting>)> = self
+                .terms
+                .iter()
+                .map(|(term, postings)| (term, remap_postings(postings, &new_ids)))
+                .filter(|(_, postings)| !postings.is_empty())
+                .collect();
+            writer.write_all(&(terms.len() as u32).to_le_bytes())?;
+            for (term, postings) in terms {
+                write_string(&mut writer, term)?;
+                writer.write_all(&(postings.len() as u32).to_le_bytes())?;
+                for posting in postings {
+                    writer.write_all(&posting.doc.to_le_bytes())?;
+                    writer.write_all(&posting.line.to_le_bytes())?;
+                    writer.write_all(&posting.position.to_le_bytes())?;
+                }
+            }
+            writer.flush()?;
+        }
+
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Returns the number of indexed documents
+    pub fn len(&self) -> usize {
+        self.by_path.len()
+    }
+
+    /// Returns true if no documents are indexed
+    pub fn is_empty(&self) -> bool {
+        self.by_path.is_empty()
+    }
+
+    /// Returns the settings the index was built with
+    pub fn config(&self) -> &FullTextConfig {
+        &self.config
+    }
+
+    /// Brings every document in line with the file index
+    ///
+    /// Returns the number of documents added, changed or removed.
+    pub fn sync(&mut self, file_index: &FileIndex) -> usize {
+        self.reconcile(file_index.entries(), None)
+    }
+
+    /// Brings the documents at or below `path` in line with the file index
+    ///
+    /// Called by the watcher after applying a change to the file index.
+    pub fn sync_path(&mut self, file_index: &FileIndex, path: &Path) -> usize {
+        self.reconcile(file_index.subtree(path), Some(path))
+    }
+
+    /// Checks whether a file's text belongs in the index
+    fn is_eligible(&self, entry: &IndexEntry) -> bool {
+        if entry.is_dir || entry.size > self.config.max_file_size {
+            return false;
+        }
+        let extension = entry
+            .path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+            .unwrap_or_default();
+        self.config.extensions.contains(&extension)
+    }
+
+    /// Makes the documents at or below `scope` (or all of them) match `entries`
+    ///
+    /// Documents that disappeared or changed are removed, and new or
+    /// changed files are read in parallel batches and added until the
+    /// indexed size limit is reached. Returns the number of changes.
+    fn reconcile(&mut self, entries: &[IndexEntry], scope: Option<&Path>) -> usize {
+        let plan = self.plan_sync(entries, scope);
+        let mut changes = self.remove_stale(&plan);
+        for batch in plan.batches() {
+            if self.is_full() {
+                log::info!("Full-text index reached its size limit; some files are left out");
+                break;
+            }
+            changes += self.add_documents(read_documents(batch, self.config.max_file_size));
+        }
+        changes
+    }
+
+    /// Works out what must change for the documents at or below `scope`
+    /// (or all of them) to match `entries`
+    ///
+    /// The plan holds no borrows, so the files it lists can be read with
+    /// no lock held on either index.
+    pub fn plan_sync(&self, entries: &[IndexEntry], scope: Option<&Path>) -> SyncPlan {
+        let wanted: HashMap<&Path, &IndexEntry> = entries
+            .iter()
+            .filter(|e| self.is_eligible(e))
+            .map(|e| (e.path.as_path(), e))
+            .collect();
+
+        let stale: Vec<PathBuf> = self
+            .by_path
+            .iter()
+            .filter(|(path, _)| scope.is_none_or(|scope| path.starts_with(scope)))
+            .filter(
+                |(path, &id)| match (wanted.get(path.as_path()), &self.docs[id as usize]) {
+                    (Some(entry), Some(doc)) => {
+                        entry.modified != doc.modified || entry.size != doc.size
+                    }
+                    _ => true,
+                },
+            )
+            .map(|(path, _)| path.clone())
+            .collect();
+        let stale_paths: HashSet<&Path> = stale.iter().map(PathBuf::as_path).collect();
+
+        let mut to_add: Vec<IndexEntry> = wanted
+            .into_values()
+            .filter(|e| {
+                !self.by_path.contains_key(&e.path) || stale_paths.contains(e.path.as_path())
+            })
+            .cloned()
+            .collect();
+        to_add.sort_by(|a, b| a.path.cmp(&b.path));
+
+        SyncPlan {
+            stale,
+            to_add,
+            max_file_size: self.config.max_file_size,
+        }
+    }
+
+    /// Removes the documents a plan found missing or changed
+    ///
+    /// Returns the number removed.
+    pub fn remove_stale(&mut self, plan: &SyncPlan) -> usize {
+        let mut removed = 0;
+        for path in &plan.stale {
+            if let Some(&id) = self.by_path.get(path) {
+                self.remove_document(id);
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    /// Returns true once the indexed size limit is reached
+    pub fn is_full(&self) -> bool {
+        self.indexed_bytes >= self.config.max_indexed_bytes
+    }
+
+    /// Adds documents read by `read_documents`
+    ///
+    /// Files indexed meanwhile are skipped. Returns the number added.
+    pub fn add_documents(&mut self, documents: Vec<ReadDocument>) -> usize {
+        let mut added = 0;
+        for document in documents {
+            if !self.by_path.contains_key(&document.entry.path) {
+                self.add_document(document);
+                added += 1;
+            }
+        }
+        self.compact_if_sparse();
+        added
+    }
+
+    /// Adds a document and all of its terms
+    fn add_document(&mut self, document: ReadDocument) {
+        let ReadDocument {
+            entry,
+            bytes,
+            terms,
+        } = document;
+        let id = self.docs.len() as u32;
+        self.by_path.insert(entry.path.clone(), id);
+        self.docs.push(Some(Document {
+            path: entry.path,
+            size: entry.size,
+            modified: entry.modified,
+        }));
+        self.indexed_bytes += bytes;
+
+        for (position, (term, line)) in terms.into_iter().enumerate() {
+            self.terms.entry(term).or_default().push(Posting {
+                doc: id,
+                line,
+                position: position as u32,
+            });
+        }
+    }
+
+    /// Marks a document as removed; its postings are dropped on compaction
+    fn remove_document(&mut self, id: u32) {
+        if let Some(document) = self.docs[id as usize].take() {
+            self.by_path.remove(&document.path);
+        }
+    }
+
+    /// Returns the live documents and each old id's new id
+    fn live_ids(&self) -> (Vec<&Document>, Vec<Option<u32>>) {
+        let mut live = Vec::with_capacity(self.by_path.len());
+        let new_ids = self
+            .docs
+            .iter()
+            .map(|doc| {
+                doc.as_ref().map(|doc| {
+                    live.push(doc);
+                    (live.len() - 1) as u32
+                })
+            })
+            .collect();
+        (live, new_ids)
+    }
+
+    /// Drops removed documents and their postings once they are the majority
+    fn compact_if_sparse(&mut self) {
+        let removed = self.docs.len() - self.by_path.len();
+        if removed < 1024 || removed < self.by_path.len() {
+            return;
+        }
+
+        let (live, new_ids) = self.live_ids();
+        let docs: Vec<Option<Document>> = live.into_iter().cloned().map(Some).collect();
+        for postings in self.terms.values_mut() {
+            *postings = remap_postings(postings, &new_ids);
+        }
+        self.terms.retain(|_, postings| !postings.is_empty());
+
+        self.by_path = docs
+            .iter()
+            .enumerate()
+            .filter_map(|(id, doc)| Some((doc.as_ref()?.path.clone(), id as u32)))
+            .collect();
+        self.docs = docs;
+    }
+
+    /// Finds the documents containing the query's terms next to each other
+    ///
+    /// Results are ordered by how often the query occurs, most first.
+    pub fn search(&self, query: &FullTextQuery) -> Vec<FullTextMatch> {
+        let Some((first, rest)) = query.terms.split_first() else {
+            return Vec::new();
+        };
+
+        // Where each occurrence of the phrase starts, and on which line
+        let is_last = |i: usize| query.prefix && i == query.terms.len() - 1;
+        let mut starts: HashMap<(u32, u32), u32> = self
+            .postings(first, is_last(0))
+            .map(|p| ((p.doc, p.position), p.line))
+            .collect();
+
+        for (i, term) in rest.iter().enumerate() {
+            let offset = i as u32 + 1;
+            let next: HashSet<(u32, u32)> = self
+                .postings(term, is_last(i + 1))
+                .filter(|p| p.position >= offset)
+                .map(|p| (p.doc, p.position - offset))
+                .collect();
+            starts.retain(|start, _| next.contains(start));
+        }
+
+        let mut per_doc: HashMap<u32, (u32, usize)> = HashMap::new();
+        for ((doc, _), line) in starts {
+            let found = per_doc.entry(doc).or_insert((line, 0));
+            found.0 = found.0.min(line);
+            found.1 += 1;
+        }
+
+        let mut matches: Vec<FullTextMatch> = per_doc
+            .into_iter()
+            .filter_map(|(doc, (line, occurrences))| {
+                Some(FullTextMatch {
+                    document: self.docs[doc as usize].clone()?,
+                    line_number: line as usize + 1,
+                    occurrences,
+                })
+            })
+            .collect();
+        matches.sort_by(|a, b| {
+            b.occurrences
+                .cmp(&a.occurrences)
+                .then_with(|| a.document.path.cmp(&b.document.path))
+        });
+        matches
+    }
+
+    /// Returns the postings of a term, or of every term starting with it
+    fn postings<'a>(
+        &'a self,
+        term: &'a str,
+        prefix: bool,
+    ) -> Box<dyn Iterator<Item = &'a Posting> + 'a> {
+        if prefix {
+            Box::new(
+                self.terms
+                    .range::<str, _>((Bound::Included(term), Bound::Unbounded))
+                    .take_while(move |(t, _)| t.starts_with(term))
+                    .flat_map(|(_, postings)| postings),
+            )
+        } else {
+            Box::new(self.terms.get(term).into_iter().flatten())
+        }
+    }
+}
+
+/// Renumbers postings after removed documents are dropped
+fn remap_postings(postings: &[Posting], new_ids: &[Option<u32>]) -> Vec<Posting> {
+    postings
+        .iter()
+        .filter_map(|p| {
+            Some(Posting {
+                doc: new_ids[p.doc as usize]?,
+                ..*p
+            })
+        })
+        .collect()
+}
+
+/// Splits text into lowercased terms
+///
+/// A term is a run of letters and digits; everything else separates terms.
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty() && word.len() <= MAX_TERM_LEN)
+        .map(|word| word.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ignore::IgnoreSet;
+    use std::fs;
+
+    fn make_docs() -> (tempfile::TempDir, FileIndex) {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("minutes.md"),
+            "# Minutes\nWe will ship the quarterly report on Friday.\nQuarterly report owners: Ana\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("main.rs"),
+            "fn main() {\n    let report = quarterly_totals();\n}\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("photo.png"), "quarterly report").unwrap();
+        let file_index = FileIndex::build(&[dir.path().to_path_buf()], IgnoreSet::default());
+        (dir, file_index)
+    }
+
+    fn names(matches: &[FullTextMatch]) -> Vec<String> {
+        matches
+            .iter()
+            .map(|m| {
+                m.document
+                    .path
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let terms: Vec<String> = tokenize("Hello, wörld! let x_1 = 42;").collect();
+        assert_eq!(terms, ["hello", "wörld", "let", "x", "1", "42"]);
+    }
+
+    #[test]
+    fn test_phrase_query() {
+        let (_dir, file_index) = make_docs();
+        let index = FullTextIndex::build(&file_index, &FullTextConfig::default());
+
+        let matches = index.search(&FullTextQuery::parse("Quarterly Report"));
+        assert_eq!(names(&matches), ["minutes.md"]);
+        assert_eq!(matches[0].line_number, 2);
+        assert_eq!(matches[0].occurrences, 2);
+
+        // Both words occur in main.rs, but not next to each other
+        assert!(index
+            .search(&FullTextQuery::parse("main report"))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_prefix_query() {
+        let (_dir, file_index) = make_docs();
+        let index = FullTextIndex::build(&file_index, &FullTextConfig::default());
+
+        let matches = index.search(&FullTextQuery::parse("quarter*"));
+        assert_eq!(names(&matches), ["minutes.md", "main.rs"]);
+        assert!(index.search(&FullTextQuery::parse("quarter")).is_empty());
+        assert_eq!(
+            names(&index.search(&FullTextQuery::parse("let rep*"))),
+            ["main.rs"]
+        );
+    }
+
+    #[test]
+    fn test_sync_follows_file_changes() {
+        let (dir, mut file_index) = make_docs();
+        let mut index = FullTextIndex::build(&file_index, &FullTextConfig::default());
+
+        let notes = dir.path().join("notes.txt");
+        fs::write(&notes, "annual review").unwrap();
+        file_index.add_path(&notes);
+        fs::remove_file(dir.path().join("main.rs")).unwrap();
+        file_index.remove_path(&dir.path().join("main.rs"));
+
+        assert_eq!(index.sync_path(&file_index, &notes), 1);
+        assert_eq!(index.sync_path(&file_index, &dir.path().join("main.rs")), 1);
+        assert_eq!(
+            names(&index.search(&FullTextQuery::parse("annual"))),
+            ["notes.txt"]
+        );
+        assert!(index.search(&FullTextQuery::parse("fn main")).is_empty());
+
+        // Nothing changed, so nothing is read again
+        assert_eq!(index.sync(&file_index), 0);
+    }
+
+    #[test]
+    fn test_save_and_load_drops_removed_documents() {
+        let (dir, mut file_index) = make_docs();
+        let mut index = FullTextIndex::build(&file_index, &FullTextConfig::default());
+        file_index.remove_path(&dir.path().join("minutes.md"));
+        index.sync(&file_index);
+
+        let path = dir.path().join("data").join("full_text.bin");
+        index.save(&path).unwrap();
+        let loaded = FullTextIndex::load(&path).unwrap();
+
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded.config(), &FullTextConfig::default());
+        assert_eq!(
+            names(&loaded.search(&FullTextQuery::parse("quarterly"))),
+            ["main.rs"]
+        );
+    }
+
+    #[test]
+    fn test_size_limits() {
+        let (_dir, file_index) = make_docs();
+        let config = FullTextConfig {
+            max_file_size: 60,
+            ..FullTextConfig::default()
+        };
+        let index = FullTextIndex::build(&file_index, &config);
+
+        // minutes.md is larger than the limit
+        assert!(index.search(&FullTextQuery::parse("minutes")).is_empty());
+        assert_eq!(index.len(), 1);
+    }
+}
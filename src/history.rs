@@ -0,0 +1,346 @@
+//! Launch history for Rustle
+//!
+//! This module records every launch made from the search window and turns
+//! it into a frecency score: launches add to a per-path score that decays
+//! exponentially over time, so things used often *and* recently rank
+//! above things that were popular months ago. The history is stored as a
+//! small text file under `Config::data_dir()`.
+
+#![allow(dead_code)]
+
+use crate::config::{Config, FrecencyConfig};
+use crate::error::{Result, RustleError};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// First line of every history file
+const HISTORY_HEADER: &str = "rustle-history 1";
+
+/// File name of the history inside the data directory
+const HISTORY_FILE_NAME: &str = "launch_history.tsv";
+
+/// Launch statistics for a single path
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    /// Path that was launched
+    pub path: PathBuf,
+
+    /// Total number of launches
+    pub count: u32,
+
+    /// Time of the last launch in seconds since the Unix epoch
+    pub last_launch: u64,
+
+    /// Decayed launch score as of `last_launch`
+    pub score: f64,
+}
+
+/// All recorded launches, keyed by lowercased path
+#[derive(Debug, Clone)]
+pub struct LaunchHistory {
+    /// Entries keyed by lowercased path
+    entries: HashMap<String, HistoryEntry>,
+
+    /// Ranking settings
+    config: FrecencyConfig,
+}
+
+impl LaunchHistory {
+    /// Creates an empty history
+    pub fn new(config: FrecencyConfig) -> Self {
+        Self {
+            entries: HashMap::new(),
+            config,
+        }
+    }
+
+    /// Returns the default location of the history file
+    pub fn default_path() -> Option<PathBuf> {
+        Config::data_dir().map(|p| p.join(HISTORY_FILE_NAME))
+    }
+
+    /// Loads the history, starting empty if it is missing or unreadable
+    pub fn load_or_default(path: &Path, config: FrecencyConfig) -> Self {
+        match Self::load(path, config.clone()) {
+            Ok(history) => {
+                log::info!("Loaded launch history with {} entries", history.len());
+                history
+            }
+            Err(e) => {
+                if path.exists() {
+                    log::warn!("Ignoring launch history at {}: {}", path.display(), e);
+                }
+                Self::new(config)
+            }
+        }
+    }
+
+    /// Loads the history from disk
+    pub fn load(path: &Path, config: FrecencyConfig) -> Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut lines = reader.lines();
+
+        match lines.next() {
+            Some(Ok(header)) if header == HISTORY_HEADER => {}
+            _ => return Err(RustleError::history_error("not a Rustle launch history")),
+        }
+
+        let mut history = Self::new(config);
+        for line in lines {
+            let line = line?;
+            // Lines are `score \t last_launch \t count \t path`
+            let mut fields = line.splitn(4, '\t');
+            let parsed = (|| {
+                let score = fields.next()?.parse().ok()?;
+                let last_launch = fields.next()?.parse().ok()?;
+                let count = fields.next()?.parse().ok()?;
+                let path = PathBuf::from(fields.next()?);
+                Some(HistoryEntry {
+                    path,
+                    count,
+                    last_launch,
+                    score,
+                })
+            })();
+
+            match parsed {
+                Some(entry) => {
+                    history.entries.insert(history_key(&entry.path), entry);
+                }
+                None => log::debug!("Skipping malformed history line: {}", line),
+            }
+        }
+
+        Ok(history)
+    }
+
+    /// Saves the history to disk
+    ///
+    /// Writes to a temporary file first and renames it into place, like the
+    /// file index does.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let tmp_path = path.with_extension("tmp");
+        {
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            writeln!(writer, "{}", HISTORY_HEADER)?;
+            for entry in self.entries.values() {
+                writeln!(
+                    writer,
+                    "{}\t{}\t{}\t{}",
+                    entry.score,
+                    entry.last_launch,
+                    entry.count,
+                    entry.path.display()
+                )?;
+            }
+            writer.flush()?;
+        }
+
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Records a launch of `path` at time `now`
+    pub fn record(&mut self, path: &Path, now: u64) {
+        let half_life = self.half_life_secs();
+        let entry = self
+            .entries
+            .entry(history_key(path))
+            .or_insert_with(|| HistoryEntry {
+                path: path.to_path_buf(),
+                count: 0,
+                last_launch: now,
+                score: 0.0,
+            });
+
+        entry.score = decay(entry.score, entry.last_launch, now, half_life) + 1.0;
+        entry.count = entry.count.saturating_add(1);
+        entry.last_launch = now;
+        entry.path = path.to_path_buf();
+
+        self.prune(now);
+    }
+
+    /// Returns the decayed launch score of a path at time `now`
+    pub fn frecency(&self, path: &Path, now: u64) -> f64 {
+        self.frecency_by_key(&history_key(path), now)
+    }
+
+    /// Returns the ranking boost for a path whose lowercased form is `key`
+    ///
+    /// The boost grows with the logarithm of the frecency so a handful of
+    /// launches matters a lot but hundreds cannot drown out the text match.
+    pub fn boost_by_key(&self, key: &str, now: u64) -> i64 {
+        if !self.config.enabled {
+            return 0;
+        }
+
+        let frecency = self.frecency_by_key(key, now);
+        if frecency <= 0.0 {
+            return 0;
+        }
+
+        (self.config.weight * frecency.ln_1p()).round() as i64
+    }
+
+    /// Returns the ranking boost for a path
+    pub fn boost(&self, path: &Path, now: u64) -> i64 {
+        self.boost_by_key(&history_key(path), now)
+    }
+
+    /// Returns the recorded entry for a path
+    pub fn get(&self, path: &Path) -> Option<&HistoryEntry> {
+        self.entries.get(&history_key(path))
+    }
+
+    /// Returns the number of recorded paths
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if nothing has been launched yet
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn frecency_by_key(&self, key: &str, now: u64) -> f64 {
+        self.entries
+            .get(key)
+            .map(|e| decay(e.score, e.last_launch, now, self.half_life_secs()))
+            .unwrap_or(0.0)
+    }
+
+    fn half_life_secs(&self) -> f64 {
+        (self.config.half_life_days * 86_400.0).max(1.0)
+    }
+
+    /// Drops the least relevant entries once the history grows too large
+    fn prune(&mut self, now: u64) {
+        if self.entries.len() <= self.config.max_entries {
+            return;
+        }
+
+        let half_life = self.half_life_secs();
+        let mut ranked: Vec<(String, f64)> = self
+            .entries
+            .iter()
+            .map(|(key, e)| (key.clone(), decay(e.score, e.last_launch, now, half_life)))
+            .collect();
+        ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        let excess = self.entries.len() - self.config.max_entries;
+        for (key, _) in ranked.into_iter().take(excess) {
+            self.entries.remove(&key);
+        }
+    }
+}
+
+/// Returns the key a path is stored under (paths are case-insensitive on Windows)
+pub fn history_key(path: &Path) -> String {
+    path.to_string_lossy().to_lowercase()
+}
+
+/// Decays `score` recorded at `then` to time `now`
+fn decay(score: f64, then: u64, now: u64, half_life_secs: f64) -> f64 {
+    let elapsed = now.saturating_sub(then) as f64;
+    score * 0.5f64.powf(elapsed / half_life_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 86_400;
+
+    fn history() -> LaunchHistory {
+        LaunchHistory::new(FrecencyConfig {
+            enabled: true,
+            weight: 100.0,
+            half_life_days: 7.0,
+            max_entries: 3,
+        })
+    }
+
+    #[test]
+    fn test_record_counts_and_decays() {
+        let mut history = history();
+        let path = Path::new(r"C:\Apps\Editor.lnk");
+
+        history.record(path, 0);
+        history.record(path, 0);
+        assert_eq!(history.get(path).unwrap().count, 2);
+        assert!((history.frecency(path, 0) - 2.0).abs() < 1e-9);
+
+        // One half-life later the score has halved
+        assert!((history.frecency(path, 7 * DAY) - 1.0).abs() < 1e-9);
+
+        // Lookups ignore case
+        assert!(history.frecency(Path::new(r"c:\apps\editor.LNK"), 0) > 0.0);
+    }
+
+    #[test]
+    fn test_frequent_and_recent_rank_higher() {
+        let mut history = history();
+        let often = Path::new(r"C:\often.exe");
+        let once = Path::new(r"C:\once.exe");
+        let stale = Path::new(r"C:\stale.exe");
+
+        for _ in 0..10 {
+            history.record(stale, 0);
+        }
+        for _ in 0..5 {
+            history.record(often, 60 * DAY);
+        }
+        history.record(once, 60 * DAY);
+
+        let now = 60 * DAY;
+        assert!(history.boost(often, now) > history.boost(once, now));
+        assert!(history.boost(once, now) > history.boost(stale, now));
+        assert_eq!(history.boost(Path::new(r"C:\never.exe"), now), 0);
+    }
+
+    #[test]
+    fn test_disabled_gives_no_boost() {
+        let mut history = LaunchHistory::new(FrecencyConfig {
+            enabled: false,
+            ..FrecencyConfig::default()
+        });
+        let path = Path::new(r"C:\app.exe");
+        history.record(path, 0);
+        assert_eq!(history.boost(path, 0), 0);
+    }
+
+    #[test]
+    fn test_prune_keeps_most_relevant() {
+        let mut history = history();
+        for (i, name) in ["a", "b", "c", "d"].iter().enumerate() {
+            history.record(Path::new(name), i as u64 * DAY);
+        }
+        assert_eq!(history.len(), 3);
+        assert!(history.get(Path::new("a")).is_none());
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("history.tsv");
+
+        let mut history = history();
+        history.record(Path::new(r"D:\Projects\My App\app.exe"), 1_000);
+        history.record(Path::new(r"D:\Projects\My App\app.exe"), 2_000);
+        history.save(&file).unwrap();
+
+        let loaded = LaunchHistory::load(&file, FrecencyConfig::default()).unwrap();
+        let entry = loaded
+            .get(Path::new(r"D:\Projects\My App\app.exe"))
+            .unwrap();
+        assert_eq!(entry.count, 2);
+        assert_eq!(entry.last_launch, 2_000);
+    }
+}
@@ -0,0 +1,674 @@
+//! Ignore rules for Rustle
+//!
+//! This module decides which files and folders are left out of the index.
+//! Rules are gitignore-style glob patterns: `*`, `?` and `[a-z]` match
+//! within a name, `**` matches any number of folders, a leading or inner
+//! `/` anchors a pattern to the directory it applies to, a trailing `/`
+//! matches only directories and a leading `!` re-includes what an earlier
+//! pattern excluded. Matching is case-insensitive, as paths are on Windows.
+//!
+//! Besides the configured rules, walkers read `.gitignore`, `.ignore` and
+//! `.rustleignore` files from each directory they enter. As in git, the
+//! patterns in such a file are relative to its directory and files deeper
+//! in the tree take precedence over those above them.
+
+#![allow(dead_code)]
+
+use crate::config::{IgnoreConfig, RootIgnoreOverride};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// Per-directory ignore files, from lowest to highest precedence
+pub const IGNORE_FILE_NAMES: [&str; 3] = [".gitignore", ".ignore", ".rustleignore"];
+
+/// Ignore files larger than this are assumed not to be ignore files
+const MAX_IGNORE_FILE_SIZE: u64 = 1024 * 1024;
+
+/// A single compiled ignore pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnorePattern {
+    /// Lowercased path segments to match, with `**` as its own segment
+    segments: Vec<String>,
+
+    /// Whether a match re-includes the path (`!pattern`)
+    negated: bool,
+
+    /// Whether the pattern only matches directories (`pattern/`)
+    dir_only: bool,
+}
+
+impl IgnorePattern {
+    /// Parses one line of an ignore file
+    ///
+    /// Returns None for blank lines and `#` comments.
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        // `\!` and `\#` escape a leading `!` or `#`
+        let negated = line.starts_with('!');
+        if negated || line.starts_with("\\!") || line.starts_with("\\#") {
+            line = &line[1..];
+        }
+
+        let dir_only = line.ends_with('/');
+        let line = line.trim_end_matches('/');
+
+        // A slash anywhere but the end anchors the pattern to its base directory
+        let anchored = line.contains('/');
+        let mut segments: Vec<String> = line
+            .trim_start_matches('/')
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_lowercase())
+            .collect();
+
+        if segments.is_empty() {
+            return None;
+        }
+        if !anchored {
+            segments.insert(0, "**".to_string());
+        }
+
+        Some(Self {
+            segments,
+            negated,
+            dir_only,
+        })
+    }
+
+    /// Checks the pattern against lowercased path segments
+    fn matches(&self, path: &[String], is_dir: bool) -> bool {
+        (is_dir || !self.dir_only) && match_segments(&self.segments, path)
+    }
+}
+
+/// An ordered list of ignore patterns where the last match wins
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IgnoreRules {
+    patterns: Vec<IgnorePattern>,
+}
+
+impl IgnoreRules {
+    /// Compiles a list of patterns, skipping blank lines and comments
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Self {
+        Self {
+            patterns: patterns
+                .iter()
+                .filter_map(|p| IgnorePattern::parse(p.as_ref()))
+                .collect(),
+        }
+    }
+
+    /// Compiles the contents of an ignore file
+    pub fn parse(text: &str) -> Self {
+        Self::new(&text.lines().collect::<Vec<_>>())
+    }
+
+    /// Returns true if there are no patterns
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Returns whether the last matching pattern ignores the path
+    ///
+    /// `relative` is relative to the directory the rules apply to. Returns
+    /// None if no pattern matches.
+    pub fn decide(&self, relative: &Path, is_dir: bool) -> Option<bool> {
+        let segments = path_segments(relative);
+        if segments.is_empty() {
+            return None;
+        }
+        self.decide_segments(&segments, is_dir)
+    }
+
+    /// Returns true if the last matching pattern ignores the path
+    pub fn is_ignored(&self, relative: &Path, is_dir: bool) -> bool {
+        self.decide(relative, is_dir).unwrap_or(false)
+    }
+
+    fn decide_segments(&self, segments: &[String], is_dir: bool) -> Option<bool> {
+        self.patterns
+            .iter()
+            .rev()
+            .find(|p| p.matches(segments, is_dir))
+            .map(|p| !p.negated)
+    }
+}
+
+/// The global ignore rules plus overrides for particular directories
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnoreSet {
+    /// Pattern source lines, kept so the set can be saved with the index
+    patterns: Vec<String>,
+
+    /// Override source lines, kept for the same reason
+    overrides: Vec<RootIgnoreOverride>,
+
+    /// Whether ignore files inside walked directories are honoured
+    use_ignore_files: bool,
+
+    /// Rules applied relative to every search root
+    global: IgnoreRules,
+
+    /// Rules applied relative to their own directory, after the global ones
+    override_rules: Vec<(PathBuf, IgnoreRules)>,
+}
+
+impl Default for IgnoreSet {
+    fn default() -> Self {
+        Self::from_config(&IgnoreConfig::default())
+    }
+}
+
+impl IgnoreSet {
+    /// Compiles global patterns and per-directory overrides
+    pub fn new(patterns: &[String], overrides: &[RootIgnoreOverride]) -> Self {
+        Self {
+            patterns: patterns.to_vec(),
+            overrides: overrides.to_vec(),
+            use_ignore_files: false,
+            global: IgnoreRules::new(patterns),
+            override_rules: overrides
+                .iter()
+                .map(|o| (o.root.clone(), IgnoreRules::new(&o.patterns)))
+                .collect(),
+        }
+    }
+
+    /// Compiles the rules from the search configuration
+    pub fn from_config(config: &IgnoreConfig) -> Self {
+        Self::new(&config.patterns, &config.root_overrides)
+            .with_ignore_files(config.use_ignore_files)
+    }
+
+    /// Sets whether ignore files inside walked directories are honoured
+    pub fn with_ignore_files(mut self, enabled: bool) -> Self {
+        self.use_ignore_files = enabled;
+        self
+    }
+
+    /// Returns whether ignore files inside walked directories are honoured
+    pub fn uses_ignore_files(&self) -> bool {
+        self.use_ignore_files
+    }
+
+    /// Reads the ignore files of a directory, if they are honoured
+    pub fn read_dir_ignore(&self, dir: &Path) -> Option<Arc<DirIgnore>> {
+        if !self.use_ignore_files {
+            return None;
+        }
+        let dir_ignore = DirIgnore::read(dir);
+        (!dir_ignore.is_empty()).then(|| Arc::new(dir_ignore))
+    }
+
+    /// Reads the ignore files of `root` and every directory down to `dir`
+    ///
+    /// Walkers that start below a root use this so that the files of the
+    /// directories above them still apply.
+    pub fn file_stack(&self, root: &Path, dir: &Path) -> IgnoreFileStack {
+        let mut stack = IgnoreFileStack::default();
+        let Ok(relative) = dir.strip_prefix(root) else {
+            return stack;
+        };
+
+        let mut current = root.to_path_buf();
+        if let Some(rules) = self.read_dir_ignore(&current) {
+            stack.enter(&current, rules);
+        }
+        for component in relative.components() {
+            current.push(component);
+            if let Some(rules) = self.read_dir_ignore(&current) {
+                stack.enter(&current, rules);
+            }
+        }
+        stack
+    }
+
+    /// Returns the global pattern lines
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Returns the per-directory overrides
+    pub fn overrides(&self) -> &[RootIgnoreOverride] {
+        &self.overrides
+    }
+
+    /// Checks a single path found below `root`
+    ///
+    /// Only the path itself is tested, not its parents; walkers never
+    /// descend into ignored directories, so they do not need to. The root
+    /// itself is never ignored.
+    pub fn is_ignored(&self, root: &Path, path: &Path, is_dir: bool) -> bool {
+        self.is_ignored_in(root, path, is_dir, &IgnoreFileStack::default())
+    }
+
+    /// Checks a single path found below `root`, also applying ignore files
+    ///
+    /// Ignore files take precedence over the global rules, and per-directory
+    /// overrides take precedence over both.
+    pub fn is_ignored_in(
+        &self,
+        root: &Path,
+        path: &Path,
+        is_dir: bool,
+        files: &IgnoreFileStack,
+    ) -> bool {
+        let Ok(relative) = path.strip_prefix(root) else {
+            return false;
+        };
+        let segments = path_segments(relative);
+        if segments.is_empty() {
+            return false;
+        }
+
+        let mut decision = self.global.decide_segments(&segments, is_dir);
+        if let Some(from_files) = files.decide(path, is_dir) {
+            decision = Some(from_files);
+        }
+        for (dir, rules) in &self.override_rules {
+            if let Some(overridden) = path
+                .strip_prefix(dir)
+                .ok()
+                .and_then(|relative| rules.decide(relative, is_dir))
+            {
+                decision = Some(overridden);
+            }
+        }
+        decision.unwrap_or(false)
+    }
+
+    /// Checks a path and every directory between it and `root`
+    ///
+    /// Ignore files along the way are read from disk, so this is meant for
+    /// single paths (such as change notifications) rather than whole walks.
+    pub fn is_path_ignored(&self, root: &Path, path: &Path, is_dir: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(root) else {
+            return false;
+        };
+
+        let mut files = IgnoreFileStack::default();
+        if let Some(rules) = self.read_dir_ignore(root) {
+            files.enter(root, rules);
+        }
+
+        let mut dir = root.to_path_buf();
+        let mut components = relative.components().peekable();
+        while let Some(component) = components.next() {
+            dir.push(component);
+            let is_last = components.peek().is_none();
+            if self.is_ignored_in(root, &dir, !is_last || is_dir, &files) {
+                return true;
+            }
+            if !is_last {
+                if let Some(rules) = self.read_dir_ignore(&dir) {
+                    files.enter(&dir, rules);
+                }
+            }
+        }
+        false
+    }
+}
+
+/// Rules read from the ignore files of a single directory
+#[derive(Debug, Clone, Default)]
+pub struct DirIgnore {
+    /// Rules from `.gitignore`, which stop applying at nested repositories
+    git: IgnoreRules,
+
+    /// Rules from `.ignore` and `.rustleignore`
+    other: IgnoreRules,
+
+    /// Whether the directory is the top of a git repository
+    repo_root: bool,
+}
+
+impl DirIgnore {
+    /// Reads the ignore files of a directory, skipping any that are missing
+    pub fn read(dir: &Path) -> Self {
+        let [git_name, other_names @ ..] = IGNORE_FILE_NAMES;
+        Self {
+            git: IgnoreRules::parse(&read_ignore_file(&dir.join(git_name))),
+            other: IgnoreRules::parse(
+                &other_names
+                    .iter()
+                    .map(|name| read_ignore_file(&dir.join(name)))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            repo_root: dir.join(".git").exists(),
+        }
+    }
+
+    /// Builds the rules from file contents
+    pub fn from_contents(gitignore: &str, ignore: &str, repo_root: bool) -> Self {
+        Self {
+            git: IgnoreRules::parse(gitignore),
+            other: IgnoreRules::parse(ignore),
+            repo_root,
+        }
+    }
+
+    /// Returns true if the directory has no rules and is not a repository
+    ///
+    /// Repository roots are kept even without rules, because they stop
+    /// `.gitignore` files further up from applying.
+    pub fn is_empty(&self) -> bool {
+        self.git.is_empty() && self.other.is_empty() && !self.repo_root
+    }
+}
+
+/// Reads an ignore file, treating missing or oversized files as empty
+fn read_ignore_file(path: &Path) -> String {
+    match std::fs::metadata(path) {
+        Ok(meta) if meta.is_file() && meta.len() <= MAX_IGNORE_FILE_SIZE => std::fs::read(path)
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+            .unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+/// Ignore files of the directories between a search root and the current walk position
+///
+/// Walkers visit directories depth-first, so they keep one frame per
+/// directory that had ignore files and drop frames as they leave them.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreFileStack {
+    /// Directories and their rules, outermost first
+    frames: Vec<(PathBuf, Arc<DirIgnore>)>,
+}
+
+impl IgnoreFileStack {
+    /// Pushes the rules of a directory the walker just entered
+    pub fn enter(&mut self, dir: &Path, rules: Arc<DirIgnore>) {
+        self.leave_to(dir);
+        self.frames.push((dir.to_path_buf(), rules));
+    }
+
+    /// Drops the frames of directories that do not contain `dir`
+    pub fn leave_to(&mut self, dir: &Path) {
+        while self
+            .frames
+            .last()
+            .is_some_and(|(frame_dir, _)| !dir.starts_with(frame_dir))
+        {
+            self.frames.pop();
+        }
+    }
+
+    /// Returns true if no directory on the stack has rules
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Returns whether the innermost matching rule ignores the path
+    ///
+    /// Returns None if no ignore file has a matching pattern.
+    pub fn decide(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        // `.gitignore` files above the innermost repository do not apply
+        let repo_start = self
+            .frames
+            .iter()
+            .rposition(|(_, rules)| rules.repo_root)
+            .unwrap_or(0);
+
+        let mut decision = None;
+        for (i, (dir, rules)) in self.frames.iter().enumerate() {
+            let Ok(relative) = path.strip_prefix(dir) else {
+                continue;
+            };
+            let segments = path_segments(relative);
+            if segments.is_empty() {
+                continue;
+            }
+            if i >= repo_start {
+                if let Some(d) = rules.git.decide_segments(&segments, is_dir) {
+                    decision = Some(d);
+                }
+            }
+            if let Some(d) = rules.other.decide_segments(&segments, is_dir) {
+                decision = Some(d);
+            }
+        }
+        decision
+    }
+}
+
+/// Returns true if `name` is one of the per-directory ignore files
+pub fn is_ignore_file_name(name: &str) -> bool {
+    IGNORE_FILE_NAMES
+        .iter()
+        .any(|ignore_name| name.eq_ignore_ascii_case(ignore_name))
+}
+
+/// Splits a relative path into lowercased names
+fn path_segments(relative: &Path) -> Vec<String> {
+    relative
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name.to_string_lossy().to_lowercase()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Matches pattern segments (which may include `**`) against path segments
+fn match_segments(pattern: &[String], path: &[String]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        // A trailing `**` matches everything inside, but not the folder itself
+        Some((first, rest)) if first == "**" && rest.is_empty() => !path.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=path.len()).any(|skip| match_segments(rest, &path[skip..]))
+        }
+        Some((first, rest)) => path
+            .split_first()
+            .is_some_and(|(name, tail)| wildcard_match(first, name) && match_segments(rest, tail)),
+    }
+}
+
+/// Matches a single name against a glob with `*`, `?`, `[...]` and `\` escapes
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    // Where to resume after the most recent `*`: (pattern index, name index)
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        let step = match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, n));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(1),
+            Some('[') => match_class(&pattern[p..], name[n]),
+            Some('\\') if p + 1 < pattern.len() => (pattern[p + 1] == name[n]).then_some(2),
+            Some(&c) => (c == name[n]).then_some(1),
+            None => None,
+        };
+
+        match step {
+            Some(len) => {
+                p += len;
+                n += 1;
+            }
+            None => match backtrack {
+                // Let the last `*` swallow one more character and retry
+                Some((star_p, star_n)) => {
+                    p = star_p;
+                    n = star_n + 1;
+                    backtrack = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Matches `c` against a `[...]` class at the start of `pattern`
+///
+/// Returns the length of the class if it matches. An unterminated `[`
+/// is treated as a literal character.
+fn match_class(pattern: &[char], c: char) -> Option<usize> {
+    let mut i = 1;
+    let negated = matches!(pattern.get(i), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+
+    let start = i;
+    let mut matched = false;
+    while i < pattern.len() && (pattern[i] != ']' || i == start) {
+        if pattern.get(i + 1) == Some(&'-') && pattern.get(i + 2).is_some_and(|&e| e != ']') {
+            matched |= (pattern[i]..=pattern[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            matched |= pattern[i] == c;
+            i += 1;
+        }
+    }
+
+    if i >= pattern.len() {
+        return (c == '[').then_some(1);
+    }
+    (matched != negated).then_some(i + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(patterns: &[&str]) -> IgnoreRules {
+        IgnoreRules::new(patterns)
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(wildcard_match("*.log", "build.log"));
+        assert!(!wildcard_match("*.log", "build.log.txt"));
+        assert!(wildcard_match("file?.txt", "file1.txt"));
+        assert!(!wildcard_match("file?.txt", "file10.txt"));
+        assert!(wildcard_match("*cache*", "__pycache__"));
+        assert!(wildcard_match("[ab]in", "bin"));
+        assert!(!wildcard_match("[!ab]in", "bin"));
+        assert!(wildcard_match("v[0-9].*", "v2.zip"));
+        assert!(wildcard_match("a\\*b", "a*b"));
+        assert!(!wildcard_match("a\\*b", "axb"));
+        assert!(wildcard_match("[oops", "[oops"));
+    }
+
+    #[test]
+    fn test_unanchored_patterns_match_at_any_depth() {
+        let rules = rules(&["node_modules", "*.tmp"]);
+        assert!(rules.is_ignored(Path::new("node_modules"), true));
+        assert!(rules.is_ignored(Path::new("web/app/node_modules"), true));
+        assert!(rules.is_ignored(Path::new("docs/draft.TMP"), false));
+        assert!(!rules.is_ignored(Path::new("docs/draft.txt"), false));
+    }
+
+    #[test]
+    fn test_anchored_and_double_star_patterns() {
+        let rules = rules(&["/build", "docs/**/*.pdf", "logs/**"]);
+        assert!(rules.is_ignored(Path::new("build"), true));
+        assert!(!rules.is_ignored(Path::new("src/build"), true));
+        assert!(rules.is_ignored(Path::new("docs/a.pdf"), false));
+        assert!(rules.is_ignored(Path::new("docs/2024/q1/a.pdf"), false));
+        assert!(!rules.is_ignored(Path::new("other/docs/a.pdf"), false));
+        assert!(rules.is_ignored(Path::new("logs/today.txt"), false));
+        assert!(!rules.is_ignored(Path::new("logs"), true));
+    }
+
+    #[test]
+    fn test_directory_only_patterns() {
+        let rules = rules(&["release/"]);
+        assert!(rules.is_ignored(Path::new("Release"), true));
+        assert!(!rules.is_ignored(Path::new("release"), false));
+    }
+
+    #[test]
+    fn test_negation_last_match_wins() {
+        let rules = rules(&["*.log", "!keep.log", "# comment", "", "\\!bang"]);
+        assert!(rules.is_ignored(Path::new("debug.log"), false));
+        assert!(!rules.is_ignored(Path::new("keep.log"), false));
+        assert_eq!(rules.decide(Path::new("readme.md"), false), None);
+        assert!(rules.is_ignored(Path::new("!bang"), false));
+    }
+
+    #[test]
+    fn test_root_overrides() {
+        let root = PathBuf::from("data");
+        let builds = root.join("Builds");
+        let set = IgnoreSet::new(
+            &["release".to_string()],
+            &[RootIgnoreOverride {
+                root: builds.clone(),
+                patterns: vec!["!Release/".to_string()],
+            }],
+        );
+
+        assert!(set.is_ignored(&root, &root.join("src").join("release"), true));
+        assert!(!set.is_ignored(&root, &builds.join("Release"), true));
+        // The root itself is never ignored
+        assert!(!set.is_ignored(&root, &root, true));
+    }
+
+    #[test]
+    fn test_is_path_ignored_checks_parents() {
+        let root = PathBuf::from("root");
+        let set = IgnoreSet::new(&["target/".to_string()], &[]);
+        let path = root
+            .join("crate")
+            .join("target")
+            .join("debug")
+            .join("app.exe");
+
+        assert!(!set.is_ignored(&root, &path, false));
+        assert!(set.is_path_ignored(&root, &path, false));
+        assert!(!set.is_path_ignored(&root, &root.join("crate").join("target"), false));
+    }
+
+    #[test]
+    fn test_file_stack_deeper_files_win() {
+        let root = PathBuf::from("root");
+        let repo = root.join("repo");
+        let nested = repo.join("vendor");
+        let mut files = IgnoreFileStack::default();
+        files.enter(&root, Arc::new(DirIgnore::from_contents("*.o", "", false)));
+        files.enter(&repo, Arc::new(DirIgnore::from_contents("*.log", "", true)));
+
+        assert_eq!(files.decide(&repo.join("a.log"), false), Some(true));
+        // `.gitignore` above the innermost repository no longer applies
+        assert_eq!(files.decide(&repo.join("a.o"), false), None);
+
+        files.enter(
+            &nested,
+            Arc::new(DirIgnore::from_contents("", "!*.log", false)),
+        );
+        assert_eq!(files.decide(&nested.join("b.log"), false), Some(false));
+
+        // Leaving the nested directory drops its rules
+        files.leave_to(&repo.join("src"));
+        assert_eq!(
+            files.decide(&repo.join("src").join("b.log"), false),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn test_default_rules_cover_builtin_list() {
+        let set = IgnoreSet::default();
+        let root = Path::new("root");
+        for name in [".git", "$Recycle.Bin", "node_modules", "AppData", "Release"] {
+            assert!(set.is_ignored(root, &root.join(name), true), "{}", name);
+        }
+        assert!(!set.is_ignored(root, &root.join("Documents"), true));
+    }
+}
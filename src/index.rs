@@ -0,0 +1,1269 @@
+//! Persistent file index for Rustle
+//!
+//! This module maintains an on-disk index of the files and folders found
+//! under the search roots. The index is built once by walking the roots,
+//! stored under `Config::data_dir()`, and loaded back into memory at
+//! startup so that queries never have to touch the disk.
+
+#![allow(dead_code)]
+
+use crate::config::{Config, RootIgnoreOverride};
+use crate::error::{Result, RustleError};
+use crate::ignore::{is_ignore_file_name, DirIgnore, IgnoreFileStack, IgnoreSet};
+use crate::mft::VolumeEntry;
+use crate::utils::display_name;
+use crate::volume;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+/// Magic bytes at the start of every index file
+const INDEX_MAGIC: &[u8; 8] = b"RSTLIDX\0";
+
+/// Current on-disk format version
+///
+/// Bump this whenever the layout written by `FileIndex::save` changes.
+/// Index files with a different version are discarded and rebuilt.
+pub const INDEX_VERSION: u32 = 5;
+
+/// File name of the index inside the data directory
+const INDEX_FILE_NAME: &str = "file_index.bin";
+
+/// Longest string accepted from an index file: the longest Windows path,
+/// with every UTF-16 unit taking three bytes in UTF-8
+const MAX_STRING_BYTES: usize = 32_767 * 3;
+
+/// Most roots, ignore patterns or overrides accepted from an index file
+const MAX_LIST_LEN: usize = 1 << 16;
+
+/// Fewest bytes a stored entry takes: an empty path, flags, size and time
+const MIN_ENTRY_BYTES: u64 = 4 + 1 + 8 + 8;
+
+/// A single file or folder stored in the index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// Full path to the file or folder
+    pub path: PathBuf,
+
+    /// Display name (derived from the path, not stored on disk)
+    pub name: String,
+
+    /// Whether the entry is a directory
+    pub is_dir: bool,
+
+    /// Size in bytes (0 for directories)
+    pub size: u64,
+
+    /// Last modification time in seconds since the Unix epoch
+    pub modified: u64,
+}
+
+impl IndexEntry {
+    /// Creates an entry for the given path, reading its metadata from disk
+    ///
+    /// Returns None if the metadata cannot be read.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let metadata = std::fs::symlink_metadata(path).ok()?;
+        Some(Self::from_metadata(path, &metadata))
+    }
+
+    /// Creates an entry from already-fetched metadata
+    pub fn from_metadata(path: &Path, metadata: &std::fs::Metadata) -> Self {
+        let is_dir = metadata.is_dir();
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        Self {
+            path: path.to_path_buf(),
+            name: display_name(path),
+            is_dir,
+            size: if is_dir { 0 } else { metadata.len() },
+            modified,
+        }
+    }
+}
+
+/// In-memory file index, persisted to disk between runs
+#[derive(Debug, Clone, Default)]
+pub struct FileIndex {
+    /// Roots that were walked to build this index
+    roots: Vec<PathBuf>,
+
+    /// Roots read from the MFT rather than walked
+    full_roots: Vec<PathBuf>,
+
+    /// Rules deciding which files and folders are left out
+    ignore: IgnoreSet,
+
+    /// All indexed files and folders
+    entries: Vec<IndexEntry>,
+
+    /// When the index file was written, in seconds since the Unix epoch,
+    /// if the index was read from disk; it may miss changes made since
+    saved_at: Option<u64>,
+}
+
+impl FileIndex {
+    /// Builds a new index by walking every root
+    ///
+    /// Drive roots whose Master File Table can be read are indexed in full
+    /// from the MFT; every other root is walked. Roots are processed in
+    /// parallel and entries are sorted by path so that the result does not
+    /// depend on enumeration order.
+    pub fn build(roots: &[PathBuf], ignore: IgnoreSet) -> Self {
+        let scanned: Vec<(Vec<IndexEntry>, bool)> = roots
+            .par_iter()
+            .map(|root| scan_root(root, &ignore))
+            .collect();
+
+        let full_roots = roots
+            .iter()
+            .zip(&scanned)
+            .filter(|(_, (_, full))| *full)
+            .map(|(root, _)| root.clone())
+            .collect();
+
+        let mut entries: Vec<IndexEntry> = scanned
+            .into_iter()
+            .flat_map(|(entries, _)| entries)
+            .collect();
+
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        entries.dedup_by(|a, b| a.path == b.path);
+
+        log::info!(
+            "Built file index with {} entries from {} roots",
+            entries.len(),
+            roots.len()
+        );
+
+        Self {
+            roots: roots.to_vec(),
+            full_roots,
+            ignore,
+            entries,
+            saved_at: None,
+        }
+    }
+
+    /// Returns the default location of the index file
+    pub fn default_path() -> Option<PathBuf> {
+        Config::data_dir().map(|p| p.join(INDEX_FILE_NAME))
+    }
+
+    /// Loads the index at `path`, or builds and saves a new one
+    ///
+    /// A rebuild happens when the file is missing, unreadable, written by
+    /// a different format version, or was built from different roots or
+    /// ignore rules. A loaded index still has to be reconciled with the
+    /// disk; `IndexWatcher` does that with `FileIndex::reconcile_check`.
+    pub fn load_or_build(path: &Path, roots: &[PathBuf], ignore: IgnoreSet) -> Self {
+        match Self::load(path) {
+            Ok(index) if index.roots == roots && index.ignore == ignore => {
+                log::info!(
+                    "Loaded file index with {} entries from {}",
+                    index.entries.len(),
+                    path.display()
+                );
+                return index;
+            }
+            Ok(_) => log::info!("Search roots or ignore rules changed, rebuilding file index"),
+            Err(e) => log::info!("Rebuilding file index: {}", e),
+        }
+
+        let index = Self::build(roots, ignore);
+        if let Err(e) = index.save(path) {
+            log::warn!("Failed to save file index: {}", e);
+        }
+        index
+    }
+
+    /// Loads an index from disk
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != INDEX_MAGIC {
+            return Err(RustleError::index_error("not a Rustle index file"));
+        }
+
+        let version = read_u32(&mut reader)?;
+        if version != INDEX_VERSION {
+            return Err(RustleError::index_error(format!(
+                "index version {} does not match expected version {}",
+                version, INDEX_VERSION
+            )));
+        }
+        let saved_at = read_u64(&mut reader)?;
+
+        let root_count = read_count(&mut reader, MAX_LIST_LEN)?;
+        let mut roots = Vec::with_capacity(root_count);
+        for _ in 0..root_count {
+            roots.push(PathBuf::from(read_string(&mut reader)?));
+        }
+
+        let full_root_count = read_count(&mut reader, MAX_LIST_LEN)?;
+        let mut full_roots = Vec::with_capacity(full_root_count);
+        for _ in 0..full_root_count {
+            full_roots.push(PathBuf::from(read_string(&mut reader)?));
+        }
+
+        let patterns = read_strings(&mut reader)?;
+        let override_count = read_count(&mut reader, MAX_LIST_LEN)?;
+        let mut overrides = Vec::with_capacity(override_count);
+        for _ in 0..override_count {
+            overrides.push(RootIgnoreOverride {
+                root: PathBuf::from(read_string(&mut reader)?),
+                patterns: read_strings(&mut reader)?,
+            });
+        }
+        let use_ignore_files = read_u32(&mut reader)? != 0;
+        let ignore = IgnoreSet::new(&patterns, &overrides).with_ignore_files(use_ignore_files);
+
+        let entry_count = read_u64(&mut reader)?;
+        if entry_count > file_len / MIN_ENTRY_BYTES {
+            return Err(RustleError::index_error(format!(
+                "entry count {} does not fit in a {} byte file",
+                entry_count, file_len
+            )));
+        }
+        let entry_count = entry_count as usize;
+        let mut entries = Vec::with_capacity(entry_count.min(1 << 20));
+        for _ in 0..entry_count {
+            let path = PathBuf::from(read_string(&mut reader)?);
+            let mut flags = [0u8; 1];
+            reader.read_exact(&mut flags)?;
+            let size = read_u64(&mut reader)?;
+            let modified = read_u64(&mut reader)?;
+
+            entries.push(IndexEntry {
+                name: display_name(&path),
+                path,
+                is_dir: flags[0] & 1 != 0,
+                size,
+                modified,
+            });
+        }
+
+        Ok(Self {
+            roots,
+            full_roots,
+            ignore,
+            entries,
+            saved_at: Some(saved_at),
+        })
+    }
+
+    /// Saves the index to disk
+    ///
+    /// Writes to a temporary file first and renames it into place so a
+    /// crash mid-write never leaves a truncated index behind.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let tmp_path = path.with_extension("tmp");
+        {
+            let file = File::create(&tmp_path)?;
+            let mut writer = BufWriter::new(file);
+
+            writer.write_all(INDEX_MAGIC)?;
+            writer.write_all(&INDEX_VERSION.to_le_bytes())?;
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            writer.write_all(&now.to_le_bytes())?;
+
+            writer.write_all(&(self.roots.len() as u32).to_le_bytes())?;
+            for root in &self.roots {
+                write_string(&mut writer, &root.to_string_lossy())?;
+            }
+
+            writer.write_all(&(self.full_roots.len() as u32).to_le_bytes())?;
+            for root in &self.full_roots {
+                write_string(&mut writer, &root.to_string_lossy())?;
+            }
+
+            write_strings(&mut writer, self.ignore.patterns())?;
+            let overrides = self.ignore.overrides();
+            writer.write_all(&(overrides.len() as u32).to_le_bytes())?;
+            for ignore_override in overrides {
+                write_string(&mut writer, &ignore_override.root.to_string_lossy())?;
+                write_strings(&mut writer, &ignore_override.patterns)?;
+            }
+            writer.write_all(&(self.ignore.uses_ignore_files() as u32).to_le_bytes())?;
+
+            writer.write_all(&(self.entries.len() as u64).to_le_bytes())?;
+            for entry in &self.entries {
+                write_string(&mut writer, &entry.path.to_string_lossy())?;
+                writer.write_all(&[entry.is_dir as u8])?;
+                writer.write_all(&entry.size.to_le_bytes())?;
+                writer.write_all(&entry.modified.to_le_bytes())?;
+            }
+
+            writer.flush()?;
+        }
+
+        std::fs::rename(&tmp_path, path)?;
+        log::debug!("Saved file index to {}", path.display());
+        Ok(())
+    }
+
+    /// Returns the roots this index was built from
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    /// Returns true if the index was read from disk rather than built
+    pub fn is_loaded(&self) -> bool {
+        self.saved_at.is_some()
+    }
+
+    /// Lists what to check to find the changes made while Rustle was not
+    /// running; None if the index was built rather than read from disk
+    ///
+    /// Only the paths are gathered here, so the checks themselves can run
+    /// without the index locked.
+    pub fn reconcile_check(&self) -> Option<ReconcileCheck> {
+        let saved_at = self.saved_at?;
+        let dirs = self
+            .roots
+            .iter()
+            .chain(self.entries.iter().filter(|e| e.is_dir).map(|e| &e.path))
+            .filter(|dir| !self.full_roots.iter().any(|root| dir.starts_with(root)))
+            .cloned()
+            .collect();
+        Some(ReconcileCheck {
+            saved_at,
+            full_roots: self.full_roots.clone(),
+            dirs,
+        })
+    }
+
+    /// Returns the entries below each root, one contiguous range per root
+    ///
+    /// Roots nested inside another root are covered by the outer root's
+    /// range and get no range of their own, so no entry appears twice.
+    pub fn root_ranges(&self) -> Vec<std::ops::Range<usize>> {
+        let mut roots: Vec<&PathBuf> = self.roots.iter().collect();
+        roots.sort();
+        roots.dedup();
+
+        roots
+            .iter()
+            .filter(|root| {
+                !roots
+                    .iter()
+                    .any(|other| other != *root && root.starts_with(other))
+            })
+            .map(|root| self.subtree_range(root))
+            .filter(|range| !range.is_empty())
+            .collect()
+    }
+
+    /// Returns the rules deciding which files and folders are left out
+    pub fn ignore(&self) -> &IgnoreSet {
+        &self.ignore
+    }
+
+    /// Returns all indexed entries
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    /// Returns the number of indexed entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the index has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Finds the nearest root covering a path
+    ///
+    /// Returns None if the path is outside every root or is ignored (or
+    /// inside an ignored directory).
+    fn covering_root(&self, path: &Path) -> Option<&PathBuf> {
+        let is_dir = path.is_dir();
+        self.roots
+            .iter()
+            .filter_map(|root| {
+                let depth = path.strip_prefix(root).ok()?.components().count();
+                if self.ignore.is_path_ignored(root, path, is_dir) {
+                    return None;
+                }
+                Some((root, depth))
+            })
+            .min_by_key(|(_, depth)| *depth)
+            .map(|(root, _)| root)
+    }
+
+    /// Returns true if the path falls inside the indexed area
+    pub fn covers(&self, path: &Path) -> bool {
+        self.covering_root(path).is_some()
+    }
+
+    /// Returns the position range of a path and all of its descendants
+    ///
+    /// Entries are kept sorted by path, and path ordering compares whole
+    /// components, so a directory's descendants directly follow it.
+    fn subtree_range(&self, path: &Path) -> std::ops::Range<usize> {
+        let start = self.entries.partition_point(|e| e.path.as_path() < path);
+        let len = self.entries[start..]
+            .iter()
+            .take_while(|e| e.path.starts_with(path))
+            .count();
+        start..start + len
+    }
+
+    /// Returns the entries for a path and everything below it
+    pub fn subtree(&self, path: &Path) -> &[IndexEntry] {
+        &self.entries[self.subtree_range(path)]
+    }
+
+    /// Inserts or replaces a single entry, keeping the index sorted
+    pub fn insert(&mut self, entry: IndexEntry) {
+        match self
+            .entries
+            .binary_search_by(|e| e.path.as_path().cmp(&entry.path))
+        {
+            Ok(pos) => self.entries[pos] = entry,
+            Err(pos) => self.entries.insert(pos, entry),
+        }
+    }
+
+    /// Adds a newly created file or folder (and any contents) to the index
+    pub fn add_path(&mut self, path: &Path) {
+        let Some(root) = self.covering_root(path).cloned() else {
+            return;
+        };
+
+        let Some(entry) = IndexEntry::from_path(path) else {
+            return;
+        };
+
+        if entry.is_dir {
+            // A directory may have been moved in together with its contents
+            self.remove_path(path);
+            for child in walk_subtree(path, &root, &self.ignore) {
+                self.insert(child);
+            }
+        } else {
+            self.insert(entry);
+        }
+    }
+
+    /// Refreshes the size and modification time of an indexed entry
+    pub fn update_path(&mut self, path: &Path) {
+        if !self.covers(path) {
+            return;
+        }
+
+        if let Some(entry) = IndexEntry::from_path(path) {
+            self.insert(entry);
+        }
+    }
+
+    /// Removes a path and everything below it from the index
+    pub fn remove_path(&mut self, path: &Path) {
+        let range = self.subtree_range(path);
+        self.entries.drain(range);
+    }
+
+    /// Moves a path and everything below it to a new location
+    pub fn rename_path(&mut self, from: &Path, to: &Path) {
+        let range = self.subtree_range(from);
+        let moved: Vec<IndexEntry> = self.entries.drain(range).collect();
+
+        if !self.covers(to) {
+            return;
+        }
+
+        if moved.is_empty() {
+            // The old location was never indexed, so index the new one fresh
+            self.add_path(to);
+            return;
+        }
+
+        for mut entry in moved {
+            let relative = entry.path.strip_prefix(from).unwrap_or(Path::new(""));
+            entry.path = if relative.as_os_str().is_empty() {
+                to.to_path_buf()
+            } else {
+                to.join(relative)
+            };
+            entry.name = display_name(&entry.path);
+            if self.covers(&entry.path) {
+                self.insert(entry);
+            }
+        }
+    }
+
+    /// Re-reads a directory, replacing whatever the index held for it
+    pub fn rescan_path(&mut self, path: &Path) {
+        let entries = self.plan_rescan(path).run();
+        self.replace_subtree(path, entries);
+    }
+
+    /// Plans a rescan of a directory, to be run without borrowing the index
+    pub fn plan_rescan(&self, path: &Path) -> SubtreeScan {
+        let root = self.covering_root(path).cloned();
+        SubtreeScan {
+            path: path.to_path_buf(),
+            from_mft: root
+                .as_ref()
+                .is_some_and(|root| self.full_roots.contains(root)),
+            root,
+            ignore: self.ignore.clone(),
+        }
+    }
+
+    /// Replaces a path and everything below it with freshly scanned entries
+    ///
+    /// `entries` must be sorted by path and lie at or below `path`, as
+    /// returned by `SubtreeScan::run`.
+    pub fn replace_subtree(&mut self, path: &Path, entries: Vec<IndexEntry>) {
+        let range = self.subtree_range(path);
+        self.entries.splice(range, entries);
+    }
+}
+
+/// Indexes a single root, preferring the MFT for whole drives
+///
+/// Returns the entries and whether they came from the MFT.
+fn scan_root(root: &Path, ignore: &IgnoreSet) -> (Vec<IndexEntry>, bool) {
+    if volume::drive_root_letter(root).is_some() {
+        match read_mft_root(root, ignore) {
+            Ok(entries) => return (entries, true),
+            Err(e) => log::debug!("Falling back to walking {}: {}", root.display(), e),
+        }
+    }
+
+    (walk_root(root, ignore), false)
+}
+
+/// Reads every entry of a drive root from its MFT, leaving out ignored ones
+fn read_mft_root(root: &Path, ignore: &IgnoreSet) -> Result<Vec<IndexEntry>> {
+    let volume_entries = volume::read_volume_entries(root)?;
+    let mut ignored_dirs = IgnoredDirs::new(root, ignore);
+    if ignore.uses_ignore_files() {
+        ignored_dirs.read_ignore_files(&volume_entries);
+    }
+    Ok(volume_entries
+        .into_iter()
+        .filter(|e| {
+            let parent = e.relative_path.parent().unwrap_or(Path::new(""));
+            !ignored_dirs.is_ignored(parent)
+                && !ignore.is_ignored_in(
+                    root,
+                    &root.join(&e.relative_path),
+                    e.is_dir,
+                    &ignored_dirs.file_stack(parent),
+                )
+        })
+        .map(|e| {
+            let path = root.join(&e.relative_path);
+            IndexEntry {
+                name: display_name(&path),
+                path,
+                is_dir: e.is_dir,
+                size: e.size,
+                modified: e.modified,
+            }
+        })
+        .collect())
+}
+
+/// A rescan of one directory, planned while the index is locked and run
+/// once the lock is released
+///
+/// Rescanning a drive root means reading its whole MFT, and a walk can take
+/// just as long, so neither should happen while searches are shut out.
+#[derive(Debug, Clone)]
+pub struct SubtreeScan {
+    /// Directory to rescan
+    path: PathBuf,
+
+    /// Root covering the directory; None if the directory is outside the
+    /// indexed area
+    root: Option<PathBuf>,
+
+    /// Whether the root is read from the MFT rather than walked
+    from_mft: bool,
+
+    ignore: IgnoreSet,
+}
+
+impl SubtreeScan {
+    /// Returns the directory being rescanned
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads the directory and everything below it from disk
+    ///
+    /// Returns the entries sorted by path, ready for
+    /// `FileIndex::replace_subtree`.
+    pub fn run(&self) -> Vec<IndexEntry> {
+        let Some(root) = &self.root else {
+            return Vec::new();
+        };
+
+        if self.from_mft {
+            match read_mft_root(root, &self.ignore) {
+                Ok(mut entries) => {
+                    entries.retain(|e| e.path.starts_with(&self.path));
+                    entries.sort_by(|a, b| a.path.cmp(&b.path));
+                    return entries;
+                }
+                Err(e) => log::debug!("Falling back to walking {}: {}", self.path.display(), e),
+            }
+        }
+
+        let Some(entry) = IndexEntry::from_path(&self.path) else {
+            return Vec::new();
+        };
+        let mut entries = if entry.is_dir {
+            walk_subtree(&self.path, root, &self.ignore)
+        } else {
+            vec![entry]
+        };
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        entries
+    }
+}
+
+/// The directories of a loaded index to check for changes made while
+/// Rustle was not running
+///
+/// Adding, removing or renaming an entry updates the modification time of
+/// the directory holding it, so only directories modified since the index
+/// was saved need a rescan. Files edited in place keep their old size and
+/// time until they change again. Roots read from the MFT are read again in
+/// full instead, which takes less time than checking each directory.
+#[derive(Debug, Clone)]
+pub struct ReconcileCheck {
+    /// When the index was saved, in seconds since the Unix epoch
+    saved_at: u64,
+
+    full_roots: Vec<PathBuf>,
+
+    /// Walked roots and the directories indexed below them
+    dirs: Vec<PathBuf>,
+}
+
+impl ReconcileCheck {
+    /// Returns the directories to rescan, outer ones only, since a rescan
+    /// takes in everything below the directory
+    ///
+    /// Directories that are gone are included, so their entries are
+    /// dropped.
+    pub fn changed_dirs(&self) -> Vec<PathBuf> {
+        let mut changed: Vec<PathBuf> = self
+            .dirs
+            .iter()
+            .filter(|dir| {
+                IndexEntry::from_path(dir).is_none_or(|entry| entry.modified >= self.saved_at)
+            })
+            .cloned()
+            .chain(self.full_roots.iter().cloned())
+            .collect();
+        changed.sort();
+        changed.dedup();
+
+        let mut outer: Vec<PathBuf> = Vec::new();
+        for dir in changed {
+            if !outer.last().is_some_and(|last| dir.starts_with(last)) {
+                outer.push(dir);
+            }
+        }
+        outer
+    }
+}
+
+/// Remembers which directories below a root are ignored
+///
+/// MFT entries arrive in no particular order, so each one's parent
+/// directories are checked once and the answers cached.
+struct IgnoredDirs<'a> {
+    root: &'a Path,
+    ignore: &'a IgnoreSet,
+    cache: HashMap<PathBuf, bool>,
+
+    /// Rules from ignore files, keyed by the relative directory holding them
+    dir_ignores: HashMap<PathBuf, Arc<DirIgnore>>,
+}
+
+impl<'a> IgnoredDirs<'a> {
+    fn new(root: &'a Path, ignore: &'a IgnoreSet) -> Self {
+        Self {
+            root,
+            ignore,
+            cache: HashMap::new(),
+            dir_ignores: HashMap::new(),
+        }
+    }
+
+    /// Reads the ignore files found among the volume entries
+    ///
+    /// Directories holding a `.git` folder are included too, since they
+    /// mark where `.gitignore` files from further up stop applying.
+    fn read_ignore_files(&mut self, entries: &[VolumeEntry]) {
+        for entry in entries {
+            let Some(name) = entry.relative_path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if !is_ignore_file_name(name) && name != ".git" {
+                continue;
+            }
+            let dir = entry.relative_path.parent().unwrap_or(Path::new(""));
+            if self.dir_ignores.contains_key(dir) {
+                continue;
+            }
+            if let Some(rules) = self.ignore.read_dir_ignore(&self.root.join(dir)) {
+                self.dir_ignores.insert(dir.to_path_buf(), rules);
+            }
+        }
+    }
+
+    /// Returns the ignore files that apply to entries of the directory at `relative`
+    fn file_stack(&self, relative: &Path) -> IgnoreFileStack {
+        let mut stack = IgnoreFileStack::default();
+        if self.dir_ignores.is_empty() {
+            return stack;
+        }
+
+        let ancestors: Vec<&Path> = relative.ancestors().collect();
+        for dir in ancestors.into_iter().rev() {
+            if let Some(rules) = self.dir_ignores.get(dir) {
+                stack.enter(&self.root.join(dir), rules.clone());
+            }
+        }
+        stack
+    }
+
+    /// Returns true if the directory at `relative`, or any parent, is ignored
+    fn is_ignored(&mut self, relative: &Path) -> bool {
+        if relative.as_os_str().is_empty() {
+            return false;
+        }
+        if let Some(&ignored) = self.cache.get(relative) {
+            return ignored;
+        }
+
+        let parent = relative.parent().unwrap_or(Path::new(""));
+        let ignored = self.is_ignored(parent)
+            || self.ignore.is_ignored_in(
+                self.root,
+                &self.root.join(relative),
+                true,
+                &self.file_stack(parent),
+            );
+        self.cache.insert(relative.to_path_buf(), ignored);
+        ignored
+    }
+}
+
+/// Walks a single root and returns its entries
+///
+/// Roots are walked as deep as they go, like the MFT is read, so only the
+/// ignore rules keep a walk from taking in a whole drive.
+pub(crate) fn walk_root(root: &Path, ignore: &IgnoreSet) -> Vec<IndexEntry> {
+    walk_subtree(root, root, ignore)
+}
+
+/// Walks a directory below `root` and returns its entries
+///
+/// The directory itself is always included, even if it would normally be
+/// ignored. Ignore files are read from each directory as it is entered.
+fn walk_subtree(dir: &Path, root: &Path, ignore: &IgnoreSet) -> Vec<IndexEntry> {
+    if !dir.exists() {
+        return Vec::new();
+    }
+
+    let mut files = ignore.file_stack(root, dir);
+    WalkDir::new(dir)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(move |e| {
+            // Never filter out the starting directory itself
+            if e.depth() == 0 {
+                return true;
+            }
+
+            // Entries arrive depth-first, so leaving a directory's subtree
+            // shows up as an entry outside it
+            let path = e.path();
+            if let Some(parent) = path.parent() {
+                files.leave_to(parent);
+            }
+
+            let is_dir = e.file_type().is_dir();
+            if ignore.is_ignored_in(root, path, is_dir, &files) {
+                return false;
+            }
+            if is_dir {
+                if let Some(rules) = ignore.read_dir_ignore(path) {
+                    files.enter(path, rules);
+                }
+            }
+            true
+        })
+        .filter_map(|e| e.ok())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some(IndexEntry::from_metadata(entry.path(), &metadata))
+        })
+        .collect()
+}
+
+pub(crate) fn read_strings(reader: &mut impl Read) -> Result<Vec<String>> {
+    let count = read_count(reader, MAX_LIST_LEN)?;
+    let mut strings = Vec::with_capacity(count);
+    for _ in 0..count {
+        strings.push(read_string(reader)?);
+    }
+    Ok(strings)
+}
+
+pub(crate) fn read_u32(reader: &mut impl Read) -> Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+pub(crate) fn read_u64(reader: &mut impl Read) -> Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Reads a u32 count, rejecting counts above `max` as corrupt
+fn read_count(reader: &mut impl Read, max: usize) -> Result<usize> {
+    let count = read_u32(reader)? as usize;
+    if count > max {
+        return Err(RustleError::index_error(format!(
+            "count {} exceeds the limit of {}",
+            count, max
+        )));
+    }
+    Ok(count)
+}
+
+/// Reads a length-prefixed string
+///
+/// The length is checked before anything is allocated, so a corrupt
+/// length cannot ask for gigabytes of memory.
+pub(crate) fn read_string(reader: &mut impl Read) -> Result<String> {
+    let len = read_count(reader, MAX_STRING_BYTES)?;
+    let mut buf = Vec::with_capacity(len);
+    reader.by_ref().take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() < len {
+        return Err(RustleError::index_error("index file ends mid-string"));
+    }
+    String::from_utf8(buf).map_err(|e| RustleError::Utf8Error(e.to_string()))
+}
+
+pub(crate) fn write_string(writer: &mut impl Write, s: &str) -> Result<()> {
+    writer.write_all(&(s.len() as u32).to_le_bytes())?;
+    writer.write_all(s.as_bytes())?;
+    Ok(())
+}
+
+pub(crate) fn write_strings(writer: &mut impl Write, strings: &[String]) -> Result<()> {
+    writer.write_all(&(strings.len() as u32).to_le_bytes())?;
+    for s in strings {
+        write_string(writer, s)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn make_tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("projects").join("rustle")).unwrap();
+        fs::create_dir_all(dir.path().join("node_modules").join("left-pad")).unwrap();
+        fs::write(dir.path().join("notes.txt"), b"hello").unwrap();
+        fs::write(
+            dir.path().join("projects").join("rustle").join("main.rs"),
+            b"fn main() {}",
+        )
+        .unwrap();
+        fs::write(
+            dir.path()
+                .join("node_modules")
+                .join("left-pad")
+                .join("index.js"),
+            b"",
+        )
+        .unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_build_indexes_tree() {
+        let dir = make_tree();
+        let index = FileIndex::build(&[dir.path().to_path_buf()], IgnoreSet::default());
+
+        let names: Vec<&str> = index.entries().iter().map(|e| e.name.as_str()).collect();
+        assert!(names.contains(&"notes.txt"));
+        assert!(names.contains(&"rustle"));
+        assert!(names.contains(&"main.rs"));
+        assert!(!names.contains(&"index.js"));
+
+        let notes = index
+            .entries()
+            .iter()
+            .find(|e| e.name == "notes.txt")
+            .unwrap();
+        assert!(!notes.is_dir);
+        assert_eq!(notes.size, 5);
+    }
+
+    #[test]
+    fn test_walks_every_level() {
+        let dir = make_tree();
+        let deep = dir.path().join("a").join("b").join("c").join("d").join("e");
+        fs::create_dir_all(&deep).unwrap();
+        fs::write(deep.join("buried.txt"), b"").unwrap();
+
+        let mut index = FileIndex::build(&[dir.path().to_path_buf()], IgnoreSet::default());
+        assert!(index.entries().iter().any(|e| e.name == "buried.txt"));
+
+        let deeper = deep.join("f").join("g");
+        fs::create_dir_all(&deeper).unwrap();
+        fs::write(deeper.join("later.txt"), b"").unwrap();
+        assert!(index.covers(&deeper.join("later.txt")));
+        index.add_path(&deep.join("f"));
+        assert!(index.entries().iter().any(|e| e.name == "later.txt"));
+    }
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let dir = make_tree();
+        let index_path = dir.path().join("data").join("index.bin");
+        let index = FileIndex::build(&[dir.path().to_path_buf()], IgnoreSet::default());
+        index.save(&index_path).unwrap();
+
+        let loaded = FileIndex::load(&index_path).unwrap();
+        assert_eq!(loaded.roots(), index.roots());
+        assert_eq!(loaded.entries(), index.entries());
+    }
+
+    #[test]
+    fn test_version_mismatch_is_rejected() {
+        let dir = make_tree();
+        let index_path = dir.path().join("index.bin");
+        FileIndex::build(&[dir.path().to_path_buf()], IgnoreSet::default())
+            .save(&index_path)
+            .unwrap();
+
+        let mut bytes = fs::read(&index_path).unwrap();
+        bytes[8..12].copy_from_slice(&(INDEX_VERSION + 1).to_le_bytes());
+        fs::write(&index_path, bytes).unwrap();
+
+        assert!(FileIndex::load(&index_path).is_err());
+    }
+
+    #[test]
+    fn test_corrupt_lengths_are_rejected() {
+        let dir = make_tree();
+        let index_path = dir.path().join("index.bin");
+        let index = FileIndex::build(&[dir.path().to_path_buf()], IgnoreSet::default());
+        index.save(&index_path).unwrap();
+        let bytes = fs::read(&index_path).unwrap();
+
+        // The entry count directly precedes the entries at the end of the file
+        let entries_len: usize = index
+            .entries()
+            .iter()
+            .map(|e| 4 + e.path.to_string_lossy().len() + 1 + 8 + 8)
+            .sum();
+        let entry_count_at = bytes.len() - entries_len - 8;
+
+        // Root count, length of the first root, and entry count
+        for (offset, value) in [
+            (20, u32::MAX.to_le_bytes().to_vec()),
+            (24, u32::MAX.to_le_bytes().to_vec()),
+            (entry_count_at, u64::MAX.to_le_bytes().to_vec()),
+        ] {
+            let mut corrupt = bytes.clone();
+            corrupt[offset..offset + value.len()].copy_from_slice(&value);
+            fs::write(&index_path, corrupt).unwrap();
+            assert!(matches!(
+                FileIndex::load(&index_path),
+                Err(RustleError::IndexError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_reconcile_checks_only_changed_dirs() {
+        let dir = make_tree();
+        let roots = vec![dir.path().to_path_buf()];
+        let index_path = dir.path().join("index.bin");
+        let index = FileIndex::build(&roots, IgnoreSet::default());
+        assert!(index.reconcile_check().is_none());
+        index.save(&index_path).unwrap();
+
+        let mut check = FileIndex::load(&index_path)
+            .unwrap()
+            .reconcile_check()
+            .unwrap();
+
+        // As if every directory had changed since the index was saved
+        check.saved_at = 0;
+        assert_eq!(check.changed_dirs(), roots);
+
+        // As if nothing had
+        check.saved_at = u64::MAX;
+        assert!(check.changed_dirs().is_empty());
+
+        // Directories that are gone are rescanned, outer ones only
+        fs::remove_dir_all(dir.path().join("projects")).unwrap();
+        assert_eq!(check.changed_dirs(), [dir.path().join("projects")]);
+    }
+
+    #[test]
+    fn test_load_or_build_rebuilds_on_root_change() {
+        let dir = make_tree();
+        let other = tempfile::tempdir().unwrap();
+        fs::write(other.path().join("other.txt"), b"").unwrap();
+        let index_path = dir.path().join("index.bin");
+
+        FileIndex::load_or_build(
+            &index_path,
+            &[dir.path().to_path_buf()],
+            IgnoreSet::default(),
+        );
+        let rebuilt = FileIndex::load_or_build(
+            &index_path,
+            &[other.path().to_path_buf()],
+            IgnoreSet::default(),
+        );
+
+        assert!(rebuilt.entries().iter().any(|e| e.name == "other.txt"));
+        assert!(!rebuilt.entries().iter().any(|e| e.name == "notes.txt"));
+    }
+
+    #[test]
+    fn test_load_or_build_rebuilds_on_ignore_change() {
+        let dir = make_tree();
+        let index_path = dir.path().join("data").join("index.bin");
+        let roots = [dir.path().to_path_buf()];
+
+        let index = FileIndex::load_or_build(&index_path, &roots, IgnoreSet::default());
+        assert!(index.entries().iter().any(|e| e.name == "notes.txt"));
+
+        let overrides = [RootIgnoreOverride {
+            root: dir.path().to_path_buf(),
+            patterns: vec!["*.txt".into(), "!node_modules/".into()],
+        }];
+        let ignore = IgnoreSet::new(&["node_modules/".to_string()], &overrides);
+        let rebuilt = FileIndex::load_or_build(&index_path, &roots, ignore.clone());
+        assert!(!rebuilt.entries().iter().any(|e| e.name == "notes.txt"));
+        assert!(rebuilt.entries().iter().any(|e| e.name == "index.js"));
+
+        // The rules are saved with the index, so the same rules load it back
+        assert_eq!(FileIndex::load(&index_path).unwrap().ignore(), &ignore);
+    }
+
+    #[test]
+    fn test_remove_and_rename_subtree() {
+        let dir = make_tree();
+        let mut index = FileIndex::build(&[dir.path().to_path_buf()], IgnoreSet::default());
+
+        let projects = dir.path().join("projects");
+        let archive = dir.path().join("archive");
+        index.rename_path(&projects, &archive);
+        assert!(index
+            .entries()
+            .iter()
+            .any(|e| e.path == archive.join("rustle").join("main.rs")));
+        assert!(!index
+            .entries()
+            .iter()
+            .any(|e| e.path.starts_with(&projects)));
+
+        index.remove_path(&archive);
+        assert!(!index.entries().iter().any(|e| e.path.starts_with(&archive)));
+        assert!(index.entries().iter().any(|e| e.name == "notes.txt"));
+    }
+
+    #[test]
+    fn test_planned_rescan_replaces_subtree() {
+        let dir = make_tree();
+        let roots = [dir.path().to_path_buf()];
+        let mut index = FileIndex::build(&roots, IgnoreSet::default());
+
+        let projects = dir.path().join("projects");
+        fs::remove_file(projects.join("rustle").join("main.rs")).unwrap();
+        fs::write(projects.join("rustle").join("lib.rs"), b"").unwrap();
+        fs::write(dir.path().join("later.txt"), b"").unwrap();
+
+        // The scan runs without the index borrowed
+        let scan = index.plan_rescan(&projects);
+        let entries = scan.run();
+        index.replace_subtree(scan.path(), entries);
+
+        let names: Vec<&str> = index.entries().iter().map(|e| e.name.as_str()).collect();
+        assert!(names.contains(&"lib.rs"));
+        assert!(!names.contains(&"main.rs"));
+        // Only the rescanned directory was touched
+        assert!(!names.contains(&"later.txt"));
+        assert!(index.entries().windows(2).all(|w| w[0].path < w[1].path));
+    }
+
+    #[test]
+    fn test_add_path_respects_skip_rules() {
+        let dir = make_tree();
+        let mut index = FileIndex::build(&[dir.path().to_path_buf()], IgnoreSet::default());
+
+        let added = dir.path().join("todo.md");
+        fs::write(&added, b"- ship it").unwrap();
+        index.add_path(&added);
+        assert!(index.entries().iter().any(|e| e.path == added));
+
+        let skipped = dir.path().join("node_modules").join("new.js");
+        fs::write(&skipped, b"").unwrap();
+        index.add_path(&skipped);
+        assert!(!index.entries().iter().any(|e| e.path == skipped));
+    }
+
+    #[test]
+    fn test_root_ranges_skip_nested_roots() {
+        let dir = make_tree();
+        let other = tempfile::tempdir().unwrap();
+        fs::write(other.path().join("other.txt"), b"").unwrap();
+
+        let index = FileIndex::build(
+            &[
+                dir.path().to_path_buf(),
+                dir.path().join("projects"),
+                other.path().to_path_buf(),
+            ],
+            IgnoreSet::default(),
+        );
+        let ranges = index.root_ranges();
+
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(ranges.iter().map(|r| r.len()).sum::<usize>(), index.len());
+    }
+
+    /// Builds two nested repositories plus a folder with a rustle ignore file
+    fn make_repos() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let outer = dir.path().join("outer");
+        let inner = outer.join("inner");
+        let files: &[(PathBuf, &str)] = &[
+            (outer.join(".gitignore"), "*.log\nbuild/\n"),
+            (outer.join("app.log"), ""),
+            (outer.join("build").join("out.bin"), ""),
+            (outer.join("src").join(".gitignore"), "!keep.log\n"),
+            (outer.join("src").join("keep.log"), ""),
+            (outer.join("src").join("main.rs"), ""),
+            (inner.join(".gitignore"), "*.tmp\n"),
+            (inner.join(".ignore"), "secret/\n"),
+            (inner.join("debug.log"), ""),
+            (inner.join("scratch.tmp"), ""),
+            (inner.join("secret").join("key.txt"), ""),
+            (dir.path().join("loose").join(".rustleignore"), "*.bak\n"),
+            (dir.path().join("loose").join("old.bak"), ""),
+            (dir.path().join("loose").join("new.txt"), ""),
+        ];
+        for (path, contents) in files {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        fs::create_dir_all(outer.join(".git")).unwrap();
+        fs::create_dir_all(inner.join(".git")).unwrap();
+        dir
+    }
+
+    fn indexed_names(index: &FileIndex) -> Vec<&str> {
+        index.entries().iter().map(|e| e.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_ignore_files_apply_hierarchically() {
+        let dir = make_repos();
+        let index = FileIndex::build(&[dir.path().to_path_buf()], IgnoreSet::default());
+        let names = indexed_names(&index);
+
+        assert!(!names.contains(&"app.log"));
+        assert!(!names.contains(&"build"));
+        assert!(!names.contains(&"out.bin"));
+        // A deeper .gitignore re-includes what the outer one excluded
+        assert!(names.contains(&"keep.log"));
+        assert!(names.contains(&"main.rs"));
+        // The outer repository's .gitignore stops at the nested repository
+        assert!(names.contains(&"debug.log"));
+        assert!(!names.contains(&"scratch.tmp"));
+        assert!(!names.contains(&"secret"));
+        assert!(!names.contains(&"old.bak"));
+        assert!(names.contains(&"new.txt"));
+    }
+
+    #[test]
+    fn test_ignore_files_can_be_turned_off() {
+        let dir = make_repos();
+        let ignore = IgnoreSet::default().with_ignore_files(false);
+        let index = FileIndex::build(&[dir.path().to_path_buf()], ignore);
+        let names = indexed_names(&index);
+
+        assert!(names.contains(&"app.log"));
+        assert!(names.contains(&"out.bin"));
+        assert!(names.contains(&"scratch.tmp"));
+        assert!(names.contains(&"old.bak"));
+    }
+
+    #[test]
+    fn test_add_path_respects_ignore_files() {
+        let dir = make_repos();
+        let mut index = FileIndex::build(&[dir.path().to_path_buf()], IgnoreSet::default());
+
+        let ignored = dir.path().join("outer").join("src").join("trace.log");
+        fs::write(&ignored, b"").unwrap();
+        index.add_path(&ignored);
+        assert!(!index.entries().iter().any(|e| e.path == ignored));
+
+        // Walking a new directory below a root still sees the files above it
+        let generated = dir.path().join("outer").join("gen");
+        fs::create_dir_all(&generated).unwrap();
+        fs::write(generated.join("gen.log"), b"").unwrap();
+        fs::write(generated.join("gen.rs"), b"").unwrap();
+        index.add_path(&generated);
+        let names = indexed_names(&index);
+        assert!(names.contains(&"gen.rs"));
+        assert!(!names.contains(&"gen.log"));
+    }
+
+    #[test]
+    fn test_default_rules_skip_noise() {
+        let dir = make_tree();
+        let index = FileIndex::build(&[dir.path().to_path_buf()], IgnoreSet::default());
+
+        assert!(!index.covers(&dir.path().join(".git")));
+        assert!(!index.covers(&dir.path().join("node_modules").join("left-pad")));
+        assert!(index.covers(&dir.path().join("Documents")));
+    }
+}
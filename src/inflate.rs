@@ -0,0 +1,398 @@
+//! DEFLATE decompression for Rustle
+//!
+//! Office documents are zip packages and PDF content streams are usually
+//! zlib compressed, so text extraction needs an inflater. This is a small,
+//! allocation-light decoder for raw DEFLATE data (RFC 1951) and the zlib
+//! wrapper around it (RFC 1950). It favours simplicity over speed: Huffman
+//! codes are decoded one bit at a time, which is plenty fast for the few
+//! megabytes a document holds.
+
+#![allow(dead_code)]
+
+use crate::error::{Result, RustleError};
+
+/// Longest Huffman code allowed by the format, in bits
+const MAX_BITS: usize = 15;
+
+/// Number of literal/length codes
+const MAX_LITERAL_CODES: usize = 286;
+
+/// Number of distance codes
+const MAX_DISTANCE_CODES: usize = 30;
+
+/// Number of fixed literal/length codes (two of which are never used)
+const FIXED_LITERAL_CODES: usize = 288;
+
+/// Base lengths for length codes 257..285
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+
+/// Extra bits for length codes 257..285
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// Base offsets for distance codes 0..29
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+
+/// Extra bits for distance codes 0..29
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Order in which code length code lengths are stored in a dynamic block
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Decompresses raw DEFLATE data
+///
+/// Fails if the data is malformed or would inflate to more than
+/// `max_output` bytes.
+pub fn inflate(data: &[u8], max_output: usize) -> Result<Vec<u8>> {
+    let mut inflater = Inflater {
+        input: BitReader::new(data),
+        output: Vec::new(),
+        max_output,
+    };
+    inflater.run()?;
+    Ok(inflater.output)
+}
+
+/// Decompresses zlib data (a two byte header, DEFLATE data and a checksum)
+///
+/// The trailing Adler-32 checksum is not verified; a truncated stream still
+/// yields whatever could be decoded before the end, as PDF writers are not
+/// always careful about stream lengths.
+pub fn zlib_decompress(data: &[u8], max_output: usize) -> Result<Vec<u8>> {
+    let [cmf, flg, ..] = *data else {
+        return Err(corrupt("zlib header is truncated"));
+    };
+    if cmf & 0x0F != 8 || (u16::from(cmf) << 8 | u16::from(flg)) % 31 != 0 {
+        return Err(corrupt("not a zlib stream"));
+    }
+    if flg & 0x20 != 0 {
+        return Err(corrupt("zlib preset dictionaries are not supported"));
+    }
+
+    let mut inflater = Inflater {
+        input: BitReader::new(&data[2..]),
+        output: Vec::new(),
+        max_output,
+    };
+    match inflater.run() {
+        Ok(()) => Ok(inflater.output),
+        Err(_) if !inflater.output.is_empty() && inflater.input.exhausted() => Ok(inflater.output),
+        Err(e) => Err(e),
+    }
+}
+
+fn corrupt(msg: &str) -> RustleError {
+    RustleError::extract_error(format!("corrupt compressed data: {}", msg))
+}
+
+/// Reads bits least significant first, as DEFLATE packs them
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit_buf: u32,
+    bit_count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            bit_buf: 0,
+            bit_count: 0,
+        }
+    }
+
+    /// Reads `count` bits (at most 16)
+    fn bits(&mut self, count: u32) -> Result<u32> {
+        while self.bit_count < count {
+            let byte = *self
+                .data
+                .get(self.pos)
+                .ok_or_else(|| corrupt("unexpected end of data"))?;
+            self.pos += 1;
+            self.bit_buf |= u32::from(byte) << self.bit_count;
+            self.bit_count += 8;
+        }
+        let value = self.bit_buf & ((1 << count) - 1);
+        self.bit_buf >>= count;
+        self.bit_count -= count;
+        Ok(value)
+    }
+
+    /// Discards bits up to the next byte boundary
+    fn align(&mut self) {
+        self.bit_buf = 0;
+        self.bit_count = 0;
+    }
+
+    /// Reads `len` whole bytes after aligning
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| corrupt("stored block runs past the end"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn exhausted(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+}
+
+/// A canonical Huffman code, stored as the number of codes of each length
+/// and the symbols ordered by code
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// Builds a code from the code length of each symbol (0 = unused)
+    fn new(lengths: &[u8]) -> Result<Self> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+
+        // Reject over-subscribed codes; incomplete ones are allowed
+        let mut left: i32 = 1;
+        for &count in &counts[1..] {
+            left = (left << 1) - i32::from(count);
+            if left < 0 {
+                return Err(corrupt("over-subscribed Huffman code"));
+            }
+        }
+
+        let mut offsets = [0u16; MAX_BITS + 1];
+        for len in 1..MAX_BITS {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+
+        Ok(Self { counts, symbols })
+    }
+
+    /// Decodes one symbol
+    fn decode(&self, input: &mut BitReader) -> Result<u16> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for &count in &self.counts[1..] {
+            code |= input.bits(1)? as i32;
+            let count = i32::from(count);
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(corrupt("invalid Huffman code"))
+    }
+}
+
+struct Inflater<'a> {
+    input: BitReader<'a>,
+    output: Vec<u8>,
+    max_output: usize,
+}
+
+impl Inflater<'_> {
+    fn run(&mut self) -> Result<()> {
+        loop {
+            let last = self.input.bits(1)? == 1;
+            match self.input.bits(2)? {
+                0 => self.stored()?,
+                1 => self.fixed()?,
+                2 => self.dynamic()?,
+                _ => return Err(corrupt("invalid block type")),
+            }
+            if last {
+                return Ok(());
+            }
+        }
+    }
+
+    fn stored(&mut self) -> Result<()> {
+        self.input.align();
+        let header = self.input.bytes(4)?;
+        let len = u16::from_le_bytes([header[0], header[1]]);
+        let inverse = u16::from_le_bytes([header[2], header[3]]);
+        if len != !inverse {
+            return Err(corrupt("stored block length mismatch"));
+        }
+        let bytes = self.input.bytes(len as usize)?;
+        self.reserve(bytes.len())?;
+        self.output.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn fixed(&mut self) -> Result<()> {
+        let mut lengths = [0u8; FIXED_LITERAL_CODES];
+        lengths[..144].fill(8);
+        lengths[144..256].fill(9);
+        lengths[256..280].fill(7);
+        lengths[280..].fill(8);
+        let literals = Huffman::new(&lengths)?;
+        let distances = Huffman::new(&[5u8; MAX_DISTANCE_CODES])?;
+        self.codes(&literals, &distances)
+    }
+
+    fn dynamic(&mut self) -> Result<()> {
+        let literal_count = self.input.bits(5)? as usize + 257;
+        let distance_count = self.input.bits(5)? as usize + 1;
+        let code_length_count = self.input.bits(4)? as usize + 4;
+        if literal_count > MAX_LITERAL_CODES || distance_count > MAX_DISTANCE_CODES {
+            return Err(corrupt("too many codes in dynamic block"));
+        }
+
+        let mut code_lengths = [0u8; 19];
+        for &index in &CODE_LENGTH_ORDER[..code_length_count] {
+            code_lengths[index] = self.input.bits(3)? as u8;
+        }
+        let code_length_code = Huffman::new(&code_lengths)?;
+
+        let mut lengths = vec![0u8; literal_count + distance_count];
+        let mut i = 0;
+        while i < lengths.len() {
+            let symbol = code_length_code.decode(&mut self.input)?;
+            let (value, repeat) = match symbol {
+                0..=15 => {
+                    lengths[i] = symbol as u8;
+                    i += 1;
+                    continue;
+                }
+                16 => {
+                    let previous = *i
+                        .checked_sub(1)
+                        .and_then(|p| lengths.get(p))
+                        .ok_or_else(|| corrupt("repeat with no previous length"))?;
+                    (previous, 3 + self.input.bits(2)? as usize)
+                }
+                17 => (0, 3 + self.input.bits(3)? as usize),
+                _ => (0, 11 + self.input.bits(7)? as usize),
+            };
+            if i + repeat > lengths.len() {
+                return Err(corrupt("code lengths overflow"));
+            }
+            lengths[i..i + repeat].fill(value);
+            i += repeat;
+        }
+
+        if lengths[256] == 0 {
+            return Err(corrupt("missing end-of-block code"));
+        }
+        let literals = Huffman::new(&lengths[..literal_count])?;
+        let distances = Huffman::new(&lengths[literal_count..])?;
+        self.codes(&literals, &distances)
+    }
+
+    /// Decodes literals and back-references until the end-of-block code
+    fn codes(&mut self, literals: &Huffman, distances: &Huffman) -> Result<()> {
+        loop {
+            let symbol = literals.decode(&mut self.input)? as usize;
+            match symbol {
+                0..=255 => {
+                    self.reserve(1)?;
+                    self.output.push(symbol as u8);
+                }
+                256 => return Ok(()),
+                _ => {
+                    let index = symbol - 257;
+                    if index >= LENGTH_BASE.len() {
+                        return Err(corrupt("invalid length code"));
+                    }
+                    let len = LENGTH_BASE[index] as usize
+                        + self.input.bits(u32::from(LENGTH_EXTRA[index]))? as usize;
+
+                    let index = distances.decode(&mut self.input)? as usize;
+                    if index >= DISTANCE_BASE.len() {
+                        return Err(corrupt("invalid distance code"));
+                    }
+                    let distance = DISTANCE_BASE[index] as usize
+                        + self.input.bits(u32::from(DISTANCE_EXTRA[index]))? as usize;
+                    if distance > self.output.len() {
+                        return Err(corrupt("distance reaches before the start"));
+                    }
+
+                    self.reserve(len)?;
+                    let start = self.output.len() - distance;
+                    for k in 0..len {
+                        let byte = self.output[start + k];
+                        self.output.push(byte);
+                    }
+                }
+            }
+        }
+    }
+
+    fn reserve(&self, additional: usize) -> Result<()> {
+        if self.output.len() + additional > self.max_output {
+            return Err(RustleError::extract_error(format!(
+                "decompressed data exceeds {} bytes",
+                self.max_output
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stored_block() {
+        // Final stored block holding "hi"
+        let data = [0x01, 0x02, 0x00, 0xFD, 0xFF, b'h', b'i'];
+        assert_eq!(inflate(&data, 16).unwrap(), b"hi");
+    }
+
+    #[test]
+    fn test_zlib_fixed_and_dynamic_blocks() {
+        // zlib.compress(b"hello hello hello hello")
+        let fixed = [
+            0x78, 0x9C, 0xCB, 0x48, 0xCD, 0xC9, 0xC9, 0x57, 0xC8, 0x40, 0x27, 0x01, 0x68, 0x03,
+            0x08, 0xB1,
+        ];
+        assert_eq!(
+            zlib_decompress(&fixed, 1024).unwrap(),
+            b"hello hello hello hello"
+        );
+        assert!(zlib_decompress(&fixed, 8).is_err());
+
+        // Longer input is compressed with dynamic Huffman codes
+        let text: String = (0..200).map(|i| format!("line {} of text\n", i)).collect();
+        let packed = include_bytes!("../tests/fixtures/extract/lines.zlib");
+        assert_eq!(zlib_decompress(packed, 1 << 20).unwrap(), text.as_bytes());
+    }
+
+    #[test]
+    fn test_rejects_garbage() {
+        assert!(zlib_decompress(b"not zlib", 1024).is_err());
+        assert!(inflate(&[0x07], 1024).is_err());
+    }
+}
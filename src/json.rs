@@ -0,0 +1,327 @@
+//! Minimal JSON reader for Rustle
+//!
+//! Browsers keep some of their profile data, such as Chromium's
+//! `Bookmarks` file, as JSON. This reader parses a whole document into a
+//! [`Json`] tree; it is strict about syntax but keeps numbers as `f64`,
+//! which is all the profile files need.
+
+#![allow(dead_code)]
+
+use crate::error::{Result, RustleError};
+
+/// Deepest nesting of arrays and objects accepted, to bound recursion
+const MAX_DEPTH: usize = 256;
+
+/// A parsed JSON value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+
+    /// Members in document order
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Returns the member `key` of an object
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, Json)]> {
+        match self {
+            Json::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a JSON document
+///
+/// A leading byte order mark is skipped. Fails on anything but a single
+/// value surrounded by whitespace.
+pub fn parse(text: &str) -> Result<Json> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut parser = Parser {
+        bytes: text.as_bytes(),
+        text,
+        pos: 0,
+    };
+    let value = parser.value(0)?;
+    parser.skip_whitespace();
+    if parser.pos != parser.bytes.len() {
+        return Err(parser.error("trailing characters"));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn value(&mut self, depth: usize) -> Result<Json> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+        self.skip_whitespace();
+        match self.bytes.get(self.pos) {
+            Some(b'{') => self.object(depth),
+            Some(b'[') => self.array(depth),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b't') => self.literal("true", Json::Bool(true)),
+            Some(b'f') => self.literal("false", Json::Bool(false)),
+            Some(b'n') => self.literal("null", Json::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of document")),
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Json> {
+        self.pos += 1;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.eat(b'}') {
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.bytes.get(self.pos) != Some(&b'"') {
+                return Err(self.error("expected a member name"));
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            if !self.eat(b':') {
+                return Err(self.error("expected ':'"));
+            }
+            let value = self.value(depth + 1)?;
+            members.push((key, value));
+            self.skip_whitespace();
+            if self.eat(b'}') {
+                return Ok(Json::Object(members));
+            }
+            if !self.eat(b',') {
+                return Err(self.error("expected ',' or '}'"));
+            }
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Json> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.eat(b']') {
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value(depth + 1)?);
+            self.skip_whitespace();
+            if self.eat(b']') {
+                return Ok(Json::Array(items));
+            }
+            if !self.eat(b',') {
+                return Err(self.error("expected ',' or ']'"));
+            }
+        }
+    }
+
+    /// Reads a string, starting at its opening quote
+    fn string(&mut self) -> Result<String> {
+        self.pos += 1;
+        let mut value = String::new();
+        loop {
+            let start = self.pos;
+            while let Some(&b) = self.bytes.get(self.pos) {
+                if b == b'"' || b == b'\\' || b < 0x20 {
+                    break;
+                }
+                self.pos += 1;
+            }
+            value.push_str(&self.text[start..self.pos]);
+
+            match self.bytes.get(self.pos) {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(value);
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    value.push(self.escape()?);
+                }
+                Some(_) => return Err(self.error("control character in string")),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    /// Reads the escape sequence after a backslash
+    fn escape(&mut self) -> Result<char> {
+        let Some(&b) = self.bytes.get(self.pos) else {
+            return Err(self.error("unterminated string"));
+        };
+        self.pos += 1;
+        let c = match b {
+            b'"' => '"',
+            b'\\' => '\\',
+            b'/' => '/',
+            b'b' => '\u{8}',
+            b'f' => '\u{c}',
+            b'n' => '\n',
+            b'r' => '\r',
+            b't' => '\t',
+            b'u' => {
+                let unit = self.hex4()?;
+                if (0xD800..0xDC00).contains(&unit) && self.text[self.pos..].starts_with("\\u") {
+                    // A surrogate pair spells one character outside the BMP
+                    let saved = self.pos;
+                    self.pos += 2;
+                    let low = self.hex4()?;
+                    if (0xDC00..0xE000).contains(&low) {
+                        let c = 0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00);
+                        return Ok(char::from_u32(c).unwrap_or('\u{fffd}'));
+                    }
+                    self.pos = saved;
+                }
+                char::from_u32(unit).unwrap_or('\u{fffd}')
+            }
+            _ => return Err(self.error("invalid escape")),
+        };
+        Ok(c)
+    }
+
+    fn hex4(&mut self) -> Result<u32> {
+        let digits = self
+            .text
+            .get(self.pos..self.pos + 4)
+            .ok_or_else(|| self.error("short \\u escape"))?;
+        let unit = u32::from_str_radix(digits, 16).map_err(|_| self.error("bad \\u escape"))?;
+        self.pos += 4;
+        Ok(unit)
+    }
+
+    fn number(&mut self) -> Result<Json> {
+        let start = self.pos;
+        self.eat(b'-');
+        let digits = |p: &mut Self| {
+            let from = p.pos;
+            while p.bytes.get(p.pos).is_some_and(u8::is_ascii_digit) {
+                p.pos += 1;
+            }
+            p.pos > from
+        };
+        if !digits(self) {
+            return Err(self.error("expected digits"));
+        }
+        if self.eat(b'.') && !digits(self) {
+            return Err(self.error("expected digits after '.'"));
+        }
+        if self.eat(b'e') || self.eat(b'E') {
+            if !self.eat(b'+') {
+                self.eat(b'-');
+            }
+            if !digits(self) {
+                return Err(self.error("expected exponent digits"));
+            }
+        }
+        self.text[start..self.pos]
+            .parse()
+            .map(Json::Number)
+            .map_err(|_| self.error("bad number"))
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json> {
+        if !self.text[self.pos..].starts_with(word) {
+            return Err(self.error("unexpected word"));
+        }
+        self.pos += word.len();
+        Ok(value)
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        let found = self.bytes.get(self.pos) == Some(&b);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.bytes.get(self.pos), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn error(&self, msg: &str) -> RustleError {
+        RustleError::browser_data_error(format!("invalid JSON at byte {}: {}", self.pos, msg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_values() {
+        let doc = parse(
+            "\u{feff} {\"name\": \"caf\\u00e9 \\ud83d\\ude00\\n\", \"n\": [-1.5e2, 0, true, null],
+              \"nested\": {\"empty\": {}, \"list\": []}} ",
+        )
+        .unwrap();
+        assert_eq!(doc.get("name").and_then(Json::as_str), Some("café 😀\n"));
+        assert_eq!(
+            doc.get("n"),
+            Some(&Json::Array(vec![
+                Json::Number(-150.0),
+                Json::Number(0.0),
+                Json::Bool(true),
+                Json::Null,
+            ]))
+        );
+        let nested = doc.get("nested").unwrap();
+        assert_eq!(nested.get("empty"), Some(&Json::Object(Vec::new())));
+        assert_eq!(nested.get("list").and_then(Json::as_array), Some(&[][..]));
+        assert_eq!(doc.get("missing"), None);
+    }
+
+    #[test]
+    fn test_rejects_invalid_documents() {
+        for text in [
+            "",
+            "{",
+            "{\"a\" 1}",
+            "[1, 2,]",
+            "\"unterminated",
+            "\"bad \\x escape\"",
+            "01x",
+            "tru",
+            "{} {}",
+            "-",
+            &"[".repeat(MAX_DEPTH + 2),
+        ] {
+            assert!(parse(text).is_err(), "{}", text);
+        }
+    }
+}
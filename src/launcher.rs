@@ -37,8 +37,9 @@ use windows::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
 /// launch(Path::new(r"C:\Windows\notepad.exe")).unwrap();
 /// ```
 pub fn launch(path: &Path) -> Result<()> {
-    // Validate path exists
-    if !path.exists() {
+    // Validate path exists; URLs go to the default browser as they are
+    let is_url = path.to_str().is_some_and(|s| s.contains("://"));
+    if !is_url && !path.exists() {
         return Err(RustleError::InvalidPath(format!(
             "Path does not exist: {}",
             path.display()
@@ -0,0 +1,431 @@
+//! Shell link (.lnk) reader for Rustle
+//!
+//! Windows shortcuts are binary files in the Shell Link format
+//! ([MS-SHLLINK]). This reader decodes the parts Rustle shows or needs to
+//! open the target: the header's times and attributes, the target path
+//! from the link info structure, the string data (description, relative
+//! path, working directory, arguments and icon location) and the
+//! environment-variable target some installers write instead. The shell
+//! item ID list is skipped; links that can only be resolved through it
+//! have no target here.
+//!
+//! [MS-SHLLINK]: https://learn.microsoft.com/openspecs/windows_protocols/ms-shllink
+
+#![allow(dead_code)]
+
+use crate::error::{Result, RustleError};
+use crate::paths;
+use std::path::{Path, PathBuf};
+
+/// Size of the header, which is also its first field
+const HEADER_SIZE: u32 = 0x4C;
+
+/// Class identifier every shell link starts with,
+/// 00021401-0000-0000-C000-000000000046
+const LINK_CLSID: [u8; 16] = [
+    0x01, 0x14, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46,
+];
+
+// Link flags saying which optional structures follow the header
+const HAS_LINK_TARGET_ID_LIST: u32 = 0x0000_0001;
+const HAS_LINK_INFO: u32 = 0x0000_0002;
+const HAS_NAME: u32 = 0x0000_0004;
+const HAS_RELATIVE_PATH: u32 = 0x0000_0008;
+const HAS_WORKING_DIR: u32 = 0x0000_0010;
+const HAS_ARGUMENTS: u32 = 0x0000_0020;
+const HAS_ICON_LOCATION: u32 = 0x0000_0040;
+const IS_UNICODE: u32 = 0x0000_0080;
+
+/// Link info flag: a local path is given
+const VOLUME_ID_AND_LOCAL_BASE_PATH: u32 = 0x1;
+
+/// Link info flag: a network share is given
+const COMMON_NETWORK_RELATIVE_LINK: u32 = 0x2;
+
+/// Link info header size from which Unicode path offsets are present
+const LINK_INFO_UNICODE_HEADER_SIZE: usize = 0x24;
+
+/// Extra data block holding the target with environment variables
+const ENVIRONMENT_VARIABLE_BLOCK: u32 = 0xA000_0001;
+
+/// Extra data block holding the icon location with environment variables
+const ICON_ENVIRONMENT_BLOCK: u32 = 0xA000_0007;
+
+/// Size of the ANSI target field of an environment-variable block; the
+/// Unicode field of twice this size follows it
+const ENVIRONMENT_TARGET_LEN: usize = 260;
+
+/// File attribute of directories
+const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+
+/// Seconds from 1601-01-01, where FILETIMEs count from, to the Unix epoch
+const FILETIME_EPOCH_OFFSET: u64 = 11_644_473_600;
+
+/// What a shortcut points at, and how to start it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShellLink {
+    /// Full path of the target, if the link gives one
+    pub target: Option<PathBuf>,
+
+    /// Target relative to the link's own folder
+    pub relative_path: Option<String>,
+
+    pub working_dir: Option<String>,
+    pub arguments: Option<String>,
+
+    /// File the icon is taken from, with environment variables as stored
+    pub icon_location: Option<String>,
+
+    /// Index of the icon within `icon_location`
+    pub icon_index: i32,
+
+    /// Comment shown as the shortcut's tooltip
+    pub description: Option<String>,
+
+    /// File attributes of the target when the link was saved
+    pub attributes: u32,
+
+    /// Last access of the target when the link was saved, in seconds
+    /// since the Unix epoch
+    pub access_time: Option<u64>,
+
+    pub write_time: Option<u64>,
+}
+
+impl ShellLink {
+    /// Parses the bytes of a .lnk file
+    pub fn parse(data: &[u8]) -> Result<ShellLink> {
+        if read_u32(data, 0)? != HEADER_SIZE || data.get(4..20) != Some(&LINK_CLSID[..]) {
+            return Err(corrupt("not a shell link"));
+        }
+        let flags = read_u32(data, 20)?;
+        let mut link = ShellLink {
+            attributes: read_u32(data, 24)?,
+            access_time: filetime_to_unix(read_u64(data, 36)?),
+            write_time: filetime_to_unix(read_u64(data, 44)?),
+            icon_index: read_u32(data, 56)? as i32,
+            ..ShellLink::default()
+        };
+
+        let mut pos = HEADER_SIZE as usize;
+        if flags & HAS_LINK_TARGET_ID_LIST != 0 {
+            pos += 2 + read_u16(data, pos)? as usize;
+        }
+        if flags & HAS_LINK_INFO != 0 {
+            let size = read_u32(data, pos)? as usize;
+            let info = data
+                .get(pos..pos + size)
+                .ok_or_else(|| corrupt("link info runs past the end"))?;
+            link.target = link_info_target(info)?;
+            pos += size;
+        }
+
+        let unicode = flags & IS_UNICODE != 0;
+        let mut string = |flag: u32| -> Result<Option<String>> {
+            if flags & flag == 0 {
+                return Ok(None);
+            }
+            let (text, len) = read_counted_string(data, pos, unicode)?;
+            pos += len;
+            Ok(Some(text))
+        };
+        link.description = string(HAS_NAME)?;
+        link.relative_path = string(HAS_RELATIVE_PATH)?;
+        link.working_dir = string(HAS_WORKING_DIR)?;
+        link.arguments = string(HAS_ARGUMENTS)?;
+        link.icon_location = string(HAS_ICON_LOCATION)?;
+
+        // Extra data blocks run until one smaller than a block header
+        while let Ok(size) = read_u32(data, pos) {
+            let size = size as usize;
+            if size < 8 {
+                break;
+            }
+            let block = data
+                .get(pos..pos + size)
+                .ok_or_else(|| corrupt("extra data runs past the end"))?;
+            match read_u32(block, 4)? {
+                ENVIRONMENT_VARIABLE_BLOCK if link.target.is_none() => {
+                    link.target =
+                        environment_target(block).map(|t| PathBuf::from(paths::expand(&t)));
+                }
+                ICON_ENVIRONMENT_BLOCK if link.icon_location.is_none() => {
+                    link.icon_location = environment_target(block);
+                }
+                _ => {}
+            }
+            pos += size;
+        }
+
+        Ok(link)
+    }
+
+    /// Reads and parses a .lnk file
+    pub fn open(path: &Path) -> Result<ShellLink> {
+        ShellLink::parse(&std::fs::read(path)?)
+    }
+
+    /// Returns the target, falling back to the relative path resolved
+    /// against the folder of the link at `link_path`
+    pub fn resolve(&self, link_path: &Path) -> Option<PathBuf> {
+        if let Some(target) = &self.target {
+            return Some(target.clone());
+        }
+        let relative = self.relative_path.as_deref()?;
+        let dir = link_path.parent()?;
+        Some(normalize(
+            &dir.join(relative.replace('\\', std::path::MAIN_SEPARATOR_STR)),
+        ))
+    }
+
+    /// Returns true if the target was a folder when the link was saved
+    pub fn is_directory(&self) -> bool {
+        self.attributes & FILE_ATTRIBUTE_DIRECTORY != 0
+    }
+}
+
+/// Reads the target path from a link info structure
+///
+/// A local path is the base path followed by the common path suffix; a
+/// network one is the share name, a backslash and the suffix. Unicode
+/// copies of the paths are preferred when present.
+fn link_info_target(info: &[u8]) -> Result<Option<PathBuf>> {
+    let header_size = read_u32(info, 4)? as usize;
+    let flags = read_u32(info, 8)?;
+    let offset = |pos: usize| -> Result<usize> { Ok(read_u32(info, pos)? as usize) };
+    let unicode = header_size >= LINK_INFO_UNICODE_HEADER_SIZE;
+
+    let suffix = if unicode && offset(0x20)? != 0 {
+        read_utf16z(info, offset(0x20)?)?
+    } else {
+        read_ansiz(info, offset(0x18)?)?
+    };
+
+    if flags & VOLUME_ID_AND_LOCAL_BASE_PATH != 0 {
+        let base = if unicode && offset(0x1C)? != 0 {
+            read_utf16z(info, offset(0x1C)?)?
+        } else {
+            read_ansiz(info, offset(0x10)?)?
+        };
+        return Ok(Some(PathBuf::from(base + &suffix)));
+    }
+
+    if flags & COMMON_NETWORK_RELATIVE_LINK != 0 {
+        let link = offset(0x14)?;
+        let net_name_offset = read_u32(info, link + 8)? as usize;
+        // Links with Unicode names have a longer header
+        let share = if net_name_offset > 0x14 {
+            read_utf16z(info, link + read_u32(info, link + 0x14)? as usize)?
+        } else {
+            read_ansiz(info, link + net_name_offset)?
+        };
+        let path = if suffix.is_empty() {
+            share
+        } else {
+            format!("{}\\{}", share.trim_end_matches('\\'), suffix)
+        };
+        return Ok(Some(PathBuf::from(path)));
+    }
+
+    Ok(None)
+}
+
+/// Reads the Unicode target of an environment-variable data block,
+/// falling back to the ANSI one
+fn environment_target(block: &[u8]) -> Option<String> {
+    let unicode_start = 8 + ENVIRONMENT_TARGET_LEN;
+    let target = read_utf16z(block, unicode_start)
+        .ok()
+        .filter(|t| !t.is_empty())
+        .or_else(|| read_ansiz(block, 8).ok())?;
+    (!target.is_empty()).then_some(target)
+}
+
+/// Removes `.` and `..` components without touching the file system
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Converts a FILETIME to seconds since the Unix epoch; zero means unset
+fn filetime_to_unix(filetime: u64) -> Option<u64> {
+    (filetime / 10_000_000).checked_sub(FILETIME_EPOCH_OFFSET)
+}
+
+/// Reads a string prefixed by its length in characters, returning it and
+/// the number of bytes it took
+fn read_counted_string(data: &[u8], pos: usize, unicode: bool) -> Result<(String, usize)> {
+    let count = read_u16(data, pos)? as usize;
+    let len = if unicode { count * 2 } else { count };
+    let bytes = data
+        .get(pos + 2..pos + 2 + len)
+        .ok_or_else(|| corrupt("string runs past the end"))?;
+    let text = if unicode {
+        decode_utf16(bytes)
+    } else {
+        decode_ansi(bytes)
+    };
+    Ok((text, 2 + len))
+}
+
+/// Reads a NUL-terminated string in the system code page
+fn read_ansiz(data: &[u8], pos: usize) -> Result<String> {
+    let bytes = data
+        .get(pos..)
+        .ok_or_else(|| corrupt("string offset past the end"))?;
+    let end = bytes
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or_else(|| corrupt("unterminated string"))?;
+    Ok(decode_ansi(&bytes[..end]))
+}
+
+/// Reads a NUL-terminated UTF-16 string
+fn read_utf16z(data: &[u8], pos: usize) -> Result<String> {
+    let bytes = data
+        .get(pos..)
+        .ok_or_else(|| corrupt("string offset past the end"))?;
+    let end = bytes
+        .chunks_exact(2)
+        .position(|c| c == [0, 0])
+        .ok_or_else(|| corrupt("unterminated string"))?;
+    Ok(decode_utf16(&bytes[..end * 2]))
+}
+
+fn decode_utf16(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Decodes text in the system code page
+///
+/// The code page the link was written with is not recorded; UTF-8 is
+/// tried first, then each byte is read as Latin-1.
+fn decode_ansi(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Result<u16> {
+    data.get(pos..pos + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| corrupt("unexpected end of file"))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Result<u32> {
+    data.get(pos..pos + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| corrupt("unexpected end of file"))
+}
+
+fn read_u64(data: &[u8], pos: usize) -> Result<u64> {
+    Ok(read_u32(data, pos)? as u64 | (read_u32(data, pos + 4)? as u64) << 32)
+}
+
+fn corrupt(msg: &str) -> RustleError {
+    RustleError::shortcut_error(format!("invalid shell link: {}", msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &[u8] = include_bytes!("../tests/fixtures/lnk/document.lnk");
+    const EDITOR: &[u8] = include_bytes!("../tests/fixtures/lnk/editor.lnk");
+    const SHARE: &[u8] = include_bytes!("../tests/fixtures/lnk/share.lnk");
+    const ENV_TARGET: &[u8] = include_bytes!("../tests/fixtures/lnk/envtarget.lnk");
+
+    #[test]
+    fn test_local_target_with_id_list() {
+        let link = ShellLink::parse(DOCUMENT).unwrap();
+        assert_eq!(
+            link.target,
+            Some(PathBuf::from(
+                r"C:\Users\ana\Documents\Résumé – 履歴書.docx"
+            ))
+        );
+        assert_eq!(
+            link.relative_path.as_deref(),
+            Some(r"..\..\..\Documents\Résumé – 履歴書.docx")
+        );
+        assert_eq!(link.working_dir.as_deref(), Some(r"C:\Users\ana\Documents"));
+        assert_eq!(link.description, None);
+        assert_eq!(link.access_time, Some(1_792_238_400));
+        assert_eq!(link.write_time, Some(1_792_238_400 - 3_600));
+        assert!(!link.is_directory());
+    }
+
+    #[test]
+    fn test_every_string_and_icon() {
+        let link = ShellLink::parse(EDITOR).unwrap();
+        assert_eq!(
+            link.target,
+            Some(PathBuf::from(r"C:\Program Files\Editor\editor.exe"))
+        );
+        assert_eq!(link.description.as_deref(), Some("Edit text files"));
+        assert_eq!(
+            link.working_dir.as_deref(),
+            Some(r"%USERPROFILE%\Documents")
+        );
+        assert_eq!(
+            link.arguments.as_deref(),
+            Some("--new-window \"notes.txt\"")
+        );
+        assert_eq!(
+            link.icon_location.as_deref(),
+            Some(r"%ProgramFiles%\Editor\editor.exe")
+        );
+        assert_eq!(link.icon_index, 2);
+    }
+
+    #[test]
+    fn test_network_and_environment_targets() {
+        let link = ShellLink::parse(SHARE).unwrap();
+        assert_eq!(
+            link.target,
+            Some(PathBuf::from(r"\\fileserver\projects\Rustle\Designs"))
+        );
+        assert_eq!(
+            link.working_dir.as_deref(),
+            Some(r"\\fileserver\projects\Rustle")
+        );
+        assert!(link.is_directory());
+
+        std::env::set_var("windir", r"C:\Windows");
+        let link = ShellLink::parse(ENV_TARGET).unwrap();
+        assert_eq!(
+            link.target,
+            Some(PathBuf::from(r"C:\Windows\system32\notepad.exe"))
+        );
+    }
+
+    #[test]
+    fn test_relative_fallback_and_bad_input() {
+        let link = ShellLink {
+            relative_path: Some(r"..\docs\notes.txt".to_string()),
+            ..ShellLink::default()
+        };
+        let resolved = link.resolve(Path::new("/home/me/links/notes.lnk")).unwrap();
+        assert_eq!(resolved, PathBuf::from("/home/me/docs/notes.txt"));
+
+        assert!(ShellLink::parse(b"").is_err());
+        assert!(ShellLink::parse(&DOCUMENT[..90]).is_err());
+        let mut wrong_class = DOCUMENT.to_vec();
+        wrong_class[4] ^= 0xff;
+        assert!(ShellLink::parse(&wrong_class).is_err());
+    }
+}
@@ -23,15 +23,47 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 // Modules
+mod apps;
+mod bookmarks;
+mod browsers;
+mod calculator;
 mod clipboard;
+mod cliphistory;
 mod config;
+mod content;
+mod datetime;
 mod error;
+mod extract;
+mod files;
+mod fulltext;
+mod history;
 mod hotkey;
 mod icons;
+mod ignore;
+mod index;
+mod inflate;
+mod json;
 mod launcher;
+mod lnk;
+mod mft;
+mod paths;
+mod pdf;
+mod provider;
+mod query;
+mod recent;
 mod search;
+mod shell;
+mod snippets;
+mod sqlite;
+mod timezone;
+mod units;
 mod utils;
+mod volume;
+mod watcher;
+mod web;
+mod webhistory;
 mod window;
+mod zip;
 
 use config::Config;
 use error::Result;
@@ -69,6 +101,10 @@ fn run() -> Result<()> {
     log::info!("Indexing applications...");
     let search_engine = SearchEngine::new(config.search.clone());
     log::info!("Indexed {} applications", search_engine.application_count());
+    log::info!(
+        "File index has {} entries",
+        search_engine.file_index_count()
+    );
 
     // Log search paths
     for path in &config.search.search_paths {
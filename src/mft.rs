@@ -0,0 +1,805 @@
+//! NTFS Master File Table parsing for Rustle
+//!
+//! This module decodes raw MFT file records: the update sequence fixups,
+//! the record header flags, and the `$STANDARD_INFORMATION`, `$FILE_NAME`
+//! and `$DATA` attributes. `MftTree` then links records to their parents
+//! to rebuild full paths for a whole volume.
+//!
+//! Everything here works on byte slices; reading the bytes off a volume
+//! lives in `volume.rs`.
+
+#![allow(dead_code)]
+
+use crate::error::{Result, RustleError};
+use std::path::PathBuf;
+
+/// Record number of the volume root directory
+pub const ROOT_RECORD: u64 = 5;
+
+/// Record header flag: the record is in use
+const RECORD_IN_USE: u16 = 0x0001;
+
+/// Record header flag: the record describes a directory
+const RECORD_IS_DIRECTORY: u16 = 0x0002;
+
+/// Attribute type codes
+const ATTR_STANDARD_INFORMATION: u32 = 0x10;
+const ATTR_FILE_NAME: u32 = 0x30;
+const ATTR_DATA: u32 = 0x80;
+const ATTR_END: u32 = 0xFFFF_FFFF;
+
+/// Size of the header every attribute starts with, resident or not
+const ATTR_HEADER_SIZE: usize = 16;
+
+/// Seconds between 1601-01-01 (FILETIME epoch) and 1970-01-01
+const FILETIME_UNIX_OFFSET_SECS: u64 = 11_644_473_600;
+
+/// Smallest file record size accepted from a boot sector
+const MIN_RECORD_SIZE: u64 = 256;
+
+/// Largest file record size accepted from a boot sector
+const MAX_RECORD_SIZE: u64 = 64 * 1024;
+
+/// `$FILE_NAME` namespaces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileNamespace {
+    /// Case-sensitive name (hard links created by POSIX tools)
+    Posix,
+
+    /// Long Windows name
+    Win32,
+
+    /// 8.3 short name
+    Dos,
+
+    /// Name that is valid as both a long and a short name
+    Win32AndDos,
+}
+
+impl FileNamespace {
+    fn from_byte(byte: u8) -> Self {
+        match byte {
+            1 => FileNamespace::Win32,
+            2 => FileNamespace::Dos,
+            3 => FileNamespace::Win32AndDos,
+            _ => FileNamespace::Posix,
+        }
+    }
+
+    /// Ranks namespaces so that long names win over 8.3 short names
+    fn preference(&self) -> u8 {
+        match self {
+            FileNamespace::Win32 | FileNamespace::Win32AndDos => 2,
+            FileNamespace::Posix => 1,
+            FileNamespace::Dos => 0,
+        }
+    }
+}
+
+/// Layout values read from an NTFS boot sector
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootSector {
+    /// Bytes per sector (usually 512 or 4096)
+    pub bytes_per_sector: u32,
+
+    /// Bytes per cluster
+    pub bytes_per_cluster: u64,
+
+    /// Logical cluster number where the MFT starts
+    pub mft_lcn: u64,
+
+    /// Size of a single MFT file record in bytes (usually 1024)
+    pub record_size: u32,
+}
+
+impl BootSector {
+    /// Byte offset of the first MFT record on the volume
+    pub fn mft_offset(&self) -> u64 {
+        self.mft_lcn * self.bytes_per_cluster
+    }
+}
+
+/// A contiguous run of clusters belonging to a non-resident attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataRun {
+    /// First logical cluster, or None for a sparse run
+    pub lcn: Option<u64>,
+
+    /// Number of clusters in the run
+    pub length: u64,
+}
+
+/// One `$FILE_NAME` attribute of a record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileName {
+    /// Record number of the parent directory
+    pub parent: u64,
+
+    /// Namespace the name belongs to
+    pub namespace: FileNamespace,
+
+    /// The name itself
+    pub name: String,
+}
+
+/// The parts of an MFT file record that Rustle cares about
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MftRecord {
+    /// Index of this record in the MFT
+    pub record_number: u64,
+
+    /// Whether the record is a directory
+    pub is_dir: bool,
+
+    /// Base record number if this is an extension record
+    pub base_record: Option<u64>,
+
+    /// All `$FILE_NAME` attributes (long, short and hard-link names)
+    pub names: Vec<FileName>,
+
+    /// Size of the unnamed `$DATA` stream, if present
+    pub data_size: Option<u64>,
+
+    /// Data runs of the unnamed `$DATA` stream, if non-resident
+    pub data_runs: Vec<DataRun>,
+
+    /// Last modification time in seconds since the Unix epoch
+    pub modified: Option<u64>,
+}
+
+impl MftRecord {
+    /// Returns the preferred name (long names win over 8.3 short names)
+    pub fn best_name(&self) -> Option<&FileName> {
+        self.names.iter().max_by_key(|n| n.namespace.preference())
+    }
+}
+
+/// Parses the NTFS boot sector at the start of a volume
+pub fn parse_boot_sector(sector: &[u8]) -> Result<BootSector> {
+    if sector.len() < 512 {
+        return Err(RustleError::mft_parse("boot sector is truncated"));
+    }
+    if &sector[3..11] != b"NTFS    " {
+        return Err(RustleError::mft_parse("volume is not NTFS"));
+    }
+
+    let bytes_per_sector = read_u16(sector, 0x0B)? as u32;
+    let sectors_per_cluster = sector[0x0D] as u64;
+    if bytes_per_sector == 0 || sectors_per_cluster == 0 {
+        return Err(RustleError::mft_parse("invalid sector or cluster size"));
+    }
+    let bytes_per_cluster = bytes_per_sector as u64 * sectors_per_cluster;
+    let mft_lcn = read_u64(sector, 0x30)?;
+
+    // Positive values count clusters, negative values are a power of two
+    let clusters_per_record = sector[0x40] as i8;
+    let record_size = match clusters_per_record {
+        1..=127 => clusters_per_record as u64 * bytes_per_cluster,
+        -31..=-9 => 1u64 << -(clusters_per_record as i32),
+        _ => {
+            return Err(RustleError::mft_parse(format!(
+                "invalid file record size {}",
+                clusters_per_record
+            )))
+        }
+    };
+    if !record_size.is_power_of_two() || !(MIN_RECORD_SIZE..=MAX_RECORD_SIZE).contains(&record_size)
+    {
+        return Err(RustleError::mft_parse(format!(
+            "unsupported file record size of {} bytes",
+            record_size
+        )));
+    }
+    let record_size = record_size as u32;
+
+    Ok(BootSector {
+        bytes_per_sector,
+        bytes_per_cluster,
+        mft_lcn,
+        record_size,
+    })
+}
+
+/// Applies the update sequence array to a record in place
+///
+/// NTFS replaces the last two bytes of every sector with an update
+/// sequence number to detect torn writes; the original bytes are kept in
+/// the update sequence array and must be restored before parsing.
+pub fn apply_fixups(record: &mut [u8], bytes_per_sector: u32) -> Result<()> {
+    let usa_offset = read_u16(record, 0x04)? as usize;
+    let usa_count = read_u16(record, 0x06)? as usize;
+    let sector_size = bytes_per_sector as usize;
+
+    if usa_count == 0 || usa_offset + usa_count * 2 > record.len() {
+        return Err(RustleError::mft_parse("invalid update sequence array"));
+    }
+
+    let usn = [record[usa_offset], record[usa_offset + 1]];
+    for i in 1..usa_count {
+        let sector_end = i * sector_size;
+        if sector_end > record.len() {
+            break;
+        }
+        if record[sector_end - 2..sector_end] != usn {
+            return Err(RustleError::mft_parse(
+                "update sequence mismatch (torn write)",
+            ));
+        }
+        let fix = usa_offset + i * 2;
+        record[sector_end - 2] = record[fix];
+        record[sector_end - 1] = record[fix + 1];
+    }
+
+    Ok(())
+}
+
+/// Parses a raw MFT file record
+///
+/// Fixups are applied in place. Returns `Ok(None)` for records that are
+/// empty or not in use.
+pub fn parse_record(
+    record_number: u64,
+    record: &mut [u8],
+    bytes_per_sector: u32,
+) -> Result<Option<MftRecord>> {
+    if record.len() < 0x30 {
+        return Err(RustleError::mft_parse("file record is truncated"));
+    }
+    if record[0..4] == [0, 0, 0, 0] {
+        return Ok(None);
+    }
+    if &record[0..4] != b"FILE" {
+        return Err(RustleError::mft_parse(format!(
+            "record {} has a bad signature",
+            record_number
+        )));
+    }
+
+    apply_fixups(record, bytes_per_sector)?;
+
+    let flags = read_u16(record, 0x16)?;
+    if flags & RECORD_IN_USE == 0 {
+        return Ok(None);
+    }
+
+    let base_reference = read_u64(record, 0x20)?;
+    let base_record = match reference_to_record(base_reference) {
+        0 => None,
+        n => Some(n),
+    };
+
+    let mut parsed = MftRecord {
+        record_number,
+        is_dir: flags & RECORD_IS_DIRECTORY != 0,
+        base_record,
+        names: Vec::new(),
+        data_size: None,
+        data_runs: Vec::new(),
+        modified: None,
+    };
+
+    let used_size = (read_u32(record, 0x18)? as usize).min(record.len());
+    let mut offset = read_u16(record, 0x14)? as usize;
+
+    while offset + 8 <= used_size {
+        let attr_type = read_u32(record, offset)?;
+        if attr_type == ATTR_END {
+            break;
+        }
+        let attr_len = read_u32(record, offset + 4)? as usize;
+        if attr_len < ATTR_HEADER_SIZE || offset + attr_len > used_size {
+            return Err(RustleError::mft_parse(format!(
+                "record {} has a malformed attribute",
+                record_number
+            )));
+        }
+
+        let attr = &record[offset..offset + attr_len];
+        let non_resident = attr[8] != 0;
+        let name_len = attr[9];
+
+        match (attr_type, non_resident) {
+            (ATTR_STANDARD_INFORMATION, false) => {
+                let value = resident_value(attr)?;
+                parsed.modified = filetime_to_unix(read_u64(value, 0x08)?);
+            }
+            (ATTR_FILE_NAME, false) => {
+                parsed.names.push(parse_file_name(resident_value(attr)?)?);
+            }
+            (ATTR_DATA, false) if name_len == 0 => {
+                parsed.data_size = Some(read_u32(attr, 0x10)? as u64);
+            }
+            (ATTR_DATA, true) if name_len == 0 => {
+                // Only the first extent of a fragmented attribute holds the size
+                if read_u64(attr, 0x10)? == 0 {
+                    parsed.data_size = Some(read_u64(attr, 0x30)?);
+                }
+                let runs_offset = read_u16(attr, 0x20)? as usize;
+                let runs = attr
+                    .get(runs_offset..)
+                    .ok_or_else(|| RustleError::mft_parse("data runs out of bounds"))?;
+                parsed.data_runs.extend(parse_data_runs(runs)?);
+            }
+            _ => {}
+        }
+
+        offset += attr_len;
+    }
+
+    Ok(Some(parsed))
+}
+
+/// Decodes a non-resident attribute's run list
+///
+/// Each run starts with a header byte whose low nibble is the size of the
+/// length field and whose high nibble is the size of the (signed, relative)
+/// cluster offset field. An offset size of zero marks a sparse run.
+pub fn parse_data_runs(bytes: &[u8]) -> Result<Vec<DataRun>> {
+    let mut runs = Vec::new();
+    let mut pos = 0;
+    let mut lcn: i64 = 0;
+
+    while pos < bytes.len() && bytes[pos] != 0 {
+        let header = bytes[pos];
+        let length_size = (header & 0x0F) as usize;
+        let offset_size = (header >> 4) as usize;
+        pos += 1;
+
+        if length_size == 0 || length_size > 8 || offset_size > 8 {
+            return Err(RustleError::mft_parse("invalid data run header"));
+        }
+        if pos + length_size + offset_size > bytes.len() {
+            return Err(RustleError::mft_parse("data run is truncated"));
+        }
+
+        let length = read_varint(&bytes[pos..pos + length_size], false) as u64;
+        pos += length_size;
+
+        let run_lcn = if offset_size == 0 {
+            None
+        } else {
+            lcn += read_varint(&bytes[pos..pos + offset_size], true);
+            pos += offset_size;
+            if lcn < 0 {
+                return Err(RustleError::mft_parse("data run points before the volume"));
+            }
+            Some(lcn as u64)
+        };
+
+        runs.push(DataRun {
+            lcn: run_lcn,
+            length,
+        });
+    }
+
+    Ok(runs)
+}
+
+/// A file or folder found on a volume, with its path relative to the root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeEntry {
+    /// Path relative to the volume root
+    pub relative_path: PathBuf,
+
+    /// Whether the entry is a directory
+    pub is_dir: bool,
+
+    /// Size in bytes (0 for directories)
+    pub size: u64,
+
+    /// Last modification time in seconds since the Unix epoch
+    pub modified: u64,
+}
+
+/// A node in the directory tree rebuilt from MFT records
+#[derive(Debug, Clone)]
+struct Node {
+    parent: u64,
+    name: String,
+    name_preference: u8,
+    is_dir: bool,
+    size: u64,
+    modified: u64,
+}
+
+/// Links MFT records together to rebuild full paths
+#[derive(Debug, Default)]
+pub struct MftTree {
+    /// Nodes indexed by record number
+    nodes: Vec<Option<Node>>,
+
+    /// Names and sizes found in extension records, merged in at the end
+    extensions: Vec<MftRecord>,
+}
+
+impl MftTree {
+    /// Creates an empty tree
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a parsed record to the tree
+    pub fn insert(&mut self, record: MftRecord) {
+        if record.base_record.is_some() {
+            self.extensions.push(record);
+            return;
+        }
+
+        let number = record.record_number as usize;
+        if self.nodes.len() <= number {
+            self.nodes.resize(number + 1, None);
+        }
+
+        let best = record.best_name().cloned();
+        self.nodes[number] = Some(Node {
+            parent: best.as_ref().map_or(ROOT_RECORD, |n| n.parent),
+            name_preference: best.as_ref().map_or(0, |n| n.namespace.preference()),
+            name: best.map(|n| n.name).unwrap_or_default(),
+            is_dir: record.is_dir,
+            size: if record.is_dir {
+                0
+            } else {
+                record.data_size.unwrap_or(0)
+            },
+            modified: record.modified.unwrap_or(0),
+        });
+    }
+
+    /// Resolves every record to its path relative to the volume root
+    ///
+    /// Records whose parent chain does not reach the root (orphans left
+    /// behind by deletions, or corrupt loops) are dropped.
+    pub fn into_entries(mut self) -> Vec<VolumeEntry> {
+        self.merge_extensions();
+
+        // Resolved relative path per record: Some(path) once known
+        let mut resolved: Vec<Option<Option<PathBuf>>> = vec![None; self.nodes.len()];
+        if let Some(slot) = resolved.get_mut(ROOT_RECORD as usize) {
+            *slot = Some(Some(PathBuf::new()));
+        }
+
+        let mut entries = Vec::new();
+        for number in 0..self.nodes.len() {
+            if number as u64 == ROOT_RECORD || self.nodes[number].is_none() {
+                continue;
+            }
+
+            let Some(relative_path) = self.resolve(number, &mut resolved) else {
+                continue;
+            };
+            let node = self.nodes[number].as_ref().unwrap();
+            entries.push(VolumeEntry {
+                relative_path,
+                is_dir: node.is_dir,
+                size: node.size,
+                modified: node.modified,
+            });
+        }
+
+        entries
+    }
+
+    /// Folds extension-record names and sizes into their base records
+    fn merge_extensions(&mut self) {
+        for extension in std::mem::take(&mut self.extensions) {
+            let base = extension.base_record.unwrap_or(0) as usize;
+            let Some(Some(node)) = self.nodes.get_mut(base) else {
+                continue;
+            };
+
+            if let Some(best) = extension.best_name() {
+                if node.name.is_empty() || best.namespace.preference() > node.name_preference {
+                    node.parent = best.parent;
+                    node.name = best.name.clone();
+                    node.name_preference = best.namespace.preference();
+                }
+            }
+            if let Some(size) = extension.data_size {
+                if !node.is_dir {
+                    node.size = size;
+                }
+            }
+        }
+    }
+
+    /// Resolves one record's path, walking up iteratively and memoising
+    fn resolve(&self, number: usize, resolved: &mut [Option<Option<PathBuf>>]) -> Option<PathBuf> {
+        let mut chain = Vec::new();
+        let mut current = number;
+
+        let base = loop {
+            if let Some(known) = &resolved[current] {
+                break known.clone();
+            }
+            // Seeing the same record twice means a corrupt parent loop
+            if chain.contains(&current) || chain.len() > 4096 {
+                break None;
+            }
+            let Some(node) = self.nodes.get(current).and_then(|n| n.as_ref()) else {
+                break None;
+            };
+            if node.name.is_empty() {
+                break None;
+            }
+            chain.push(current);
+            current = node.parent as usize;
+            if current >= self.nodes.len() {
+                break None;
+            }
+        };
+
+        let mut path = base;
+        for &record in chain.iter().rev() {
+            path = path.map(|p| p.join(&self.nodes[record].as_ref().unwrap().name));
+            resolved[record] = Some(path.clone());
+        }
+        path
+    }
+}
+
+/// Extracts the record number from a 64-bit file reference
+///
+/// The low 48 bits are the record number; the high 16 bits are a
+/// sequence number used to detect reuse.
+pub fn reference_to_record(reference: u64) -> u64 {
+    reference & 0x0000_FFFF_FFFF_FFFF
+}
+
+/// Converts a FILETIME (100ns ticks since 1601) to Unix seconds
+fn filetime_to_unix(filetime: u64) -> Option<u64> {
+    (filetime / 10_000_000).checked_sub(FILETIME_UNIX_OFFSET_SECS)
+}
+
+fn parse_file_name(value: &[u8]) -> Result<FileName> {
+    let parent = reference_to_record(read_u64(value, 0x00)?);
+    let name_len = *value
+        .get(0x40)
+        .ok_or_else(|| RustleError::mft_parse("file name is truncated"))?
+        as usize;
+    let namespace = FileNamespace::from_byte(value[0x41]);
+    let name_bytes = value
+        .get(0x42..0x42 + name_len * 2)
+        .ok_or_else(|| RustleError::mft_parse("file name is truncated"))?;
+    let name_utf16: Vec<u16> = name_bytes
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+
+    Ok(FileName {
+        parent,
+        namespace,
+        name: String::from_utf16_lossy(&name_utf16),
+    })
+}
+
+fn resident_value(attr: &[u8]) -> Result<&[u8]> {
+    let len = read_u32(attr, 0x10)? as usize;
+    let offset = read_u16(attr, 0x14)? as usize;
+    attr.get(offset..offset + len)
+        .ok_or_else(|| RustleError::mft_parse("resident value out of bounds"))
+}
+
+/// Reads a little-endian integer of 1-8 bytes, optionally sign-extended
+fn read_varint(bytes: &[u8], signed: bool) -> i64 {
+    let mut value: i64 = 0;
+    for (i, &b) in bytes.iter().enumerate() {
+        value |= (b as i64) << (i * 8);
+    }
+    let bits = bytes.len() * 8;
+    if signed && bits < 64 && bytes.last().is_some_and(|&b| b & 0x80 != 0) {
+        value |= -1i64 << bits;
+    }
+    value
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Result<u16> {
+    bytes
+        .get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| RustleError::mft_parse("unexpected end of record"))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| RustleError::mft_parse("unexpected end of record"))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64> {
+    bytes
+        .get(offset..offset + 8)
+        .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| RustleError::mft_parse("unexpected end of record"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOOT_SECTOR: &[u8] = include_bytes!("../tests/fixtures/mft/boot_sector.bin");
+    const RECORD_MFT: &[u8] = include_bytes!("../tests/fixtures/mft/record_0_mft.bin");
+    const RECORD_ROOT: &[u8] = include_bytes!("../tests/fixtures/mft/record_5_root.bin");
+    const RECORD_USERS: &[u8] = include_bytes!("../tests/fixtures/mft/record_64_users.bin");
+    const RECORD_REPORT: &[u8] = include_bytes!("../tests/fixtures/mft/record_65_report.bin");
+    const RECORD_DELETED: &[u8] = include_bytes!("../tests/fixtures/mft/record_66_deleted.bin");
+    const RECORD_TORN: &[u8] = include_bytes!("../tests/fixtures/mft/record_67_torn.bin");
+
+    fn parse(number: u64, bytes: &[u8]) -> Result<Option<MftRecord>> {
+        parse_record(number, &mut bytes.to_vec(), 512)
+    }
+
+    #[test]
+    fn test_parse_boot_sector() {
+        let boot = parse_boot_sector(BOOT_SECTOR).unwrap();
+        assert_eq!(boot.bytes_per_sector, 512);
+        assert_eq!(boot.bytes_per_cluster, 4096);
+        assert_eq!(boot.mft_lcn, 786432);
+        assert_eq!(boot.record_size, 1024);
+        assert_eq!(boot.mft_offset(), 786432 * 4096);
+    }
+
+    #[test]
+    fn test_rejects_non_ntfs_boot_sector() {
+        let mut sector = BOOT_SECTOR.to_vec();
+        sector[3..11].copy_from_slice(b"FAT32   ");
+        assert!(parse_boot_sector(&sector).is_err());
+    }
+
+    #[test]
+    fn test_rejects_odd_record_sizes() {
+        let with_record_size = |value: u8| {
+            let mut sector = BOOT_SECTOR.to_vec();
+            sector[0x40] = value;
+            parse_boot_sector(&sector)
+        };
+        assert_eq!(with_record_size(0xF6).unwrap().record_size, 1024); // -10
+        assert_eq!(with_record_size(0x01).unwrap().record_size, 4096);
+        for value in [0x00, 0xE0, 0x80, 0xC0, 0xF8, 0x03, 0x7F] {
+            assert!(with_record_size(value).is_err(), "{:#x}", value);
+        }
+    }
+
+    #[test]
+    fn test_parse_mft_record_data_runs() {
+        let record = parse(0, RECORD_MFT).unwrap().unwrap();
+        assert_eq!(record.best_name().unwrap().name, "$MFT");
+        assert_eq!(record.data_size, Some(262144));
+        assert_eq!(
+            record.data_runs,
+            vec![
+                DataRun {
+                    lcn: Some(786432),
+                    length: 32
+                },
+                DataRun {
+                    lcn: Some(786400),
+                    length: 32
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_file_record_prefers_long_name() {
+        let record = parse(65, RECORD_REPORT).unwrap().unwrap();
+        assert!(!record.is_dir);
+        assert_eq!(record.names.len(), 2);
+        let name = record.best_name().unwrap();
+        assert_eq!(name.name, "Quarterly Report.pdf");
+        assert_eq!(name.namespace, FileNamespace::Win32);
+        assert_eq!(name.parent, 64);
+        assert_eq!(record.data_size, Some(1_048_576));
+        assert_eq!(record.modified, Some(1_700_000_000));
+    }
+
+    #[test]
+    fn test_parse_directory_record() {
+        let record = parse(64, RECORD_USERS).unwrap().unwrap();
+        assert!(record.is_dir);
+        assert_eq!(record.best_name().unwrap().name, "Users");
+        assert_eq!(record.best_name().unwrap().parent, ROOT_RECORD);
+    }
+
+    #[test]
+    fn test_unused_record_is_skipped() {
+        assert_eq!(parse(66, RECORD_DELETED).unwrap(), None);
+        assert_eq!(parse(70, &[0u8; 1024]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_torn_record_is_rejected() {
+        assert!(parse(67, RECORD_TORN).is_err());
+    }
+
+    #[test]
+    fn test_truncated_attribute_is_rejected() {
+        let first_attr = u16::from_le_bytes([RECORD_USERS[0x14], RECORD_USERS[0x15]]) as usize;
+        for attr_len in [1u32, 9, 15] {
+            let mut record = RECORD_USERS.to_vec();
+            record[first_attr + 4..first_attr + 8].copy_from_slice(&attr_len.to_le_bytes());
+            assert!(parse(64, &record).is_err(), "{}", attr_len);
+        }
+    }
+
+    #[test]
+    fn test_parse_data_runs_sparse_and_negative() {
+        // 0x21: 1-byte length, 2-byte offset; 0x01: sparse; 0x11 with negative offset
+        let runs =
+            parse_data_runs(&[0x21, 0x10, 0x00, 0x01, 0x01, 0x05, 0x11, 0x08, 0xF0, 0x00]).unwrap();
+        assert_eq!(
+            runs,
+            vec![
+                DataRun {
+                    lcn: Some(256),
+                    length: 16
+                },
+                DataRun {
+                    lcn: None,
+                    length: 5
+                },
+                DataRun {
+                    lcn: Some(240),
+                    length: 8
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tree_rebuilds_paths() {
+        let mut tree = MftTree::new();
+        for (number, bytes) in [
+            (0, RECORD_MFT),
+            (5, RECORD_ROOT),
+            (64, RECORD_USERS),
+            (65, RECORD_REPORT),
+            (66, RECORD_DELETED),
+        ] {
+            if let Some(record) = parse(number, bytes).unwrap() {
+                tree.insert(record);
+            }
+        }
+
+        let mut entries = tree.into_entries();
+        entries.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+        let paths: Vec<PathBuf> = entries.iter().map(|e| e.relative_path.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("$MFT"),
+                PathBuf::from("Users"),
+                PathBuf::from("Users").join("Quarterly Report.pdf"),
+            ]
+        );
+        assert_eq!(entries[2].size, 1_048_576);
+    }
+
+    #[test]
+    fn test_tree_drops_orphans_and_loops() {
+        let node = |number: u64, parent: u64, name: &str| MftRecord {
+            record_number: number,
+            is_dir: true,
+            base_record: None,
+            names: vec![FileName {
+                parent,
+                namespace: FileNamespace::Win32,
+                name: name.to_string(),
+            }],
+            data_size: None,
+            data_runs: Vec::new(),
+            modified: None,
+        };
+
+        let mut tree = MftTree::new();
+        tree.insert(node(ROOT_RECORD, ROOT_RECORD, "."));
+        tree.insert(node(20, ROOT_RECORD, "kept"));
+        tree.insert(node(21, 99, "orphan"));
+        tree.insert(node(22, 23, "loop-a"));
+        tree.insert(node(23, 22, "loop-b"));
+
+        let entries = tree.into_entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].relative_path, PathBuf::from("kept"));
+    }
+}
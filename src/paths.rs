@@ -0,0 +1,411 @@
+//! Typed path browsing for Rustle
+//!
+//! A query that starts like a path, with a drive letter (`C:\`), a network
+//! share (`\\server\share`), the home directory (`~`) or an environment
+//! variable (`%APPDATA%`), browses the file system instead of the index.
+//! The entries of the directory typed so far are listed, fuzzy-filtered by
+//! the last, unfinished segment, and Tab completes that segment to the
+//! selected entry.
+
+#![allow(dead_code)]
+
+use crate::provider::{Provider, ProviderQuery, Trigger};
+use crate::search::{CancelToken, ResultType, SearchResult, Section};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use std::cmp::Reverse;
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+
+/// Most directory entries shown for one query
+const MAX_ENTRIES: usize = 50;
+
+fn is_separator(c: char) -> bool {
+    c == '\\' || c == '/'
+}
+
+/// Returns true if `text` starts with a drive letter, `\\`, `~` or a
+/// `%VAR%` reference followed by the end or a separator
+pub fn is_typed_path(text: &str) -> bool {
+    let text = text.trim_start();
+    let ends_segment = |rest: &str| rest.chars().next().is_none_or(is_separator);
+
+    let bytes = text.as_bytes();
+    if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        return ends_segment(&text[2..]);
+    }
+    if text.starts_with(r"\\") {
+        return true;
+    }
+    if let Some(rest) = text.strip_prefix('~') {
+        return ends_segment(rest);
+    }
+    if let Some(rest) = text.strip_prefix('%') {
+        return match rest.split_once('%') {
+            Some((name, rest)) => is_variable_name(name) && ends_segment(rest),
+            None => false,
+        };
+    }
+    false
+}
+
+/// Returns true for a plausible environment variable name such as
+/// `APPDATA` or `ProgramFiles(x86)`
+fn is_variable_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| !c.is_whitespace() && !is_separator(c) && c != '=')
+}
+
+/// Replaces a leading `~` with the home directory and every `%VAR%` with
+/// the variable's value
+///
+/// References to variables that are not set are left as typed, as Windows
+/// does.
+pub fn expand(text: &str) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    if let Some(after) = rest.strip_prefix('~') {
+        if after.chars().next().is_none_or(is_separator) {
+            if let Some(home) = dirs::home_dir() {
+                expanded.push_str(&home.to_string_lossy());
+                rest = after;
+            }
+        }
+    }
+
+    while let Some(start) = rest.find('%') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after.split_once('%').and_then(|(name, tail)| {
+            let value = is_variable_name(name)
+                .then(|| std::env::var(name).ok())
+                .flatten()?;
+            Some((value, tail))
+        });
+        match value {
+            Some((value, tail)) => {
+                expanded.push_str(&value);
+                rest = tail;
+            }
+            None => {
+                expanded.push('%');
+                rest = after;
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// A path as typed, split into the directory to list and the unfinished
+/// last segment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypedPath {
+    /// Directory part as typed, ending in a separator
+    pub directory: String,
+
+    /// Text after the last separator, used to filter the entries
+    pub partial: String,
+}
+
+impl TypedPath {
+    /// Splits a typed path, or returns None if `text` is not one
+    ///
+    /// A bare root such as `C:`, `~` or `%APPDATA%` lists that directory,
+    /// as if a separator had been typed after it.
+    pub fn parse(text: &str) -> Option<Self> {
+        if !is_typed_path(text) {
+            return None;
+        }
+        let text = text.trim_start();
+
+        let path = match text.rfind(is_separator) {
+            Some(i) => Self {
+                directory: text[..=i].to_string(),
+                partial: text[i + 1..].to_string(),
+            },
+            None => Self {
+                directory: format!("{}{}", text, MAIN_SEPARATOR),
+                partial: String::new(),
+            },
+        };
+        Some(path)
+    }
+
+    /// Returns the directory to list, with `~` and variables expanded
+    pub fn expanded_directory(&self) -> PathBuf {
+        PathBuf::from(expand(&self.directory))
+    }
+
+    /// Returns the typed path completed to the entry `name`
+    ///
+    /// Folders get a trailing separator, matching the one already typed, so
+    /// their contents are listed next.
+    pub fn complete(&self, name: &str, is_dir: bool) -> String {
+        let mut completed = format!("{}{}", self.directory, name);
+        if is_dir {
+            completed.push(self.separator());
+        }
+        completed
+    }
+
+    /// Returns the last separator typed
+    fn separator(&self) -> char {
+        self.directory
+            .chars()
+            .rev()
+            .find(|&c| is_separator(c))
+            .unwrap_or(MAIN_SEPARATOR)
+    }
+}
+
+/// One entry of a listed directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub score: i64,
+}
+
+/// Lists the entries of `directory` whose names fuzzy-match `partial`
+///
+/// The best matches come first, folders before files when the scores are
+/// equal, then by name. With an empty `partial` every entry is listed.
+pub fn list_entries(directory: &Path, partial: &str, matcher: &SkimMatcherV2) -> Vec<Entry> {
+    let Ok(read_dir) = std::fs::read_dir(directory) else {
+        return Vec::new();
+    };
+
+    let mut entries: Vec<Entry> = read_dir
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let score = if partial.is_empty() {
+                0
+            } else {
+                matcher.fuzzy_match(&name, partial)?
+            };
+            let path = entry.path();
+            let is_dir = match entry.file_type() {
+                Ok(kind) if kind.is_symlink() => path.is_dir(),
+                Ok(kind) => kind.is_dir(),
+                Err(_) => false,
+            };
+            Some(Entry {
+                name,
+                path,
+                is_dir,
+                score,
+            })
+        })
+        .collect();
+
+    entries.sort_by_cached_key(|e| (Reverse(e.score), !e.is_dir, e.name.to_lowercase()));
+    entries
+}
+
+/// Lists the directory being typed when the query looks like a path
+pub struct PathProvider {
+    matcher: SkimMatcherV2,
+}
+
+impl PathProvider {
+    pub fn new() -> Self {
+        Self {
+            matcher: SkimMatcherV2::default().smart_case(),
+        }
+    }
+}
+
+impl Default for PathProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Provider for PathProvider {
+    fn id(&self) -> &'static str {
+        "paths"
+    }
+
+    fn title(&self) -> &str {
+        "Path"
+    }
+
+    fn priority(&self) -> i32 {
+        15
+    }
+
+    fn trigger(&self) -> Trigger {
+        Trigger::Pattern(is_typed_path)
+    }
+
+    /// Paths are listed as typed
+    fn wants_filters(&self) -> bool {
+        false
+    }
+
+    fn query(
+        &self,
+        query: &ProviderQuery,
+        cancel: &CancelToken,
+        emit: &(dyn Fn(Vec<Section>) + Sync),
+    ) -> bool {
+        let Some(typed) = TypedPath::parse(query.text) else {
+            return true;
+        };
+
+        let mut entries = list_entries(&typed.expanded_directory(), &typed.partial, &self.matcher);
+        if cancel.is_cancelled() {
+            return false;
+        }
+        entries.truncate(MAX_ENTRIES);
+
+        let results: Vec<SearchResult> = entries
+            .into_iter()
+            .map(|entry| SearchResult {
+                description: entry.path.display().to_string(),
+                name: entry.name,
+                path: entry.path,
+                result_type: if entry.is_dir {
+                    ResultType::Folder
+                } else {
+                    ResultType::File
+                },
+                score: entry.score,
+                provider: self.id(),
+                shortcut: None,
+            })
+            .collect();
+        if !results.is_empty() {
+            emit(vec![self.section(results)]);
+        }
+        true
+    }
+
+    fn complete(&self, query: &str, result: &SearchResult) -> Option<String> {
+        let typed = TypedPath::parse(query)?;
+        Some(typed.complete(&result.name, result.result_type == ResultType::Folder))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::run_provider;
+    use std::fs;
+
+    #[test]
+    fn test_is_typed_path() {
+        for text in [
+            r"C:\Users\",
+            "c:",
+            "D:/games",
+            r"\\server\share",
+            "~",
+            r"~\Documents",
+            "%APPDATA%",
+            r"%ProgramFiles(x86)%\Steam",
+            r"  C:\",
+        ] {
+            assert!(is_typed_path(text), "{}", text);
+        }
+        for text in [
+            "notepad",
+            "ext:pdf",
+            "C:report",
+            "~notes",
+            "%",
+            "50%",
+            "%APPDATA",
+            "%APP DATA%",
+            "%APPDATA%x",
+            r"\server",
+        ] {
+            assert!(!is_typed_path(text), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_parse_and_complete() {
+        let typed = TypedPath::parse(r"C:\Users\me\Doc").unwrap();
+        assert_eq!(typed.directory, r"C:\Users\me\");
+        assert_eq!(typed.partial, "Doc");
+        assert_eq!(typed.complete("Documents", true), r"C:\Users\me\Documents\");
+        assert_eq!(typed.complete("Doc.txt", false), r"C:\Users\me\Doc.txt");
+
+        let typed = TypedPath::parse("~/src/ru").unwrap();
+        assert_eq!(typed.complete("rustle", true), "~/src/rustle/");
+
+        let typed = TypedPath::parse("C:").unwrap();
+        assert_eq!(typed.directory, format!("C:{}", MAIN_SEPARATOR));
+        assert_eq!(typed.partial, "");
+    }
+
+    #[test]
+    fn test_expand() {
+        std::env::set_var("RUSTLE_PATHS_TEST", "/data");
+        assert_eq!(expand("%RUSTLE_PATHS_TEST%/x"), "/data/x");
+        assert_eq!(expand("%rustle_unset_var%/x"), "%rustle_unset_var%/x");
+        assert_eq!(expand("50% of %RUSTLE_PATHS_TEST%"), "50% of /data");
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(
+                expand("~/notes"),
+                format!("{}/notes", home.to_string_lossy())
+            );
+        }
+        assert_eq!(expand("~notes"), "~notes");
+    }
+
+    fn query_names(provider: &PathProvider, text: &str) -> Vec<(String, ResultType)> {
+        run_provider(provider, text)
+            .into_iter()
+            .map(|r| (r.name, r.result_type))
+            .collect()
+    }
+
+    #[test]
+    fn test_lists_typed_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("Documents")).unwrap();
+        fs::create_dir(dir.path().join("Downloads")).unwrap();
+        fs::write(dir.path().join("docs.txt"), "").unwrap();
+        fs::write(dir.path().join("music.mp3"), "").unwrap();
+        std::env::set_var("RUSTLE_PATHS_ROOT", dir.path());
+
+        let provider = PathProvider::new();
+        let all = query_names(&provider, "%RUSTLE_PATHS_ROOT%");
+        assert_eq!(
+            all,
+            vec![
+                ("Documents".to_string(), ResultType::Folder),
+                ("Downloads".to_string(), ResultType::Folder),
+                ("docs.txt".to_string(), ResultType::File),
+                ("music.mp3".to_string(), ResultType::File),
+            ]
+        );
+
+        let text = "%RUSTLE_PATHS_ROOT%/dcmt";
+        let found = query_names(&provider, text);
+        assert_eq!(found, vec![("Documents".to_string(), ResultType::Folder)]);
+
+        let result = SearchResult {
+            name: "Documents".to_string(),
+            path: dir.path().join("Documents"),
+            result_type: ResultType::Folder,
+            score: 0,
+            description: String::new(),
+            provider: "paths",
+            shortcut: None,
+        };
+        assert_eq!(
+            provider.complete(text, &result).as_deref(),
+            Some("%RUSTLE_PATHS_ROOT%/Documents/")
+        );
+
+        assert!(query_names(&provider, "%RUSTLE_PATHS_ROOT%/missing/").is_empty());
+    }
+}
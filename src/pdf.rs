@@ -0,0 +1,1372 @@
+//! PDF text extraction for Rustle
+//!
+//! This is not a renderer: it finds every `N G obj` in the file (which
+//! also copes with damaged cross-reference tables), walks the page tree
+//! and interprets just the text operators of each page's content streams.
+//! Glyph codes are mapped to Unicode through the font's `/ToUnicode` CMap
+//! when there is one, and through WinAnsi/Latin-1 for simple fonts
+//! otherwise. Line breaks are inferred from text positioning.
+
+#![allow(dead_code)]
+
+use crate::error::{Result, RustleError};
+use crate::inflate;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+/// Largest decoded stream, so a zip bomb cannot exhaust memory
+const MAX_STREAM_SIZE: usize = 64 * 1024 * 1024;
+
+/// How deeply nested page trees and form XObjects are followed
+const MAX_DEPTH: usize = 32;
+
+/// Most references followed when resolving one object
+const MAX_REF_CHAIN: usize = 16;
+
+/// Horizontal gap in TJ arrays, in thousandths of an em, read as a space
+const TJ_SPACE_THRESHOLD: f64 = 200.0;
+
+/// Characters for WinAnsiEncoding codes 0x80..=0x9F, which differ from Latin-1
+const WIN_ANSI_HIGH: [char; 32] = [
+    '€', '\u{fffd}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{fffd}', 'Ž',
+    '\u{fffd}', '\u{fffd}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{fffd}',
+    'ž', 'Ÿ',
+];
+
+/// Glyph names often used in `/Differences` arrays, with their characters
+const GLYPH_NAMES: &[(&str, char)] = &[
+    ("space", ' '),
+    ("period", '.'),
+    ("comma", ','),
+    ("colon", ':'),
+    ("semicolon", ';'),
+    ("hyphen", '-'),
+    ("endash", '–'),
+    ("emdash", '—'),
+    ("quoteleft", '‘'),
+    ("quoteright", '’'),
+    ("quotedblleft", '“'),
+    ("quotedblright", '”'),
+    ("quotesingle", '\''),
+    ("bullet", '•'),
+    ("ellipsis", '…'),
+    ("fi", 'ﬁ'),
+    ("fl", 'ﬂ'),
+];
+
+/// Extracts the text of every page of a PDF, in page order
+pub fn extract_text(data: &[u8], max_output: usize) -> Result<String> {
+    if !data.starts_with(b"%PDF-") {
+        return Err(RustleError::extract_error("not a PDF file"));
+    }
+
+    let document = Document::parse(data);
+    if document.trailer_has(b"Encrypt") {
+        return Err(RustleError::extract_error(
+            "encrypted PDFs are not supported",
+        ));
+    }
+
+    let pages = document.pages();
+    if pages.is_empty() {
+        return Err(RustleError::extract_error("no pages found"));
+    }
+
+    let mut extractor = TextState::new(&document, max_output);
+    for (page, resources) in pages {
+        let content = document.page_content(page);
+        extractor.run(&content, resources, 0);
+        extractor.out.newline();
+        if extractor.out.is_full() {
+            break;
+        }
+    }
+    Ok(extractor.out.text)
+}
+
+/// A PDF object
+#[derive(Debug, Clone, PartialEq)]
+enum Object {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Name(Vec<u8>),
+    String(Vec<u8>),
+    Array(Vec<Object>),
+    Dict(Dict),
+    Stream(Stream),
+    /// Reference to an indirect object, by object number
+    Ref(u32),
+    /// A bare keyword, which in content streams is an operator
+    Keyword(Vec<u8>),
+}
+
+type Dict = HashMap<Vec<u8>, Object>;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Stream {
+    dict: Dict,
+    /// Stream contents, still encoded
+    data: Vec<u8>,
+}
+
+impl Object {
+    fn as_dict(&self) -> Option<&Dict> {
+        match self {
+            Object::Dict(dict) => Some(dict),
+            Object::Stream(stream) => Some(&stream.dict),
+            _ => None,
+        }
+    }
+
+    fn as_name(&self) -> Option<&[u8]> {
+        match self {
+            Object::Name(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Object::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+/// Lexical tokens shared by the object syntax and content streams
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    String(Vec<u8>),
+    Name(Vec<u8>),
+    Keyword(Vec<u8>),
+    DictStart,
+    DictEnd,
+    ArrayStart,
+    ArrayEnd,
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b'\0' | b'\t' | b'\n' | b'\x0C' | b'\r' | b' ')
+}
+
+fn is_delimiter(b: u8) -> bool {
+    matches!(
+        b,
+        b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%'
+    )
+}
+
+fn is_regular(b: u8) -> bool {
+    !is_whitespace(b) && !is_delimiter(b)
+}
+
+/// Splits PDF syntax into tokens
+#[derive(Clone)]
+struct Lexer<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos }
+    }
+
+    fn peek_byte(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    /// Skips whitespace and comments
+    fn skip_whitespace(&mut self) {
+        while let Some(b) = self.peek_byte() {
+            if is_whitespace(b) {
+                self.pos += 1;
+            } else if b == b'%' {
+                while self.peek_byte().is_some_and(|b| b != b'\n' && b != b'\r') {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        self.skip_whitespace();
+        let b = self.peek_byte()?;
+        match b {
+            b'(' => {
+                self.pos += 1;
+                Some(Token::String(self.literal_string()))
+            }
+            b'<' if self.data.get(self.pos + 1) == Some(&b'<') => {
+                self.pos += 2;
+                Some(Token::DictStart)
+            }
+            b'>' if self.data.get(self.pos + 1) == Some(&b'>') => {
+                self.pos += 2;
+                Some(Token::DictEnd)
+            }
+            b'<' => {
+                self.pos += 1;
+                Some(Token::String(self.hex_string()))
+            }
+            b'[' => {
+                self.pos += 1;
+                Some(Token::ArrayStart)
+            }
+            b']' => {
+                self.pos += 1;
+                Some(Token::ArrayEnd)
+            }
+            b'/' => {
+                self.pos += 1;
+                Some(Token::Name(self.name()))
+            }
+            b')' | b'>' | b'{' | b'}' => {
+                // Stray delimiters and PostScript braces carry no text
+                self.pos += 1;
+                Some(Token::Keyword(vec![b]))
+            }
+            _ => {
+                let start = self.pos;
+                while self.peek_byte().is_some_and(is_regular) {
+                    self.pos += 1;
+                }
+                let word = &self.data[start..self.pos];
+                Some(match parse_number(word) {
+                    Some(n) => Token::Number(n),
+                    None => Token::Keyword(word.to_vec()),
+                })
+            }
+        }
+    }
+
+    /// Reads a `(...)` string after its opening parenthesis
+    fn literal_string(&mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut depth = 1;
+        while let Some(b) = self.peek_byte() {
+            self.pos += 1;
+            match b {
+                b'(' => {
+                    depth += 1;
+                    out.push(b);
+                }
+                b')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                    out.push(b);
+                }
+                b'\\' => {
+                    let Some(escaped) = self.peek_byte() else {
+                        break;
+                    };
+                    self.pos += 1;
+                    match escaped {
+                        b'n' => out.push(b'\n'),
+                        b'r' => out.push(b'\r'),
+                        b't' => out.push(b'\t'),
+                        b'b' => out.push(0x08),
+                        b'f' => out.push(0x0C),
+                        b'0'..=b'7' => {
+                            let mut value = u32::from(escaped - b'0');
+                            for _ in 0..2 {
+                                match self.peek_byte() {
+                                    Some(d @ b'0'..=b'7') => {
+                                        value = value * 8 + u32::from(d - b'0');
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            out.push(value as u8);
+                        }
+                        b'\r' => {
+                            // Line continuation
+                            if self.peek_byte() == Some(b'\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        b'\n' => {}
+                        other => out.push(other),
+                    }
+                }
+                _ => out.push(b),
+            }
+        }
+        out
+    }
+
+    /// Reads a `<...>` hex string after its opening bracket
+    fn hex_string(&mut self) -> Vec<u8> {
+        let mut digits = Vec::new();
+        while let Some(b) = self.peek_byte() {
+            self.pos += 1;
+            match b {
+                b'>' => break,
+                _ => {
+                    if let Some(d) = (b as char).to_digit(16) {
+                        digits.push(d as u8);
+                    }
+                }
+            }
+        }
+        if digits.len() % 2 == 1 {
+            digits.push(0);
+        }
+        digits
+            .chunks(2)
+            .map(|pair| pair[0] << 4 | pair[1])
+            .collect()
+    }
+
+    /// Reads a name after its slash, decoding `#xx` escapes
+    fn name(&mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+        while let Some(b) = self.peek_byte().filter(|&b| is_regular(b)) {
+            self.pos += 1;
+            if b == b'#' {
+                let hex = self.data.get(self.pos..self.pos + 2);
+                if let Some(value) = hex
+                    .and_then(|h| std::str::from_utf8(h).ok())
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                {
+                    out.push(value);
+                    self.pos += 2;
+                    continue;
+                }
+            }
+            out.push(b);
+        }
+        out
+    }
+
+    /// Reads a complete object, combining `N G R` into a reference
+    fn next_object(&mut self) -> Option<Object> {
+        let token = self.next_token()?;
+        self.object_from(token, 0)
+    }
+
+    fn object_from(&mut self, token: Token, depth: usize) -> Option<Object> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        Some(match token {
+            Token::Number(n) => {
+                if let Some(reference) = self.try_reference(n) {
+                    return Some(reference);
+                }
+                Object::Number(n)
+            }
+            Token::String(s) => Object::String(s),
+            Token::Name(n) => Object::Name(n),
+            Token::Keyword(k) => match k.as_slice() {
+                b"true" => Object::Bool(true),
+                b"false" => Object::Bool(false),
+                b"null" => Object::Null,
+                _ => Object::Keyword(k),
+            },
+            Token::ArrayStart => {
+                let mut items = Vec::new();
+                loop {
+                    match self.next_token()? {
+                        Token::ArrayEnd => break,
+                        token => items.push(self.object_from(token, depth + 1)?),
+                    }
+                }
+                Object::Array(items)
+            }
+            Token::DictStart => {
+                let mut dict = Dict::new();
+                loop {
+                    match self.next_token()? {
+                        Token::DictEnd => break,
+                        Token::Name(key) => {
+                            let token = self.next_token()?;
+                            if token == Token::DictEnd {
+                                break;
+                            }
+                            dict.insert(key, self.object_from(token, depth + 1)?);
+                        }
+                        _ => {}
+                    }
+                }
+                Object::Dict(dict)
+            }
+            Token::DictEnd | Token::ArrayEnd => Object::Keyword(Vec::new()),
+        })
+    }
+
+    /// Checks whether the number just read starts an `N G R` reference
+    fn try_reference(&mut self, number: f64) -> Option<Object> {
+        if number < 0.0 || number.fract() != 0.0 {
+            return None;
+        }
+        let mut ahead = self.clone();
+        match (ahead.next_token(), ahead.next_token()) {
+            (Some(Token::Number(generation)), Some(Token::Keyword(k)))
+                if generation.fract() == 0.0 && k == b"R" =>
+            {
+                self.pos = ahead.pos;
+                Some(Object::Ref(number as u32))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn parse_number(word: &[u8]) -> Option<f64> {
+    let first = *word.first()?;
+    if !(first.is_ascii_digit() || matches!(first, b'+' | b'-' | b'.')) {
+        return None;
+    }
+    std::str::from_utf8(word).ok()?.parse().ok()
+}
+
+/// Finds the first occurrence of `needle` in `haystack` at or after `from`
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|p| p + from)
+}
+
+/// Every indirect object of a file, keyed by object number
+struct Document {
+    objects: HashMap<u32, Object>,
+    trailer: Dict,
+}
+
+impl Document {
+    /// Scans a file for indirect objects
+    ///
+    /// Later definitions of an object replace earlier ones, which is how
+    /// incremental updates work.
+    fn parse(data: &[u8]) -> Self {
+        let mut objects = HashMap::new();
+        let mut pos = 0;
+        while let Some(found) = find(data, b"obj", pos) {
+            pos = found + 3;
+            let Some(number) = object_number_before(data, found) else {
+                continue;
+            };
+            if data.get(pos).is_some_and(|&b| is_regular(b)) {
+                continue;
+            }
+
+            let mut lexer = Lexer::new(data, pos);
+            let Some(object) = lexer.next_object() else {
+                continue;
+            };
+            let object = match object {
+                Object::Dict(dict) => match read_stream(data, &mut lexer, &dict) {
+                    Some(stream_data) => Object::Stream(Stream {
+                        dict,
+                        data: stream_data,
+                    }),
+                    None => Object::Dict(dict),
+                },
+                other => other,
+            };
+            objects.insert(number, object);
+            pos = lexer.pos;
+        }
+
+        let mut document = Self {
+            objects,
+            trailer: Dict::new(),
+        };
+        document.unpack_object_streams();
+        document.trailer = document.find_trailer(data);
+        document
+    }
+
+    /// Adds the objects stored inside compressed object streams
+    fn unpack_object_streams(&mut self) {
+        let mut unpacked = Vec::new();
+        for object in self.objects.values() {
+            let Object::Stream(stream) = object else {
+                continue;
+            };
+            if stream
+                .dict
+                .get(b"Type".as_slice())
+                .and_then(Object::as_name)
+                != Some(b"ObjStm")
+            {
+                continue;
+            }
+            let Ok(data) = self.decode_stream(stream) else {
+                continue;
+            };
+            let count = self.number(&stream.dict, b"N").unwrap_or(0.0) as usize;
+            let first = self.number(&stream.dict, b"First").unwrap_or(0.0) as usize;
+
+            let mut header = Lexer::new(&data, 0);
+            for _ in 0..count {
+                let (Some(Token::Number(number)), Some(Token::Number(offset))) =
+                    (header.next_token(), header.next_token())
+                else {
+                    break;
+                };
+                let mut lexer = Lexer::new(&data, first + offset as usize);
+                if let Some(object) = lexer.next_object() {
+                    unpacked.push((number as u32, object));
+                }
+            }
+        }
+
+        for (number, object) in unpacked {
+            self.objects.entry(number).or_insert(object);
+        }
+    }
+
+    /// Finds the trailer dictionary, or the dictionary of a cross-reference stream
+    fn find_trailer(&self, data: &[u8]) -> Dict {
+        let keyword = data.windows(7).rposition(|w| w == b"trailer");
+        if let Some(Object::Dict(dict)) =
+            keyword.and_then(|k| Lexer::new(data, k + 7).next_object())
+        {
+            return dict;
+        }
+        self.objects
+            .values()
+            .filter_map(Object::as_dict)
+            .find(|d| d.get(b"Type".as_slice()).and_then(Object::as_name) == Some(b"XRef"))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn trailer_has(&self, key: &[u8]) -> bool {
+        self.trailer.contains_key(key)
+    }
+
+    /// Follows references until reaching a direct object
+    fn resolve<'a>(&'a self, mut object: &'a Object) -> &'a Object {
+        for _ in 0..MAX_REF_CHAIN {
+            match object {
+                Object::Ref(number) => match self.objects.get(number) {
+                    Some(target) => object = target,
+                    None => return &Object::Null,
+                },
+                _ => return object,
+            }
+        }
+        &Object::Null
+    }
+
+    /// Looks up a key in a dictionary, resolving references
+    fn get<'a>(&'a self, dict: &'a Dict, key: &[u8]) -> Option<&'a Object> {
+        match self.resolve(dict.get(key)?) {
+            Object::Null => None,
+            object => Some(object),
+        }
+    }
+
+    fn number(&self, dict: &Dict, key: &[u8]) -> Option<f64> {
+        self.get(dict, key).and_then(Object::as_number)
+    }
+
+    fn name<'a>(&'a self, dict: &'a Dict, key: &[u8]) -> Option<&'a [u8]> {
+        self.get(dict, key).and_then(Object::as_name)
+    }
+
+    /// Decodes a stream's contents according to its filters
+    fn decode_stream(&self, stream: &Stream) -> Result<Vec<u8>> {
+        let filters: Vec<&[u8]> = match self.get(&stream.dict, b"Filter") {
+            None => Vec::new(),
+            Some(Object::Name(name)) => vec![name],
+            Some(Object::Array(items)) => items
+                .iter()
+                .filter_map(|i| self.resolve(i).as_name())
+                .collect(),
+            Some(_) => return Err(RustleError::extract_error("invalid stream filter")),
+        };
+
+        let mut data = stream.data.clone();
+        for filter in filters {
+            data = match filter {
+                b"FlateDecode" | b"Fl" => inflate::zlib_decompress(&data, MAX_STREAM_SIZE)?,
+                b"ASCIIHexDecode" | b"AHx" => Lexer::new(&data, 0).hex_string(),
+                other => {
+                    return Err(RustleError::extract_error(format!(
+                        "unsupported stream filter {}",
+                        String::from_utf8_lossy(other)
+                    )))
+                }
+            };
+        }
+        Ok(data)
+    }
+
+    /// Returns each page with the resources it uses, in page order
+    fn pages(&self) -> Vec<(&Dict, Option<&Dict>)> {
+        let mut pages = Vec::new();
+        let root = self
+            .get(&self.trailer, b"Root")
+            .and_then(Object::as_dict)
+            .or_else(|| {
+                self.objects.values().filter_map(Object::as_dict).find(|d| {
+                    d.get(b"Type".as_slice()).and_then(Object::as_name) == Some(b"Catalog")
+                })
+            });
+        if let Some(tree) = root.and_then(|r| self.get(r, b"Pages")) {
+            let mut visited = HashSet::new();
+            self.collect_pages(tree, None, &mut pages, &mut visited, 0);
+        }
+
+        if pages.is_empty() {
+            // No usable page tree, so take every page object in file order
+            let mut numbers: Vec<&u32> = self.objects.keys().collect();
+            numbers.sort();
+            for number in numbers {
+                if let Some(dict) = self.objects[number].as_dict() {
+                    if dict.get(b"Type".as_slice()).and_then(Object::as_name) == Some(b"Page") {
+                        let resources = self.get(dict, b"Resources").and_then(Object::as_dict);
+                        pages.push((dict, resources));
+                    }
+                }
+            }
+        }
+        pages
+    }
+
+    fn collect_pages<'a>(
+        &'a self,
+        node: &'a Object,
+        inherited: Option<&'a Dict>,
+        pages: &mut Vec<(&'a Dict, Option<&'a Dict>)>,
+        visited: &mut HashSet<*const Dict>,
+        depth: usize,
+    ) {
+        let Some(dict) = node.as_dict() else {
+            return;
+        };
+        if depth > MAX_DEPTH || !visited.insert(dict as *const Dict) {
+            return;
+        }
+
+        let resources = self
+            .get(dict, b"Resources")
+            .and_then(Object::as_dict)
+            .or(inherited);
+        match self.get(dict, b"Kids") {
+            Some(Object::Array(kids)) => {
+                for kid in kids {
+                    self.collect_pages(self.resolve(kid), resources, pages, visited, depth + 1);
+                }
+            }
+            _ => pages.push((dict, resources)),
+        }
+    }
+
+    /// Returns the decoded content streams of a page, joined together
+    fn page_content(&self, page: &Dict) -> Vec<u8> {
+        let streams: Vec<&Object> = match self.get(page, b"Contents") {
+            Some(Object::Array(items)) => items.iter().map(|i| self.resolve(i)).collect(),
+            Some(object) => vec![object],
+            None => Vec::new(),
+        };
+
+        let mut content = Vec::new();
+        for object in streams {
+            if let Object::Stream(stream) = object {
+                match self.decode_stream(stream) {
+                    Ok(data) => content.extend_from_slice(&data),
+                    Err(e) => log::debug!("Skipping PDF content stream: {}", e),
+                }
+                content.push(b'\n');
+            }
+        }
+        content
+    }
+}
+
+/// Reads the object number of `N G obj` whose keyword starts at `keyword`
+fn object_number_before(data: &[u8], keyword: usize) -> Option<u32> {
+    let mut pos = keyword;
+    let mut read_integer = |require_space: bool| -> Option<&[u8]> {
+        let end = pos;
+        while pos > 0 && is_whitespace(data[pos - 1]) {
+            pos -= 1;
+        }
+        if require_space && pos == end {
+            return None;
+        }
+        let digits_end = pos;
+        while pos > 0 && data[pos - 1].is_ascii_digit() {
+            pos -= 1;
+        }
+        (pos < digits_end).then(|| &data[pos..digits_end])
+    };
+    read_integer(false)?;
+    let number = read_integer(true)?;
+    std::str::from_utf8(number).ok()?.parse().ok()
+}
+
+/// Reads the data of a stream whose dictionary the lexer just finished
+fn read_stream(data: &[u8], lexer: &mut Lexer, dict: &Dict) -> Option<Vec<u8>> {
+    let mut ahead = lexer.clone();
+    if ahead.next_token()? != Token::Keyword(b"stream".to_vec()) {
+        return None;
+    }
+    // The keyword is followed by CRLF or LF before the data
+    let mut start = ahead.pos;
+    if data.get(start) == Some(&b'\r') {
+        start += 1;
+    }
+    if data.get(start) == Some(&b'\n') {
+        start += 1;
+    }
+
+    // Trust a direct /Length only if `endstream` follows it
+    let declared = match dict.get(b"Length".as_slice()) {
+        Some(Object::Number(len)) if *len >= 0.0 => Some(start + *len as usize),
+        _ => None,
+    };
+    let end = declared
+        .filter(|&end| {
+            let mut check = Lexer::new(data, end);
+            end <= data.len() && check.next_token() == Some(Token::Keyword(b"endstream".to_vec()))
+        })
+        .or_else(|| {
+            let mut end = find(data, b"endstream", start)?;
+            if end > start && data[end - 1] == b'\n' {
+                end -= 1;
+            }
+            if end > start && data[end - 1] == b'\r' {
+                end -= 1;
+            }
+            Some(end)
+        })?;
+
+    lexer.pos = find(data, b"endstream", end).map_or(data.len(), |p| p + 9);
+    Some(data[start..end].to_vec())
+}
+
+/// Accumulates page text, collapsing repeated breaks
+struct TextOutput {
+    text: String,
+    max_len: usize,
+}
+
+impl TextOutput {
+    fn push_str(&mut self, s: &str) {
+        if !self.is_full() {
+            self.text.push_str(s);
+        }
+    }
+
+    fn space(&mut self) {
+        if !self.text.is_empty() && !self.text.ends_with(char::is_whitespace) {
+            self.push_str(" ");
+        }
+    }
+
+    fn newline(&mut self) {
+        // Drop trailing spaces before the break
+        let trimmed = self.text.trim_end_matches(' ').len();
+        self.text.truncate(trimmed);
+        if !self.text.is_empty() && !self.text.ends_with('\n') {
+            self.push_str("\n");
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.text.len() >= self.max_len
+    }
+}
+
+/// How a font maps glyph codes to text
+#[derive(Default)]
+struct Font {
+    /// Mappings from a `/ToUnicode` CMap
+    to_unicode: HashMap<u32, String>,
+
+    /// Characters replaced through the `/Differences` of a simple font
+    differences: HashMap<u8, char>,
+
+    /// Bytes per glyph code (2 for most composite fonts)
+    code_len: usize,
+
+    /// True for composite fonts, whose codes only mean something through a CMap
+    composite: bool,
+}
+
+impl Font {
+    fn decode(&self, bytes: &[u8], out: &mut TextOutput) {
+        if self.composite || !self.to_unicode.is_empty() {
+            for code in bytes.chunks(self.code_len.max(1)) {
+                let code = code.iter().fold(0u32, |acc, &b| acc << 8 | u32::from(b));
+                match self.to_unicode.get(&code) {
+                    Some(text) => out.push_str(text),
+                    None if !self.composite && code < 256 => {
+                        out.push_str(&self.simple_char(code as u8).to_string())
+                    }
+                    None => {}
+                }
+            }
+            return;
+        }
+
+        let text: String = bytes.iter().map(|&b| self.simple_char(b)).collect();
+        out.push_str(&text);
+    }
+
+    /// Decodes a one-byte code of a simple font
+    fn simple_char(&self, code: u8) -> char {
+        if let Some(&c) = self.differences.get(&code) {
+            return c;
+        }
+        match code {
+            0x80..=0x9F => WIN_ANSI_HIGH[(code - 0x80) as usize],
+            _ => code as char,
+        }
+    }
+}
+
+/// Interprets content streams and collects the text they show
+struct TextState<'a> {
+    document: &'a Document,
+    out: TextOutput,
+    fonts: HashMap<*const Dict, Rc<Font>>,
+}
+
+impl<'a> TextState<'a> {
+    fn new(document: &'a Document, max_len: usize) -> Self {
+        Self {
+            document,
+            out: TextOutput {
+                text: String::new(),
+                max_len,
+            },
+            fonts: HashMap::new(),
+        }
+    }
+
+    /// Runs one content stream with the given resources
+    fn run(&mut self, content: &[u8], resources: Option<&'a Dict>, depth: usize) {
+        if depth > MAX_DEPTH {
+            return;
+        }
+
+        let mut lexer = Lexer::new(content, 0);
+        let mut operands: Vec<Object> = Vec::new();
+        let mut font: Option<Rc<Font>> = None;
+        let mut line_y: Option<f64> = None;
+
+        while let Some(token) = lexer.next_token() {
+            if self.out.is_full() {
+                return;
+            }
+            let operator = match token {
+                Token::Keyword(k) => k,
+                token => {
+                    if let Some(object) = lexer.object_from(token, 0) {
+                        operands.push(object);
+                    }
+                    continue;
+                }
+            };
+
+            match operator.as_slice() {
+                b"BT" => line_y = None,
+                b"Tf" => {
+                    font = operands
+                        .first()
+                        .and_then(Object::as_name)
+                        .and_then(|name| self.font(resources, name));
+                }
+                b"Tj" => self.show(operands.last(), font.as_deref()),
+                b"'" | b"\"" => {
+                    self.out.newline();
+                    self.show(operands.last(), font.as_deref());
+                }
+                b"TJ" => {
+                    if let Some(Object::Array(items)) = operands.last() {
+                        for item in items {
+                            match item {
+                                Object::Number(n) if -n > TJ_SPACE_THRESHOLD => self.out.space(),
+                                Object::String(_) => self.show(Some(item), font.as_deref()),
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+                b"Td" | b"TD" => {
+                    let ty = operands.get(1).and_then(Object::as_number).unwrap_or(0.0);
+                    if ty != 0.0 {
+                        self.out.newline();
+                    } else {
+                        self.out.space();
+                    }
+                }
+                b"T*" => self.out.newline(),
+                b"Tm" => {
+                    let y = operands.get(5).and_then(Object::as_number);
+                    if line_y.is_some() && y != line_y {
+                        self.out.newline();
+                    } else {
+                        self.out.space();
+                    }
+                    line_y = y;
+                }
+                b"ET" => self.out.space(),
+                b"BI" => skip_inline_image(&mut lexer),
+                b"Do" => {
+                    if let Some(name) = operands.first().and_then(Object::as_name) {
+                        self.run_form(resources, name, depth);
+                    }
+                }
+                _ => {}
+            }
+            operands.clear();
+        }
+    }
+
+    /// Runs a form XObject drawn with `Do`
+    fn run_form(&mut self, resources: Option<&'a Dict>, name: &[u8], depth: usize) {
+        let document = self.document;
+        let form = resources
+            .and_then(|r| document.get(r, b"XObject"))
+            .and_then(Object::as_dict)
+            .and_then(|x| document.get(x, name));
+        let Some(Object::Stream(stream)) = form else {
+            return;
+        };
+        if document.name(&stream.dict, b"Subtype") != Some(b"Form") {
+            return;
+        }
+        let Ok(content) = document.decode_stream(stream) else {
+            return;
+        };
+        let form_resources = document
+            .get(&stream.dict, b"Resources")
+            .and_then(Object::as_dict)
+            .or(resources);
+        self.run(&content, form_resources, depth + 1);
+    }
+
+    fn show(&mut self, string: Option<&Object>, font: Option<&Font>) {
+        let Some(Object::String(bytes)) = string else {
+            return;
+        };
+        match font {
+            Some(font) => font.decode(bytes, &mut self.out),
+            None => Font::default().decode(bytes, &mut self.out),
+        }
+    }
+
+    /// Looks up a font resource by name, caching what was learned about it
+    fn font(&mut self, resources: Option<&'a Dict>, name: &[u8]) -> Option<Rc<Font>> {
+        let document = self.document;
+        let dict = resources
+            .and_then(|r| document.get(r, b"Font"))
+            .and_then(Object::as_dict)
+            .and_then(|fonts| document.get(fonts, name))
+            .and_then(Object::as_dict)?;
+
+        let key = dict as *const Dict;
+        if let Some(font) = self.fonts.get(&key) {
+            return Some(font.clone());
+        }
+        let font = Rc::new(load_font(document, dict));
+        self.fonts.insert(key, font.clone());
+        Some(font)
+    }
+}
+
+/// Skips the data of an inline image, up to and including `EI`
+fn skip_inline_image(lexer: &mut Lexer) {
+    let data = lexer.data;
+    let Some(id) = find(data, b"ID", lexer.pos) else {
+        lexer.pos = data.len();
+        return;
+    };
+    let mut pos = id + 2;
+    while let Some(ei) = find(data, b"EI", pos) {
+        let before = ei == 0 || is_whitespace(data[ei - 1]);
+        let after = data.get(ei + 2).is_none_or(|&b| !is_regular(b));
+        if before && after {
+            lexer.pos = ei + 2;
+            return;
+        }
+        pos = ei + 2;
+    }
+    lexer.pos = data.len();
+}
+
+/// Reads what is needed to decode a font's text
+fn load_font(document: &Document, dict: &Dict) -> Font {
+    let composite = document.name(dict, b"Subtype") == Some(b"Type0");
+    let mut font = Font {
+        code_len: if composite { 2 } else { 1 },
+        composite,
+        ..Font::default()
+    };
+
+    if let Some(Object::Stream(stream)) = document.get(dict, b"ToUnicode") {
+        if let Ok(cmap) = document.decode_stream(stream) {
+            parse_cmap(&cmap, &mut font);
+        }
+    }
+
+    let differences = document
+        .get(dict, b"Encoding")
+        .and_then(Object::as_dict)
+        .and_then(|e| document.get(e, b"Differences"));
+    if let Some(Object::Array(items)) = differences {
+        let mut code = 0u32;
+        for item in items {
+            match document.resolve(item) {
+                Object::Number(n) => code = *n as u32,
+                Object::Name(glyph) => {
+                    if let (Ok(byte), Some(c)) = (u8::try_from(code), glyph_char(glyph)) {
+                        font.differences.insert(byte, c);
+                    }
+                    code += 1;
+                }
+                _ => {}
+            }
+        }
+    }
+    font
+}
+
+/// Maps a glyph name such as `A`, `uni00E9` or `quoteright` to a character
+fn glyph_char(glyph: &[u8]) -> Option<char> {
+    let glyph = std::str::from_utf8(glyph).ok()?;
+    let mut chars = glyph.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(c);
+    }
+    if let Some(hex) = glyph.strip_prefix("uni").filter(|h| h.len() == 4) {
+        return char::from_u32(u32::from_str_radix(hex, 16).ok()?);
+    }
+    GLYPH_NAMES
+        .iter()
+        .find(|(name, _)| *name == glyph)
+        .map(|&(_, c)| c)
+}
+
+/// Reads the code space and `bfchar`/`bfrange` mappings of a ToUnicode CMap
+fn parse_cmap(cmap: &[u8], font: &mut Font) {
+    let mut lexer = Lexer::new(cmap, 0);
+    let mut operands: Vec<Object> = Vec::new();
+    let mut in_section = false;
+
+    while let Some(token) = lexer.next_token() {
+        let keyword = match token {
+            Token::Keyword(k) => k,
+            token => {
+                if let Some(object) = lexer.object_from(token, 0) {
+                    operands.push(object);
+                }
+                continue;
+            }
+        };
+
+        match keyword.as_slice() {
+            b"begincodespacerange" | b"beginbfchar" | b"beginbfrange" => {
+                operands.clear();
+                in_section = true;
+            }
+            b"endcodespacerange" => {
+                if let Some(Object::String(low)) = operands.first() {
+                    font.code_len = low.len().clamp(1, 4);
+                }
+                in_section = false;
+            }
+            b"endbfchar" => {
+                for pair in operands.chunks_exact(2) {
+                    if let (Object::String(code), Object::String(dest)) = (&pair[0], &pair[1]) {
+                        font.to_unicode.insert(code_value(code), utf16_be(dest));
+                    }
+                }
+                in_section = false;
+            }
+            b"endbfrange" => {
+                for range in operands.chunks_exact(3) {
+                    let (Object::String(low), Object::String(high)) = (&range[0], &range[1]) else {
+                        continue;
+                    };
+                    let (low, high) = (code_value(low), code_value(high));
+                    // Ranges are meant to stay within one byte of codes
+                    if high < low || high - low > 0xFFFF {
+                        continue;
+                    }
+                    match &range[2] {
+                        Object::String(start) => {
+                            let mut units = utf16_units(start);
+                            for code in low..=high {
+                                font.to_unicode.insert(code, decode_utf16(&units));
+                                if let Some(last) = units.last_mut() {
+                                    *last = last.wrapping_add(1);
+                                }
+                            }
+                        }
+                        Object::Array(dests) => {
+                            for (code, dest) in (low..=high).zip(dests) {
+                                if let Object::String(dest) = dest {
+                                    font.to_unicode.insert(code, utf16_be(dest));
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                in_section = false;
+            }
+            _ => {}
+        }
+        if !in_section {
+            operands.clear();
+        }
+    }
+}
+
+fn code_value(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0u32, |acc, &b| acc << 8 | u32::from(b))
+}
+
+fn utf16_units(bytes: &[u8]) -> Vec<u16> {
+    bytes
+        .chunks(2)
+        .map(|pair| u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)]))
+        .collect()
+}
+
+fn utf16_be(bytes: &[u8]) -> String {
+    decode_utf16(&utf16_units(bytes))
+}
+
+fn decode_utf16(units: &[u16]) -> String {
+    char::decode_utf16(units.iter().copied())
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FONT: &str = "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>";
+
+    /// Wraps data in zlib framing with a single uncompressed block
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        let len = data.len() as u16;
+        let mut out = vec![0x78, 0x01, 0x01];
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(data);
+        let (mut a, mut b) = (1u32, 0u32);
+        for &byte in data {
+            a = (a + u32::from(byte)) % 65_521;
+            b = (b + a) % 65_521;
+        }
+        out.extend_from_slice(&(b << 16 | a).to_be_bytes());
+        out
+    }
+
+    fn stream(dict: &str, data: &[u8]) -> Vec<u8> {
+        let mut out = format!("<< {} /Length {} >>\nstream\n", dict, data.len()).into_bytes();
+        out.extend_from_slice(data);
+        out.extend_from_slice(b"\nendstream");
+        out
+    }
+
+    fn text_content(text: &str) -> Vec<u8> {
+        stream(
+            "",
+            format!("BT /F1 12 Tf 72 700 Td ({}) Tj ET", text).as_bytes(),
+        )
+    }
+
+    /// Builds a file from objects numbered from 1, followed by `tail`
+    ///
+    /// Empty objects are left out of the file, keeping their numbers free.
+    fn pdf(objects: &[Vec<u8>], tail: &str) -> Vec<u8> {
+        let mut out = b"%PDF-1.5\n".to_vec();
+        for (i, object) in objects.iter().enumerate().filter(|(_, o)| !o.is_empty()) {
+            out.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            out.extend_from_slice(object);
+            out.extend_from_slice(b"\nendobj\n");
+        }
+        out.extend_from_slice(tail.as_bytes());
+        out
+    }
+
+    /// A file whose pages show `texts`, with an xref table that points nowhere
+    fn pages_pdf(texts: &[&str], trailer: &str) -> Vec<u8> {
+        let kids: Vec<String> = (0..texts.len())
+            .map(|i| format!("{} 0 R", 4 + 2 * i))
+            .collect();
+        let mut objects = vec![
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} /Resources << /Font << /F1 3 0 R >> >> >>",
+                kids.join(" "),
+                texts.len()
+            )
+            .into_bytes(),
+            FONT.as_bytes().to_vec(),
+        ];
+        for (i, text) in texts.iter().enumerate() {
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /Contents {} 0 R >>",
+                    5 + 2 * i
+                )
+                .into_bytes(),
+            );
+            objects.push(text_content(text));
+        }
+        let tail = format!(
+            "xref\n0 1\n0000000000 65535 f \ntrailer\n{}\nstartxref\n99999\n%%EOF\n",
+            trailer
+        );
+        pdf(&objects, &tail)
+    }
+
+    #[test]
+    fn test_object_streams() {
+        // The page tree lives in a compressed object stream and the trailer
+        // is the dictionary of a cross-reference stream
+        let packed = [
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /Resources << /Font << /F1 4 0 R >> >> /Contents 5 0 R >>",
+        ];
+        let mut header = String::new();
+        let mut body = String::new();
+        for (i, object) in packed.iter().enumerate() {
+            header.push_str(&format!("{} {} ", i + 1, body.len()));
+            body.push_str(object);
+            body.push('\n');
+        }
+        let object_stream = stream(
+            &format!(
+                "/Type /ObjStm /N 3 /First {} /Filter /FlateDecode",
+                header.len()
+            ),
+            &zlib((header.clone() + &body).as_bytes()),
+        );
+        let content = stream(
+            "/Filter /FlateDecode",
+            &zlib(b"BT /F1 12 Tf 72 700 Td (Packed text) Tj ET"),
+        );
+        // Objects 1 to 3 only exist inside the object stream
+        let data = pdf(
+            &[
+                Vec::new(),
+                Vec::new(),
+                Vec::new(),
+                FONT.as_bytes().to_vec(),
+                content,
+                object_stream,
+                stream("/Type /XRef /Size 8 /Root 1 0 R /W [1 2 1]", &[0; 8]),
+            ],
+            "startxref\n999\n%%EOF\n",
+        );
+        assert_eq!(extract_text(&data, 1024).unwrap(), "Packed text\n");
+    }
+
+    #[test]
+    fn test_broken_xref_falls_back_to_scan() {
+        let data = pages_pdf(&["First page", "Second page"], "<< /Size 8 /Root 1 0 R >>");
+        assert_eq!(
+            extract_text(&data, 1024).unwrap(),
+            "First page\nSecond page\n"
+        );
+
+        // Without an xref table or trailer, the catalog is found by its type
+        let end = data.windows(4).rposition(|w| w == b"xref").unwrap();
+        assert_eq!(
+            extract_text(&data[..end], 1024).unwrap(),
+            "First page\nSecond page\n"
+        );
+
+        // Without a usable page tree, page objects are taken in file order
+        let data = pdf(
+            &[
+                FONT.as_bytes().to_vec(),
+                b"<< /Type /Page /Resources << /Font << /F1 1 0 R >> >> /Contents 3 0 R >>"
+                    .to_vec(),
+                text_content("Orphan page"),
+            ],
+            "",
+        );
+        assert_eq!(extract_text(&data, 1024).unwrap(), "Orphan page\n");
+    }
+
+    #[test]
+    fn test_encrypted_documents_are_rejected() {
+        let data = pages_pdf(&["Secret"], "<< /Size 6 /Root 1 0 R /Encrypt 9 0 R >>");
+        let err = extract_text(&data, 1024).unwrap_err();
+        assert!(err.to_string().contains("encrypted"));
+    }
+
+    #[test]
+    fn test_max_output_stops_early() {
+        let data = pages_pdf(
+            &["First page", "Second page", "Third page"],
+            "<< /Root 1 0 R >>",
+        );
+        assert_eq!(extract_text(&data, 5).unwrap(), "First page");
+        assert!(extract_text(&data, 15)
+            .unwrap()
+            .starts_with("First page\nSecond"));
+        assert!(!extract_text(&data, 15).unwrap().contains("Third"));
+    }
+
+    #[test]
+    fn test_truncated_file() {
+        let data = pages_pdf(&["First page", "Second page"], "<< /Root 1 0 R >>");
+        for len in 0..data.len() {
+            let _ = extract_text(&data[..len], 1024);
+        }
+
+        // Cut inside the second page's content stream
+        let end = data.windows(11).position(|w| w == b"Second page").unwrap() + 3;
+        let text = extract_text(&data[..end], 1024).unwrap();
+        assert!(text.starts_with("First page\n"));
+        assert!(!text.contains("Second page"));
+    }
+
+    #[test]
+    fn test_lexer_strings_and_names() {
+        let mut lexer = Lexer::new(br"(a\(b\)\101) <48 69> /A#20B 12 0 R [1 -2.5]", 0);
+        assert_eq!(lexer.next_object(), Some(Object::String(b"a(b)A".to_vec())));
+        assert_eq!(lexer.next_object(), Some(Object::String(b"Hi".to_vec())));
+        assert_eq!(lexer.next_object(), Some(Object::Name(b"A B".to_vec())));
+        assert_eq!(lexer.next_object(), Some(Object::Ref(12)));
+        assert_eq!(
+            lexer.next_object(),
+            Some(Object::Array(vec![
+                Object::Number(1.0),
+                Object::Number(-2.5)
+            ]))
+        );
+        assert_eq!(lexer.next_object(), None);
+    }
+
+    #[test]
+    fn test_parse_cmap() {
+        let cmap = b"1 begincodespacerange <0000> <FFFF> endcodespacerange
+            2 beginbfchar <0001> <0048> <0002> <00690021> endbfchar
+            1 beginbfrange <0010> <0012> <0061> endbfrange";
+        let mut font = Font::default();
+        parse_cmap(cmap, &mut font);
+
+        assert_eq!(font.code_len, 2);
+        assert_eq!(font.to_unicode[&1], "H");
+        assert_eq!(font.to_unicode[&2], "i!");
+        assert_eq!(font.to_unicode[&0x12], "c");
+    }
+}
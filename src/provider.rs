@@ -0,0 +1,277 @@
+//! Result providers for Rustle
+//!
+//! A provider is one source of search results: installed applications,
+//! files on disk, text inside files, and so on. The search engine asks
+//! every provider whose trigger matches the query for results, in
+//! parallel, and shows what each returns as its own section. Adding a new
+//! kind of result means implementing [`Provider`] and registering it with
+//! `SearchEngine::add_provider`; nothing in `search.rs` has to change.
+
+#![allow(dead_code)]
+
+use crate::clipboard;
+use crate::error::{Result, RustleError};
+use crate::launcher;
+use crate::query::Query;
+use crate::search::{CancelToken, SearchResult, Section};
+use std::path::PathBuf;
+
+/// A source of search results
+///
+/// Queries run on the search worker's thread pool, several providers at
+/// once, so implementations must be thread-safe. A query may report its
+/// results more than once as it finds more of them, and should stop early
+/// once its cancel token is cancelled.
+pub trait Provider: Send + Sync {
+    /// Short, stable identifier such as `apps` or `files`
+    fn id(&self) -> &'static str;
+
+    /// Heading of the provider's section of results
+    fn title(&self) -> &str;
+
+    /// Position of the provider's section; lower comes first
+    fn priority(&self) -> i32;
+
+    /// Which queries the provider answers
+    fn trigger(&self) -> Trigger {
+        Trigger::Always
+    }
+
+    /// Whether filters such as `ext:pdf` are parsed out of the query
+    ///
+    /// Providers that take the text as typed, such as shell commands or
+    /// web searches, return false: their text is passed on untouched and
+    /// a filter-like word in it is never an error.
+    fn wants_filters(&self) -> bool {
+        true
+    }
+
+    /// Looks for results matching `query`
+    ///
+    /// Each call to `emit` replaces everything the provider reported
+    /// before, so partial results can be refined as the search goes on.
+    /// Returns false if the search was cancelled before it finished.
+    fn query(
+        &self,
+        query: &ProviderQuery,
+        cancel: &CancelToken,
+        emit: &(dyn Fn(Vec<Section>) + Sync),
+    ) -> bool;
+
+    /// Returns what can be done with one of the provider's results
+    ///
+    /// The first action is the default one, run when a result is chosen.
+    fn actions(&self, result: &SearchResult) -> Vec<Action> {
+        vec![
+            Action::Open(result.path.clone()),
+            Action::OpenContainingFolder(result.path.clone()),
+        ]
+    }
+
+    /// Returns the text Tab should replace the query with to complete
+    /// `result`, or None if the provider does not complete queries
+    ///
+    /// `query` is the full text typed into the search box.
+    fn complete(&self, query: &str, result: &SearchResult) -> Option<String> {
+        let _ = (query, result);
+        None
+    }
+
+    /// Carries out one of the actions returned by `actions`
+    fn run_action(&self, result: &SearchResult, action: &Action) -> Result<()> {
+        let _ = result;
+        action.run()
+    }
+
+    /// Builds a section of results under the provider's title and priority
+    fn section(&self, results: Vec<SearchResult>) -> Section {
+        Section {
+            provider: self.id(),
+            title: self.title().to_string(),
+            priority: self.priority(),
+            results,
+        }
+    }
+}
+
+/// When a provider answers a query
+#[derive(Debug, Clone)]
+pub enum Trigger {
+    /// Answers every query that has no matching prefix
+    Always,
+
+    /// Answers only queries starting with this prefix, which is removed
+    /// before the provider sees the query
+    Prefix(String),
+
+    /// Answers only queries the function recognises, such as typed paths,
+    /// which are passed on unchanged
+    Pattern(fn(&str) -> bool),
+}
+
+impl Trigger {
+    /// Returns the part of `query` the provider should see, or None if
+    /// the trigger does not match
+    ///
+    /// Prefixes match after leading whitespace. An empty prefix never matches.
+    pub fn strip<'a>(&self, query: &'a str) -> Option<&'a str> {
+        match self {
+            Trigger::Always => Some(query),
+            Trigger::Prefix(prefix) if prefix.is_empty() => None,
+            Trigger::Prefix(prefix) => query.trim_start().strip_prefix(prefix.as_str()),
+            Trigger::Pattern(recognises) => recognises(query).then_some(query),
+        }
+    }
+}
+
+/// A query as handed to a provider
+#[derive(Debug, Clone)]
+pub struct ProviderQuery<'a> {
+    /// The text typed into the search box, without any trigger prefix
+    pub text: &'a str,
+
+    /// `text` split into free text and filters, or all of `text` as free
+    /// text for providers that do not want filters
+    pub parsed: &'a Query,
+}
+
+/// Something that can be done with a search result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Opens a file, folder, shortcut or URL with its default handler
+    Open(PathBuf),
+
+    /// Opens the folder containing a path
+    OpenContainingFolder(PathBuf),
+
+    /// Copies text to the clipboard
+    Copy(String),
+
+    /// Runs a shell command in a new terminal window
+    RunInTerminal(String),
+
+    /// Runs a shell command and shows its output in the search window
+    RunAndCapture(String),
+
+    /// Stops the shell command whose output is being shown
+    Kill,
+
+    /// Copies text to the clipboard and pastes it into the window that
+    /// had focus before the search window
+    Paste(String),
+
+    /// Keeps a clipboard entry at the top and out of reach of clearing
+    Pin,
+
+    /// Lets a pinned clipboard entry be dropped again
+    Unpin,
+
+    /// Forgets one clipboard entry
+    Remove,
+
+    /// Forgets every clipboard entry that is not pinned
+    ClearHistory,
+
+    /// Replaces the query with this text, to ask for more input
+    SetQuery(String),
+}
+
+impl Action {
+    /// Returns a short label for menus and hints
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::Open(_) => "Open",
+            Action::OpenContainingFolder(_) => "Open containing folder",
+            Action::Copy(_) => "Copy",
+            Action::RunInTerminal(_) => "Run in terminal",
+            Action::RunAndCapture(_) => "Run and show output",
+            Action::Kill => "Stop",
+            Action::Paste(_) => "Paste",
+            Action::Pin => "Pin",
+            Action::Unpin => "Unpin",
+            Action::Remove => "Remove from history",
+            Action::ClearHistory => "Clear history",
+            Action::SetQuery(_) => "Continue",
+        }
+    }
+
+    /// Returns true if the search window should stay open after the action,
+    /// because its results are shown there
+    pub fn keeps_window_open(&self) -> bool {
+        matches!(
+            self,
+            Action::RunAndCapture(_)
+                | Action::Kill
+                | Action::Pin
+                | Action::Unpin
+                | Action::Remove
+                | Action::ClearHistory
+                | Action::SetQuery(_)
+        )
+    }
+
+    /// Carries out the action
+    pub fn run(&self) -> Result<()> {
+        match self {
+            Action::Open(path) => launcher::launch(path),
+            Action::OpenContainingFolder(path) => launcher::open_containing_folder(path),
+            Action::Copy(text) => {
+                clipboard::copy_to_clipboard(None, text).map_err(RustleError::clipboard_error)
+            }
+            // The window pastes it once focus is back where it was
+            Action::Paste(text) => {
+                clipboard::copy_to_clipboard(None, text).map_err(RustleError::clipboard_error)
+            }
+            _ => Err(RustleError::search_error(format!(
+                "\"{}\" can only be carried out by the provider that offered it",
+                self.label()
+            ))),
+        }
+    }
+}
+
+/// Searches for `text` as typed into the search box with `provider` as
+/// the only one registered, returning the sections of the final results
+///
+/// The query goes through the same dispatch as a real search, so the
+/// provider's trigger and filter parsing apply.
+#[cfg(test)]
+pub(crate) fn run_provider_sections(provider: &dyn Provider, text: &str) -> Vec<Section> {
+    let last = std::sync::Mutex::new(None);
+    crate::search::SearchEngine::dispatch(&[provider], text, &CancelToken::never(), |update| {
+        let update = update.unwrap();
+        if update.complete {
+            *last.lock().unwrap() = Some(update.results.sections);
+        }
+    });
+    last.into_inner().unwrap().unwrap_or_default()
+}
+
+/// Like `run_provider_sections`, returning the results of every section
+/// in order
+#[cfg(test)]
+pub(crate) fn run_provider(provider: &dyn Provider, text: &str) -> Vec<SearchResult> {
+    run_provider_sections(provider, text)
+        .into_iter()
+        .flat_map(|s| s.results)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trigger_strip() {
+        assert_eq!(Trigger::Always.strip(" notes"), Some(" notes"));
+
+        let prefix = Trigger::Prefix("/".to_string());
+        assert_eq!(prefix.strip("  /budget"), Some("budget"));
+        assert_eq!(prefix.strip("budget"), None);
+        assert_eq!(Trigger::Prefix(String::new()).strip("budget"), None);
+
+        let pattern = Trigger::Pattern(|q| q.starts_with('~'));
+        assert_eq!(pattern.strip("~/notes"), Some("~/notes"));
+        assert_eq!(pattern.strip("notes"), None);
+    }
+}
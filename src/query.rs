@@ -0,0 +1,593 @@
+//! Query parsing for Rustle
+//!
+//! This module turns the text typed into the search box into a typed
+//! `Query`: the free text used for fuzzy matching plus any structured
+//! filters such as `ext:pdf`, `in:Downloads`, `type:folder`,
+//! `size:>100mb` or `modified:<7d`. Filters with invalid values are
+//! reported as errors so the UI can tell the user what went wrong.
+
+#![allow(dead_code)]
+
+use crate::error::{Result, RustleError};
+use crate::search::ResultType;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds in one day
+const SECS_PER_DAY: u64 = 86_400;
+
+/// A parsed search query
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    /// Free text to fuzzy match against names (filters removed)
+    pub text: String,
+
+    /// Structured filters every result must satisfy
+    pub filters: Vec<Filter>,
+}
+
+/// A single structured filter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    /// `ext:pdf` or `ext:jpg,png` - file extension is one of these
+    Extension(Vec<String>),
+
+    /// `in:Downloads` - path lies inside a matching folder
+    In(String),
+
+    /// `type:file`, `type:folder` or `type:app`
+    Type(ResultType),
+
+    /// `size:>100mb` - file size in bytes
+    Size(Comparison, u64),
+
+    /// `modified:<7d` or `modified:>=2024-01-31` - last modification time
+    Modified(Comparison, TimeBound),
+}
+
+/// Comparison operator for numeric filters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    /// Splits a leading operator off a filter value
+    ///
+    /// Values without an operator compare for equality.
+    fn split(value: &str) -> (Self, &str) {
+        for (prefix, op) in [
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+            ("=", Comparison::Equal),
+        ] {
+            if let Some(rest) = value.strip_prefix(prefix) {
+                return (op, rest);
+            }
+        }
+        (Comparison::Equal, value)
+    }
+
+    /// Returns true if `value <op> bound` holds
+    pub fn matches(self, value: u64, bound: u64) -> bool {
+        match self {
+            Comparison::Less => value < bound,
+            Comparison::LessOrEqual => value <= bound,
+            Comparison::Equal => value == bound,
+            Comparison::GreaterOrEqual => value >= bound,
+            Comparison::Greater => value > bound,
+        }
+    }
+}
+
+/// The time a `modified:` filter compares against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeBound {
+    /// Age in seconds, e.g. `7d` (`modified:7d` means within the last 7 days)
+    Age(u64),
+
+    /// Start of a calendar day (UTC) in seconds since the Unix epoch
+    Date(u64),
+}
+
+/// The facts about a result that filters are checked against
+#[derive(Debug, Clone, Copy)]
+pub struct Candidate<'a> {
+    /// Full path of the result
+    pub path: &'a Path,
+
+    /// Kind of result
+    pub result_type: ResultType,
+
+    /// Size in bytes, if known
+    pub size: Option<u64>,
+
+    /// Last modification time in seconds since the Unix epoch, if known
+    pub modified: Option<u64>,
+
+    /// What an application's shortcut starts, if known; `ext:` matches
+    /// applications on its extension rather than the shortcut's
+    pub target: Option<&'a Path>,
+}
+
+impl Query {
+    /// Parses the text typed into the search box
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut words = Vec::new();
+        let mut filters = Vec::new();
+
+        for token in tokenize(input) {
+            match token.split_once(':') {
+                Some((key, value)) if is_filter_key(key) => {
+                    filters.push(parse_filter(&key.to_lowercase(), value)?);
+                }
+                _ => words.push(token),
+            }
+        }
+
+        Ok(Self {
+            text: words.join(" "),
+            filters,
+        })
+    }
+
+    /// Makes a query of `text` as typed, with no filters
+    pub fn literal(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            filters: Vec::new(),
+        }
+    }
+
+    /// Returns true if there is neither free text nor any filter
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.filters.is_empty()
+    }
+
+    /// Returns true if the query has at least one filter
+    pub fn has_filters(&self) -> bool {
+        !self.filters.is_empty()
+    }
+
+    /// Returns false if a `type:` filter rules out this kind of result
+    pub fn wants(&self, result_type: ResultType) -> bool {
+        self.filters.iter().all(|filter| match filter {
+            Filter::Type(wanted) => *wanted == result_type,
+            _ => true,
+        })
+    }
+
+    /// Checks a result against every filter
+    ///
+    /// `now` is the current time in seconds since the Unix epoch. Size and
+    /// time filters never match results whose size or time is unknown.
+    pub fn matches(&self, candidate: &Candidate, now: u64) -> bool {
+        self.filters
+            .iter()
+            .all(|filter| filter_matches(filter, candidate, now))
+    }
+}
+
+/// Returns the current time in seconds since the Unix epoch
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Splits a query on whitespace, keeping double-quoted runs together
+///
+/// Quotes may appear anywhere in a token (`in:"My Documents"`) and are
+/// removed from the result.
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+/// Checks whether a `key:` prefix names a filter
+///
+/// Anything else containing a colon (`C:\Users`, `re:zero`) stays free text.
+fn is_filter_key(key: &str) -> bool {
+    matches!(
+        key.to_lowercase().as_str(),
+        "ext" | "in" | "type" | "size" | "modified"
+    )
+}
+
+/// Parses the value of a single filter
+fn parse_filter(key: &str, value: &str) -> Result<Filter> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(RustleError::query_parse(format!("{}: needs a value", key)));
+    }
+
+    match key {
+        "ext" => {
+            let extensions: Vec<String> = value
+                .split(',')
+                .map(|e| e.trim().trim_start_matches('.').to_lowercase())
+                .filter(|e| !e.is_empty())
+                .collect();
+            if extensions.is_empty() {
+                return Err(RustleError::query_parse(format!(
+                    "'{}' is not a file extension",
+                    value
+                )));
+            }
+            Ok(Filter::Extension(extensions))
+        }
+        "in" => Ok(Filter::In(value.to_lowercase())),
+        "type" => match value.to_lowercase().as_str() {
+            "file" | "files" => Ok(Filter::Type(ResultType::File)),
+            "folder" | "folders" | "dir" | "directory" => Ok(Filter::Type(ResultType::Folder)),
+            "app" | "apps" | "application" => Ok(Filter::Type(ResultType::Application)),
+            _ => Err(RustleError::query_parse(format!(
+                "unknown type '{}' (use file, folder or app)",
+                value
+            ))),
+        },
+        "size" => {
+            let (op, amount) = Comparison::split(value);
+            let bytes = parse_size(amount).ok_or_else(|| {
+                RustleError::query_parse(format!(
+                    "'{}' is not a size (use e.g. >100mb or <2gb)",
+                    value
+                ))
+            })?;
+            Ok(Filter::Size(op, bytes))
+        }
+        "modified" => {
+            let (op, time) = Comparison::split(value);
+            let bound = parse_time_bound(time).ok_or_else(|| {
+                RustleError::query_parse(format!(
+                    "'{}' is not a time (use e.g. <7d, >12h or >=2024-01-31)",
+                    value
+                ))
+            })?;
+            Ok(Filter::Modified(op, bound))
+        }
+        _ => Err(RustleError::query_parse(format!("unknown filter {}:", key))),
+    }
+}
+
+/// Parses a size such as `100mb`, `1.5gb` or `512` (bytes)
+fn parse_size(value: &str) -> Option<u64> {
+    let (number, unit) = split_number(value)?;
+    let multiplier: u64 = match unit.to_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1 << 10,
+        "m" | "mb" => 1 << 20,
+        "g" | "gb" => 1 << 30,
+        "t" | "tb" => 1 << 40,
+        _ => return None,
+    };
+    Some((number * multiplier as f64) as u64)
+}
+
+/// Parses an age such as `7d` or `12h`, or a date such as `2024-01-31`
+fn parse_time_bound(value: &str) -> Option<TimeBound> {
+    if let Some(date) = parse_date(value) {
+        return Some(TimeBound::Date(date));
+    }
+
+    let (number, unit) = split_number(value)?;
+    let unit_secs: u64 = match unit.to_lowercase().as_str() {
+        "min" | "mins" => 60,
+        "h" | "hr" | "hrs" => 3_600,
+        "d" | "day" | "days" => SECS_PER_DAY,
+        "w" | "wk" | "weeks" => 7 * SECS_PER_DAY,
+        "mo" | "month" | "months" => 30 * SECS_PER_DAY,
+        "y" | "yr" | "years" => 365 * SECS_PER_DAY,
+        _ => return None,
+    };
+    Some(TimeBound::Age((number * unit_secs as f64) as u64))
+}
+
+/// Splits `1.5gb` into `(1.5, "gb")`
+fn split_number(value: &str) -> Option<(f64, &str)> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let number: f64 = value[..split].parse().ok()?;
+    Some((number, value[split..].trim()))
+}
+
+/// Parses `YYYY-MM-DD` into the start of that day in Unix seconds (UTC)
+fn parse_date(value: &str) -> Option<u64> {
+    let mut parts = value.splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: u32 = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+
+    if !(1970..=9999).contains(&year) || !(1..=12).contains(&month) {
+        return None;
+    }
+    if day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    Some(days_from_civil(year, month, day) as u64 * SECS_PER_DAY)
+}
+
+/// Returns the number of days in a month of the proleptic Gregorian calendar
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Converts a calendar date to days since 1970-01-01
+///
+/// Howard Hinnant's `days_from_civil` algorithm.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Checks a single filter against a result
+fn filter_matches(filter: &Filter, candidate: &Candidate, now: u64) -> bool {
+    match filter {
+        Filter::Extension(extensions) => {
+            let path = match candidate.result_type {
+                ResultType::Application => candidate.target,
+                _ => Some(candidate.path),
+            };
+            path.and_then(|p| p.extension())
+                .and_then(|e| e.to_str())
+                .is_some_and(|ext| extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+        }
+        Filter::In(folder) => in_folder(candidate.path, folder),
+        Filter::Type(wanted) => candidate.result_type == *wanted,
+        Filter::Size(op, bytes) => {
+            candidate.result_type == ResultType::File
+                && candidate.size.is_some_and(|size| op.matches(size, *bytes))
+        }
+        Filter::Modified(op, bound) => candidate
+            .modified
+            .is_some_and(|modified| time_matches(*op, *bound, modified, now)),
+    }
+}
+
+/// Checks whether a path lies inside a folder named by an `in:` filter
+///
+/// Values that look like paths (`in:D:\Projects`) must prefix the parent
+/// path; plain names match the start of any ancestor folder's name.
+fn in_folder(path: &Path, folder: &str) -> bool {
+    let path = path.to_string_lossy().to_lowercase().replace('/', "\\");
+    let Some((parent, _)) = path.rsplit_once('\\') else {
+        return false;
+    };
+
+    if folder.contains(['\\', '/', ':']) {
+        let folder = folder.replace('/', "\\");
+        return parent.starts_with(folder.trim_end_matches('\\'));
+    }
+
+    parent.split('\\').any(|name| name.starts_with(folder))
+}
+
+/// Checks a modification time against a `modified:` filter
+fn time_matches(op: Comparison, bound: TimeBound, modified: u64, now: u64) -> bool {
+    match bound {
+        TimeBound::Age(max_age) => {
+            let age = now.saturating_sub(modified);
+            match op {
+                // `modified:7d` reads as "within the last 7 days"
+                Comparison::Equal => age <= max_age,
+                op => op.matches(age, max_age),
+            }
+        }
+        TimeBound::Date(day_start) => {
+            let day_end = day_start + SECS_PER_DAY;
+            match op {
+                Comparison::Less => modified < day_start,
+                Comparison::LessOrEqual => modified < day_end,
+                Comparison::Equal => (day_start..day_end).contains(&modified),
+                Comparison::GreaterOrEqual => modified >= day_start,
+                Comparison::Greater => modified >= day_end,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn file(path: &str, size: u64, modified: u64) -> (PathBuf, u64, u64) {
+        (PathBuf::from(path), size, modified)
+    }
+
+    fn candidate(entry: &(PathBuf, u64, u64), result_type: ResultType) -> Candidate<'_> {
+        Candidate {
+            path: &entry.0,
+            result_type,
+            size: Some(entry.1),
+            modified: Some(entry.2),
+            target: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_plain_text() {
+        let query = Query::parse("  visual   studio ").unwrap();
+        assert_eq!(query.text, "visual studio");
+        assert!(!query.has_filters());
+    }
+
+    #[test]
+    fn test_parse_filters_and_text() {
+        let query = Query::parse("report ext:pdf,.DOCX in:\"My Documents\" type:file").unwrap();
+        assert_eq!(query.text, "report");
+        assert_eq!(
+            query.filters,
+            vec![
+                Filter::Extension(vec!["pdf".into(), "docx".into()]),
+                Filter::In("my documents".into()),
+                Filter::Type(ResultType::File),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_size_and_modified() {
+        let query = Query::parse("size:>1.5mb modified:<7d modified:>=2024-02-29").unwrap();
+        assert_eq!(
+            query.filters,
+            vec![
+                Filter::Size(Comparison::Greater, 1_572_864),
+                Filter::Modified(Comparison::Less, TimeBound::Age(7 * SECS_PER_DAY)),
+                Filter::Modified(Comparison::GreaterOrEqual, TimeBound::Date(1_709_164_800)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_colons_outside_filters_are_text() {
+        let query = Query::parse(r"C:\Users re:zero").unwrap();
+        assert_eq!(query.text, r"C:\Users re:zero");
+        assert!(!query.has_filters());
+    }
+
+    #[test]
+    fn test_bad_filters_are_errors() {
+        for input in [
+            "ext:",
+            "type:spreadsheet",
+            "size:huge",
+            "size:>10zb",
+            "modified:<7m",
+            "modified:2023-02-29",
+        ] {
+            let err = Query::parse(input).unwrap_err();
+            assert!(matches!(err, RustleError::QueryParse(_)), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_extension_and_type_filters() {
+        let query = Query::parse("ext:pdf").unwrap();
+        let pdf = file(r"D:\Docs\report.PDF", 10, 0);
+        let txt = file(r"D:\Docs\notes.txt", 10, 0);
+        assert!(query.matches(&candidate(&pdf, ResultType::File), 0));
+        assert!(!query.matches(&candidate(&txt, ResultType::File), 0));
+
+        let query = Query::parse("type:folder").unwrap();
+        assert!(query.wants(ResultType::Folder));
+        assert!(!query.wants(ResultType::Application));
+    }
+
+    #[test]
+    fn test_in_filter() {
+        let entry = file(r"C:\Users\me\Downloads\setup.exe", 0, 0);
+        let c = candidate(&entry, ResultType::File);
+
+        assert!(Query::parse("in:downloads").unwrap().matches(&c, 0));
+        assert!(Query::parse("in:down").unwrap().matches(&c, 0));
+        assert!(Query::parse(r"in:C:\Users\me").unwrap().matches(&c, 0));
+        assert!(!Query::parse("in:Documents").unwrap().matches(&c, 0));
+        assert!(!Query::parse(r"in:D:\").unwrap().matches(&c, 0));
+    }
+
+    #[test]
+    fn test_size_filter() {
+        let big = file(r"D:\big.iso", 200 << 20, 0);
+        let small = file(r"D:\small.txt", 1 << 10, 0);
+        let query = Query::parse("size:>100mb").unwrap();
+
+        assert!(query.matches(&candidate(&big, ResultType::File), 0));
+        assert!(!query.matches(&candidate(&small, ResultType::File), 0));
+        // Folders and apps have no meaningful size
+        assert!(!query.matches(&candidate(&big, ResultType::Folder), 0));
+    }
+
+    #[test]
+    fn test_modified_filter() {
+        let now = 1_700_000_000;
+        let recent = file(r"D:\recent.txt", 0, now - 2 * SECS_PER_DAY);
+        let old = file(r"D:\old.txt", 0, now - 30 * SECS_PER_DAY);
+
+        let within_week = Query::parse("modified:<7d").unwrap();
+        assert!(within_week.matches(&candidate(&recent, ResultType::File), now));
+        assert!(!within_week.matches(&candidate(&old, ResultType::File), now));
+
+        let older = Query::parse("modified:>1w").unwrap();
+        assert!(older.matches(&candidate(&old, ResultType::File), now));
+
+        // 1_700_000_000 is 2023-11-14T22:13:20Z
+        let on_day = Query::parse("modified:2023-11-14").unwrap();
+        let today = file(r"D:\today.txt", 0, now);
+        assert!(on_day.matches(&candidate(&today, ResultType::File), now));
+        assert!(!on_day.matches(&candidate(&recent, ResultType::File), now));
+    }
+
+    #[test]
+    fn test_unknown_metadata_never_matches() {
+        let path = PathBuf::from(r"C:\Start Menu\Programs\App.lnk");
+        let app = Candidate {
+            path: &path,
+            result_type: ResultType::Application,
+            size: None,
+            modified: None,
+            target: None,
+        };
+        assert!(!Query::parse("modified:<7d").unwrap().matches(&app, 0));
+        assert!(!Query::parse("ext:lnk").unwrap().matches(&app, 0));
+    }
+
+    #[test]
+    fn test_extension_of_shortcut_target() {
+        let path = PathBuf::from(r"C:\Start Menu\Programs\App.lnk");
+        let target = PathBuf::from(r"C:\Program Files\App\App.EXE");
+        let app = Candidate {
+            path: &path,
+            result_type: ResultType::Application,
+            size: None,
+            modified: None,
+            target: Some(&target),
+        };
+        assert!(Query::parse("ext:exe").unwrap().matches(&app, 0));
+        assert!(!Query::parse("ext:lnk").unwrap().matches(&app, 0));
+
+        let shortcut = file(r"D:\Desktop\App.lnk", 1_024, 0);
+        assert!(Query::parse("ext:lnk")
+            .unwrap()
+            .matches(&candidate(&shortcut, ResultType::File), 0));
+    }
+}
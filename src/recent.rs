@@ -0,0 +1,436 @@
+//! Recent items for Rustle
+//!
+//! Windows keeps a shortcut in the Recent Items folder for every document
+//! and folder opened through the shell. This provider reads those
+//! shortcuts with the shell link parser, shows the files they point at
+//! rather than the shortcuts themselves, and ranks them by how well their
+//! names match and how recently they were opened. Shortcuts whose target
+//! has since been moved or deleted are left out. The folder is scanned
+//! again in the background, and queries search whatever was found last.
+
+#![allow(dead_code)]
+
+use crate::cliphistory::format_age;
+use crate::config::RecentConfig;
+use crate::history::LaunchHistory;
+use crate::lnk::ShellLink;
+use crate::provider::{Provider, ProviderQuery};
+use crate::query;
+use crate::search::{name_score, CancelToken, ResultType, SearchResult, Section};
+use crate::utils::normalize_for_search;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+/// Recent Items folder, relative to the roaming application data folder
+const RECENT_DIR: &str = r"Microsoft\Windows\Recent";
+
+/// Shortest time between rescans of the Recent Items folder
+const REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// `GetDriveTypeW` result for a local hard drive or SSD
+#[cfg(windows)]
+const DRIVE_FIXED: u32 = 3;
+
+/// A document or folder opened recently
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentItem {
+    /// File the shortcut points at
+    pub target: PathBuf,
+
+    pub is_dir: bool,
+
+    /// When the item was last opened, in seconds since the Unix epoch
+    pub opened: u64,
+}
+
+/// Reads the shortcuts in a Recent Items folder, newest first
+///
+/// Several shortcuts to the same target are merged. Targets that are not
+/// on a fixed drive, such as network shares and removable drives, are kept
+/// without checking them, as an unreachable server or an empty drive
+/// would stall the scan.
+pub fn scan(dir: &Path) -> Vec<RecentItem> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut items: HashMap<String, RecentItem> = HashMap::new();
+    for path in entries.flatten().map(|e| e.path()) {
+        let is_link = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("lnk"));
+        if !is_link {
+            continue;
+        }
+        let link = match ShellLink::open(&path) {
+            Ok(link) => link,
+            Err(e) => {
+                log::debug!("Skipping recent item {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        let Some(item) = resolve(&path, &link) else {
+            continue;
+        };
+        let key = item.target.to_string_lossy().to_lowercase();
+        if items
+            .get(&key)
+            .is_none_or(|known| known.opened < item.opened)
+        {
+            items.insert(key, item);
+        }
+    }
+
+    let mut items: Vec<RecentItem> = items.into_values().collect();
+    items.sort_by(|a, b| {
+        b.opened
+            .cmp(&a.opened)
+            .then_with(|| a.target.cmp(&b.target))
+    });
+    items
+}
+
+/// Works out the target of one shortcut, or None if it no longer exists
+///
+/// The shortcut is rewritten each time its target is opened again, so the
+/// later of its own modification time and the access time it records is
+/// taken as the last opening.
+fn resolve(link_path: &Path, link: &ShellLink) -> Option<RecentItem> {
+    let target = link.resolve(link_path)?;
+    let is_dir = if is_on_fixed_drive(&target) {
+        std::fs::metadata(&target).ok()?.is_dir()
+    } else {
+        link.is_directory()
+    };
+    let modified = std::fs::metadata(link_path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    let opened = link.access_time.max(modified)?;
+    Some(RecentItem {
+        target,
+        is_dir,
+        opened,
+    })
+}
+
+fn is_network_path(path: &Path) -> bool {
+    let path = path.to_string_lossy();
+    path.starts_with(r"\\") || path.starts_with("//")
+}
+
+/// Returns true if `path` is on a local fixed drive, where checking it
+/// is quick
+#[cfg(windows)]
+fn is_on_fixed_drive(path: &Path) -> bool {
+    use crate::utils::to_wide_string;
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::GetDriveTypeW;
+
+    if is_network_path(path) {
+        return false;
+    }
+    let Some(root) = path.ancestors().last() else {
+        return false;
+    };
+    let root = to_wide_string(&root.to_string_lossy());
+    unsafe { GetDriveTypeW(PCWSTR(root.as_ptr())) == DRIVE_FIXED }
+}
+
+/// Drive types only exist on Windows
+#[cfg(not(windows))]
+fn is_on_fixed_drive(path: &Path) -> bool {
+    !is_network_path(path)
+}
+
+/// Score added for an item opened `now`, halving every week since
+fn recency_boost(opened: u64, now: u64) -> i64 {
+    let age_days = now.saturating_sub(opened) as f64 / 86_400.0;
+    (30.0 * 0.5f64.powf(age_days / 7.0)) as i64
+}
+
+/// The scanned folder, and when it was last scanned
+struct Scan {
+    checked: Option<Instant>,
+    items: Arc<Vec<RecentItem>>,
+}
+
+/// Finds documents and folders in the Recent Items folder
+pub struct RecentProvider {
+    config: RecentConfig,
+
+    /// Recent Items folder; None if it could not be located
+    dir: Option<PathBuf>,
+
+    scan: Arc<Mutex<Scan>>,
+
+    /// Thread scanning the folder again, if one was started
+    refresh: Mutex<Option<JoinHandle<()>>>,
+
+    history: Arc<RwLock<LaunchHistory>>,
+    matcher: SkimMatcherV2,
+}
+
+impl RecentProvider {
+    /// Creates the provider over the user's Recent Items folder and starts
+    /// scanning it
+    pub fn new(config: RecentConfig, history: Arc<RwLock<LaunchHistory>>) -> Self {
+        let dir = dirs::data_dir().map(|d| d.join(RECENT_DIR));
+        let provider = Self::with_dir(config, dir, history);
+        provider.start_refresh();
+        provider
+    }
+
+    /// Creates the provider over the given folder, without scanning it
+    pub fn with_dir(
+        config: RecentConfig,
+        dir: Option<PathBuf>,
+        history: Arc<RwLock<LaunchHistory>>,
+    ) -> Self {
+        Self {
+            config,
+            dir,
+            scan: Arc::new(Mutex::new(Scan {
+                checked: None,
+                items: Arc::default(),
+            })),
+            refresh: Mutex::new(None),
+            history,
+            matcher: SkimMatcherV2::default().smart_case(),
+        }
+    }
+
+    /// Starts scanning the folder again, in the background, if the last
+    /// scan is older than the refresh interval
+    ///
+    /// Does nothing while an earlier scan is still running.
+    fn start_refresh(&self) {
+        let mut refresh = self.refresh.lock().unwrap();
+        if refresh.as_ref().is_some_and(|thread| !thread.is_finished()) {
+            return;
+        }
+        {
+            let mut state = self.scan.lock().unwrap();
+            if state
+                .checked
+                .is_some_and(|t| t.elapsed() < REFRESH_INTERVAL)
+            {
+                return;
+            }
+            state.checked = Some(Instant::now());
+        }
+
+        let Some(dir) = self.dir.clone() else {
+            return;
+        };
+        let state = self.scan.clone();
+        *refresh = Some(std::thread::spawn(move || {
+            let items = scan(&dir);
+            state.lock().unwrap().items = Arc::new(items);
+        }));
+    }
+
+    /// Waits for a running scan to finish
+    fn wait_for_refresh(&self) {
+        if let Some(thread) = self.refresh.lock().unwrap().take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Provider for RecentProvider {
+    fn id(&self) -> &'static str {
+        "recent"
+    }
+
+    fn title(&self) -> &str {
+        "Recent"
+    }
+
+    fn priority(&self) -> i32 {
+        14
+    }
+
+    fn query(
+        &self,
+        query: &ProviderQuery,
+        cancel: &CancelToken,
+        emit: &(dyn Fn(Vec<Section>) + Sync),
+    ) -> bool {
+        let query = query.parsed;
+        if query.text.is_empty() || query.has_filters() {
+            return true;
+        }
+
+        let text = query.text.as_str();
+        let normalized_query = normalize_for_search(text);
+        let query_lower = text.to_lowercase();
+        let now = query::now_secs();
+
+        self.start_refresh();
+        let items = self.scan.lock().unwrap().items.clone();
+        if cancel.is_cancelled() {
+            return false;
+        }
+
+        let history = self.history.read().unwrap();
+        let mut results = Vec::new();
+        for item in items.iter() {
+            let name = item
+                .target
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| item.target.to_string_lossy().into_owned());
+            let Some(score) = name_score(&self.matcher, &name, &normalized_query, &query_lower)
+            else {
+                continue;
+            };
+            results.push(SearchResult {
+                name,
+                path: item.target.clone(),
+                result_type: if item.is_dir {
+                    ResultType::Folder
+                } else {
+                    ResultType::File
+                },
+                score: score + recency_boost(item.opened, now) + history.boost(&item.target, now),
+                description: format!(
+                    "{} · opened {}",
+                    item.target.display(),
+                    format_age(item.opened, now)
+                ),
+                provider: self.id(),
+                shortcut: None,
+            });
+        }
+
+        results.sort_unstable_by_key(|r| std::cmp::Reverse(r.score));
+        results.truncate(self.config.max_results);
+        if !results.is_empty() {
+            emit(vec![self.section(results)]);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::run_provider;
+    use std::fs::{self, File};
+    use std::time::SystemTime;
+
+    /// Shortcut to a document on drive C: that does not exist here
+    const DOCUMENT: &[u8] = include_bytes!("../tests/fixtures/lnk/document.lnk");
+
+    /// Shortcut to the folder `\\fileserver\projects\Rustle\Designs`
+    const SHARE: &[u8] = include_bytes!("../tests/fixtures/lnk/share.lnk");
+
+    /// Writes a shortcut whose file was last modified at `modified`
+    fn write_link(path: &Path, bytes: &[u8], modified: u64) {
+        fs::write(path, bytes).unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(UNIX_EPOCH + Duration::from_secs(modified))
+            .unwrap();
+    }
+
+    /// The share shortcut, pointing at `Reviews` instead of `Designs`
+    fn other_share() -> Vec<u8> {
+        let at = SHARE.windows(7).position(|w| w == b"Designs").unwrap();
+        let mut bytes = SHARE.to_vec();
+        bytes[at..at + 7].copy_from_slice(b"Reviews");
+        bytes
+    }
+
+    #[test]
+    fn test_scan_orders_merges_and_drops_dead_targets() {
+        let dir = tempfile::tempdir().unwrap();
+        let accessed = ShellLink::parse(SHARE).unwrap().access_time.unwrap();
+        let designs = PathBuf::from(r"\\fileserver\projects\Rustle\Designs");
+        let reviews = PathBuf::from(r"\\fileserver\projects\Rustle\Reviews");
+
+        write_link(&dir.path().join("Designs.lnk"), SHARE, accessed + 10);
+        write_link(
+            &dir.path().join("Reviews.lnk"),
+            &other_share(),
+            accessed + 20,
+        );
+        // Opened again later, through another shortcut
+        write_link(&dir.path().join("Designs (2).lnk"), SHARE, accessed + 30);
+        write_link(&dir.path().join("Résumé.lnk"), DOCUMENT, accessed + 40);
+        fs::write(dir.path().join("desktop.ini"), "").unwrap();
+        fs::write(dir.path().join("Broken.lnk"), "not a link").unwrap();
+
+        let items = scan(dir.path());
+        let found: Vec<(&Path, u64)> = items
+            .iter()
+            .map(|i| (i.target.as_path(), i.opened))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (designs.as_path(), accessed + 30),
+                (reviews.as_path(), accessed + 20)
+            ]
+        );
+        assert!(items.iter().all(|i| i.is_dir));
+    }
+
+    #[test]
+    fn test_access_time_counts_when_later() {
+        let dir = tempfile::tempdir().unwrap();
+        let accessed = ShellLink::parse(SHARE).unwrap().access_time.unwrap();
+        write_link(&dir.path().join("Designs.lnk"), SHARE, accessed - 3_600);
+        assert_eq!(scan(dir.path())[0].opened, accessed);
+    }
+
+    #[test]
+    fn test_provider_scans_in_background() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let designs = PathBuf::from(r"\\fileserver\projects\Rustle\Designs");
+        let reviews = PathBuf::from(r"\\fileserver\projects\Rustle\Reviews");
+        write_link(&dir.path().join("Designs.lnk"), SHARE, now);
+        write_link(
+            &dir.path().join("Reviews.lnk"),
+            &other_share(),
+            now - 86_400,
+        );
+
+        let history = Arc::new(RwLock::new(LaunchHistory::new(Default::default())));
+        let provider = RecentProvider::with_dir(
+            RecentConfig::default(),
+            Some(dir.path().to_path_buf()),
+            history,
+        );
+        provider.start_refresh();
+        provider.wait_for_refresh();
+
+        let results = run_provider(&provider, "designs");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, designs);
+        assert_eq!(results[0].result_type, ResultType::Folder);
+        assert!(results[0].description.ends_with("· opened just now"));
+        assert_eq!(run_provider(&provider, "reviews")[0].path, reviews);
+        assert!(run_provider(&provider, "résumé").is_empty());
+
+        // Shortcuts added since are found once the folder is due a rescan
+        write_link(&dir.path().join("Résumé.lnk"), DOCUMENT, now);
+        fs::remove_file(dir.path().join("Reviews.lnk")).unwrap();
+        provider.scan.lock().unwrap().checked = None;
+        provider.start_refresh();
+        provider.wait_for_refresh();
+        assert!(run_provider(&provider, "reviews").is_empty());
+    }
+}
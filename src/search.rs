@@ -1,21 +1,39 @@
 //! Search functionality for Rustle
 //!
-//! This module handles searching for applications and files across
-//! the system. It indexes Start Menu shortcuts for applications and
-//! traverses user directories for files.
+//! This module runs queries against the registered result providers
+//! (see `provider.rs`) and merges what they find into sections for the
+//! UI. Searches run on a background worker and can be cancelled.
 
 #![allow(dead_code)]
 
+use crate::apps::ApplicationProvider;
+use crate::bookmarks::BookmarkProvider;
+use crate::calculator::CalculatorProvider;
+use crate::cliphistory::{ClipboardChange, ClipboardProvider};
 use crate::config::SearchConfig;
+use crate::datetime::DateTimeProvider;
 use crate::error::Result;
-use crate::utils::{display_name, is_shortcut, normalize_for_search};
+use crate::files::{ContentProvider, FileProvider};
+use crate::history::LaunchHistory;
+use crate::lnk::ShellLink;
+use crate::paths::PathProvider;
+use crate::provider::{Action, Provider, ProviderQuery, Trigger};
+use crate::query::{self, Query};
+use crate::recent::RecentProvider;
+use crate::shell::ShellProvider;
+use crate::snippets::SnippetProvider;
+use crate::units::UnitProvider;
+use crate::utils::normalize_for_search;
+use crate::web::WebProvider;
+use crate::webhistory::BrowserHistoryProvider;
 use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
 use rayon::prelude::*;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
-use std::sync::Mutex;
-use walkdir::WalkDir;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::JoinHandle;
 
 /// Represents a search result item
 #[derive(Debug, Clone)]
@@ -26,7 +44,7 @@ pub struct SearchResult {
     /// Full path to the file or shortcut
     pub path: PathBuf,
 
-    /// Type of result (Application, File, Folder)
+    /// Type of result (Application, File, Folder, Answer)
     pub result_type: ResultType,
 
     /// Fuzzy match score (higher is better)
@@ -34,6 +52,12 @@ pub struct SearchResult {
 
     /// Optional description or path preview
     pub description: String,
+
+    /// Identifier of the provider that found the result
+    pub provider: &'static str,
+
+    /// Target and start-up settings of a shortcut, for applications
+    pub shortcut: Option<Box<ShellLink>>,
 }
 
 /// Types of search results
@@ -47,6 +71,18 @@ pub enum ResultType {
 
     /// A directory/folder
     Folder,
+
+    /// A value worked out from the query, such as a calculation
+    Answer,
+
+    /// A web page or web search
+    Web,
+
+    /// A shell command, or a line of its output
+    Command,
+
+    /// A piece of text, such as a clipboard entry or snippet
+    Text,
 }
 
 impl ResultType {
@@ -56,89 +92,109 @@ impl ResultType {
             ResultType::Application => "Application",
             ResultType::File => "File",
             ResultType::Folder => "Folder",
+            ResultType::Answer => "Answer",
+            ResultType::Web => "Web",
+            ResultType::Command => "Command",
+            ResultType::Text => "Text",
         }
     }
 
     /// Returns a priority for sorting (lower = higher priority)
     pub fn priority(&self) -> u8 {
         match self {
-            ResultType::Application => 0,
-            ResultType::Folder => 1,
-            ResultType::File => 2,
+            ResultType::Answer => 0,
+            ResultType::Web => 1,
+            ResultType::Command => 2,
+            ResultType::Text => 3,
+            ResultType::Application => 4,
+            ResultType::Folder => 5,
+            ResultType::File => 6,
         }
     }
+}
 
-    /// Returns a section header for UI display
-    pub fn section_header(&self) -> &'static str {
-        match self {
-            ResultType::Application => "APPLICATIONS",
-            ResultType::File => "FILES",
-            ResultType::Folder => "FOLDERS",
-        }
-    }
+/// A group of results from one provider, shown under its own heading
+#[derive(Debug, Clone)]
+pub struct Section {
+    /// Identifier of the provider that found the results
+    pub provider: &'static str,
+
+    /// Heading shown above the results
+    pub title: String,
+
+    /// Position among the other sections; lower comes first
+    pub priority: i32,
+
+    /// The results, best first
+    pub results: Vec<SearchResult>,
 }
 
 /// Grouped search results for UI display
 #[derive(Debug, Clone, Default)]
 pub struct GroupedResults {
-    pub applications: Vec<SearchResult>,
-    pub folders: Vec<SearchResult>,
-    pub files: Vec<SearchResult>,
+    /// Non-empty sections ordered by priority
+    pub sections: Vec<Section>,
 }
 
 impl GroupedResults {
     /// Returns total count of all results
     pub fn total_count(&self) -> usize {
-        self.applications.len() + self.folders.len() + self.files.len()
+        self.sections.iter().map(|s| s.results.len()).sum()
     }
 
     /// Returns true if there are no results
     pub fn is_empty(&self) -> bool {
-        self.applications.is_empty() && self.folders.is_empty() && self.files.is_empty()
+        self.sections.iter().all(|s| s.results.is_empty())
     }
 
-    /// Gets results by type
-    pub fn get_by_type(&self, result_type: ResultType) -> &Vec<SearchResult> {
-        match result_type {
-            ResultType::Application => &self.applications,
-            ResultType::Folder => &self.folders,
-            ResultType::File => &self.files,
-        }
+    /// Gets the section with the given title
+    pub fn section(&self, title: &str) -> Option<&Section> {
+        self.sections.iter().find(|s| s.title == title)
+    }
+
+    /// Replaces everything reported by `provider` with `sections`
+    ///
+    /// Empty sections are dropped, and sections of equal priority keep
+    /// the order they were added in.
+    pub fn replace_provider(&mut self, provider: &str, sections: Vec<Section>) {
+        self.sections.retain(|s| s.provider != provider);
+        self.sections
+            .extend(sections.into_iter().filter(|s| !s.results.is_empty()));
+        self.sections.sort_by_key(|s| s.priority);
     }
 
     /// Flattens results into a single vector with section markers
     pub fn flatten_with_sections(&self) -> Vec<FlatResult> {
         let mut results = Vec::new();
 
-        if !self.applications.is_empty() {
-            results.push(FlatResult::SectionHeader(ResultType::Application));
-            for app in &self.applications {
-                results.push(FlatResult::Item(app.clone()));
+        for section in &self.sections {
+            results.push(FlatResult::SectionHeader(section.title.clone()));
+            for result in &section.results {
+                results.push(FlatResult::Item(result.clone()));
             }
         }
 
-        if !self.folders.is_empty() {
-            results.push(FlatResult::SectionHeader(ResultType::Folder));
-            for folder in &self.folders {
-                results.push(FlatResult::Item(folder.clone()));
-            }
-        }
+        results
+    }
 
-        if !self.files.is_empty() {
-            results.push(FlatResult::SectionHeader(ResultType::File));
-            for file in &self.files {
-                results.push(FlatResult::Item(file.clone()));
-            }
+    /// Returns the position in `flatten_with_sections` of a section's item
+    pub fn flat_index(&self, section_index: usize, item_index: usize) -> Option<usize> {
+        let section = self.sections.get(section_index)?;
+        if item_index >= section.results.len() {
+            return None;
         }
-
-        results
+        let before: usize = self.sections[..section_index]
+            .iter()
+            .map(|s| s.results.len() + 1)
+            .sum();
+        Some(before + 1 + item_index)
     }
 }
 
 /// Flattened result for UI rendering (includes section headers)
 #[derive(Debug, Clone)]
 pub enum FlatResult {
-    SectionHeader(ResultType),
+    SectionHeader(String),
     Item(SearchResult),
 }
 
@@ -150,651 +206,604 @@ impl FlatResult {
 
 /// The main search engine
 ///
-/// Maintains an index of applications and provides search functionality
-/// for both apps and files.
+/// Hands each query to the providers it is meant for and merges what they
+/// report into sections. Applications and files are always available; more
+/// providers can be registered with `add_provider`.
 pub struct SearchEngine {
-    /// Configuration for search behavior
-    config: SearchConfig,
+    /// Every registered provider, in registration order
+    providers: Vec<Arc<dyn Provider>>,
 
-    /// Cached list of applications (Start Menu shortcuts)
-    applications: Vec<SearchResult>,
+    /// Start Menu applications
+    applications: Arc<ApplicationProvider>,
 
-    /// Fuzzy matcher instance
-    matcher: SkimMatcherV2,
+    /// Files and folders, and the indexes behind them
+    files: Arc<FileProvider>,
 
-    /// Additional search paths (beyond config)
-    extra_search_paths: Vec<PathBuf>,
+    /// Clipboard history, if it is enabled
+    clipboard: Option<Arc<ClipboardProvider>>,
+
+    /// Launch history used to boost frequently and recently used results
+    /// (behind a lock so launches can be recorded while searches run)
+    history: Arc<RwLock<LaunchHistory>>,
 }
 
 impl SearchEngine {
     /// Creates a new search engine with the given configuration
     pub fn new(config: SearchConfig) -> Self {
-        let mut engine = Self {
-            config,
-            applications: Vec::new(),
-            matcher: SkimMatcherV2::default().smart_case(),
-            extra_search_paths: Vec::new(),
+        let history = match LaunchHistory::default_path() {
+            Some(path) => LaunchHistory::load_or_default(&path, config.frecency.clone()),
+            None => LaunchHistory::new(config.frecency.clone()),
         };
+        let history = Arc::new(RwLock::new(history));
 
-        // Add extra search paths for comprehensive search
-        engine.init_extra_search_paths();
+        let files = FileProvider::new(config.clone(), history.clone());
+        let applications = ApplicationProvider::new(history.clone());
+        Self::with_providers(&config, applications, files, history)
+    }
 
-        // Index applications on creation
-        if let Err(e) = engine.index_applications() {
-            log::warn!("Failed to index some applications: {}", e);
-        }
+    /// Creates an engine around already built application and file
+    /// providers, adding the calculator, unit conversion, dates and times,
+    /// web searches and paths, and bookmarks, browser history, recent
+    /// items, content search, shell commands, clipboard history and
+    /// snippets if they are enabled
+    fn with_providers(
+        config: &SearchConfig,
+        applications: ApplicationProvider,
+        files: FileProvider,
+        history: Arc<RwLock<LaunchHistory>>,
+    ) -> Self {
+        let applications = Arc::new(applications);
+        let files = Arc::new(files);
+        let mut engine = Self {
+            providers: vec![
+                Arc::new(CalculatorProvider),
+                Arc::new(UnitProvider),
+                Arc::new(DateTimeProvider::new()),
+                Arc::new(WebProvider::new(config.web.clone())),
+                Arc::new(PathProvider::new()),
+                applications.clone(),
+                files.clone(),
+            ],
+            applications,
+            files: files.clone(),
+            clipboard: None,
+            history,
+        };
 
-        engine
-    }
+        let bookmarks = &config.bookmarks;
+        if bookmarks.enabled {
+            let provider = BookmarkProvider::new(bookmarks.clone(), engine.history.clone());
+            engine.add_provider(Arc::new(provider));
+        }
 
-    /// Initialize additional search paths including all available drives
-    fn init_extra_search_paths(&mut self) {
-        // User home directory
-        if let Some(home) = dirs::home_dir() {
-            self.extra_search_paths.push(home);
+        let browser_history = &config.browser_history;
+        if browser_history.enabled {
+            let provider = BrowserHistoryProvider::new(browser_history.clone());
+            engine.add_provider(Arc::new(provider));
         }
 
-        // Pictures
-        if let Some(pics) = dirs::picture_dir() {
-            self.extra_search_paths.push(pics);
+        let recent = &config.recent;
+        if recent.enabled {
+            let provider = RecentProvider::new(recent.clone(), engine.history.clone());
+            engine.add_provider(Arc::new(provider));
         }
 
-        // Videos
-        if let Some(vids) = dirs::video_dir() {
-            self.extra_search_paths.push(vids);
+        let content = &config.content;
+        if content.enabled && !content.prefix.is_empty() {
+            engine.add_provider(Arc::new(ContentProvider::new(files)));
         }
 
-        // Music
-        if let Some(music) = dirs::audio_dir() {
-            self.extra_search_paths.push(music);
+        let shell = &config.shell;
+        if shell.enabled && !shell.prefix.is_empty() {
+            engine.add_provider(Arc::new(ShellProvider::new(shell.clone())));
         }
 
-        // Common program locations on C drive
-        let program_files = PathBuf::from(r"C:\Program Files");
-        if program_files.exists() {
-            self.extra_search_paths.push(program_files);
+        let clipboard = &config.clipboard;
+        if clipboard.enabled && !clipboard.prefix.is_empty() {
+            let clipboard = Arc::new(ClipboardProvider::new(clipboard.clone()));
+            engine.add_provider(clipboard.clone());
+            engine.clipboard = Some(clipboard);
         }
 
-        let program_files_x86 = PathBuf::from(r"C:\Program Files (x86)");
-        if program_files_x86.exists() {
-            self.extra_search_paths.push(program_files_x86);
+        let snippets = &config.snippets;
+        if snippets.enabled && !snippets.prefix.is_empty() {
+            engine.add_provider(Arc::new(SnippetProvider::new(snippets.clone())));
         }
 
-        // Use Windows API to get all logical drives (more reliable than checking exists())
-        let available_drives = Self::get_logical_drives();
+        engine
+    }
 
-        log::info!(
-            "Found {} logical drives: {:?}",
-            available_drives.len(),
-            available_drives
-        );
+    /// Registers another provider
+    pub fn add_provider(&mut self, provider: Arc<dyn Provider>) {
+        log::debug!("Registered {} provider", provider.id());
+        self.providers.push(provider);
+    }
 
-        for drive_letter in available_drives {
-            let drive = format!("{}:\\", drive_letter);
-            let drive_path = PathBuf::from(&drive);
+    /// Returns every registered provider
+    pub fn providers(&self) -> &[Arc<dyn Provider>] {
+        &self.providers
+    }
 
-            // Skip A: and B: (usually floppy drives)
-            if drive_letter == 'A' || drive_letter == 'B' {
-                continue;
-            }
+    /// Performs an advanced search with the given query
+    /// Returns grouped results for sectioned UI display, or an error if
+    /// the query contains an invalid filter
+    pub fn search(&self, query: &str) -> Result<GroupedResults> {
+        let last = Mutex::new(Ok(GroupedResults::default()));
+        self.search_cancellable(query, &CancelToken::never(), |update| match update {
+            Ok(update) if update.complete => *last.lock().unwrap() = Ok(update.results),
+            Ok(_) => {}
+            Err(e) => *last.lock().unwrap() = Err(e),
+        });
+        last.into_inner().unwrap()
+    }
 
-            // Verify drive is accessible by checking if we can read its root
-            if !Self::is_drive_accessible(&drive_path) {
-                log::debug!("Drive {}: is not accessible, skipping", drive_letter);
-                continue;
-            }
+    /// Parses `query` and streams its results to `on_update`
+    ///
+    /// Returns false if the search was cancelled before it finished. See
+    /// `dispatch` for which providers answer.
+    pub fn search_cancellable<F>(&self, query: &str, cancel: &CancelToken, on_update: F) -> bool
+    where
+        F: Fn(Result<SearchUpdate>) + Sync,
+    {
+        let providers: Vec<&dyn Provider> = self.providers.iter().map(|p| p.as_ref()).collect();
+        Self::dispatch(&providers, query, cancel, on_update)
+    }
 
-            log::info!("Scanning drive {}: ({})", drive_letter, drive);
-
-            // For non-C drives, scan ALL top-level directories (user's data drive)
-            // For C drive, only scan specific system/user directories
-            if drive_letter != 'C' {
-                // Add the entire drive root - this will search all directories on the drive
-                self.extra_search_paths.push(drive_path.clone());
-                log::info!(
-                    "  Added entire drive {}: for comprehensive search",
-                    drive_letter
-                );
-
-                // Also try to enumerate top-level directories for better organization
-                if let Ok(entries) = std::fs::read_dir(&drive_path) {
-                    let mut top_level_dirs = 0;
-                    for entry in entries.filter_map(|e| e.ok()) {
-                        if let Ok(metadata) = entry.metadata() {
-                            if metadata.is_dir() {
-                                let dir_path = entry.path();
-                                let dir_name =
-                                    dir_path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-
-                                // Skip system directories
-                                let lower = dir_name.to_lowercase();
-                                if !matches!(
-                                    lower.as_str(),
-                                    "$recycle.bin"
-                                        | "system volume information"
-                                        | "recovery"
-                                        | "boot"
-                                        | "perflogs"
-                                        | "msocache"
-                                        | "config.msi"
-                                        | "intel"
-                                        | "amd"
-                                        | "nvidia"
-                                        | "windows"
-                                        | "program files"
-                                        | "program files (x86)"
-                                        | "programdata"
-                                        | "users"
-                                        | "windows.old"
-                                ) {
-                                    self.extra_search_paths.push(dir_path.clone());
-                                    top_level_dirs += 1;
-                                    log::debug!("  Added top-level dir: {}", dir_path.display());
-                                }
-                            }
-                        }
-                    }
-                    if top_level_dirs > 0 {
-                        log::info!(
-                            "  Found {} top-level directories on drive {}:",
-                            top_level_dirs,
-                            drive_letter
-                        );
-                    }
-                }
-            } else {
-                // For C: drive, only add common user directories (avoid system dirs)
-                let common_dirs = [
-                    "Users",
-                    "Projects",
-                    "Work",
-                    "Development",
-                    "Dev",
-                    "Code",
-                    "Documents",
-                    "Downloads",
-                    "Desktop",
-                    "Games",
-                    "Software",
-                    "Programs",
-                    "Apps",
-                    "Data",
-                ];
-
-                let mut found_dirs_on_drive = 0;
-                for dir_name in common_dirs {
-                    let dir_path = drive_path.join(dir_name);
-                    if dir_path.exists() && dir_path.is_dir() {
-                        self.extra_search_paths.push(dir_path.clone());
-                        found_dirs_on_drive += 1;
-                        log::debug!("  Added: {}", dir_path.display());
-                    }
-                }
+    /// Hands `query` to the providers it is meant for and streams their
+    /// results to `on_update`
+    ///
+    /// Providers whose prefix starts the query, or whose pattern recognises
+    /// it, answer it alone; otherwise every provider without a prefix does.
+    /// Only providers that want filters have them parsed out of their
+    /// text; the others see it as typed. An invalid filter leaves out just
+    /// the providers that wanted it parsed, and is reported as an error
+    /// only if nothing else was found.
+    pub(crate) fn dispatch<F>(
+        providers: &[&dyn Provider],
+        query: &str,
+        cancel: &CancelToken,
+        on_update: F,
+    ) -> bool
+    where
+        F: Fn(Result<SearchUpdate>) + Sync,
+    {
+        let mut active: Vec<(&dyn Provider, &str)> = providers
+            .iter()
+            .filter_map(|&p| match p.trigger() {
+                Trigger::Always => None,
+                trigger => trigger.strip(query).map(|rest| (p, rest)),
+            })
+            .collect();
+        if active.is_empty() {
+            active = providers
+                .iter()
+                .filter(|p| matches!(p.trigger(), Trigger::Always))
+                .map(|&p| (p, query))
+                .collect();
+        }
 
-                if found_dirs_on_drive > 0 {
-                    log::info!("  Found {} directories on drive C:", found_dirs_on_drive);
+        let mut parsed = Vec::with_capacity(active.len());
+        let mut parse_error = None;
+        for (provider, text) in active {
+            if !provider.wants_filters() {
+                parsed.push((provider, text, Query::literal(text)));
+                continue;
+            }
+            match Query::parse(text) {
+                Ok(query) => parsed.push((provider, text, query)),
+                Err(e) => {
+                    log::debug!("{} provider skipped: {}", provider.id(), e);
+                    parse_error.get_or_insert(e);
                 }
             }
         }
 
-        log::info!(
-            "Initialized {} total search paths across all drives",
-            self.extra_search_paths.len()
-        );
+        let parse_error = Mutex::new(parse_error);
+        Self::run_providers(&parsed, cancel, |update| {
+            let error = if update.complete && update.results.is_empty() {
+                parse_error.lock().unwrap().take()
+            } else {
+                None
+            };
+            match error {
+                Some(e) => on_update(Err(e)),
+                None => on_update(Ok(update)),
+            }
+        })
     }
 
-    /// Gets all logical drives using Windows API
-    fn get_logical_drives() -> Vec<char> {
-        use windows::Win32::Storage::FileSystem::GetLogicalDrives;
+    /// Searches with an already parsed query
+    ///
+    /// Returns None if `cancel` was cancelled before the search finished.
+    pub fn search_query(&self, query: &Query, cancel: &CancelToken) -> Option<GroupedResults> {
+        let last = Mutex::new(None);
+        self.search_streaming(query, cancel, |update| {
+            if update.complete {
+                *last.lock().unwrap() = Some(update.results);
+            }
+        });
+        last.into_inner().unwrap()
+    }
 
-        unsafe {
-            let drives = GetLogicalDrives();
-            let mut result = Vec::new();
+    /// Searches every provider without a prefix with an already parsed query,
+    /// reporting results as they arrive
+    pub fn search_streaming<F>(&self, query: &Query, cancel: &CancelToken, on_update: F) -> bool
+    where
+        F: Fn(SearchUpdate) + Sync,
+    {
+        let active: Vec<(&dyn Provider, &str, Query)> = self
+            .always_providers()
+            .map(|p| (p, query.text.as_str(), query.clone()))
+            .collect();
+        Self::run_providers(&active, cancel, on_update)
+    }
 
-            // Each bit represents a drive (bit 0 = A:, bit 1 = B:, etc.)
-            for i in 0..26 {
-                if (drives & (1u32 << i)) != 0 {
-                    let letter = (b'A' + i as u8) as char;
-                    result.push(letter);
+    /// Returns the providers that answer queries without a prefix
+    fn always_providers(&self) -> impl Iterator<Item = &dyn Provider> {
+        self.providers
+            .iter()
+            .filter(|p| matches!(p.trigger(), Trigger::Always))
+            .map(|p| p.as_ref())
+    }
+
+    /// Runs providers in parallel, merging what they report
+    ///
+    /// `on_update` receives the merged sections each time a provider reports
+    /// something, and finally the full results with `complete` set. Nothing
+    /// more is reported once `cancel` is cancelled, and false is returned.
+    fn run_providers<F>(
+        active: &[(&dyn Provider, &str, Query)],
+        cancel: &CancelToken,
+        on_update: F,
+    ) -> bool
+    where
+        F: Fn(SearchUpdate) + Sync,
+    {
+        let merged = Mutex::new(GroupedResults::default());
+
+        active.par_iter().for_each(|(provider, text, parsed)| {
+            let query = ProviderQuery { text, parsed };
+            let emit = |sections: Vec<Section>| {
+                // Hold the lock while reporting so updates arrive in order
+                let mut merged = merged.lock().unwrap();
+                merged.replace_provider(provider.id(), sections);
+                if !cancel.is_cancelled() {
+                    on_update(SearchUpdate {
+                        results: merged.clone(),
+                        complete: false,
+                    });
                 }
+            };
+            if !provider.query(&query, cancel, &emit) {
+                log::debug!("{} provider was cancelled", provider.id());
             }
+        });
 
-            result
+        if cancel.is_cancelled() {
+            return false;
         }
+
+        on_update(SearchUpdate {
+            results: merged.into_inner().unwrap(),
+            complete: true,
+        });
+        true
     }
 
-    /// Checks if a drive is accessible (not just exists, but can be read)
-    fn is_drive_accessible(drive_path: &Path) -> bool {
-        // Try to read the drive root directory
-        match std::fs::read_dir(drive_path) {
-            Ok(_) => true,
-            Err(e) => {
-                log::debug!("Cannot access {}: {}", drive_path.display(), e);
-                false
-            }
+    /// Returns what can be done with a result; the first action is the
+    /// default one
+    pub fn actions(&self, result: &SearchResult) -> Vec<Action> {
+        match self.providers.iter().find(|p| p.id() == result.provider) {
+            Some(provider) => provider.actions(result),
+            None => vec![Action::Open(result.path.clone())],
         }
     }
 
-    /// Indexes all Start Menu shortcuts
-    fn index_applications(&mut self) -> Result<()> {
-        self.applications.clear();
-
-        // User Start Menu
-        if let Some(start_menu) = dirs::data_dir() {
-            let user_start = start_menu
-                .parent()
-                .map(|p| p.join("Roaming"))
-                .map(|p| p.join("Microsoft"))
-                .map(|p| p.join("Windows"))
-                .map(|p| p.join("Start Menu"))
-                .map(|p| p.join("Programs"));
-
-            if let Some(path) = user_start {
-                self.index_directory(&path, ResultType::Application)?;
-            }
+    /// Carries out the default action of a result
+    ///
+    /// Returns true if the search window should stay open to show what the
+    /// action did.
+    pub fn activate(&self, result: &SearchResult) -> Result<bool> {
+        match self.actions(result).into_iter().next() {
+            Some(action) => self.run_action(result, &action),
+            None => Ok(false),
         }
+    }
 
-        // System-wide Start Menu
-        let system_start = PathBuf::from(r"C:\ProgramData\Microsoft\Windows\Start Menu\Programs");
-        if system_start.exists() {
-            self.index_directory(&system_start, ResultType::Application)?;
+    /// Carries out one of the actions of a result
+    ///
+    /// Opened results are recorded in the launch history. Returns true if
+    /// the search window should stay open to show what the action did.
+    pub fn run_action(&self, result: &SearchResult, action: &Action) -> Result<bool> {
+        match self.providers.iter().find(|p| p.id() == result.provider) {
+            Some(provider) => provider.run_action(result, action)?,
+            None => action.run()?,
         }
-
-        log::info!("Indexed {} applications", self.applications.len());
-        Ok(())
+        if let Action::Open(path) = action {
+            self.record_launch(path);
+        }
+        Ok(action.keeps_window_open())
     }
 
-    /// Indexes a directory for applications
-    fn index_directory(&mut self, path: &Path, result_type: ResultType) -> Result<()> {
-        if !path.exists() {
-            return Ok(());
+    /// Adds a clipboard change to the clipboard history, if it is enabled
+    pub fn record_clipboard(&self, change: ClipboardChange) {
+        if let Some(clipboard) = &self.clipboard {
+            clipboard.record(change, query::now_secs());
         }
+    }
 
-        for entry in WalkDir::new(path)
-            .max_depth(5)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            let path = entry.path();
-
-            if result_type == ResultType::Application && !is_shortcut(path) {
-                continue;
-            }
-
-            let name = display_name(path);
-            if should_skip_app(&name) {
-                continue;
-            }
-
-            let description = path
-                .parent()
-                .and_then(|p| p.file_name())
-                .and_then(|s| s.to_str())
-                .unwrap_or("")
-                .to_string();
-
-            self.applications.push(SearchResult {
-                name,
-                path: path.to_path_buf(),
-                result_type,
-                score: 0,
-                description,
-            });
-        }
+    /// Returns the text Tab should turn `query` into to complete `result`
+    pub fn complete(&self, query: &str, result: &SearchResult) -> Option<String> {
+        self.providers
+            .iter()
+            .find(|p| p.id() == result.provider)?
+            .complete(query, result)
+    }
 
-        Ok(())
+    /// Rebuilds the file index from disk and saves it
+    pub fn rebuild_file_index(&self) -> Result<()> {
+        self.files.rebuild()
     }
 
-    /// Performs an advanced search with the given query
-    /// Returns grouped results for sectioned UI display
-    pub fn search(&self, query: &str) -> GroupedResults {
-        if query.is_empty() {
-            return GroupedResults::default();
-        }
+    /// Returns the number of entries in the file index
+    pub fn file_index_count(&self) -> usize {
+        self.files.len()
+    }
 
-        let normalized_query = normalize_for_search(query);
-        let query_lower = query.to_lowercase();
-        let mut grouped = GroupedResults::default();
+    /// Records that a result was launched, so it ranks higher next time
+    pub fn record_launch(&self, path: &Path) {
+        let mut history = self.history.write().unwrap();
+        history.record(path, query::now_secs());
 
-        // Search applications (fast - in memory)
-        for app in &self.applications {
-            if let Some(score) = self.calculate_score(&app.name, &normalized_query, &query_lower) {
-                let mut result = app.clone();
-                result.score = score;
-                grouped.applications.push(result);
+        if let Some(history_path) = LaunchHistory::default_path() {
+            if let Err(e) = history.save(&history_path) {
+                log::warn!("Failed to save launch history: {}", e);
             }
         }
+    }
 
-        // Sort and limit applications
-        grouped
-            .applications
-            .sort_unstable_by(|a, b| b.score.cmp(&a.score));
-        grouped.applications.truncate(5);
-
-        // Search files and folders if query is meaningful
-        if query.len() >= 2 {
-            self.search_files_and_folders(&normalized_query, &query_lower, &mut grouped);
-        }
-
-        // Remove duplicates by path (case-insensitive)
-        let mut seen_paths: std::collections::HashSet<String> = std::collections::HashSet::new();
-
-        grouped.folders.retain(|result| {
-            let path_lower = result.path.to_string_lossy().to_lowercase();
-            seen_paths.insert(path_lower)
-        });
-
-        grouped.files.retain(|result| {
-            let path_lower = result.path.to_string_lossy().to_lowercase();
-            seen_paths.insert(path_lower)
-        });
+    /// Refreshes the application index
+    pub fn refresh(&self) -> Result<()> {
+        self.applications.refresh()
+    }
 
-        // Sort folders and files
-        grouped
-            .folders
-            .sort_unstable_by(|a, b| b.score.cmp(&a.score));
-        grouped.folders.truncate(4);
+    /// Returns the number of indexed applications
+    pub fn application_count(&self) -> usize {
+        self.applications.len()
+    }
+}
 
-        grouped.files.sort_unstable_by(|a, b| b.score.cmp(&a.score));
-        grouped.files.truncate(5);
+/// Scores how well a name matches the query
+///
+/// `normalized_query` and `query_lower` are the query passed through
+/// `normalize_for_search` and lowercased. Returns None if the name does
+/// not match at all.
+pub(crate) fn name_score(
+    matcher: &SkimMatcherV2,
+    name: &str,
+    normalized_query: &str,
+    query_lower: &str,
+) -> Option<i64> {
+    let normalized_name = normalize_for_search(name);
+    let name_lower = name.to_lowercase();
+
+    // Get base fuzzy score
+    let base_score = matcher.fuzzy_match(&normalized_name, normalized_query)?;
+
+    let mut score = base_score;
+
+    // Bonus for exact match
+    if name_lower == *query_lower {
+        score += 1000;
+    }
 
-        grouped
+    // Bonus for prefix match (name starts with query)
+    if name_lower.starts_with(query_lower) {
+        score += 500;
     }
 
-    /// Advanced scoring algorithm
-    fn calculate_score(
-        &self,
-        name: &str,
-        normalized_query: &str,
-        query_lower: &str,
-    ) -> Option<i64> {
-        let normalized_name = normalize_for_search(name);
-        let name_lower = name.to_lowercase();
+    // Bonus for word-start match
+    if name_lower
+        .split_whitespace()
+        .any(|word| word.starts_with(query_lower))
+    {
+        score += 200;
+    }
 
-        // Get base fuzzy score
-        let base_score = self
-            .matcher
-            .fuzzy_match(&normalized_name, normalized_query)?;
+    // Bonus for shorter names (more relevant)
+    if name.len() < 20 {
+        score += (20 - name.len() as i64) * 5;
+    }
 
-        let mut score = base_score;
+    // Penalty for very long names
+    if name.len() > 50 {
+        score -= 50;
+    }
 
-        // Bonus for exact match
-        if name_lower == *query_lower {
-            score += 1000;
-        }
+    Some(score)
+}
 
-        // Bonus for prefix match (name starts with query)
-        if name_lower.starts_with(query_lower) {
-            score += 500;
-        }
+/// An incremental set of search results
+#[derive(Debug, Clone)]
+pub struct SearchUpdate {
+    /// Everything found so far, merged and ranked
+    pub results: GroupedResults,
 
-        // Bonus for word-start match
-        if name_lower
-            .split_whitespace()
-            .any(|word| word.starts_with(query_lower))
-        {
-            score += 200;
-        }
+    /// Whether the search has finished and no more updates will follow
+    pub complete: bool,
+}
 
-        // Bonus for shorter names (more relevant)
-        if name.len() < 20 {
-            score += (20 - name.len() as i64) * 5;
-        }
+/// Cooperative cancellation for a single search request
+///
+/// Each request is tagged with a generation number. Submitting a newer
+/// request (or cancelling) bumps the shared latest generation, which makes
+/// every older token report itself as cancelled.
+#[derive(Debug, Clone)]
+pub struct CancelToken {
+    generation: u64,
+    latest: Arc<AtomicU64>,
+}
 
-        // Penalty for very long names
-        if name.len() > 50 {
-            score -= 50;
+impl CancelToken {
+    /// Creates a token that is never cancelled
+    pub fn never() -> Self {
+        Self {
+            generation: 0,
+            latest: Arc::new(AtomicU64::new(0)),
         }
-
-        Some(score)
     }
 
-    /// Searches files and folders in all configured paths across all drives
-    /// Uses parallel processing to search all drives simultaneously
-    fn search_files_and_folders(
-        &self,
-        normalized_query: &str,
-        query_lower: &str,
-        grouped: &mut GroupedResults,
-    ) {
-        let max_per_path = 300; // Max files to check per search path
-
-        // Combine config paths and extra paths
-        let all_paths: Vec<PathBuf> = self
-            .config
-            .search_paths
-            .iter()
-            .chain(self.extra_search_paths.iter())
-            .cloned()
-            .collect();
-
-        // Remove duplicates
-        let mut seen_paths: std::collections::HashSet<String> = std::collections::HashSet::new();
-        let unique_paths: Vec<PathBuf> = all_paths
-            .into_iter()
-            .filter(|path| {
-                let path_str = path.to_string_lossy().to_lowercase();
-                seen_paths.insert(path_str)
-            })
-            .collect();
-
-        // Shared results collections for parallel access
-        let folders = Mutex::new(Vec::<SearchResult>::new());
-        let files = Mutex::new(Vec::<SearchResult>::new());
+    /// Returns the generation this token belongs to
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
 
-        // Clone query strings for parallel access
-        let normalized_query = normalized_query.to_string();
-        let query_lower = query_lower.to_string();
+    /// Returns true once a newer request has superseded this one
+    pub fn is_cancelled(&self) -> bool {
+        self.latest.load(Ordering::SeqCst) != self.generation
+    }
+}
 
-        // PARALLEL SEARCH: All drives searched simultaneously!
-        unique_paths.par_iter().for_each(|search_path| {
-            if !search_path.exists() {
-                return;
-            }
+/// Results of a finished background search
+#[derive(Debug)]
+pub struct SearchResponse {
+    /// Generation of the request that produced these results
+    pub generation: u64,
 
-            // Determine search depth based on path type
-            let path_str = search_path.to_string_lossy().to_lowercase();
-            let is_drive_root =
-                search_path.parent().is_none() || search_path.to_string_lossy().len() <= 3;
-            let is_non_c_drive = !path_str.starts_with("c:");
-
-            // For non-C drive roots, search 4 levels deep (comprehensive)
-            // For C drive or user dirs, use 3 levels
-            let max_depth = if is_drive_root && is_non_c_drive {
-                4 // Deep search for data drives
-            } else if is_drive_root {
-                2 // Shallow for C: drive root
-            } else {
-                3 // Normal depth for user directories
-            };
+    /// The results so far, or why the query could not be run
+    pub results: Result<GroupedResults>,
 
-            let walker = WalkDir::new(search_path)
-                .max_depth(max_depth)
-                .follow_links(false)
-                .into_iter()
-                .filter_entry(|e| {
-                    if let Some(name) = e.file_name().to_str() {
-                        // Skip hidden files/directories
-                        if name.starts_with('.') || name.starts_with('$') {
-                            return false;
-                        }
-                        let lower = name.to_lowercase();
-                        // Skip system and build directories
-                        if matches!(
-                            lower.as_str(),
-                            "node_modules"
-                                | ".git"
-                                | "target"
-                                | "__pycache__"
-                                | ".cache"
-                                | "appdata"
-                                | "cache"
-                                | "temp"
-                                | "tmp"
-                                | "$recycle.bin"
-                                | "system volume information"
-                                | "windows"
-                                | "programdata"
-                                | "recovery"
-                                | "boot"
-                                | "perflogs"
-                                | "msocache"
-                                | "config.msi"
-                                | "intel"
-                                | "amd"
-                                | "nvidia"
-                                | ".vs"
-                                | ".idea"
-                                | ".vscode"
-                                | "bin"
-                                | "obj"
-                                | "debug"
-                                | "release"
-                                | "packages"
-                                | ".nuget"
-                                | "wpsystem"
-                                | "windowsapps"
-                                | "xboxgames"
-                        ) {
-                            return false;
-                        }
-                    }
-                    true
-                });
-
-            let mut path_results_folders = Vec::new();
-            let mut path_results_files = Vec::new();
-            let mut path_checked = 0;
-
-            // Check if the search path itself matches the query (for main folders)
-            if search_path.is_dir() {
-                let search_path_name = display_name(search_path);
-                if let Some(score) =
-                    self.calculate_score(&search_path_name, &normalized_query, &query_lower)
-                {
-                    let drive_boost = if is_non_c_drive { 100 } else { 0 };
-                    let description = search_path
-                        .parent()
-                        .map(|p| p.to_string_lossy().to_string())
-                        .unwrap_or_default();
-
-                    let result = SearchResult {
-                        name: search_path_name,
-                        path: search_path.clone(),
-                        result_type: ResultType::Folder,
-                        score: score + drive_boost,
-                        description,
-                    };
-                    path_results_folders.push(result);
-                }
-            }
+    /// Whether these are the final results for the request
+    pub complete: bool,
+}
 
-            for entry in walker.filter_map(|e| e.ok()) {
-                path_checked += 1;
+/// Runs searches on a background thread so the UI never blocks
+///
+/// Only the newest request matters: queued requests are skipped when a
+/// newer one arrives, running ones are cancelled cooperatively, and only
+/// results for the latest generation are handed back. Results stream in
+/// as they are found; the worker calls `notify` whenever a newer update is
+/// ready to be collected with `take_response`, the last one being marked
+/// complete. The thread is stopped when the handle is dropped.
+pub struct SearchWorker {
+    latest: Arc<AtomicU64>,
+    sender: Option<Sender<(u64, String)>>,
+    response: Arc<Mutex<Option<SearchResponse>>>,
+    thread: Option<JoinHandle<()>>,
+}
 
-                // Limit per path
-                if path_checked > max_per_path {
-                    break;
+impl SearchWorker {
+    /// Starts a worker that runs queries with `search`
+    ///
+    /// `search` reports each update through the callback it is given and
+    /// returns false if it notices its token was cancelled. An error
+    /// counts as the final update.
+    pub fn spawn<S, N>(search: S, notify: N) -> Self
+    where
+        S: Fn(&str, &CancelToken, &(dyn Fn(Result<SearchUpdate>) + Sync)) -> bool + Send + 'static,
+        N: Fn() + Send + Sync + 'static,
+    {
+        let latest = Arc::new(AtomicU64::new(0));
+        let response = Arc::new(Mutex::new(None));
+        let (sender, receiver) = mpsc::channel::<(u64, String)>();
+
+        let thread_latest = latest.clone();
+        let thread_response = response.clone();
+        let thread = std::thread::spawn(move || {
+            while let Ok(mut request) = receiver.recv() {
+                // Skip straight to the newest queued request
+                while let Ok(newer) = receiver.try_recv() {
+                    request = newer;
                 }
 
-                let path = entry.path();
-                // Don't skip the search path itself - we already checked it above
-                if path == search_path {
+                let (generation, query) = request;
+                let token = CancelToken {
+                    generation,
+                    latest: thread_latest.clone(),
+                };
+                if token.is_cancelled() {
                     continue;
                 }
 
-                let name = display_name(path);
-
-                // Skip very short names for drive root searches
-                if is_drive_root && name.len() < 2 {
-                    continue;
-                }
+                let emit = |update: Result<SearchUpdate>| {
+                    if token.is_cancelled() {
+                        return;
+                    }
 
-                if let Some(score) = self.calculate_score(&name, &normalized_query, &query_lower) {
-                    // Boost score for files/folders on non-C drives
-                    let drive_boost = if is_non_c_drive { 100 } else { 0 };
-
-                    let description = path
-                        .parent()
-                        .map(|p| p.to_string_lossy().to_string())
-                        .unwrap_or_default();
-
-                    let result = SearchResult {
-                        name,
-                        path: path.to_path_buf(),
-                        result_type: if path.is_dir() {
-                            ResultType::Folder
-                        } else {
-                            ResultType::File
-                        },
-                        score: score + drive_boost,
-                        description,
+                    let (results, complete) = match update {
+                        Ok(update) => (Ok(update.results), update.complete),
+                        Err(e) => (Err(e), true),
                     };
-
-                    if path.is_dir() {
-                        path_results_folders.push(result);
-                    } else {
-                        path_results_files.push(result);
-                    }
+                    *thread_response.lock().unwrap() = Some(SearchResponse {
+                        generation,
+                        results,
+                        complete,
+                    });
+                    notify();
+                };
+
+                if !search(&query, &token, &emit) {
+                    log::debug!("Search for {:?} was cancelled", query);
                 }
             }
-
-            // Merge results into shared collections
-            if !path_results_folders.is_empty() {
-                folders.lock().unwrap().extend(path_results_folders);
-            }
-            if !path_results_files.is_empty() {
-                files.lock().unwrap().extend(path_results_files);
-            }
         });
 
-        // Collect results from parallel search
-        let mut all_folders = folders.into_inner().unwrap();
-        let mut all_files = files.into_inner().unwrap();
-
-        // Remove duplicates by path (case-insensitive) - final deduplication
-        let mut seen_paths: std::collections::HashSet<String> = std::collections::HashSet::new();
+        Self {
+            latest,
+            sender: Some(sender),
+            response,
+            thread: Some(thread),
+        }
+    }
 
-        all_folders.retain(|result| {
-            let path_lower = result.path.to_string_lossy().to_lowercase();
-            seen_paths.insert(path_lower)
-        });
+    /// Starts a worker that searches with `engine`
+    pub fn for_engine<N>(engine: Arc<SearchEngine>, notify: N) -> Self
+    where
+        N: Fn() + Send + Sync + 'static,
+    {
+        Self::spawn(
+            move |query, cancel, emit| engine.search_cancellable(query, cancel, emit),
+            notify,
+        )
+    }
 
-        all_files.retain(|result| {
-            let path_lower = result.path.to_string_lossy().to_lowercase();
-            seen_paths.insert(path_lower)
-        });
+    /// Queues a search, cancelling any older one, and returns its generation
+    pub fn submit(&self, query: &str) -> u64 {
+        let generation = self.latest.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Some(sender) = &self.sender {
+            let _ = sender.send((generation, query.to_string()));
+        }
+        generation
+    }
 
-        grouped.folders = all_folders;
-        grouped.files = all_files;
+    /// Cancels every outstanding search without starting a new one
+    pub fn cancel(&self) {
+        self.latest.fetch_add(1, Ordering::SeqCst);
     }
 
-    /// Refreshes the application index
-    pub fn refresh(&mut self) -> Result<()> {
-        self.index_applications()
+    /// Returns the generation of the most recent request
+    pub fn generation(&self) -> u64 {
+        self.latest.load(Ordering::SeqCst)
     }
 
-    /// Returns the number of indexed applications
-    pub fn application_count(&self) -> usize {
-        self.applications.len()
+    /// Takes the newest results of the latest request, if any are ready
+    ///
+    /// Results of superseded requests are discarded.
+    pub fn take_response(&self) -> Option<SearchResponse> {
+        let response = self.response.lock().unwrap().take()?;
+        (response.generation == self.generation()).then_some(response)
     }
 }
 
-/// Checks if an application should be skipped during indexing
-fn should_skip_app(name: &str) -> bool {
-    let lower = name.to_lowercase();
-
-    lower.contains("uninstall")
-        || lower.contains("remove")
-        || lower.contains("repair")
-        || lower.contains("help")
-        || lower.contains("readme")
-        || lower.contains("manual")
-        || lower.contains("documentation")
-        || lower.contains("license")
-        || lower.contains("website")
-        || lower.contains("url")
-        || lower == "about"
+impl Drop for SearchWorker {
+    fn drop(&mut self) {
+        // Closing the channel ends the thread once its current search stops
+        self.cancel();
+        self.sender.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
 }
 
 /// Creates a search engine with an Arc wrapper for thread-safe sharing
@@ -805,24 +814,324 @@ pub fn create_shared_engine(config: SearchConfig) -> Arc<SearchEngine> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fulltext::FullTextIndex;
+    use crate::ignore::IgnoreSet;
+    use crate::index::FileIndex;
+    use std::time::Duration;
 
     #[test]
     fn test_result_type_priority() {
         assert!(ResultType::Application.priority() < ResultType::File.priority());
     }
 
+    /// Builds an engine over the given roots without touching system paths
+    fn test_engine(roots: &[PathBuf]) -> SearchEngine {
+        test_engine_with(roots, SearchConfig::default())
+    }
+
+    fn test_engine_with(roots: &[PathBuf], mut config: SearchConfig) -> SearchEngine {
+        // The bookmarks, history and recent items of the machine running the
+        // tests would mix in
+        config.bookmarks.enabled = false;
+        config.browser_history.enabled = false;
+        config.recent.enabled = false;
+        let file_index = FileIndex::build(roots, IgnoreSet::default());
+        let full_text = FullTextIndex::build(&file_index, &config.full_text);
+        let history = Arc::new(RwLock::new(LaunchHistory::new(Default::default())));
+        let files =
+            FileProvider::with_indexes(config.clone(), file_index, full_text, history.clone());
+        let applications = ApplicationProvider::with_applications(Vec::new(), history.clone());
+        SearchEngine::with_providers(&config, applications, files, history)
+    }
+
+    fn named_results(name: &str) -> GroupedResults {
+        GroupedResults {
+            sections: vec![Section {
+                provider: "apps",
+                title: "Applications".to_string(),
+                priority: 0,
+                results: vec![SearchResult {
+                    name: name.to_string(),
+                    path: PathBuf::from(name),
+                    result_type: ResultType::Application,
+                    score: 0,
+                    description: String::new(),
+                    provider: "apps",
+                    shortcut: None,
+                }],
+            }],
+        }
+    }
+
+    /// Returns the results of one section, or nothing if it is missing
+    fn section<'a>(results: &'a GroupedResults, title: &str) -> &'a [SearchResult] {
+        results.section(title).map_or(&[], |s| &s.results)
+    }
+
+    #[test]
+    fn test_cancel_token() {
+        let never = CancelToken::never();
+        assert!(!never.is_cancelled());
+
+        let latest = Arc::new(AtomicU64::new(1));
+        let token = CancelToken {
+            generation: 1,
+            latest: latest.clone(),
+        };
+        assert!(!token.is_cancelled());
+        latest.fetch_add(1, Ordering::SeqCst);
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn test_worker_cancels_older_searches() {
+        let (notify_tx, notify_rx) = mpsc::channel();
+        let (started_tx, started_rx) = mpsc::channel();
+        let (cancelled_tx, cancelled_rx) = mpsc::channel();
+
+        let worker = SearchWorker::spawn(
+            move |query: &str, cancel: &CancelToken, emit| {
+                if query == "slow" {
+                    started_tx.send(()).unwrap();
+                    // Spin until a newer request cancels this one
+                    while !cancel.is_cancelled() {
+                        std::thread::sleep(Duration::from_millis(1));
+                    }
+                    cancelled_tx.send(()).unwrap();
+                    return false;
+                }
+                emit(Ok(SearchUpdate {
+                    results: named_results(query),
+                    complete: true,
+                }));
+                true
+            },
+            move || notify_tx.send(()).unwrap(),
+        );
+
+        worker.submit("slow");
+        started_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        let latest = worker.submit("fast");
+
+        cancelled_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        notify_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        let response = worker.take_response().unwrap();
+        assert_eq!(response.generation, latest);
+        assert!(response.complete);
+        let results = response.results.unwrap();
+        assert_eq!(section(&results, "Applications")[0].name, "fast");
+        assert!(worker.take_response().is_none());
+    }
+
+    #[test]
+    fn test_worker_drops_superseded_results() {
+        let (notify_tx, notify_rx) = mpsc::channel();
+        let worker = SearchWorker::spawn(
+            |query: &str, _: &CancelToken, emit| {
+                emit(Ok(SearchUpdate {
+                    results: named_results(query),
+                    complete: true,
+                }));
+                true
+            },
+            move || notify_tx.send(()).unwrap(),
+        );
+
+        worker.submit("first");
+        notify_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        // Results that arrive after the window moved on are not handed out
+        worker.cancel();
+        assert!(worker.take_response().is_none());
+    }
+
     #[test]
-    fn test_should_skip_app() {
-        assert!(should_skip_app("Uninstall Chrome"));
-        assert!(should_skip_app("Remove App"));
-        assert!(!should_skip_app("Google Chrome"));
-        assert!(!should_skip_app("Visual Studio Code"));
+    fn test_streaming_reports_each_root_then_completes() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        std::fs::write(first.path().join("report-2023.txt"), b"").unwrap();
+        std::fs::write(second.path().join("report-2024.txt"), b"").unwrap();
+        let engine = test_engine(&[first.path().to_path_buf(), second.path().to_path_buf()]);
+
+        let updates = Mutex::new(Vec::new());
+        let query = Query::parse("report").unwrap();
+        let finished = engine.search_streaming(&query, &CancelToken::never(), |update| {
+            updates.lock().unwrap().push(update)
+        });
+        let updates = updates.into_inner().unwrap();
+
+        assert!(finished);
+        // Applications, one update per root, then the final results
+        assert_eq!(updates.len(), 4);
+        assert!(updates[..3].iter().all(|u| !u.complete));
+        assert!(updates[..3]
+            .iter()
+            .any(|u| section(&u.results, "Files").len() == 1));
+
+        let last = updates.last().unwrap();
+        assert!(last.complete);
+        assert_eq!(section(&last.results, "Files").len(), 2);
+    }
+
+    #[test]
+    fn test_cancelled_search_never_completes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("report.txt"), b"").unwrap();
+        let engine = test_engine(&[dir.path().to_path_buf()]);
+
+        let cancelled = CancelToken {
+            generation: 1,
+            latest: Arc::new(AtomicU64::new(2)),
+        };
+        let completed = Mutex::new(false);
+        let query = Query::parse("report").unwrap();
+        let finished = engine.search_streaming(&query, &cancelled, |update| {
+            *completed.lock().unwrap() |= update.complete
+        });
+
+        assert!(!finished);
+        assert!(!*completed.lock().unwrap());
+        assert!(engine.search_query(&query, &cancelled).is_none());
+    }
+
+    #[test]
+    fn test_content_search_shows_matching_line() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("minutes.txt"),
+            "Attendees: all\nAgreed to ship the quarterly report Friday\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("other.md"), "nothing relevant").unwrap();
+        std::fs::write(dir.path().join("data.bin"), b"\0\0quarterly report").unwrap();
+        let engine = test_engine(&[dir.path().to_path_buf()]);
+
+        let results = engine.search("/\"Quarterly Report\"").unwrap();
+        let files = section(&results, "Files");
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "minutes.txt");
+        assert_eq!(
+            files[0].description,
+            "2: Agreed to ship the quarterly report Friday"
+        );
+
+        // Filters still apply, and without the prefix names are matched
+        assert!(engine.search("/quarterly ext:md").unwrap().is_empty());
+        let results = engine.search("quarterly report").unwrap();
+        assert!(section(&results, "Files").is_empty());
+    }
+
+    #[test]
+    fn test_content_search_without_full_text_index() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("notes.dat"), "the quarterly report").unwrap();
+        let engine = test_engine(&[dir.path().to_path_buf()]);
+
+        // .dat files are not in the full-text index
+        assert!(engine.search("/quarterly").unwrap().is_empty());
+
+        let mut config = SearchConfig::default();
+        config.full_text.enabled = false;
+        let engine = test_engine_with(&[dir.path().to_path_buf()], config);
+        let results = engine.search("/quarterly").unwrap();
+        assert_eq!(
+            section(&results, "Files")[0].description,
+            "1: the quarterly report"
+        );
+    }
+
+    #[test]
+    fn test_full_text_prefix_query() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("todo.md"), "- review quarterly numbers\n").unwrap();
+        let engine = test_engine(&[dir.path().to_path_buf()]);
+
+        let results = engine.search("/review quart*").unwrap();
+        let files = section(&results, "Files");
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].description, "1: - review quarterly numbers");
+    }
+
+    #[test]
+    fn test_cancelled_content_search_never_completes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "needle").unwrap();
+        let mut config = SearchConfig::default();
+        config.full_text.enabled = false;
+        let engine = test_engine_with(&[dir.path().to_path_buf()], config);
+
+        let cancelled = CancelToken {
+            generation: 1,
+            latest: Arc::new(AtomicU64::new(2)),
+        };
+        let completed = Mutex::new(false);
+        let finished = engine.search_cancellable("/needle", &cancelled, |update| {
+            *completed.lock().unwrap() |= update.unwrap().complete
+        });
+
+        assert!(!finished);
+        assert!(!*completed.lock().unwrap());
+    }
+
+    #[test]
+    fn test_filter_words_reach_raw_text_providers() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = test_engine(&[dir.path().to_path_buf()]);
+
+        let updates = Mutex::new(Vec::new());
+        engine.search_cancellable(">echo size:big", &CancelToken::never(), |update| {
+            updates.lock().unwrap().push(update)
+        });
+        let last = updates.into_inner().unwrap().pop().unwrap().unwrap();
+        assert!(last.complete);
+        assert_eq!(section(&last.results, "Shell")[0].name, "echo size:big");
+
+        // Quotes are kept for the command, and filter-like words that are
+        // not valid filters are no error either
+        let results = engine.search(">findstr \"type:x\" log.txt").unwrap();
+        assert_eq!(
+            section(&results, "Shell")[0].name,
+            "findstr \"type:x\" log.txt"
+        );
+        assert!(!section(&engine.search(">dir in:").unwrap(), "Shell").is_empty());
+
+        // The web search runs alongside the providers whose filter is invalid
+        let results = engine.search("g size:large shirts").unwrap();
+        assert_eq!(
+            section(&results, "Web")[0].name,
+            "Search Google for “size:large shirts”"
+        );
+
+        // With nothing else found, the invalid filter is reported
+        assert!(engine.search("report size:big").is_err());
     }
 
     #[test]
     fn test_grouped_results() {
-        let grouped = GroupedResults::default();
+        let mut grouped = GroupedResults::default();
         assert!(grouped.is_empty());
         assert_eq!(grouped.total_count(), 0);
+
+        let mut files = named_results("notes.txt").sections.remove(0);
+        files.provider = "files";
+        files.title = "Files".to_string();
+        files.priority = 30;
+        grouped.replace_provider("files", vec![files]);
+        grouped.replace_provider("apps", named_results("Notepad").sections);
+        let titles: Vec<&str> = grouped.sections.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, ["Applications", "Files"]);
+        assert_eq!(grouped.total_count(), 2);
+        assert_eq!(grouped.flat_index(1, 0), Some(3));
+        assert!(grouped.flat_index(1, 1).is_none());
+
+        // A provider's new results replace its old ones, and empty
+        // sections disappear
+        grouped.replace_provider("apps", Vec::new());
+        assert_eq!(grouped.sections.len(), 1);
+        assert!(matches!(
+            &grouped.flatten_with_sections()[0],
+            FlatResult::SectionHeader(title) if title == "Files"
+        ));
     }
 }
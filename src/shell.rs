@@ -0,0 +1,1090 @@
+//! Shell commands for Rustle
+//!
+//! A query starting with the shell prefix (`>` by default) runs the rest of
+//! the query as a command in the configured shell: cmd, PowerShell or any
+//! other program. The command can be started in a new terminal window, or
+//! run hidden with its stdout and stderr captured and shown as results while
+//! it runs. Captured commands are killed, along with every process they
+//! started, when they outlive the timeout or when asked to. Every command
+//! that is run is remembered in a small text file under
+//! `Config::data_dir()`, so it can be completed with Tab later.
+
+#![allow(dead_code)]
+
+use crate::config::{Config, ShellConfig, ShellKind};
+use crate::error::{Result, RustleError};
+use crate::provider::{Action, Provider, ProviderQuery, Trigger};
+use crate::search::{CancelToken, ResultType, SearchResult, Section};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// First line of every command history file
+const HISTORY_HEADER: &str = "rustle-commands 1";
+
+/// File name of the command history inside the data directory
+const HISTORY_FILE_NAME: &str = "command_history.txt";
+
+/// How often a captured command is checked for exit and timeout
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Most past commands shown for one query
+const MAX_HISTORY_RESULTS: usize = 5;
+
+/// Description of the result that runs the command in a terminal
+const RUN_IN_TERMINAL: &str = "Run in a new terminal window";
+
+/// Description of the result that runs the command and shows its output
+const RUN_AND_CAPTURE: &str = "Run and show the output here";
+
+/// Description of past commands
+const FROM_HISTORY: &str = "Recent command · Tab to edit";
+
+/// Description of output lines written to stderr
+const STDERR: &str = "stderr";
+
+#[cfg(windows)]
+const CREATE_NEW_CONSOLE: u32 = 0x0000_0010;
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+/// Returns the name of a shell for display
+pub fn shell_name(shell: &ShellKind) -> String {
+    match shell {
+        ShellKind::Cmd => "cmd".to_string(),
+        ShellKind::PowerShell => "PowerShell".to_string(),
+        ShellKind::Custom { program, .. } => program
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| program.display().to_string()),
+    }
+}
+
+/// Builds the process that runs `command` in `shell`
+///
+/// In a terminal, cmd and PowerShell keep their window open once the
+/// command finishes so its output can be read; otherwise the shell exits
+/// as soon as the command does.
+pub fn shell_command(shell: &ShellKind, command: &str, in_terminal: bool) -> Command {
+    let mut process = match shell {
+        ShellKind::Cmd => {
+            let mut process = Command::new("cmd.exe");
+            process.arg("/D").arg(if in_terminal { "/K" } else { "/C" });
+            push_raw_arg(&mut process, command);
+            process
+        }
+        ShellKind::PowerShell => {
+            let mut process = Command::new("powershell.exe");
+            process.arg("-NoProfile");
+            if in_terminal {
+                process.arg("-NoExit");
+            } else {
+                process.arg("-NonInteractive");
+            }
+            process.arg("-Command").arg(command);
+            process
+        }
+        ShellKind::Custom { program, args } => {
+            let mut process = Command::new(program);
+            process.args(args).arg(command);
+            process
+        }
+    };
+    set_window(&mut process, in_terminal);
+    process
+}
+
+/// Passes `command` to cmd as typed
+///
+/// cmd does its own parsing of the command line, which the usual quoting
+/// of arguments would break.
+fn push_raw_arg(process: &mut Command, command: &str) {
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        process.raw_arg(command);
+    }
+    #[cfg(not(windows))]
+    process.arg(command);
+}
+
+/// Gives a terminal command its own console, and hides the console of a
+/// captured one
+fn set_window(process: &mut Command, in_terminal: bool) {
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        process.creation_flags(if in_terminal {
+            CREATE_NEW_CONSOLE
+        } else {
+            CREATE_NO_WINDOW
+        });
+    }
+    #[cfg(not(windows))]
+    let _ = (process, in_terminal);
+}
+
+/// Starts `command` in a new terminal window and leaves it running
+pub fn run_in_terminal(shell: &ShellKind, command: &str) -> Result<()> {
+    log::info!("Running in terminal: {}", command);
+    shell_command(shell, command, true)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| RustleError::command_error(format!("{}: {}", shell_name(shell), e)))
+}
+
+/// Where a captured command is up to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunStatus {
+    /// Still running
+    Running,
+
+    /// Exited by itself, with its exit code if it has one
+    Exited(Option<i32>),
+
+    /// Killed for running longer than the timeout
+    TimedOut,
+
+    /// Killed on request
+    Killed,
+}
+
+/// One line written by a captured command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputLine {
+    pub text: String,
+
+    /// Whether the line was written to stderr rather than stdout
+    pub is_error: bool,
+}
+
+/// Everything a captured command has written so far
+#[derive(Debug, Clone)]
+pub struct Output {
+    /// The most recent lines, oldest first
+    pub lines: VecDeque<OutputLine>,
+
+    /// Number of older lines dropped to stay within the limit
+    pub dropped: usize,
+
+    pub status: RunStatus,
+
+    /// Number of output streams still open
+    open_streams: usize,
+
+    /// Bumped on every change, so waiters can tell something happened
+    generation: u64,
+}
+
+impl Output {
+    /// Returns true once the command has ended and all its output is in,
+    /// or once it has been killed
+    ///
+    /// A killed command is finished straight away: anything it started
+    /// that could not be killed may hold its output open for much longer.
+    pub fn is_finished(&self) -> bool {
+        match self.status {
+            RunStatus::Running => false,
+            RunStatus::Exited(_) => self.open_streams == 0,
+            RunStatus::TimedOut | RunStatus::Killed => true,
+        }
+    }
+}
+
+/// A command running hidden, with its output captured
+pub struct CapturedRun {
+    /// The command as typed
+    pub command: String,
+
+    processes: Mutex<ProcessTree>,
+
+    output: Mutex<Output>,
+
+    /// Signalled whenever `output` changes
+    changed: Condvar,
+
+    max_lines: usize,
+
+    /// How long the command may run; None for no limit
+    timeout: Option<Duration>,
+}
+
+impl CapturedRun {
+    /// Starts `command` with its output captured
+    ///
+    /// Background threads collect the output and kill the command if it is
+    /// still running after `timeout`.
+    pub fn start(
+        shell: &ShellKind,
+        command: &str,
+        timeout: Option<Duration>,
+        max_lines: usize,
+    ) -> Result<Arc<Self>> {
+        log::info!("Running with captured output: {}", command);
+        let mut process = shell_command(shell, command, false);
+        new_process_group(&mut process);
+        let mut child = process
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| RustleError::command_error(format!("{}: {}", shell_name(shell), e)))?;
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+
+        let run = Arc::new(Self {
+            command: command.to_string(),
+            processes: Mutex::new(ProcessTree::new(child)),
+            output: Mutex::new(Output {
+                lines: VecDeque::new(),
+                dropped: 0,
+                status: RunStatus::Running,
+                open_streams: 2,
+                generation: 0,
+            }),
+            changed: Condvar::new(),
+            max_lines: max_lines.max(1),
+            timeout,
+        });
+
+        for (stream, is_error) in [
+            (stdout.map(|s| Box::new(s) as Box<dyn Read + Send>), false),
+            (stderr.map(|s| Box::new(s) as Box<dyn Read + Send>), true),
+        ] {
+            let run = run.clone();
+            std::thread::spawn(move || {
+                if let Some(stream) = stream {
+                    run.read_lines(stream, is_error);
+                }
+                run.update(|output| output.open_streams -= 1);
+            });
+        }
+
+        let waiter = run.clone();
+        std::thread::spawn(move || waiter.wait_for_exit());
+
+        Ok(run)
+    }
+
+    /// Kills the command if it is still running
+    pub fn kill(&self) {
+        self.stop(RunStatus::Killed);
+    }
+
+    /// Returns a copy of the output so far
+    pub fn output(&self) -> Output {
+        self.output.lock().unwrap().clone()
+    }
+
+    /// Waits up to `timeout` for the output to change from `generation`,
+    /// returning the output as it is then
+    pub fn wait_for_change(&self, generation: u64, timeout: Duration) -> Output {
+        let output = self.output.lock().unwrap();
+        let (output, _) = self
+            .changed
+            .wait_timeout_while(output, timeout, |o| o.generation == generation)
+            .unwrap();
+        output.clone()
+    }
+
+    /// Waits up to `timeout` for the command to end, returning its status
+    pub fn wait(&self, timeout: Duration) -> RunStatus {
+        let output = self.output.lock().unwrap();
+        let (output, _) = self
+            .changed
+            .wait_timeout_while(output, timeout, |o| o.status == RunStatus::Running)
+            .unwrap();
+        output.status
+    }
+
+    fn update(&self, change: impl FnOnce(&mut Output)) {
+        let mut output = self.output.lock().unwrap();
+        change(&mut output);
+        output.generation += 1;
+        self.changed.notify_all();
+    }
+
+    fn read_lines(&self, stream: Box<dyn Read + Send>, is_error: bool) {
+        for line in BufReader::new(stream).split(b'\n') {
+            let Ok(line) = line else {
+                break;
+            };
+            let text = String::from_utf8_lossy(&line).trim_end().to_string();
+            self.update(|output| {
+                output.lines.push_back(OutputLine { text, is_error });
+                if output.lines.len() > self.max_lines {
+                    output.lines.pop_front();
+                    output.dropped += 1;
+                }
+            });
+        }
+    }
+
+    /// Polls the command until it exits, killing it at the timeout
+    fn wait_for_exit(&self) {
+        let started = Instant::now();
+        loop {
+            let exited = self.processes.lock().unwrap().child.try_wait();
+            match exited {
+                Ok(Some(status)) => {
+                    self.finish(RunStatus::Exited(status.code()));
+                    return;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    log::warn!("Lost track of command {}: {}", self.command, e);
+                    self.finish(RunStatus::Exited(None));
+                    return;
+                }
+            }
+            if self.timeout.is_some_and(|t| started.elapsed() >= t) {
+                self.stop(RunStatus::TimedOut);
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Records how the command ended, unless that is already known
+    fn finish(&self, status: RunStatus) {
+        self.update(|output| {
+            if output.status == RunStatus::Running {
+                output.status = status;
+            }
+        });
+    }
+
+    fn stop(&self, status: RunStatus) {
+        if self.output.lock().unwrap().status != RunStatus::Running {
+            return;
+        }
+        // Recorded first, so the exit the kill causes is not taken for the
+        // command ending by itself
+        self.finish(status);
+        self.processes.lock().unwrap().kill(&self.command);
+    }
+}
+
+/// A captured command's shell and every process it starts
+///
+/// Killing only the shell would leave what it started running, such as
+/// `ping -t` or a dev server, still writing to the captured output.
+struct ProcessTree {
+    child: Child,
+
+    /// Job object the shell was put in; None if that failed
+    #[cfg(windows)]
+    job: Option<job::Job>,
+}
+
+impl ProcessTree {
+    fn new(child: Child) -> Self {
+        #[cfg(windows)]
+        let job = job::Job::assign(&child)
+            .map_err(|e| log::debug!("Could not put command in a job object: {}", e))
+            .ok();
+        Self {
+            child,
+            #[cfg(windows)]
+            job,
+        }
+    }
+
+    /// Kills the shell and everything it started
+    fn kill(&mut self, command: &str) {
+        #[cfg(windows)]
+        {
+            if let Some(job) = &self.job {
+                match job.terminate() {
+                    Ok(()) => return,
+                    Err(e) => log::debug!("Could not end the job of {}: {}", command, e),
+                }
+            }
+        }
+        #[cfg(unix)]
+        {
+            // The shell leads its own process group, see `new_process_group`
+            let group = format!("-{}", self.child.id());
+            let killed = Command::new("kill")
+                .args(["-KILL", "--", &group])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+            if killed.is_ok_and(|status| status.success()) {
+                return;
+            }
+        }
+        if let Err(e) = self.child.kill() {
+            log::debug!("Could not kill {}: {}", command, e);
+        }
+    }
+}
+
+/// Starts a captured command in a process group of its own, so that
+/// `ProcessTree::kill` can kill the whole group
+///
+/// On Windows the shell is put in a job object once started instead.
+fn new_process_group(process: &mut Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        process.process_group(0);
+    }
+    #[cfg(not(unix))]
+    let _ = process;
+}
+
+#[cfg(windows)]
+mod job {
+    use std::os::windows::io::AsRawHandle;
+    use std::process::Child;
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+        SetInformationJobObject, TerminateJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+        JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+
+    /// Job object whose processes are killed when it is closed
+    ///
+    /// Holds the raw handle, as `HANDLE` cannot be sent between threads.
+    pub struct Job(isize);
+
+    impl Job {
+        /// Creates a job and puts `child` in it
+        ///
+        /// Processes the child starts from then on join the job too.
+        pub fn assign(child: &Child) -> windows::core::Result<Self> {
+            let job = Self(unsafe { CreateJobObjectW(None, PCWSTR::null()) }?.0 as isize);
+
+            let mut limits = JOBOBJECT_EXTENDED_LIMIT_INFORMATION::default();
+            limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+            unsafe {
+                SetInformationJobObject(
+                    job.handle(),
+                    JobObjectExtendedLimitInformation,
+                    &limits as *const _ as *const core::ffi::c_void,
+                    std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+                )?;
+                AssignProcessToJobObject(job.handle(), HANDLE(child.as_raw_handle()))?;
+            }
+            Ok(job)
+        }
+
+        /// Kills every process in the job
+        pub fn terminate(&self) -> windows::core::Result<()> {
+            unsafe { TerminateJobObject(self.handle(), 1) }
+        }
+
+        fn handle(&self) -> HANDLE {
+            HANDLE(self.0 as *mut core::ffi::c_void)
+        }
+    }
+
+    impl Drop for Job {
+        fn drop(&mut self) {
+            unsafe {
+                let _ = CloseHandle(self.handle());
+            }
+        }
+    }
+}
+
+/// Commands run from the search window, most recent first
+#[derive(Debug, Clone)]
+pub struct CommandHistory {
+    commands: Vec<String>,
+    max_entries: usize,
+}
+
+impl CommandHistory {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            commands: Vec::new(),
+            max_entries,
+        }
+    }
+
+    /// Returns the default location of the command history file
+    pub fn default_path() -> Option<PathBuf> {
+        Config::data_dir().map(|p| p.join(HISTORY_FILE_NAME))
+    }
+
+    /// Loads the history, starting empty if it is missing or unreadable
+    pub fn load_or_default(path: &Path, max_entries: usize) -> Self {
+        match Self::load(path, max_entries) {
+            Ok(history) => history,
+            Err(e) => {
+                if path.exists() {
+                    log::warn!("Ignoring command history at {}: {}", path.display(), e);
+                }
+                Self::new(max_entries)
+            }
+        }
+    }
+
+    /// Loads the history from disk
+    pub fn load(path: &Path, max_entries: usize) -> Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut lines = reader.lines();
+
+        match lines.next() {
+            Some(Ok(header)) if header == HISTORY_HEADER => {}
+            _ => return Err(RustleError::history_error("not a Rustle command history")),
+        }
+
+        let mut history = Self::new(max_entries);
+        for line in lines {
+            let line = line?;
+            if !line.trim().is_empty() && history.commands.len() < max_entries {
+                history.commands.push(line);
+            }
+        }
+        Ok(history)
+    }
+
+    /// Saves the history to disk, through a temporary file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let tmp_path = path.with_extension("tmp");
+        {
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            writeln!(writer, "{}", HISTORY_HEADER)?;
+            for command in &self.commands {
+                writeln!(writer, "{}", command)?;
+            }
+            writer.flush()?;
+        }
+
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Moves `command` to the front, dropping the oldest once full
+    pub fn record(&mut self, command: &str) {
+        let command = command.trim();
+        if command.is_empty() || self.max_entries == 0 {
+            return;
+        }
+        self.commands.retain(|c| c != command);
+        self.commands.insert(0, command.to_string());
+        self.commands.truncate(self.max_entries);
+    }
+
+    /// Returns past commands fuzzy-matching `text`, best first
+    ///
+    /// Equal matches keep their order, most recent first. An empty `text`
+    /// returns every command.
+    pub fn matching(&self, text: &str, matcher: &SkimMatcherV2) -> Vec<&str> {
+        let mut found: Vec<(i64, &str)> = self
+            .commands
+            .iter()
+            .filter_map(|c| {
+                let score = if text.is_empty() {
+                    0
+                } else {
+                    matcher.fuzzy_match(c, text)?
+                };
+                Some((score, c.as_str()))
+            })
+            .collect();
+        found.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        found.into_iter().map(|(_, c)| c).collect()
+    }
+
+    pub fn commands(&self) -> &[String] {
+        &self.commands
+    }
+}
+
+/// Runs the query as a shell command
+pub struct ShellProvider {
+    config: ShellConfig,
+
+    /// Past commands, for completion
+    history: Mutex<CommandHistory>,
+
+    /// Where `history` is saved; None to keep it in memory only
+    history_path: Option<PathBuf>,
+
+    /// The command whose output is shown, if any
+    run: Mutex<Option<Arc<CapturedRun>>>,
+
+    matcher: SkimMatcherV2,
+}
+
+impl ShellProvider {
+    /// Creates the provider with the saved command history
+    pub fn new(config: ShellConfig) -> Self {
+        let history_path = CommandHistory::default_path();
+        let history = match &history_path {
+            Some(path) => CommandHistory::load_or_default(path, config.max_history),
+            None => CommandHistory::new(config.max_history),
+        };
+        Self::with_history(config, history, history_path)
+    }
+
+    /// Creates the provider over an already loaded command history
+    pub fn with_history(
+        config: ShellConfig,
+        history: CommandHistory,
+        history_path: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            config,
+            history: Mutex::new(history),
+            history_path,
+            run: Mutex::new(None),
+            matcher: SkimMatcherV2::default().smart_case(),
+        }
+    }
+
+    /// Returns the command whose output is shown, if any
+    pub fn current_run(&self) -> Option<Arc<CapturedRun>> {
+        self.run.lock().unwrap().clone()
+    }
+
+    fn record(&self, command: &str) {
+        let mut history = self.history.lock().unwrap();
+        history.record(command);
+        if let Some(path) = &self.history_path {
+            if let Err(e) = history.save(path) {
+                log::warn!("Failed to save command history: {}", e);
+            }
+        }
+    }
+
+    fn result(&self, name: &str, description: &str) -> SearchResult {
+        SearchResult {
+            name: name.to_string(),
+            path: PathBuf::new(),
+            result_type: ResultType::Command,
+            score: 0,
+            description: description.to_string(),
+            provider: self.id(),
+            shortcut: None,
+        }
+    }
+
+    /// Results for running `command` and for past commands like it
+    fn command_results(&self, command: &str) -> Vec<SearchResult> {
+        let mut results = Vec::new();
+        if !command.is_empty() {
+            results.push(self.result(command, RUN_IN_TERMINAL));
+            results.push(self.result(command, RUN_AND_CAPTURE));
+        }
+
+        let history = self.history.lock().unwrap();
+        let past = history
+            .matching(command, &self.matcher)
+            .into_iter()
+            .filter(|c| *c != command)
+            .take(MAX_HISTORY_RESULTS);
+        results.extend(past.map(|c| self.result(c, FROM_HISTORY)));
+        results
+    }
+
+    /// The output section for a captured run
+    fn output_section(&self, run: &CapturedRun, output: &Output) -> Section {
+        let status = match output.status {
+            RunStatus::Running => format!("Running… {}", plural_lines(output)),
+            RunStatus::Exited(Some(code)) => {
+                format!("Exited with code {} · {}", code, plural_lines(output))
+            }
+            RunStatus::Exited(None) => format!("Exited · {}", plural_lines(output)),
+            RunStatus::TimedOut => format!(
+                "Timed out after {} s · {}",
+                self.config.timeout_secs,
+                plural_lines(output)
+            ),
+            RunStatus::Killed => format!("Stopped · {}", plural_lines(output)),
+        };
+
+        let mut results = vec![self.result(&status, &run.command)];
+        for line in output.lines.iter().filter(|l| !l.text.is_empty()) {
+            let description = if line.is_error { STDERR } else { "" };
+            results.push(self.result(&line.text, description));
+        }
+
+        Section {
+            provider: self.id(),
+            title: "Output".to_string(),
+            priority: self.priority() + 1,
+            results,
+        }
+    }
+}
+
+/// Describes how many lines a command has written
+fn plural_lines(output: &Output) -> String {
+    let count = output.lines.len() + output.dropped;
+    if count == 1 {
+        "1 line".to_string()
+    } else {
+        format!("{} lines", count)
+    }
+}
+
+impl Provider for ShellProvider {
+    fn id(&self) -> &'static str {
+        "shell"
+    }
+
+    fn title(&self) -> &str {
+        "Shell"
+    }
+
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    fn trigger(&self) -> Trigger {
+        Trigger::Prefix(self.config.prefix.clone())
+    }
+
+    /// Commands are run exactly as typed, quotes and colons included
+    fn wants_filters(&self) -> bool {
+        false
+    }
+
+    /// Offers to run the command, and streams the output of the command
+    /// last run with its output captured while the query still names it
+    fn query(
+        &self,
+        query: &ProviderQuery,
+        cancel: &CancelToken,
+        emit: &(dyn Fn(Vec<Section>) + Sync),
+    ) -> bool {
+        let command = query.text.trim();
+        let commands = self.section(self.command_results(command));
+
+        let run = self.current_run().filter(|run| run.command == command);
+        let Some(run) = run else {
+            emit(vec![commands]);
+            return true;
+        };
+
+        let mut output = run.output();
+        loop {
+            emit(vec![commands.clone(), self.output_section(&run, &output)]);
+            if output.is_finished() {
+                return true;
+            }
+            output = run.wait_for_change(output.generation, Duration::from_millis(100));
+            if cancel.is_cancelled() {
+                return false;
+            }
+        }
+    }
+
+    fn actions(&self, result: &SearchResult) -> Vec<Action> {
+        let command = result.name.clone();
+        match result.description.as_str() {
+            RUN_AND_CAPTURE => vec![
+                Action::RunAndCapture(command.clone()),
+                Action::RunInTerminal(command.clone()),
+                Action::Copy(command),
+            ],
+            RUN_IN_TERMINAL | FROM_HISTORY => vec![
+                Action::RunInTerminal(command.clone()),
+                Action::RunAndCapture(command.clone()),
+                Action::Copy(command),
+            ],
+            _ => {
+                let running = self
+                    .current_run()
+                    .is_some_and(|run| run.output().status == RunStatus::Running);
+                let mut actions = vec![Action::Copy(command)];
+                if running {
+                    actions.push(Action::Kill);
+                }
+                actions
+            }
+        }
+    }
+
+    fn run_action(&self, result: &SearchResult, action: &Action) -> Result<()> {
+        match action {
+            Action::RunInTerminal(command) => {
+                self.record(command);
+                run_in_terminal(&self.config.shell, command)
+            }
+            Action::RunAndCapture(command) => {
+                if let Some(previous) = self.run.lock().unwrap().take() {
+                    previous.kill();
+                }
+                self.record(command);
+                let timeout = (self.config.timeout_secs > 0)
+                    .then(|| Duration::from_secs(self.config.timeout_secs));
+                let run = CapturedRun::start(
+                    &self.config.shell,
+                    command,
+                    timeout,
+                    self.config.max_output_lines,
+                )?;
+                *self.run.lock().unwrap() = Some(run);
+                Ok(())
+            }
+            Action::Kill => {
+                if let Some(run) = self.current_run() {
+                    run.kill();
+                }
+                Ok(())
+            }
+            _ => {
+                let _ = result;
+                action.run()
+            }
+        }
+    }
+
+    /// Completes past commands
+    fn complete(&self, query: &str, result: &SearchResult) -> Option<String> {
+        let _ = query;
+        (result.description == FROM_HISTORY)
+            .then(|| format!("{}{}", self.config.prefix, result.name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::{run_provider, run_provider_sections};
+
+    /// A shell that exists wherever the tests run
+    fn test_shell() -> ShellKind {
+        if cfg!(windows) {
+            ShellKind::Cmd
+        } else {
+            ShellKind::Custom {
+                program: PathBuf::from("sh"),
+                args: vec!["-c".to_string()],
+            }
+        }
+    }
+
+    /// A command that runs for several seconds
+    fn slow_command() -> &'static str {
+        if cfg!(windows) {
+            "ping -n 10 127.0.0.1"
+        } else {
+            "sleep 10"
+        }
+    }
+
+    /// A command whose shell starts a slow process of its own, which
+    /// shares the shell's output
+    fn slow_grandchild_command() -> &'static str {
+        if cfg!(windows) {
+            "ping -n 10 127.0.0.1 & echo done"
+        } else {
+            "sleep 10; echo done"
+        }
+    }
+
+    fn args(process: &Command) -> Vec<String> {
+        process
+            .get_args()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_shell_command() {
+        let process = shell_command(&ShellKind::Cmd, "dir /b", false);
+        assert_eq!(process.get_program(), "cmd.exe");
+        assert_eq!(args(&process), ["/D", "/C", "dir /b"]);
+        assert_eq!(args(&shell_command(&ShellKind::Cmd, "dir", true))[1], "/K");
+
+        let process = shell_command(&ShellKind::PowerShell, "Get-Date", true);
+        assert_eq!(process.get_program(), "powershell.exe");
+        assert_eq!(
+            args(&process),
+            ["-NoProfile", "-NoExit", "-Command", "Get-Date"]
+        );
+
+        let bash = ShellKind::Custom {
+            program: PathBuf::from("bash.exe"),
+            args: vec!["-c".to_string()],
+        };
+        assert_eq!(
+            args(&shell_command(&bash, "ls -la", false)),
+            ["-c", "ls -la"]
+        );
+        assert_eq!(shell_name(&bash), "bash");
+    }
+
+    #[test]
+    fn test_command_history() {
+        let mut history = CommandHistory::new(3);
+        for command in ["ipconfig", "dir /b", "ping localhost", " ipconfig ", ""] {
+            history.record(command);
+        }
+        assert_eq!(history.commands(), ["ipconfig", "ping localhost", "dir /b"]);
+        history.record("whoami");
+        assert_eq!(history.commands(), ["whoami", "ipconfig", "ping localhost"]);
+
+        let matcher = SkimMatcherV2::default().smart_case();
+        assert_eq!(history.matching("ping", &matcher), ["ping localhost"]);
+        assert_eq!(history.matching("", &matcher).len(), 3);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(HISTORY_FILE_NAME);
+        history.save(&path).unwrap();
+        let loaded = CommandHistory::load(&path, 2).unwrap();
+        assert_eq!(loaded.commands(), ["whoami", "ipconfig"]);
+
+        std::fs::write(&path, "something else\nwhoami\n").unwrap();
+        assert!(CommandHistory::load(&path, 3).is_err());
+        assert!(CommandHistory::load_or_default(&path, 3)
+            .commands()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_captures_output() {
+        let run = CapturedRun::start(
+            &test_shell(),
+            "echo hello&& echo oops 1>&2",
+            Some(Duration::from_secs(20)),
+            100,
+        )
+        .unwrap();
+        assert_eq!(
+            run.wait(Duration::from_secs(20)),
+            RunStatus::Exited(Some(0))
+        );
+
+        let mut output = run.output();
+        while !output.is_finished() {
+            output = run.wait_for_change(output.generation, Duration::from_secs(1));
+        }
+        let stdout: Vec<&str> = output
+            .lines
+            .iter()
+            .filter(|l| !l.is_error)
+            .map(|l| l.text.as_str())
+            .collect();
+        let stderr: Vec<&str> = output
+            .lines
+            .iter()
+            .filter(|l| l.is_error)
+            .map(|l| l.text.as_str())
+            .collect();
+        assert_eq!(stdout, ["hello"]);
+        assert_eq!(stderr, ["oops"]);
+    }
+
+    #[test]
+    fn test_timeout_and_kill() {
+        let shell = test_shell();
+        let run = CapturedRun::start(
+            &shell,
+            slow_command(),
+            Some(Duration::from_millis(200)),
+            100,
+        )
+        .unwrap();
+        assert_eq!(run.wait(Duration::from_secs(5)), RunStatus::TimedOut);
+
+        let run = CapturedRun::start(&shell, slow_command(), None, 100).unwrap();
+        assert_eq!(run.wait(Duration::from_millis(100)), RunStatus::Running);
+        run.kill();
+        assert_eq!(run.wait(Duration::from_secs(5)), RunStatus::Killed);
+    }
+
+    #[test]
+    fn test_kill_stops_grandchildren() {
+        let run = CapturedRun::start(&test_shell(), slow_grandchild_command(), None, 100).unwrap();
+        assert_eq!(run.wait(Duration::from_millis(200)), RunStatus::Running);
+        run.kill();
+        assert!(run.output().is_finished());
+
+        // The output closes once the grandchild is gone too
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut output = run.output();
+        while output.open_streams > 0 && Instant::now() < deadline {
+            output = run.wait_for_change(output.generation, Duration::from_millis(100));
+        }
+        assert_eq!(output.open_streams, 0);
+        assert!(output.lines.iter().all(|l| l.text != "done"));
+    }
+
+    #[test]
+    fn test_provider() {
+        let mut history = CommandHistory::new(10);
+        history.record("ping example.com");
+        history.record("ipconfig /all");
+        let config = ShellConfig {
+            shell: test_shell(),
+            ..ShellConfig::default()
+        };
+        let provider = ShellProvider::with_history(config, history, None);
+
+        let found = &run_provider_sections(&provider, ">ip")[0].results;
+        let names: Vec<(&str, &str)> = found
+            .iter()
+            .map(|r| (r.name.as_str(), r.description.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("ip", RUN_IN_TERMINAL),
+                ("ip", RUN_AND_CAPTURE),
+                ("ipconfig /all", FROM_HISTORY),
+                ("ping example.com", FROM_HISTORY),
+            ]
+        );
+        assert_eq!(
+            provider.actions(&found[0])[0],
+            Action::RunInTerminal("ip".to_string())
+        );
+        assert_eq!(
+            provider.complete(">ip", &found[2]).as_deref(),
+            Some(">ipconfig /all")
+        );
+        assert_eq!(provider.complete(">ip", &found[0]), None);
+
+        // Words that look like search filters, and quotes, are part of the
+        // command
+        for command in [
+            r#"findstr "type:x" log.txt"#,
+            "echo size:big",
+            "dir in:",
+            "dir ext:txt modified:foo",
+        ] {
+            let found = run_provider(&provider, &format!(">{}", command));
+            assert_eq!(found[0].name, command);
+            assert_eq!(
+                provider.actions(&found[0])[0],
+                Action::RunInTerminal(command.to_string())
+            );
+        }
+
+        let capture = provider.result("echo done", RUN_AND_CAPTURE);
+        let action = &provider.actions(&capture)[0];
+        assert!(action.keeps_window_open());
+        provider.run_action(&capture, action).unwrap();
+        assert_eq!(provider.history.lock().unwrap().commands()[0], "echo done");
+
+        let found = run_provider_sections(&provider, "> echo done ");
+        assert_eq!(found.len(), 2);
+        let output = &found[1].results;
+        assert!(output[0].name.starts_with("Exited with code 0"));
+        assert_eq!(output[1].name, "done");
+        assert_eq!(
+            provider.actions(&output[1]),
+            [Action::Copy("done".to_string())]
+        );
+    }
+}
@@ -0,0 +1,685 @@
+//! Text snippets for Rustle
+//!
+//! Snippets are canned pieces of text kept in a Markdown file next to the
+//! config file: each `## Name` heading starts a snippet, and everything up
+//! to the next such heading is its body. Queries starting with the snippets
+//! prefix (`snip ` by default) search snippet names and bodies, and
+//! choosing one copies it with its placeholders filled in:
+//!
+//! - `{date}` and `{time}`: the current local date and time
+//! - `{clipboard}`: the text on the clipboard
+//! - `{input:Name}`: a value asked for in the search window
+//!
+//! `{{` and `}}` stand for literal braces. While values are being asked
+//! for, the query holds the snippet name and the values typed so far,
+//! separated by ` › `, so each prompt is an ordinary search.
+
+#![allow(dead_code)]
+
+use crate::clipboard;
+use crate::cliphistory::preview;
+use crate::config::{Config, SnippetsConfig};
+use crate::datetime::civil_from_days;
+use crate::error::Result;
+use crate::provider::{Action, Provider, ProviderQuery, Trigger};
+use crate::search::{CancelToken, ResultType, SearchResult, Section};
+use crate::timezone::{self, Zone};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// File name of the snippets next to the config file
+const SNIPPETS_FILE_NAME: &str = "snippets.md";
+
+/// Separates the snippet name and the values typed so far in the query
+const FIELD_SEPARATOR: &str = " › ";
+
+/// Scheme of the pseudo-paths that identify snippets in search results
+const SNIPPET_SCHEME: &str = "snippet:";
+
+/// Most snippets shown for one query
+const MAX_RESULTS: usize = 20;
+
+/// Written to a new snippets file to show how it works
+const EXAMPLE_SNIPPETS: &str = "# Rustle snippets
+
+Each `## Name` heading starts a snippet; its body is everything up to the
+next one. Placeholders: `{date}`, `{time}`, `{clipboard}` and
+`{input:Name}`, which is asked for when the snippet is used.
+
+## Thanks
+Hi {input:Name},
+
+Thanks for getting in touch. I have looked into this and will get back to
+you by {date}.
+
+Best regards
+";
+
+/// A named piece of text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    pub name: String,
+    pub body: String,
+}
+
+impl Snippet {
+    /// Returns the names of the `{input:Name}` placeholders, in the order
+    /// they first appear
+    pub fn inputs(&self) -> Vec<String> {
+        let mut inputs: Vec<String> = Vec::new();
+        for token in tokens(&self.body) {
+            if let Token::Placeholder(name) = token {
+                if let Some(input) = name.strip_prefix("input:") {
+                    let input = input.trim();
+                    if !inputs.iter().any(|i| i == input) {
+                        inputs.push(input.to_string());
+                    }
+                }
+            }
+        }
+        inputs
+    }
+
+    /// Whether the body has a `{clipboard}` placeholder
+    pub fn uses_clipboard(&self) -> bool {
+        tokens(&self.body)
+            .iter()
+            .any(|token| matches!(token, Token::Placeholder(name) if name.trim() == "clipboard"))
+    }
+}
+
+/// Reads snippets from Markdown
+///
+/// Text before the first `## ` heading is ignored, as are blank lines at
+/// the start and end of each body.
+pub fn parse_snippets(markdown: &str) -> Vec<Snippet> {
+    let mut snippets = Vec::new();
+    let mut current: Option<(String, Vec<&str>)> = None;
+
+    for line in markdown.lines() {
+        if let Some(name) = line.strip_prefix("## ") {
+            snippets.extend(current.take().map(finish_snippet));
+            current = Some((name.trim().to_string(), Vec::new()));
+        } else if let Some((_, body)) = &mut current {
+            body.push(line);
+        }
+    }
+    snippets.extend(current.map(finish_snippet));
+    snippets.retain(|s| !s.name.is_empty());
+    snippets
+}
+
+fn finish_snippet((name, lines): (String, Vec<&str>)) -> Snippet {
+    let start = lines.iter().position(|l| !l.trim().is_empty());
+    let end = lines.iter().rposition(|l| !l.trim().is_empty());
+    let body = match (start, end) {
+        (Some(start), Some(end)) => lines[start..=end].join("\n"),
+        _ => String::new(),
+    };
+    Snippet { name, body }
+}
+
+/// Part of a snippet body
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token<'a> {
+    Text(&'a str),
+    Placeholder(&'a str),
+}
+
+/// Splits a body into literal text and `{placeholders}`
+///
+/// `{{` and `}}` come out as single braces. A `{` without a closing brace
+/// is kept as text.
+fn tokens(body: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = body;
+    while let Some(i) = rest.find(['{', '}']) {
+        if i > 0 {
+            tokens.push(Token::Text(&rest[..i]));
+        }
+        let after = &rest[i + 1..];
+        if rest[i..].starts_with("{{") || rest[i..].starts_with("}}") {
+            tokens.push(Token::Text(&rest[i..i + 1]));
+            rest = &after[1..];
+        } else if rest[i..].starts_with('}') {
+            tokens.push(Token::Text("}"));
+            rest = after;
+        } else {
+            match after.find('}') {
+                Some(end) if !after[..end].contains(['{', '\n']) => {
+                    tokens.push(Token::Placeholder(&after[..end]));
+                    rest = &after[end + 1..];
+                }
+                _ => {
+                    tokens.push(Token::Text("{"));
+                    rest = after;
+                }
+            }
+        }
+    }
+    if !rest.is_empty() {
+        tokens.push(Token::Text(rest));
+    }
+    tokens
+}
+
+/// What placeholders expand to
+pub struct Expansion<'a> {
+    /// Current time in seconds since the Unix epoch, and the zone `{date}`
+    /// and `{time}` are given in; without it they are left as they are
+    pub clock: Option<(i64, &'a Zone)>,
+
+    /// Text on the clipboard; without it `{clipboard}` is left as it is
+    pub clipboard: Option<&'a str>,
+
+    /// Values for `{input:Name}`, by name
+    pub inputs: &'a [(String, String)],
+}
+
+/// Fills in the placeholders of a snippet body
+///
+/// Unknown placeholders, inputs with no value, and placeholders whose value
+/// was not given are left as they are.
+pub fn expand(body: &str, expansion: &Expansion) -> String {
+    let local = expansion.clock.map(|(now, zone)| zone.to_local(now));
+
+    let mut expanded = String::with_capacity(body.len());
+    for token in tokens(body) {
+        match token {
+            Token::Text(text) => expanded.push_str(text),
+            Token::Placeholder(name) => {
+                let value = match (name.trim(), local) {
+                    ("date", Some(local)) => {
+                        let (year, month, day) = civil_from_days(local.div_euclid(86_400));
+                        Some(format!("{:04}-{:02}-{:02}", year, month, day))
+                    }
+                    ("time", Some(local)) => {
+                        let seconds = local.rem_euclid(86_400);
+                        Some(format!("{:02}:{:02}", seconds / 3_600, seconds / 60 % 60))
+                    }
+                    ("clipboard", _) => expansion.clipboard.map(str::to_string),
+                    (other, _) => other.strip_prefix("input:").and_then(|input| {
+                        let input = input.trim();
+                        expansion
+                            .inputs
+                            .iter()
+                            .find(|(name, _)| name == input)
+                            .map(|(_, value)| value.clone())
+                    }),
+                };
+                match value {
+                    Some(value) => expanded.push_str(&value),
+                    None => {
+                        expanded.push('{');
+                        expanded.push_str(name);
+                        expanded.push('}');
+                    }
+                }
+            }
+        }
+    }
+    expanded
+}
+
+/// A snippet being filled in: its name and the values typed so far
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Form {
+    name: String,
+    values: Vec<String>,
+}
+
+impl Form {
+    /// Reads `Name › value › value` as typed after the prefix
+    fn parse(text: &str) -> Form {
+        let mut parts = text.split(FIELD_SEPARATOR.trim());
+        let name = parts.next().unwrap_or_default().trim().to_string();
+        let values = parts.map(|v| v.trim().to_string()).collect();
+        Form { name, values }
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = self.name.clone();
+        for value in &self.values {
+            text.push_str(FIELD_SEPARATOR);
+            text.push_str(value);
+        }
+        text
+    }
+}
+
+/// Searches snippets and copies them with their placeholders filled in
+pub struct SnippetProvider {
+    config: SnippetsConfig,
+
+    /// Where the snippets are kept
+    path: Option<PathBuf>,
+
+    /// Snippets as last read, and the modification time they were read at
+    snippets: Mutex<(Option<SystemTime>, Vec<Snippet>)>,
+
+    matcher: SkimMatcherV2,
+}
+
+impl SnippetProvider {
+    pub fn new(config: SnippetsConfig) -> Self {
+        let path = config.file.clone().or_else(|| {
+            Config::config_file_path()
+                .and_then(|p| p.parent().map(|dir| dir.join(SNIPPETS_FILE_NAME)))
+        });
+        Self {
+            config,
+            path,
+            snippets: Mutex::new((None, Vec::new())),
+            matcher: SkimMatcherV2::default().smart_case(),
+        }
+    }
+
+    /// Returns the snippets, reading the file again if it has changed
+    pub fn snippets(&self) -> Vec<Snippet> {
+        let mut cached = self.snippets.lock().unwrap();
+        let Some(path) = &self.path else {
+            return Vec::new();
+        };
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        if modified != cached.0 || modified.is_none() {
+            let snippets = match std::fs::read_to_string(path) {
+                Ok(markdown) => parse_snippets(&markdown),
+                Err(_) => Vec::new(),
+            };
+            *cached = (modified, snippets);
+        }
+        cached.1.clone()
+    }
+
+    fn find(&self, name: &str) -> Option<Snippet> {
+        self.snippets()
+            .into_iter()
+            .find(|s| s.name.eq_ignore_ascii_case(name))
+    }
+
+    fn result(&self, form: &Form, name: String, description: String) -> SearchResult {
+        SearchResult {
+            name,
+            path: PathBuf::from(format!("{}{}", SNIPPET_SCHEME, form.to_text())),
+            result_type: ResultType::Text,
+            score: 0,
+            description,
+            provider: self.id(),
+            shortcut: None,
+        }
+    }
+
+    /// Returns the snippet being filled in by a result, with its form
+    fn form(&self, result: &SearchResult) -> Option<(Snippet, Form)> {
+        let text = result.path.to_str()?.strip_prefix(SNIPPET_SCHEME)?;
+        let form = Form::parse(text);
+        Some((self.find(&form.name)?, form))
+    }
+
+    /// Returns the query that asks for the next value of `form`, if any
+    /// is still missing
+    fn next_prompt(&self, snippet: &Snippet, form: &Form) -> Option<String> {
+        if form.values.len() >= snippet.inputs().len() {
+            return None;
+        }
+        let mut next = form.clone();
+        next.values.push(String::new());
+        Some(format!("{}{}", self.config.prefix, next.to_text()))
+    }
+
+    /// Expands a snippet with the values typed into `form`, reading the
+    /// clock, time zone and clipboard
+    fn expand(&self, snippet: &Snippet, form: &Form) -> String {
+        let inputs = Self::inputs(snippet, form);
+        let clipboard = if snippet.uses_clipboard() {
+            Some(clipboard::paste_from_clipboard(None).unwrap_or_default())
+        } else {
+            None
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        expand(
+            &snippet.body,
+            &Expansion {
+                clock: Some((now, &timezone::local_zone())),
+                clipboard: clipboard.as_deref(),
+                inputs: &inputs,
+            },
+        )
+    }
+
+    /// Fills in only the values typed into `form`, for showing a snippet
+    /// before it is used
+    fn fill_in(&self, snippet: &Snippet, form: &Form) -> String {
+        expand(
+            &snippet.body,
+            &Expansion {
+                clock: None,
+                clipboard: None,
+                inputs: &Self::inputs(snippet, form),
+            },
+        )
+    }
+
+    fn inputs(snippet: &Snippet, form: &Form) -> Vec<(String, String)> {
+        snippet
+            .inputs()
+            .into_iter()
+            .zip(form.values.iter().cloned())
+            .collect()
+    }
+
+    /// Results for searching snippet names and bodies
+    fn search(&self, text: &str) -> Vec<SearchResult> {
+        let snippets = self.snippets();
+        if snippets.is_empty() {
+            let path = self.path.clone().unwrap_or_default();
+            return vec![SearchResult {
+                name: "Edit snippets".to_string(),
+                description: path.display().to_string(),
+                path,
+                result_type: ResultType::File,
+                score: 0,
+                provider: self.id(),
+                shortcut: None,
+            }];
+        }
+
+        let mut found: Vec<(i64, Snippet)> = snippets
+            .into_iter()
+            .filter_map(|snippet| {
+                let score = if text.is_empty() {
+                    0
+                } else {
+                    let name = self.matcher.fuzzy_match(&snippet.name, text).map(|s| s * 2);
+                    let body = self.matcher.fuzzy_match(&snippet.body, text);
+                    name.max(body)?
+                };
+                Some((score, snippet))
+            })
+            .collect();
+        found.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+
+        found
+            .into_iter()
+            .take(MAX_RESULTS)
+            .map(|(score, snippet)| {
+                let inputs = snippet.inputs();
+                let mut description = preview(&snippet.body);
+                if !inputs.is_empty() {
+                    description = format!("Asks for {} · {}", inputs.join(", "), description);
+                }
+                let form = Form {
+                    name: snippet.name.clone(),
+                    values: Vec::new(),
+                };
+                let mut result = self.result(&form, snippet.name, description);
+                result.score = score;
+                result
+            })
+            .collect()
+    }
+
+    /// The prompt for the value being typed into `form`
+    ///
+    /// Values beyond the snippet's inputs are dropped; a snippet without
+    /// inputs is shown with its placeholders as they are.
+    fn prompt(&self, snippet: &Snippet, mut form: Form) -> Vec<SearchResult> {
+        let inputs = snippet.inputs();
+        form.values.truncate(inputs.len());
+        let field = form.values.len();
+        if field == 0 {
+            let description = preview(&self.fill_in(snippet, &form));
+            return vec![self.result(&form, snippet.name.clone(), description)];
+        }
+        let input = &inputs[field - 1];
+        let value = form.values[field - 1].as_str();
+
+        let name = if value.is_empty() {
+            format!("{}: …", input)
+        } else {
+            format!("{}: {}", input, value)
+        };
+        let description = if field < inputs.len() {
+            format!("{} of {} · Enter for the next value", field, inputs.len())
+        } else {
+            format!("Enter to copy · {}", preview(&self.fill_in(snippet, &form)))
+        };
+        vec![self.result(&form, name, description)]
+    }
+}
+
+impl Provider for SnippetProvider {
+    fn id(&self) -> &'static str {
+        "snippets"
+    }
+
+    fn title(&self) -> &str {
+        "Snippets"
+    }
+
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    fn trigger(&self) -> Trigger {
+        Trigger::Prefix(self.config.prefix.clone())
+    }
+
+    /// Snippet inputs are filled in as typed
+    fn wants_filters(&self) -> bool {
+        false
+    }
+
+    fn query(
+        &self,
+        query: &ProviderQuery,
+        _cancel: &CancelToken,
+        emit: &(dyn Fn(Vec<Section>) + Sync),
+    ) -> bool {
+        let text = query.text.trim();
+        let results = if text.contains(FIELD_SEPARATOR.trim()) {
+            let form = Form::parse(text);
+            match self.find(&form.name) {
+                Some(snippet) => self.prompt(&snippet, form),
+                None => Vec::new(),
+            }
+        } else {
+            self.search(text)
+        };
+
+        emit(vec![self.section(results)]);
+        true
+    }
+
+    /// Asks for the next missing value, or copies the snippet
+    ///
+    /// The actions hold the snippet with only its inputs filled in; the
+    /// rest is expanded by `run_action` when one of them is chosen.
+    fn actions(&self, result: &SearchResult) -> Vec<Action> {
+        let Some((snippet, form)) = self.form(result) else {
+            return vec![Action::Open(result.path.clone())];
+        };
+        if let Some(next) = self.next_prompt(&snippet, &form) {
+            return vec![Action::SetQuery(next)];
+        }
+        let text = self.fill_in(&snippet, &form);
+        vec![Action::Copy(text.clone()), Action::Paste(text)]
+    }
+
+    /// Expands a snippet before copying or pasting it, and creates the
+    /// snippets file with an example before opening it
+    fn run_action(&self, result: &SearchResult, action: &Action) -> Result<()> {
+        if let (Action::Copy(_) | Action::Paste(_), Some((snippet, form))) =
+            (action, self.form(result))
+        {
+            let text = self.expand(&snippet, &form);
+            return match action {
+                Action::Copy(_) => Action::Copy(text),
+                _ => Action::Paste(text),
+            }
+            .run();
+        }
+        if let (Action::Open(path), Some(own)) = (action, &self.path) {
+            if path == own && !path.exists() {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(path, EXAMPLE_SNIPPETS)?;
+            }
+        }
+        action.run()
+    }
+
+    /// Starts filling in a snippet, or moves on to its next value
+    fn complete(&self, _query: &str, result: &SearchResult) -> Option<String> {
+        let (snippet, form) = self.form(result)?;
+        self.next_prompt(&snippet, &form)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::run_provider;
+
+    const NOW: i64 = 1_792_238_400;
+
+    const MARKDOWN: &str = "# My snippets
+
+Notes before the first snippet are ignored.
+
+## Refund
+Hi {input:Name},
+
+your refund for order {input:Order} was issued on {date}.
+Thanks, {input:Name}!
+
+## Address
+
+Main Street 1 {{not a placeholder}}
+
+## Empty
+";
+
+    #[test]
+    fn test_parse_snippets() {
+        let snippets = parse_snippets(MARKDOWN);
+        let names: Vec<&str> = snippets.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["Refund", "Address", "Empty"]);
+        assert!(snippets[0].body.starts_with("Hi {input:Name},\n\nyour"));
+        assert!(snippets[0].body.ends_with("{input:Name}!"));
+        assert_eq!(snippets[1].body, "Main Street 1 {{not a placeholder}}");
+        assert_eq!(snippets[2].body, "");
+
+        assert_eq!(snippets[0].inputs(), ["Name", "Order"]);
+        assert!(snippets[1].inputs().is_empty());
+    }
+
+    #[test]
+    fn test_expand() {
+        let inputs = vec![("Name".to_string(), "Ada".to_string())];
+        let zone = Zone::find("Tokyo").unwrap();
+        let expansion = Expansion {
+            clock: Some((NOW, &zone)),
+            clipboard: Some("#4711"),
+            inputs: &inputs,
+        };
+        assert_eq!(
+            expand("{input:Name} on {date} at {time}: {clipboard}", &expansion),
+            "Ada on 2026-10-17 at 21:00: #4711"
+        );
+        assert_eq!(expand("{ clipboard }", &expansion), "#4711");
+        assert_eq!(
+            expand("{{literal}} {input:Other} {unknown} { x", &expansion),
+            "{literal} {input:Other} {unknown} { x"
+        );
+        assert_eq!(expand("a } b", &expansion), "a } b");
+
+        let unread = Expansion {
+            clock: None,
+            clipboard: None,
+            inputs: &inputs,
+        };
+        assert_eq!(
+            expand("{input:Name} on { date } at {time}: {clipboard}", &unread),
+            "Ada on { date } at {time}: {clipboard}"
+        );
+    }
+
+    #[test]
+    fn test_uses_clipboard() {
+        let snippet = |body: &str| Snippet {
+            name: "Test".to_string(),
+            body: body.to_string(),
+        };
+        assert!(snippet("Paste: {clipboard}").uses_clipboard());
+        assert!(snippet("Paste: { clipboard }").uses_clipboard());
+        assert!(!snippet("Paste: {{clipboard}}").uses_clipboard());
+        assert!(!snippet("Paste: {date}").uses_clipboard());
+    }
+
+    #[test]
+    fn test_fill_in_flow() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(SNIPPETS_FILE_NAME);
+        let provider = SnippetProvider::new(SnippetsConfig {
+            file: Some(path.clone()),
+            ..SnippetsConfig::default()
+        });
+
+        // A missing file offers to create it
+        let found = run_provider(&provider, "snip ");
+        assert_eq!(found[0].name, "Edit snippets");
+        provider
+            .run_action(&found[0], &Action::Open(path.clone()))
+            .unwrap();
+        assert_eq!(provider.snippets()[0].name, "Thanks");
+
+        std::fs::write(&path, MARKDOWN).unwrap();
+        let found = run_provider(&provider, "snip main street");
+        assert_eq!(found[0].name, "Address");
+        assert_eq!(
+            provider.actions(&found[0])[0],
+            Action::Copy("Main Street 1 {not a placeholder}".to_string())
+        );
+
+        let found = run_provider(&provider, "snip refund");
+        assert_eq!(found[0].name, "Refund");
+        assert!(found[0].description.starts_with("Asks for Name, Order · "));
+        let first = "snip Refund › ".to_string();
+        assert_eq!(
+            provider.actions(&found[0]),
+            [Action::SetQuery(first.clone())]
+        );
+        assert_eq!(provider.complete("snip ref", &found[0]), Some(first));
+
+        let found = run_provider(&provider, "snip Refund › Ada");
+        assert_eq!(found[0].name, "Name: Ada");
+        assert_eq!(
+            provider.actions(&found[0]),
+            [Action::SetQuery("snip Refund › Ada › ".to_string())]
+        );
+
+        let found = run_provider(&provider, "snip Refund › Ada › ");
+        assert_eq!(found[0].name, "Order: …");
+        let found = run_provider(&provider, "snip Refund › Ada › 42");
+        let Action::Copy(text) = &provider.actions(&found[0])[0] else {
+            panic!("expected the expanded snippet to be copied");
+        };
+        assert_eq!(
+            text,
+            "Hi Ada,\n\nyour refund for order 42 was issued on {date}.\nThanks, Ada!"
+        );
+
+        let found = run_provider(&provider, "snip Address › ");
+        assert_eq!(found[0].name, "Address");
+
+        assert!(run_provider(&provider, "snip Nothing › x").is_empty());
+    }
+}
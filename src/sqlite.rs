@@ -0,0 +1,653 @@
+//! Minimal read-only SQLite reader for Rustle
+//!
+//! Firefox keeps bookmarks and history in `places.sqlite`, and Chromium
+//! keeps history in an SQLite file called `History`. This reader walks the
+//! table b-trees of a database held in memory and decodes their records,
+//! which is all Rustle needs to list bookmarks and visited pages; there is
+//! no SQL. Pages committed to a write-ahead log (`-wal` file) but not yet
+//! checkpointed are read from the log, so recent changes are seen too.
+//!
+//! The file format is described at <https://www.sqlite.org/fileformat.html>.
+
+#![allow(dead_code)]
+
+use crate::error::{Result, RustleError};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// First bytes of every database file
+const MAGIC: &[u8] = b"SQLite format 3\0";
+
+/// Size of the database header at the start of page 1
+const HEADER_LEN: usize = 100;
+
+/// Size of the write-ahead log header
+const WAL_HEADER_LEN: usize = 32;
+
+/// Size of the header before each page in the write-ahead log
+const WAL_FRAME_HEADER_LEN: usize = 24;
+
+/// Magic number of a log whose checksums are little-endian; the
+/// big-endian variant is one more
+const WAL_MAGIC_LE: u32 = 0x377f_0682;
+
+/// Page type of an interior page of a table b-tree
+const INTERIOR_TABLE_PAGE: u8 = 5;
+
+/// Page type of a leaf page of a table b-tree
+const LEAF_TABLE_PAGE: u8 = 13;
+
+/// Root page of the schema table
+const SCHEMA_ROOT_PAGE: u32 = 1;
+
+/// A value stored in a table
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+}
+
+/// Every row of one table
+#[derive(Debug, Clone)]
+pub struct Table {
+    /// Column names, as declared
+    pub columns: Vec<String>,
+
+    /// Values of each row, one per column, in rowid order
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    /// Returns the position of a column, matched case-insensitively
+    pub fn column(&self, name: &str) -> Option<usize> {
+        self.columns
+            .iter()
+            .position(|c| c.eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the rows with their values looked up by column name
+    pub fn iter(&self) -> impl Iterator<Item = Row<'_>> {
+        self.rows.iter().map(move |values| Row {
+            table: self,
+            values,
+        })
+    }
+}
+
+/// One row of a table
+#[derive(Debug, Clone, Copy)]
+pub struct Row<'a> {
+    table: &'a Table,
+    values: &'a [Value],
+}
+
+impl<'a> Row<'a> {
+    /// Returns the value of a column; unknown columns read as NULL
+    pub fn get(&self, column: &str) -> &'a Value {
+        self.table
+            .column(column)
+            .and_then(|i| self.values.get(i))
+            .unwrap_or(&Value::Null)
+    }
+
+    pub fn text(&self, column: &str) -> Option<&'a str> {
+        self.get(column).as_str()
+    }
+
+    pub fn integer(&self, column: &str) -> Option<i64> {
+        self.get(column).as_i64()
+    }
+}
+
+/// A database file, and its write-ahead log if it has one
+pub struct Database {
+    data: Vec<u8>,
+    wal: Vec<u8>,
+
+    /// Offset in `wal` of the latest committed copy of each logged page
+    wal_pages: HashMap<u32, usize>,
+
+    page_size: usize,
+
+    /// Bytes of each page used for b-tree content
+    usable_size: usize,
+
+    /// Text encoding: 1 for UTF-8, 2 for UTF-16LE, 3 for UTF-16BE
+    encoding: u32,
+}
+
+impl Database {
+    /// Reads a database file and its `-wal` file, if there is one
+    pub fn open(path: &Path) -> Result<Database> {
+        let data = std::fs::read(path)?;
+        let mut wal_path = path.as_os_str().to_owned();
+        wal_path.push("-wal");
+        let wal = std::fs::read(PathBuf::from(wal_path)).ok();
+        Database::from_bytes(data, wal)
+    }
+
+    /// Reads a database held in memory
+    pub fn from_bytes(data: Vec<u8>, wal: Option<Vec<u8>>) -> Result<Database> {
+        if data.len() < HEADER_LEN || !data.starts_with(MAGIC) {
+            return Err(corrupt("not an SQLite database"));
+        }
+        let page_size = match read_u16(&data, 16)? {
+            1 => 65_536,
+            size => size as usize,
+        };
+        if page_size < 512 || !page_size.is_power_of_two() {
+            return Err(corrupt("bad page size"));
+        }
+        let reserved = data[20] as usize;
+        let mut db = Database {
+            wal: Vec::new(),
+            wal_pages: HashMap::new(),
+            page_size,
+            usable_size: page_size - reserved,
+            encoding: read_u32(&data, 56)?,
+            data,
+        };
+        if let Some(wal) = wal {
+            db.wal_pages = committed_wal_pages(&wal, page_size);
+            db.wal = wal;
+        }
+        Ok(db)
+    }
+
+    /// Reads every row of the table called `name`
+    pub fn table(&self, name: &str) -> Result<Table> {
+        let schema = self.records(SCHEMA_ROOT_PAGE)?;
+        let entry = schema
+            .into_iter()
+            .find(|(_, values)| {
+                values.first().and_then(Value::as_str) == Some("table")
+                    && values
+                        .get(1)
+                        .and_then(Value::as_str)
+                        .is_some_and(|n| n.eq_ignore_ascii_case(name))
+            })
+            .ok_or_else(|| corrupt(&format!("no table called {}", name)))?;
+
+        let root = entry
+            .1
+            .get(3)
+            .and_then(Value::as_i64)
+            .ok_or_else(|| corrupt("table without a root page"))?;
+        let sql = entry.1.get(4).and_then(Value::as_str).unwrap_or_default();
+        let (columns, rowid_alias) = parse_columns(sql);
+        if sql.to_uppercase().contains("WITHOUT ROWID") {
+            return Err(corrupt("WITHOUT ROWID tables are not supported"));
+        }
+
+        let rows = self
+            .records(u32::try_from(root).map_err(|_| corrupt("bad root page"))?)?
+            .into_iter()
+            .map(|(rowid, mut values)| {
+                // Columns added later are missing from older records
+                values.resize(columns.len(), Value::Null);
+                if let Some(i) = rowid_alias {
+                    values[i] = Value::Integer(rowid);
+                }
+                values
+            })
+            .collect();
+        Ok(Table { columns, rows })
+    }
+
+    /// Returns the rowid and values of every record in a table b-tree
+    fn records(&self, root: u32) -> Result<Vec<(i64, Vec<Value>)>> {
+        let mut records = Vec::new();
+        let mut pending = vec![root];
+        let mut visited = 0usize;
+        // No b-tree has more pages than the file and its log together
+        let max_pages = self.data.len() / self.page_size + self.wal_pages.len();
+
+        while let Some(number) = pending.pop() {
+            visited += 1;
+            if visited > max_pages {
+                return Err(corrupt("b-tree loops back on itself"));
+            }
+            let page = self.page(number)?;
+            // Page 1 starts with the database header
+            let header = if number == 1 { HEADER_LEN } else { 0 };
+            let page_type = *page.get(header).ok_or_else(|| corrupt("short page"))?;
+            let cell_count = read_u16(page, header + 3)? as usize;
+
+            match page_type {
+                LEAF_TABLE_PAGE => {
+                    for i in 0..cell_count {
+                        let offset = read_u16(page, header + 8 + i * 2)? as usize;
+                        records.push(self.leaf_cell(page, offset)?);
+                    }
+                }
+                INTERIOR_TABLE_PAGE => {
+                    // Children are pushed right to left so rows come out in order
+                    pending.push(read_u32(page, header + 8)?);
+                    for i in (0..cell_count).rev() {
+                        let offset = read_u16(page, header + 12 + i * 2)? as usize;
+                        pending.push(read_u32(page, offset)?);
+                    }
+                }
+                _ => return Err(corrupt("not a table b-tree page")),
+            }
+        }
+        Ok(records)
+    }
+
+    /// Decodes a cell of a table leaf page, following any overflow pages
+    fn leaf_cell(&self, page: &[u8], offset: usize) -> Result<(i64, Vec<Value>)> {
+        let (payload_len, n) = read_varint(page, offset)?;
+        let (rowid, m) = read_varint(page, offset + n)?;
+        let start = offset + n + m;
+        // No payload can be longer than the files holding it
+        if payload_len > (self.data.len() + self.wal.len()) as u64 {
+            return Err(corrupt("cell payload is longer than the database"));
+        }
+        let payload_len = payload_len as usize;
+
+        let local_len = self.local_payload_len(payload_len);
+        let local = page
+            .get(start..start + local_len)
+            .ok_or_else(|| corrupt("cell runs past its page"))?;
+        let payload = if local_len == payload_len {
+            std::borrow::Cow::Borrowed(local)
+        } else {
+            let mut payload = Vec::with_capacity(payload_len);
+            payload.extend_from_slice(local);
+            let mut next = read_u32(page, start + local_len)?;
+            while payload.len() < payload_len {
+                if next == 0 {
+                    return Err(corrupt("overflow chain ends early"));
+                }
+                let overflow = self.page(next)?;
+                let take = (payload_len - payload.len()).min(self.usable_size - 4);
+                payload.extend_from_slice(
+                    overflow
+                        .get(4..4 + take)
+                        .ok_or_else(|| corrupt("short overflow page"))?,
+                );
+                next = read_u32(overflow, 0)?;
+            }
+            std::borrow::Cow::Owned(payload)
+        };
+
+        Ok((rowid as i64, self.decode_record(&payload)?))
+    }
+
+    /// Returns how much of a payload is stored on the leaf page itself
+    fn local_payload_len(&self, payload_len: usize) -> usize {
+        let usable = self.usable_size;
+        let max_local = usable - 35;
+        if payload_len <= max_local {
+            return payload_len;
+        }
+        let min_local = (usable - 12) * 32 / 255 - 23;
+        let local = min_local + (payload_len - min_local) % (usable - 4);
+        if local <= max_local {
+            local
+        } else {
+            min_local
+        }
+    }
+
+    /// Decodes a record: a header of serial types, then the values
+    fn decode_record(&self, payload: &[u8]) -> Result<Vec<Value>> {
+        let (header_len, n) = read_varint(payload, 0)?;
+        let header_len = header_len as usize;
+        let mut pos = n;
+        let mut body = header_len;
+        let mut values = Vec::new();
+
+        while pos < header_len {
+            let (serial_type, n) = read_varint(payload, pos)?;
+            pos += n;
+            let len = match serial_type {
+                0 | 8 | 9 => 0,
+                1..=4 => serial_type as usize,
+                5 => 6,
+                6 | 7 => 8,
+                t if t >= 12 => (t as usize - 12) / 2,
+                _ => return Err(corrupt("reserved serial type")),
+            };
+            let end = body
+                .checked_add(len)
+                .ok_or_else(|| corrupt("record runs past its payload"))?;
+            let bytes = payload
+                .get(body..end)
+                .ok_or_else(|| corrupt("record runs past its payload"))?;
+            body = end;
+
+            values.push(match serial_type {
+                0 => Value::Null,
+                1..=6 => {
+                    // Big-endian two's complement, sign-extended
+                    let first = (bytes[0] as i8) as i64;
+                    Value::Integer(bytes[1..].iter().fold(first, |v, &b| (v << 8) | b as i64))
+                }
+                7 => Value::Real(f64::from_be_bytes(bytes.try_into().unwrap())),
+                8 => Value::Integer(0),
+                9 => Value::Integer(1),
+                t if t % 2 == 0 => Value::Blob(bytes.to_vec()),
+                _ => Value::Text(self.decode_text(bytes)),
+            });
+        }
+        Ok(values)
+    }
+
+    fn decode_text(&self, bytes: &[u8]) -> String {
+        let units = |from: fn([u8; 2]) -> u16| -> Vec<u16> {
+            bytes.chunks_exact(2).map(|c| from([c[0], c[1]])).collect()
+        };
+        match self.encoding {
+            2 => String::from_utf16_lossy(&units(u16::from_le_bytes)),
+            3 => String::from_utf16_lossy(&units(u16::from_be_bytes)),
+            _ => String::from_utf8_lossy(bytes).into_owned(),
+        }
+    }
+
+    /// Returns a page by its 1-based number, preferring the log's copy
+    fn page(&self, number: u32) -> Result<&[u8]> {
+        let (data, start) = match self.wal_pages.get(&number) {
+            Some(&offset) => (&self.wal, offset),
+            None => {
+                let index = (number as usize)
+                    .checked_sub(1)
+                    .ok_or_else(|| corrupt("page 0 does not exist"))?;
+                (&self.data, index * self.page_size)
+            }
+        };
+        data.get(start..start + self.page_size)
+            .ok_or_else(|| corrupt(&format!("page {} is past the end", number)))
+    }
+}
+
+/// Finds the latest committed copy of each page in a write-ahead log
+///
+/// Frames count only while their salts match the log header and their
+/// running checksum holds, and only up to the last commit frame; anything
+/// after that is a transaction that was never finished.
+fn committed_wal_pages(wal: &[u8], page_size: usize) -> HashMap<u32, usize> {
+    let mut pages = HashMap::new();
+    if wal.len() < WAL_HEADER_LEN {
+        return pages;
+    }
+    let (Ok(magic), Ok(wal_page_size)) = (read_u32(wal, 0), read_u32(wal, 8)) else {
+        return pages;
+    };
+    if magic & !1 != WAL_MAGIC_LE || wal_page_size as usize != page_size {
+        return pages;
+    }
+    let big_endian = magic & 1 == 1;
+    let salts = &wal[16..24];
+
+    let mut checksum = wal_checksum((0, 0), &wal[..24], big_endian);
+    if (read_u32(wal, 24).ok(), read_u32(wal, 28).ok()) != (Some(checksum.0), Some(checksum.1)) {
+        return pages;
+    }
+
+    let mut uncommitted = Vec::new();
+    let mut pos = WAL_HEADER_LEN;
+    while let Some(frame) = wal.get(pos..pos + WAL_FRAME_HEADER_LEN + page_size) {
+        if &frame[8..16] != salts {
+            break;
+        }
+        checksum = wal_checksum(checksum, &frame[..8], big_endian);
+        checksum = wal_checksum(checksum, &frame[WAL_FRAME_HEADER_LEN..], big_endian);
+        let stored = (read_u32(frame, 16).ok(), read_u32(frame, 20).ok());
+        if stored != (Some(checksum.0), Some(checksum.1)) {
+            break;
+        }
+
+        let page = read_u32(frame, 0).unwrap_or(0);
+        uncommitted.push((page, pos + WAL_FRAME_HEADER_LEN));
+        // A non-zero database size marks the last frame of a transaction
+        if read_u32(frame, 4).unwrap_or(0) != 0 {
+            pages.extend(uncommitted.drain(..));
+        }
+        pos += WAL_FRAME_HEADER_LEN + page_size;
+    }
+    pages
+}
+
+/// Continues the write-ahead log's running checksum over `data`
+fn wal_checksum(mut sum: (u32, u32), data: &[u8], big_endian: bool) -> (u32, u32) {
+    for chunk in data.chunks_exact(8) {
+        let word = |b: &[u8]| {
+            let b = [b[0], b[1], b[2], b[3]];
+            if big_endian {
+                u32::from_be_bytes(b)
+            } else {
+                u32::from_le_bytes(b)
+            }
+        };
+        sum.0 = sum.0.wrapping_add(word(&chunk[..4])).wrapping_add(sum.1);
+        sum.1 = sum.1.wrapping_add(word(&chunk[4..])).wrapping_add(sum.0);
+    }
+    sum
+}
+
+/// Reads the column names of a `CREATE TABLE` statement
+///
+/// Also returns which column, if any, is an alias for the rowid: one
+/// declared `INTEGER PRIMARY KEY`, whose value is stored as the rowid
+/// rather than in the record.
+fn parse_columns(sql: &str) -> (Vec<String>, Option<usize>) {
+    let (Some(open), Some(close)) = (sql.find('('), sql.rfind(')')) else {
+        return (Vec::new(), None);
+    };
+    if close <= open {
+        return (Vec::new(), None);
+    }
+
+    let mut definitions = Vec::new();
+    let mut depth = 0;
+    let mut quote = None;
+    let mut start = open + 1;
+    for (i, c) in sql[open + 1..close].char_indices() {
+        let i = open + 1 + i;
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'' | '`') => quote = Some(c),
+            (None, '[') => quote = Some(']'),
+            (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                definitions.push(&sql[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    definitions.push(&sql[start..close]);
+
+    let mut columns = Vec::new();
+    let mut rowid_alias = None;
+    for definition in definitions {
+        let Some((name, rest)) = split_column_name(definition.trim()) else {
+            continue;
+        };
+        if matches!(
+            name.to_uppercase().as_str(),
+            "CONSTRAINT" | "PRIMARY" | "UNIQUE" | "CHECK" | "FOREIGN"
+        ) && !definition.trim().starts_with(['"', '\'', '`', '['])
+        {
+            continue;
+        }
+
+        let rest = rest.split_whitespace().collect::<Vec<_>>().join(" ");
+        let rest = rest.to_uppercase();
+        if rest.starts_with("INTEGER PRIMARY KEY") && !rest.contains("PRIMARY KEY DESC") {
+            rowid_alias = Some(columns.len());
+        }
+        columns.push(name.to_string());
+    }
+    (columns, rowid_alias)
+}
+
+/// Splits a column definition into the column's name, without quotes,
+/// and the rest
+fn split_column_name(definition: &str) -> Option<(&str, &str)> {
+    let close = match definition.chars().next()? {
+        '"' => '"',
+        '\'' => '\'',
+        '`' => '`',
+        '[' => ']',
+        _ => {
+            let end = definition
+                .find(char::is_whitespace)
+                .unwrap_or(definition.len());
+            return Some((&definition[..end], &definition[end..]));
+        }
+    };
+    let end = definition[1..].find(close)? + 1;
+    Some((&definition[1..end], &definition[end + 1..]))
+}
+
+/// Reads a variable-length integer, returning it and its length in bytes
+fn read_varint(data: &[u8], pos: usize) -> Result<(u64, usize)> {
+    let mut value = 0u64;
+    for i in 0..9 {
+        let b = *data
+            .get(pos + i)
+            .ok_or_else(|| corrupt("varint runs past the end"))?;
+        if i == 8 {
+            return Ok(((value << 8) | b as u64, 9));
+        }
+        value = (value << 7) | (b & 0x7f) as u64;
+        if b & 0x80 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+    unreachable!()
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Result<u16> {
+    data.get(pos..pos + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .ok_or_else(|| corrupt("unexpected end of page"))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Result<u32> {
+    data.get(pos..pos + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| corrupt("unexpected end of page"))
+}
+
+fn corrupt(msg: &str) -> RustleError {
+    RustleError::browser_data_error(format!("invalid SQLite database: {}", msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[u8] = include_bytes!("../tests/fixtures/browsers/sample.sqlite");
+    const SAMPLE_WAL: &[u8] = include_bytes!("../tests/fixtures/browsers/sample.sqlite-wal");
+
+    #[test]
+    fn test_reads_tables() {
+        let db = Database::from_bytes(SAMPLE.to_vec(), None).unwrap();
+        let table = db.table("items").unwrap();
+        assert_eq!(table.columns, ["id", "name", "weight", "data", "note"]);
+
+        // Enough rows to need interior pages, in rowid order
+        assert_eq!(table.rows.len(), 300);
+        let ids: Vec<i64> = table.iter().filter_map(|r| r.integer("id")).collect();
+        assert_eq!(ids, (1..=300).collect::<Vec<i64>>());
+
+        let row = table.iter().nth(6).unwrap();
+        assert_eq!(row.text("name"), Some("item 7 ✓"));
+        assert_eq!(row.get("weight"), &Value::Real(3.5));
+        assert_eq!(row.get("data"), &Value::Blob(vec![0, 1, 2]));
+        assert_eq!(row.get("note"), &Value::Null);
+        assert_eq!(row.get("missing"), &Value::Null);
+
+        // A value too big for its page continues on overflow pages
+        let long = table.iter().find(|r| r.integer("id") == Some(42)).unwrap();
+        assert_eq!(long.text("note"), Some("x".repeat(3000).as_str()));
+        let negative = table.iter().find(|r| r.integer("id") == Some(43)).unwrap();
+        assert_eq!(negative.integer("weight"), Some(-70_000));
+
+        assert!(db.table("missing").is_err());
+    }
+
+    #[test]
+    fn test_reads_committed_wal_pages() {
+        let without = Database::from_bytes(SAMPLE.to_vec(), None).unwrap();
+        let name = |db: &Database| {
+            let table = db.table("items").unwrap();
+            let row = table.iter().next().unwrap();
+            row.text("name").map(str::to_string)
+        };
+        assert_eq!(name(&without).as_deref(), Some("item 1 ✓"));
+
+        let with = Database::from_bytes(SAMPLE.to_vec(), Some(SAMPLE_WAL.to_vec())).unwrap();
+        assert_eq!(name(&with).as_deref(), Some("renamed in the log"));
+
+        // A log with a broken frame is ignored from that frame on
+        let mut torn = SAMPLE_WAL.to_vec();
+        torn[WAL_HEADER_LEN + WAL_FRAME_HEADER_LEN] ^= 0xff;
+        let torn = Database::from_bytes(SAMPLE.to_vec(), Some(torn)).unwrap();
+        assert_eq!(name(&torn).as_deref(), Some("item 1 ✓"));
+
+        // So is a log cut off inside its header
+        for len in [12, 20, WAL_HEADER_LEN - 1] {
+            let short = Database::from_bytes(SAMPLE.to_vec(), Some(SAMPLE_WAL[..len].to_vec()));
+            assert_eq!(name(&short.unwrap()).as_deref(), Some("item 1 ✓"));
+        }
+    }
+
+    #[test]
+    fn test_parse_columns() {
+        let (columns, alias) = parse_columns(
+            "CREATE TABLE t (\"a b\" TEXT, id INTEGER PRIMARY KEY AUTOINCREMENT, \
+             c NUMERIC(10, 2) DEFAULT (1), [d], CONSTRAINT pk UNIQUE (c, d))",
+        );
+        assert_eq!(columns, ["a b", "id", "c", "d"]);
+        assert_eq!(alias, Some(1));
+        assert_eq!(parse_columns("CREATE TABLE t (x INT PRIMARY KEY)").1, None);
+    }
+
+    #[test]
+    fn test_rejects_non_databases() {
+        assert!(Database::from_bytes(b"not a database".to_vec(), None).is_err());
+        assert_eq!(read_varint(&[0x81, 0x00], 0).unwrap(), (128, 2));
+        assert_eq!(read_varint(&[0xff; 9], 0).unwrap(), (u64::MAX, 9));
+    }
+
+    #[test]
+    fn test_rejects_oversized_record_lengths() {
+        let db = Database::from_bytes(SAMPLE.to_vec(), None).unwrap();
+
+        // A header claiming to be 2^64 - 1 bytes long, then a 1-byte integer
+        let mut payload = vec![0xff; 9];
+        payload.push(1);
+        assert!(db.decode_record(&payload).is_err());
+
+        // A text value claiming to be nearly 2^63 bytes long
+        let mut payload = vec![11];
+        payload.extend([0xff; 9]);
+        payload.push(13);
+        assert!(db.decode_record(&payload).is_err());
+    }
+}
@@ -0,0 +1,964 @@
+//! Time zones for Rustle
+//!
+//! Zones are described by POSIX TZ rules (`EST5EDT,M3.2.0,M11.1.0`): a
+//! standard offset and, for zones with daylight saving time, the daylight
+//! offset and the yearly rules for when it starts and ends. The rules of
+//! common zones are embedded below so conversions work offline; they are
+//! the current rules from the IANA database, so times before a zone last
+//! changed its rules may be off.
+//!
+//! Zones can be looked up by IANA name (`Europe/Paris`), city (`Tokyo`),
+//! country (`Japan`) or abbreviation (`PST`). Abbreviations are fixed
+//! offsets, so `PST` is UTC−8 even in summer; names follow daylight saving.
+
+#![allow(dead_code)]
+
+use crate::datetime::{civil_from_days, days_from_civil, days_in_month, weekday, SECONDS_PER_DAY};
+use crate::error::{Result, RustleError};
+
+/// Embedded zone rules: IANA name and POSIX TZ rule
+///
+/// The city shown for a zone is the last part of its name.
+static ZONES: &[(&str, &str)] = &[
+    ("Etc/UTC", "UTC0"),
+    // Europe
+    ("Europe/London", "GMT0BST,M3.5.0/1,M10.5.0"),
+    ("Europe/Dublin", "IST-1GMT0,M10.5.0,M3.5.0/1"),
+    ("Europe/Lisbon", "WET0WEST,M3.5.0/1,M10.5.0"),
+    ("Atlantic/Reykjavik", "GMT0"),
+    ("Atlantic/Azores", "<-01>1<+00>,M3.5.0/0,M10.5.0/1"),
+    ("Europe/Paris", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Berlin", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Madrid", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Rome", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Amsterdam", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Brussels", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Vienna", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Zurich", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Stockholm", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Oslo", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Copenhagen", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Prague", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Warsaw", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Budapest", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Belgrade", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Athens", "EET-2EEST,M3.5.0/3,M10.5.0/4"),
+    ("Europe/Helsinki", "EET-2EEST,M3.5.0/3,M10.5.0/4"),
+    ("Europe/Kyiv", "EET-2EEST,M3.5.0/3,M10.5.0/4"),
+    ("Europe/Bucharest", "EET-2EEST,M3.5.0/3,M10.5.0/4"),
+    ("Europe/Sofia", "EET-2EEST,M3.5.0/3,M10.5.0/4"),
+    ("Europe/Riga", "EET-2EEST,M3.5.0/3,M10.5.0/4"),
+    ("Europe/Tallinn", "EET-2EEST,M3.5.0/3,M10.5.0/4"),
+    ("Europe/Vilnius", "EET-2EEST,M3.5.0/3,M10.5.0/4"),
+    ("Europe/Istanbul", "<+03>-3"),
+    ("Europe/Minsk", "<+03>-3"),
+    ("Europe/Moscow", "MSK-3"),
+    // Africa
+    ("Africa/Casablanca", "<+01>-1"),
+    ("Africa/Lagos", "WAT-1"),
+    ("Africa/Cairo", "EET-2EEST,M4.5.5/0,M10.5.4/24"),
+    ("Africa/Johannesburg", "SAST-2"),
+    ("Africa/Nairobi", "EAT-3"),
+    // Asia
+    ("Asia/Jerusalem", "IST-2IDT,M3.4.4/26,M10.5.0"),
+    ("Asia/Riyadh", "<+03>-3"),
+    ("Asia/Baghdad", "<+03>-3"),
+    ("Asia/Tehran", "<+0330>-3:30"),
+    ("Asia/Dubai", "<+04>-4"),
+    ("Asia/Kabul", "<+0430>-4:30"),
+    ("Asia/Karachi", "PKT-5"),
+    ("Asia/Tashkent", "<+05>-5"),
+    ("Asia/Almaty", "<+05>-5"),
+    ("Asia/Yekaterinburg", "<+05>-5"),
+    ("Asia/Kolkata", "IST-5:30"),
+    ("Asia/Colombo", "<+0530>-5:30"),
+    ("Asia/Kathmandu", "<+0545>-5:45"),
+    ("Asia/Dhaka", "<+06>-6"),
+    ("Asia/Yangon", "<+0630>-6:30"),
+    ("Asia/Bangkok", "<+07>-7"),
+    ("Asia/Ho_Chi_Minh", "<+07>-7"),
+    ("Asia/Jakarta", "WIB-7"),
+    ("Asia/Novosibirsk", "<+07>-7"),
+    ("Asia/Shanghai", "CST-8"),
+    ("Asia/Hong_Kong", "HKT-8"),
+    ("Asia/Taipei", "CST-8"),
+    ("Asia/Singapore", "<+08>-8"),
+    ("Asia/Kuala_Lumpur", "<+08>-8"),
+    ("Asia/Manila", "PST-8"),
+    ("Asia/Seoul", "KST-9"),
+    ("Asia/Tokyo", "JST-9"),
+    ("Asia/Vladivostok", "<+10>-10"),
+    // Australia and the Pacific
+    ("Australia/Perth", "AWST-8"),
+    ("Australia/Darwin", "ACST-9:30"),
+    ("Australia/Adelaide", "ACST-9:30ACDT,M10.1.0,M4.1.0/3"),
+    ("Australia/Brisbane", "AEST-10"),
+    ("Australia/Sydney", "AEST-10AEDT,M10.1.0,M4.1.0/3"),
+    ("Australia/Melbourne", "AEST-10AEDT,M10.1.0,M4.1.0/3"),
+    ("Australia/Hobart", "AEST-10AEDT,M10.1.0,M4.1.0/3"),
+    ("Pacific/Guam", "ChST-10"),
+    ("Pacific/Auckland", "NZST-12NZDT,M9.5.0,M4.1.0/3"),
+    ("Pacific/Fiji", "<+12>-12"),
+    (
+        "Pacific/Chatham",
+        "<+1245>-12:45<+1345>,M9.5.0/2:45,M4.1.0/3:45",
+    ),
+    ("Pacific/Kiritimati", "<+14>-14"),
+    ("Pacific/Pago_Pago", "SST11"),
+    ("Pacific/Honolulu", "HST10"),
+    // Americas
+    ("America/Anchorage", "AKST9AKDT,M3.2.0,M11.1.0"),
+    ("America/Los_Angeles", "PST8PDT,M3.2.0,M11.1.0"),
+    ("America/Vancouver", "PST8PDT,M3.2.0,M11.1.0"),
+    ("America/Tijuana", "PST8PDT,M3.2.0,M11.1.0"),
+    ("America/Phoenix", "MST7"),
+    ("America/Denver", "MST7MDT,M3.2.0,M11.1.0"),
+    ("America/Edmonton", "MST7MDT,M3.2.0,M11.1.0"),
+    ("America/Chicago", "CST6CDT,M3.2.0,M11.1.0"),
+    ("America/Winnipeg", "CST6CDT,M3.2.0,M11.1.0"),
+    ("America/Mexico_City", "CST6"),
+    ("America/New_York", "EST5EDT,M3.2.0,M11.1.0"),
+    ("America/Toronto", "EST5EDT,M3.2.0,M11.1.0"),
+    ("America/Detroit", "EST5EDT,M3.2.0,M11.1.0"),
+    ("America/Havana", "CST5CDT,M3.2.0/0,M11.1.0/1"),
+    ("America/Bogota", "<-05>5"),
+    ("America/Lima", "<-05>5"),
+    ("America/Caracas", "<-04>4"),
+    ("America/Halifax", "AST4ADT,M3.2.0,M11.1.0"),
+    ("America/St_Johns", "NST3:30NDT,M3.2.0,M11.1.0"),
+    ("America/Santiago", "<-04>4<-03>,M9.1.6/24,M4.1.6/24"),
+    ("America/Sao_Paulo", "<-03>3"),
+    ("America/Argentina/Buenos_Aires", "<-03>3"),
+    ("America/Montevideo", "<-03>3"),
+    ("America/Nuuk", "<-02>2<-01>,M3.5.0/-1,M10.5.0/0"),
+];
+
+/// Other names for embedded zones: countries, and cities sharing a zone
+static ALIASES: &[(&str, &str)] = &[
+    ("uk", "Europe/London"),
+    ("england", "Europe/London"),
+    ("scotland", "Europe/London"),
+    ("edinburgh", "Europe/London"),
+    ("manchester", "Europe/London"),
+    ("ireland", "Europe/Dublin"),
+    ("portugal", "Europe/Lisbon"),
+    ("iceland", "Atlantic/Reykjavik"),
+    ("france", "Europe/Paris"),
+    ("germany", "Europe/Berlin"),
+    ("munich", "Europe/Berlin"),
+    ("frankfurt", "Europe/Berlin"),
+    ("hamburg", "Europe/Berlin"),
+    ("spain", "Europe/Madrid"),
+    ("barcelona", "Europe/Madrid"),
+    ("italy", "Europe/Rome"),
+    ("milan", "Europe/Rome"),
+    ("netherlands", "Europe/Amsterdam"),
+    ("belgium", "Europe/Brussels"),
+    ("austria", "Europe/Vienna"),
+    ("switzerland", "Europe/Zurich"),
+    ("geneva", "Europe/Zurich"),
+    ("sweden", "Europe/Stockholm"),
+    ("norway", "Europe/Oslo"),
+    ("denmark", "Europe/Copenhagen"),
+    ("czechia", "Europe/Prague"),
+    ("poland", "Europe/Warsaw"),
+    ("hungary", "Europe/Budapest"),
+    ("serbia", "Europe/Belgrade"),
+    ("greece", "Europe/Athens"),
+    ("finland", "Europe/Helsinki"),
+    ("ukraine", "Europe/Kyiv"),
+    ("kiev", "Europe/Kyiv"),
+    ("romania", "Europe/Bucharest"),
+    ("bulgaria", "Europe/Sofia"),
+    ("turkey", "Europe/Istanbul"),
+    ("russia", "Europe/Moscow"),
+    ("morocco", "Africa/Casablanca"),
+    ("nigeria", "Africa/Lagos"),
+    ("egypt", "Africa/Cairo"),
+    ("south africa", "Africa/Johannesburg"),
+    ("cape town", "Africa/Johannesburg"),
+    ("kenya", "Africa/Nairobi"),
+    ("israel", "Asia/Jerusalem"),
+    ("tel aviv", "Asia/Jerusalem"),
+    ("saudi arabia", "Asia/Riyadh"),
+    ("iraq", "Asia/Baghdad"),
+    ("iran", "Asia/Tehran"),
+    ("uae", "Asia/Dubai"),
+    ("abu dhabi", "Asia/Dubai"),
+    ("afghanistan", "Asia/Kabul"),
+    ("pakistan", "Asia/Karachi"),
+    ("lahore", "Asia/Karachi"),
+    ("india", "Asia/Kolkata"),
+    ("mumbai", "Asia/Kolkata"),
+    ("delhi", "Asia/Kolkata"),
+    ("new delhi", "Asia/Kolkata"),
+    ("bangalore", "Asia/Kolkata"),
+    ("bengaluru", "Asia/Kolkata"),
+    ("calcutta", "Asia/Kolkata"),
+    ("sri lanka", "Asia/Colombo"),
+    ("nepal", "Asia/Kathmandu"),
+    ("bangladesh", "Asia/Dhaka"),
+    ("myanmar", "Asia/Yangon"),
+    ("thailand", "Asia/Bangkok"),
+    ("vietnam", "Asia/Ho_Chi_Minh"),
+    ("hanoi", "Asia/Ho_Chi_Minh"),
+    ("saigon", "Asia/Ho_Chi_Minh"),
+    ("indonesia", "Asia/Jakarta"),
+    ("china", "Asia/Shanghai"),
+    ("beijing", "Asia/Shanghai"),
+    ("shenzhen", "Asia/Shanghai"),
+    ("taiwan", "Asia/Taipei"),
+    ("malaysia", "Asia/Kuala_Lumpur"),
+    ("philippines", "Asia/Manila"),
+    ("korea", "Asia/Seoul"),
+    ("south korea", "Asia/Seoul"),
+    ("japan", "Asia/Tokyo"),
+    ("osaka", "Asia/Tokyo"),
+    ("canberra", "Australia/Sydney"),
+    ("new zealand", "Pacific/Auckland"),
+    ("wellington", "Pacific/Auckland"),
+    ("hawaii", "Pacific/Honolulu"),
+    ("alaska", "America/Anchorage"),
+    ("california", "America/Los_Angeles"),
+    ("la", "America/Los_Angeles"),
+    ("san francisco", "America/Los_Angeles"),
+    ("sf", "America/Los_Angeles"),
+    ("seattle", "America/Los_Angeles"),
+    ("portland", "America/Los_Angeles"),
+    ("arizona", "America/Phoenix"),
+    ("calgary", "America/Edmonton"),
+    ("texas", "America/Chicago"),
+    ("houston", "America/Chicago"),
+    ("dallas", "America/Chicago"),
+    ("austin", "America/Chicago"),
+    ("mexico", "America/Mexico_City"),
+    ("nyc", "America/New_York"),
+    ("boston", "America/New_York"),
+    ("washington", "America/New_York"),
+    ("miami", "America/New_York"),
+    ("atlanta", "America/New_York"),
+    ("philadelphia", "America/New_York"),
+    ("montreal", "America/Toronto"),
+    ("ottawa", "America/Toronto"),
+    ("cuba", "America/Havana"),
+    ("colombia", "America/Bogota"),
+    ("peru", "America/Lima"),
+    ("venezuela", "America/Caracas"),
+    ("chile", "America/Santiago"),
+    ("brazil", "America/Sao_Paulo"),
+    ("são paulo", "America/Sao_Paulo"),
+    ("rio", "America/Sao_Paulo"),
+    ("rio de janeiro", "America/Sao_Paulo"),
+    ("argentina", "America/Argentina/Buenos_Aires"),
+    ("uruguay", "America/Montevideo"),
+    ("greenland", "America/Nuuk"),
+];
+
+/// Zones following their region's daylight saving time
+static GENERIC_ABBREVIATIONS: &[(&str, &str)] = &[
+    ("pt", "America/Los_Angeles"),
+    ("mt", "America/Denver"),
+    ("ct", "America/Chicago"),
+    ("et", "America/New_York"),
+];
+
+/// Abbreviations with their fixed offset in minutes east of UTC
+///
+/// Where an abbreviation is ambiguous the most common meaning wins, so
+/// `CST` is US Central and `IST` is India.
+static ABBREVIATIONS: &[(&str, i32)] = &[
+    ("UTC", 0),
+    ("GMT", 0),
+    ("WET", 0),
+    ("WEST", 60),
+    ("BST", 60),
+    ("CET", 60),
+    ("CEST", 120),
+    ("WAT", 60),
+    ("EET", 120),
+    ("EEST", 180),
+    ("CAT", 120),
+    ("SAST", 120),
+    ("MSK", 180),
+    ("EAT", 180),
+    ("PKT", 300),
+    ("IST", 330),
+    ("ICT", 420),
+    ("WIB", 420),
+    ("HKT", 480),
+    ("SGT", 480),
+    ("AWST", 480),
+    ("JST", 540),
+    ("KST", 540),
+    ("ACST", 570),
+    ("ACDT", 630),
+    ("AEST", 600),
+    ("AEDT", 660),
+    ("NZST", 720),
+    ("NZDT", 780),
+    ("HST", -600),
+    ("AKST", -540),
+    ("AKDT", -480),
+    ("PST", -480),
+    ("PDT", -420),
+    ("MST", -420),
+    ("MDT", -360),
+    ("CST", -360),
+    ("CDT", -300),
+    ("EST", -300),
+    ("EDT", -240),
+    ("AST", -240),
+    ("ADT", -180),
+    ("NST", -210),
+    ("NDT", -150),
+    ("BRT", -180),
+    ("ART", -180),
+];
+
+/// Windows time zone keys and the embedded zone each corresponds to
+static WINDOWS_ZONES: &[(&str, &str)] = &[
+    ("UTC", "Etc/UTC"),
+    ("GMT Standard Time", "Europe/London"),
+    ("Greenwich Standard Time", "Atlantic/Reykjavik"),
+    ("Azores Standard Time", "Atlantic/Azores"),
+    ("Morocco Standard Time", "Africa/Casablanca"),
+    ("W. Europe Standard Time", "Europe/Berlin"),
+    ("Romance Standard Time", "Europe/Paris"),
+    ("Central Europe Standard Time", "Europe/Budapest"),
+    ("Central European Standard Time", "Europe/Warsaw"),
+    ("W. Central Africa Standard Time", "Africa/Lagos"),
+    ("GTB Standard Time", "Europe/Bucharest"),
+    ("FLE Standard Time", "Europe/Helsinki"),
+    ("Egypt Standard Time", "Africa/Cairo"),
+    ("South Africa Standard Time", "Africa/Johannesburg"),
+    ("Israel Standard Time", "Asia/Jerusalem"),
+    ("Turkey Standard Time", "Europe/Istanbul"),
+    ("Belarus Standard Time", "Europe/Minsk"),
+    ("Russian Standard Time", "Europe/Moscow"),
+    ("E. Africa Standard Time", "Africa/Nairobi"),
+    ("Arab Standard Time", "Asia/Riyadh"),
+    ("Arabic Standard Time", "Asia/Baghdad"),
+    ("Iran Standard Time", "Asia/Tehran"),
+    ("Arabian Standard Time", "Asia/Dubai"),
+    ("Afghanistan Standard Time", "Asia/Kabul"),
+    ("Pakistan Standard Time", "Asia/Karachi"),
+    ("West Asia Standard Time", "Asia/Tashkent"),
+    ("Central Asia Standard Time", "Asia/Almaty"),
+    ("Ekaterinburg Standard Time", "Asia/Yekaterinburg"),
+    ("India Standard Time", "Asia/Kolkata"),
+    ("Sri Lanka Standard Time", "Asia/Colombo"),
+    ("Nepal Standard Time", "Asia/Kathmandu"),
+    ("Bangladesh Standard Time", "Asia/Dhaka"),
+    ("Myanmar Standard Time", "Asia/Yangon"),
+    ("SE Asia Standard Time", "Asia/Bangkok"),
+    ("N. Central Asia Standard Time", "Asia/Novosibirsk"),
+    ("China Standard Time", "Asia/Shanghai"),
+    ("Taipei Standard Time", "Asia/Taipei"),
+    ("Singapore Standard Time", "Asia/Singapore"),
+    ("W. Australia Standard Time", "Australia/Perth"),
+    ("Tokyo Standard Time", "Asia/Tokyo"),
+    ("Korea Standard Time", "Asia/Seoul"),
+    ("AUS Central Standard Time", "Australia/Darwin"),
+    ("Cen. Australia Standard Time", "Australia/Adelaide"),
+    ("E. Australia Standard Time", "Australia/Brisbane"),
+    ("AUS Eastern Standard Time", "Australia/Sydney"),
+    ("Tasmania Standard Time", "Australia/Hobart"),
+    ("West Pacific Standard Time", "Pacific/Guam"),
+    ("Vladivostok Standard Time", "Asia/Vladivostok"),
+    ("New Zealand Standard Time", "Pacific/Auckland"),
+    ("Fiji Standard Time", "Pacific/Fiji"),
+    ("Chatham Islands Standard Time", "Pacific/Chatham"),
+    ("Line Islands Standard Time", "Pacific/Kiritimati"),
+    ("UTC-11", "Pacific/Pago_Pago"),
+    ("Hawaiian Standard Time", "Pacific/Honolulu"),
+    ("Alaskan Standard Time", "America/Anchorage"),
+    ("Pacific Standard Time", "America/Los_Angeles"),
+    ("Pacific Standard Time (Mexico)", "America/Tijuana"),
+    ("US Mountain Standard Time", "America/Phoenix"),
+    ("Mountain Standard Time", "America/Denver"),
+    ("Central Standard Time", "America/Chicago"),
+    ("Central Standard Time (Mexico)", "America/Mexico_City"),
+    ("Eastern Standard Time", "America/New_York"),
+    ("Cuba Standard Time", "America/Havana"),
+    ("SA Pacific Standard Time", "America/Bogota"),
+    ("Venezuela Standard Time", "America/Caracas"),
+    ("Atlantic Standard Time", "America/Halifax"),
+    ("Newfoundland Standard Time", "America/St_Johns"),
+    ("Pacific SA Standard Time", "America/Santiago"),
+    ("E. South America Standard Time", "America/Sao_Paulo"),
+    ("Argentina Standard Time", "America/Argentina/Buenos_Aires"),
+    ("Montevideo Standard Time", "America/Montevideo"),
+    ("Greenland Standard Time", "America/Nuuk"),
+];
+
+/// A UTC offset and the abbreviation shown while it applies
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Period {
+    abbreviation: String,
+
+    /// Seconds east of UTC
+    offset: i32,
+}
+
+/// When daylight saving time starts or ends: the `week`th `weekday` of
+/// `month` (week 5 being the last), `time` seconds after local midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Transition {
+    month: u32,
+    week: u32,
+
+    /// 0 is Sunday
+    weekday: u32,
+
+    time: i32,
+}
+
+impl Transition {
+    /// Returns the local time of the transition in `year`, in seconds
+    /// since the epoch, measured in the offset in effect before it
+    fn local_time(&self, year: i64) -> i64 {
+        let first = days_from_civil(year, self.month, 1);
+        let mut day = first + ((self.weekday + 7 - weekday(first)) % 7) as i64;
+        day += 7 * (self.week as i64 - 1);
+        let month_end = first + days_in_month(year, self.month) as i64;
+        while day >= month_end {
+            day -= 7;
+        }
+        day * SECONDS_PER_DAY + self.time as i64
+    }
+}
+
+/// The offsets of a zone and when each applies
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    standard: Period,
+
+    /// Daylight saving offset, with when it starts and ends
+    daylight: Option<(Period, Transition, Transition)>,
+}
+
+impl Rule {
+    /// Creates a rule for a fixed offset in seconds east of UTC
+    pub fn fixed(abbreviation: &str, offset: i32) -> Rule {
+        Rule {
+            standard: Period {
+                abbreviation: abbreviation.to_string(),
+                offset,
+            },
+            daylight: None,
+        }
+    }
+
+    /// Parses a POSIX TZ rule such as `CET-1CEST,M3.5.0,M10.5.0/3`
+    ///
+    /// Only the `Mm.w.d` form of transition dates is supported, which is
+    /// what every current zone uses.
+    pub fn parse(spec: &str) -> Result<Rule> {
+        let invalid = || RustleError::date_error(format!("bad time zone rule {}", spec));
+        let mut cursor = Cursor {
+            chars: spec.chars().collect(),
+            pos: 0,
+        };
+
+        let standard = cursor.period(None).ok_or_else(invalid)?;
+        if cursor.done() {
+            return Ok(Rule {
+                standard,
+                daylight: None,
+            });
+        }
+
+        let daylight = cursor
+            .period(Some(standard.offset + 3600))
+            .ok_or_else(invalid)?;
+        let start = cursor.transition().ok_or_else(invalid)?;
+        let end = cursor.transition().ok_or_else(invalid)?;
+        if !cursor.done() {
+            return Err(invalid());
+        }
+        Ok(Rule {
+            standard,
+            daylight: Some((daylight, start, end)),
+        })
+    }
+
+    /// Returns the period in effect at `utc` seconds since the epoch
+    fn period_at(&self, utc: i64) -> &Period {
+        let Some((daylight, start, end)) = &self.daylight else {
+            return &self.standard;
+        };
+
+        let local_day = (utc + self.standard.offset as i64).div_euclid(SECONDS_PER_DAY);
+        let (year, _, _) = civil_from_days(local_day);
+        let start = start.local_time(year) - self.standard.offset as i64;
+        let end = end.local_time(year) - daylight.offset as i64;
+
+        // In the southern hemisphere daylight time spans the new year
+        let in_daylight = if start < end {
+            start <= utc && utc < end
+        } else {
+            utc >= start || utc < end
+        };
+        if in_daylight {
+            daylight
+        } else {
+            &self.standard
+        }
+    }
+}
+
+/// Reads POSIX TZ rules
+struct Cursor {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Cursor {
+    fn done(&self) -> bool {
+        self.pos >= self.chars.len()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    /// Reads an abbreviation and its offset, which may be left out and
+    /// default to `default_offset`
+    fn period(&mut self, default_offset: Option<i32>) -> Option<Period> {
+        let start = self.pos;
+        let abbreviation = if self.peek() == Some('<') {
+            while self.peek().is_some_and(|c| c != '>') {
+                self.pos += 1;
+            }
+            self.pos += 1;
+            self.chars[start + 1..self.pos - 1].iter().collect()
+        } else {
+            while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+                self.pos += 1;
+            }
+            self.chars[start..self.pos].iter().collect::<String>()
+        };
+        if abbreviation.len() < 3 || self.pos > self.chars.len() {
+            return None;
+        }
+
+        // POSIX offsets count hours west of UTC
+        let offset = match (self.peek(), default_offset) {
+            (None | Some(','), Some(default)) => default,
+            _ => -self.offset()?,
+        };
+        Some(Period {
+            abbreviation,
+            offset,
+        })
+    }
+
+    /// Reads `[+-]hh[:mm[:ss]]` as seconds
+    fn offset(&mut self) -> Option<i32> {
+        let sign = match self.peek() {
+            Some('-') => -1,
+            Some('+') => 1,
+            _ => 0,
+        };
+        if sign != 0 {
+            self.pos += 1;
+        }
+
+        let mut seconds = 0;
+        for (i, scale) in [3600, 60, 1].into_iter().enumerate() {
+            if i > 0 {
+                if self.peek() != Some(':') {
+                    break;
+                }
+                self.pos += 1;
+            }
+            seconds += self.number()? * scale;
+        }
+        Some(if sign < 0 { -seconds } else { seconds })
+    }
+
+    /// Reads `,Mm.w.d[/time]`
+    fn transition(&mut self) -> Option<Transition> {
+        if self.peek() != Some(',') || self.chars.get(self.pos + 1) != Some(&'M') {
+            return None;
+        }
+        self.pos += 2;
+        let month = self.number()? as u32;
+        self.expect('.')?;
+        let week = self.number()? as u32;
+        self.expect('.')?;
+        let weekday = self.number()? as u32;
+        let time = if self.peek() == Some('/') {
+            self.pos += 1;
+            self.offset()?
+        } else {
+            7200
+        };
+
+        let valid = (1..=12).contains(&month) && (1..=5).contains(&week) && weekday < 7;
+        valid.then_some(Transition {
+            month,
+            week,
+            weekday,
+            time,
+        })
+    }
+
+    fn expect(&mut self, c: char) -> Option<()> {
+        (self.peek() == Some(c)).then(|| self.pos += 1)
+    }
+
+    fn number(&mut self) -> Option<i32> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        digits.parse().ok()
+    }
+}
+
+/// A named time zone
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Zone {
+    /// Name shown to the user, such as `Tokyo` or `PST`
+    pub name: String,
+
+    rule: Rule,
+}
+
+impl Zone {
+    pub fn new(name: impl Into<String>, rule: Rule) -> Zone {
+        Zone {
+            name: name.into(),
+            rule,
+        }
+    }
+
+    /// Returns Coordinated Universal Time
+    pub fn utc() -> Zone {
+        Zone::new("UTC", Rule::fixed("UTC", 0))
+    }
+
+    /// Looks up a zone by IANA name, city, country or abbreviation
+    ///
+    /// `UTC+5:30` style offsets are accepted too. Case and extra spaces
+    /// are ignored.
+    pub fn find(name: &str) -> Option<Zone> {
+        let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+        let lower = name.to_lowercase();
+
+        if let Some(zone) = fixed_offset(&lower) {
+            return Some(zone);
+        }
+        if let Some(&(abbreviation, minutes)) = ABBREVIATIONS
+            .iter()
+            .find(|(abbreviation, _)| abbreviation.eq_ignore_ascii_case(&lower))
+        {
+            return Some(Zone::new(
+                abbreviation,
+                Rule::fixed(abbreviation, minutes * 60),
+            ));
+        }
+
+        let id = ZONES
+            .iter()
+            .map(|&(id, _)| id)
+            .find(|id| id.to_lowercase() == lower || city(id).to_lowercase() == lower)
+            .or_else(|| {
+                ALIASES
+                    .iter()
+                    .chain(GENERIC_ABBREVIATIONS)
+                    .find(|(alias, _)| *alias == lower)
+                    .map(|&(_, id)| id)
+            })?;
+        Zone::embedded(id)
+    }
+
+    /// Returns the embedded zone with the given IANA name
+    fn embedded(id: &str) -> Option<Zone> {
+        let (id, spec) = ZONES.iter().find(|(zone, _)| *zone == id)?;
+        Rule::parse(spec).ok().map(|rule| Zone::new(city(id), rule))
+    }
+
+    /// Returns the offset from UTC at `utc`, in seconds
+    pub fn offset_at(&self, utc: i64) -> i32 {
+        self.rule.period_at(utc).offset
+    }
+
+    /// Returns the abbreviation in use at `utc`, such as `CEST`
+    ///
+    /// Numeric abbreviations from the IANA database are shown as offsets,
+    /// so `+0530` becomes `UTC+05:30`.
+    pub fn abbreviation_at(&self, utc: i64) -> String {
+        let period = self.rule.period_at(utc);
+        if period.abbreviation.starts_with(['+', '-']) {
+            format_offset(period.offset)
+        } else {
+            period.abbreviation.clone()
+        }
+    }
+
+    /// Converts seconds since the epoch in UTC to local time in this zone
+    pub fn to_local(&self, utc: i64) -> i64 {
+        utc + self.offset_at(utc) as i64
+    }
+
+    /// Converts local time in this zone to seconds since the epoch in UTC
+    ///
+    /// A time repeated when clocks go back is the first of the two, and
+    /// a time skipped when clocks go forward is read with the offset from
+    /// before the change, so 02:30 on a spring-forward night is 03:30.
+    pub fn to_utc(&self, local: i64) -> i64 {
+        let mut offsets = vec![self.rule.standard.offset];
+        if let Some((daylight, _, _)) = &self.rule.daylight {
+            offsets.push(daylight.offset);
+        }
+
+        let valid = offsets
+            .iter()
+            .map(|&offset| local - offset as i64)
+            .filter(|&utc| self.to_local(utc) == local)
+            .min();
+        valid.unwrap_or_else(|| {
+            let before = offsets.iter().min().copied().unwrap_or(0);
+            local - before as i64
+        })
+    }
+}
+
+/// Returns the city part of an IANA name, such as `New York`
+fn city(id: &str) -> String {
+    id.rsplit('/').next().unwrap_or(id).replace('_', " ")
+}
+
+/// Parses `utc+5`, `gmt-3:30` or `utc` as a fixed offset
+fn fixed_offset(lower: &str) -> Option<Zone> {
+    let rest = lower
+        .strip_prefix("utc")
+        .or_else(|| lower.strip_prefix("gmt"))?
+        .replace(' ', "");
+    if rest.is_empty() {
+        return None;
+    }
+
+    let (sign, rest) = match rest.split_at(1) {
+        ("+", rest) => (1, rest),
+        ("-" | "−", rest) => (-1, rest),
+        _ => return None,
+    };
+    let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+    let hours: i32 = hours.parse().ok()?;
+    let minutes: i32 = minutes.parse().ok()?;
+    if hours > 14 || minutes >= 60 {
+        return None;
+    }
+
+    let offset = sign * (hours * 3600 + minutes * 60);
+    let name = format_offset(offset);
+    Some(Zone::new(name.clone(), Rule::fixed(&name, offset)))
+}
+
+/// Formats an offset in seconds as `UTC+05:30`
+pub fn format_offset(offset: i32) -> String {
+    let sign = if offset < 0 { '−' } else { '+' };
+    let minutes = offset.abs() / 60;
+    format!("UTC{}{:02}:{:02}", sign, minutes / 60, minutes % 60)
+}
+
+/// Returns the time zone Windows is set to, or the one in the `TZ`
+/// environment variable elsewhere, falling back to UTC
+pub fn local_zone() -> Zone {
+    #[cfg(windows)]
+    {
+        if let Some(zone) = native::local_zone() {
+            return zone;
+        }
+    }
+
+    std::env::var("TZ")
+        .ok()
+        .and_then(|tz| {
+            Zone::find(&tz).or_else(|| Rule::parse(&tz).ok().map(|r| Zone::new("Local", r)))
+        })
+        .unwrap_or_else(Zone::utc)
+}
+
+#[cfg(windows)]
+mod native {
+    use super::{format_offset, Period, Rule, Transition, Zone, WINDOWS_ZONES};
+    use crate::utils::from_wide_string;
+    use windows::Win32::Foundation::SYSTEMTIME;
+    use windows::Win32::System::Time::{
+        GetDynamicTimeZoneInformation, DYNAMIC_TIME_ZONE_INFORMATION, TIME_ZONE_ID_INVALID,
+    };
+
+    /// Reads the system time zone
+    ///
+    /// Zones Windows shares with the embedded database use its rules and
+    /// abbreviations; others are built from the offsets Windows reports.
+    pub fn local_zone() -> Option<Zone> {
+        let mut info = DYNAMIC_TIME_ZONE_INFORMATION::default();
+        if unsafe { GetDynamicTimeZoneInformation(&mut info) } == TIME_ZONE_ID_INVALID {
+            return None;
+        }
+
+        // Bias is in minutes west of UTC
+        let standard = -(info.Bias + info.StandardBias) * 60;
+        let mut rule = Rule::fixed(&format_offset(standard), standard);
+        if info.DynamicDaylightTimeDisabled.as_bool() {
+            return Some(Zone::new("Local", rule));
+        }
+
+        let key = from_wide_string(&info.TimeZoneKeyName);
+        let embedded = WINDOWS_ZONES
+            .iter()
+            .find(|(windows, _)| *windows == key)
+            .and_then(|(_, id)| Zone::embedded(id));
+        if let Some(mut zone) = embedded {
+            zone.name = "Local".to_string();
+            return Some(zone);
+        }
+
+        if let (Some(start), Some(end)) = (
+            transition(&info.DaylightDate),
+            transition(&info.StandardDate),
+        ) {
+            let daylight = -(info.Bias + info.DaylightBias) * 60;
+            let period = Period {
+                abbreviation: format_offset(daylight),
+                offset: daylight,
+            };
+            rule.daylight = Some((period, start, end));
+        }
+        Some(Zone::new("Local", rule))
+    }
+
+    /// Converts a recurring transition date; Windows marks one by leaving
+    /// the year out and stores the week in `wDay`
+    fn transition(date: &SYSTEMTIME) -> Option<Transition> {
+        if date.wMonth == 0 || date.wYear != 0 {
+            return None;
+        }
+        Some(Transition {
+            month: date.wMonth as u32,
+            week: date.wDay as u32,
+            weekday: date.wDayOfWeek as u32,
+            time: date.wHour as i32 * 3600 + date.wMinute as i32 * 60,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Seconds since the epoch for a UTC date and time
+    fn utc(year: i64, month: u32, day: u32, hour: i64, minute: i64) -> i64 {
+        days_from_civil(year, month, day) * SECONDS_PER_DAY + hour * 3600 + minute * 60
+    }
+
+    #[test]
+    fn test_embedded_rules_parse() {
+        for (id, spec) in ZONES {
+            assert!(Rule::parse(spec).is_ok(), "{} {}", id, spec);
+        }
+        for (alias, id) in ALIASES
+            .iter()
+            .chain(GENERIC_ABBREVIATIONS)
+            .chain(WINDOWS_ZONES)
+        {
+            assert!(Zone::embedded(id).is_some(), "{} -> {}", alias, id);
+        }
+        assert!(Rule::parse("EST5EDT,M3.2.0").is_err());
+        assert!(Rule::parse("X5").is_err());
+    }
+
+    #[test]
+    fn test_find() {
+        assert_eq!(Zone::find("Tokyo").unwrap().name, "Tokyo");
+        assert_eq!(Zone::find("new  york").unwrap().name, "New York");
+        assert_eq!(
+            Zone::find("America/Los_Angeles").unwrap().name,
+            "Los Angeles"
+        );
+        assert_eq!(Zone::find("nyc").unwrap().name, "New York");
+        assert_eq!(Zone::find("pst").unwrap().offset_at(0), -8 * 3600);
+        assert_eq!(Zone::find("UTC+5:30").unwrap().offset_at(0), 19_800);
+        assert_eq!(Zone::find("gmt-3").unwrap().name, "UTC−03:00");
+        assert!(Zone::find("Atlantis").is_none());
+        assert!(Zone::find("utc+15").is_none());
+    }
+
+    #[test]
+    fn test_northern_transitions() {
+        // New York: 2026-03-08 02:00 EST and 2026-11-01 02:00 EDT
+        let new_york = Zone::find("New York").unwrap();
+        let start = utc(2026, 3, 8, 7, 0);
+        assert_eq!(new_york.offset_at(start - 1), -5 * 3600);
+        assert_eq!(new_york.offset_at(start), -4 * 3600);
+        assert_eq!(new_york.abbreviation_at(start), "EDT");
+        let end = utc(2026, 11, 1, 6, 0);
+        assert_eq!(new_york.offset_at(end - 1), -4 * 3600);
+        assert_eq!(new_york.offset_at(end), -5 * 3600);
+
+        // London and Paris change at 01:00 UTC on the last Sunday
+        let london = Zone::find("London").unwrap();
+        let paris = Zone::find("Paris").unwrap();
+        let start = utc(2026, 3, 29, 1, 0);
+        assert_eq!(london.abbreviation_at(start - 1), "GMT");
+        assert_eq!(london.abbreviation_at(start), "BST");
+        assert_eq!(paris.abbreviation_at(start - 1), "CET");
+        assert_eq!(paris.abbreviation_at(start), "CEST");
+        assert_eq!(paris.abbreviation_at(utc(2026, 10, 25, 1, 0)), "CET");
+    }
+
+    #[test]
+    fn test_southern_and_unusual_transitions() {
+        // Sydney is on daylight time over the new year
+        let sydney = Zone::find("Sydney").unwrap();
+        assert_eq!(sydney.abbreviation_at(utc(2026, 1, 15, 0, 0)), "AEDT");
+        assert_eq!(sydney.abbreviation_at(utc(2026, 7, 15, 0, 0)), "AEST");
+        // 2026-04-05 03:00 AEDT
+        let end = utc(2026, 4, 4, 16, 0);
+        assert_eq!(sydney.offset_at(end - 1), 11 * 3600);
+        assert_eq!(sydney.offset_at(end), 10 * 3600);
+
+        // Israel starts on the Friday before the last Sunday of March
+        let jerusalem = Zone::find("Jerusalem").unwrap();
+        let start = utc(2026, 3, 27, 0, 0);
+        assert_eq!(jerusalem.abbreviation_at(start - 1), "IST");
+        assert_eq!(jerusalem.abbreviation_at(start), "IDT");
+
+        // Nuuk changes at -01:00 local time, the evening before
+        let nuuk = Zone::find("Nuuk").unwrap();
+        let start = utc(2026, 3, 29, 1, 0);
+        assert_eq!(nuuk.abbreviation_at(start - 1), "UTC−02:00");
+        assert_eq!(nuuk.abbreviation_at(start), "UTC−01:00");
+
+        // Ireland's standard time is its summer time
+        let dublin = Zone::find("Dublin").unwrap();
+        assert_eq!(dublin.abbreviation_at(utc(2026, 7, 1, 0, 0)), "IST");
+        assert_eq!(dublin.abbreviation_at(utc(2026, 12, 1, 0, 0)), "GMT");
+    }
+
+    #[test]
+    fn test_local_times_around_transitions() {
+        let new_york = Zone::find("New York").unwrap();
+        let local = |day, hour, minute| utc(2026, 3, day, hour, minute);
+
+        // Skipped: 02:30 is read as 02:30 EST, shown as 03:30 EDT
+        let skipped = new_york.to_utc(local(8, 2, 30));
+        assert_eq!(skipped, utc(2026, 3, 8, 7, 30));
+        assert_eq!(new_york.to_local(skipped), local(8, 3, 30));
+
+        // Repeated: 01:30 on 1 November happens twice; the first is EDT
+        let repeated = utc(2026, 11, 1, 1, 30);
+        assert_eq!(new_york.to_utc(repeated), utc(2026, 11, 1, 5, 30));
+
+        // Ordinary times round-trip
+        for time in [local(7, 12, 0), local(9, 0, 0), utc(2026, 11, 1, 3, 0)] {
+            assert_eq!(new_york.to_local(new_york.to_utc(time)), time);
+        }
+    }
+}
@@ -0,0 +1,871 @@
+//! Offline unit conversion for Rustle
+//!
+//! Queries such as `5 km to mi`, `72 f in c`, `3.5 GiB in MB` or
+//! `2h30m in minutes` are answered from the unit table below, without
+//! going online. Units are recognised by symbol (case-sensitive, so `Mb`
+//! and `MB` differ) or by name (case-insensitive, singular or plural), and
+//! metric units take SI prefixes while data sizes also take IEC ones
+//! (`KiB`, `MiB`, ...). Speeds can be written as any length over any time,
+//! like `km/h` or `miles per hour`.
+
+#![allow(dead_code)]
+
+use crate::calculator::Number;
+use crate::error::{Result, RustleError};
+use crate::provider::{Action, Provider, ProviderQuery};
+use crate::search::{CancelToken, ResultType, SearchResult, Section};
+use std::path::PathBuf;
+
+/// Words separating the quantity from the unit to convert it to
+const CONNECTORS: &[&str] = &["to", "in", "into", "as", "->", "=>", "="];
+
+/// What a unit measures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    Length,
+    Mass,
+    Temperature,
+    Volume,
+    Data,
+    Time,
+    Speed,
+    Area,
+}
+
+impl Dimension {
+    /// Returns the dimension's name for messages
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Dimension::Length => "length",
+            Dimension::Mass => "mass",
+            Dimension::Temperature => "temperature",
+            Dimension::Volume => "volume",
+            Dimension::Data => "data size",
+            Dimension::Time => "time",
+            Dimension::Speed => "speed",
+            Dimension::Area => "area",
+        }
+    }
+}
+
+/// Which prefixes a unit accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Prefixes {
+    /// No prefixes
+    None,
+
+    /// Every SI prefix from pico to tera
+    Si,
+
+    /// SI prefixes from kilo up, and IEC binary prefixes
+    Data,
+}
+
+/// One entry of the unit table
+struct UnitDef {
+    /// Symbol shown in answers
+    symbol: &'static str,
+
+    /// Other case-sensitive symbols
+    aliases: &'static [&'static str],
+
+    /// Case-insensitive singular names; an `s` plural is also accepted
+    names: &'static [&'static str],
+
+    dimension: Dimension,
+
+    /// Size of the unit in the dimension's base unit
+    factor: f64,
+
+    /// Added after scaling to reach the base unit (temperatures only)
+    offset: f64,
+
+    prefixes: Prefixes,
+
+    /// Power the prefix factor is raised to, e.g. 2 for `km²`
+    power: i32,
+}
+
+/// Shorthand for table entries without offset
+const fn unit(
+    symbol: &'static str,
+    aliases: &'static [&'static str],
+    names: &'static [&'static str],
+    dimension: Dimension,
+    factor: f64,
+) -> UnitDef {
+    UnitDef {
+        symbol,
+        aliases,
+        names,
+        dimension,
+        factor,
+        offset: 0.0,
+        prefixes: Prefixes::None,
+        power: 1,
+    }
+}
+
+/// Shorthand for table entries taking prefixes
+const fn metric(
+    symbol: &'static str,
+    aliases: &'static [&'static str],
+    names: &'static [&'static str],
+    dimension: Dimension,
+    factor: f64,
+    prefixes: Prefixes,
+    power: i32,
+) -> UnitDef {
+    UnitDef {
+        symbol,
+        aliases,
+        names,
+        dimension,
+        factor,
+        offset: 0.0,
+        prefixes,
+        power,
+    }
+}
+
+/// Seconds in a mean Gregorian year
+const YEAR: f64 = 365.2425 * 86_400.0;
+
+/// Metres in an inch, foot and mile
+const INCH: f64 = 0.0254;
+const FOOT: f64 = 12.0 * INCH;
+const MILE: f64 = 5280.0 * FOOT;
+
+/// Litres in a US gallon
+const GALLON: f64 = 3.785_411_784;
+
+/// Every known unit; base units are metre, kilogram, kelvin, litre, byte,
+/// second, metre per second and square metre. Earlier entries win when a
+/// symbol is ambiguous, so `m` is a metre unless a time is expected.
+static UNITS: &[UnitDef] = &[
+    // Length
+    metric(
+        "m",
+        &[],
+        &["meter", "metre"],
+        Dimension::Length,
+        1.0,
+        Prefixes::Si,
+        1,
+    ),
+    unit("in", &["\""], &["inch", "inches"], Dimension::Length, INCH),
+    unit("ft", &["'"], &["foot", "feet"], Dimension::Length, FOOT),
+    unit("yd", &[], &["yard"], Dimension::Length, 3.0 * FOOT),
+    unit("mi", &[], &["mile"], Dimension::Length, MILE),
+    unit(
+        "nmi",
+        &["NM"],
+        &["nautical mile"],
+        Dimension::Length,
+        1852.0,
+    ),
+    unit(
+        "au",
+        &["AU"],
+        &["astronomical unit"],
+        Dimension::Length,
+        149_597_870_700.0,
+    ),
+    unit(
+        "ly",
+        &[],
+        &["light year", "lightyear"],
+        Dimension::Length,
+        9_460_730_472_580_800.0,
+    ),
+    // Mass
+    metric(
+        "g",
+        &[],
+        &["gram", "gramme"],
+        Dimension::Mass,
+        0.001,
+        Prefixes::Si,
+        1,
+    ),
+    unit("t", &[], &["tonne", "metric ton"], Dimension::Mass, 1000.0),
+    unit("lb", &["lbs"], &["pound"], Dimension::Mass, 0.453_592_37),
+    unit("oz", &[], &["ounce"], Dimension::Mass, 0.453_592_37 / 16.0),
+    unit("st", &[], &["stone"], Dimension::Mass, 14.0 * 0.453_592_37),
+    unit(
+        "ton",
+        &[],
+        &["short ton", "us ton"],
+        Dimension::Mass,
+        2000.0 * 0.453_592_37,
+    ),
+    // Temperature
+    UnitDef {
+        symbol: "°C",
+        aliases: &["C", "c", "℃", "degC"],
+        names: &["celsius", "centigrade", "degree celsius", "degrees celsius"],
+        dimension: Dimension::Temperature,
+        factor: 1.0,
+        offset: 273.15,
+        prefixes: Prefixes::None,
+        power: 1,
+    },
+    UnitDef {
+        symbol: "°F",
+        aliases: &["F", "f", "℉", "degF"],
+        names: &["fahrenheit", "degree fahrenheit", "degrees fahrenheit"],
+        dimension: Dimension::Temperature,
+        factor: 5.0 / 9.0,
+        offset: 273.15 - 32.0 * 5.0 / 9.0,
+        prefixes: Prefixes::None,
+        power: 1,
+    },
+    unit("K", &["k"], &["kelvin"], Dimension::Temperature, 1.0),
+    unit(
+        "°R",
+        &["R"],
+        &["rankine"],
+        Dimension::Temperature,
+        5.0 / 9.0,
+    ),
+    // Volume
+    metric(
+        "L",
+        &["l"],
+        &["liter", "litre"],
+        Dimension::Volume,
+        1.0,
+        Prefixes::Si,
+        1,
+    ),
+    metric(
+        "m³",
+        &[],
+        &["meter³", "metre³"],
+        Dimension::Volume,
+        1000.0,
+        Prefixes::Si,
+        3,
+    ),
+    unit("cc", &[], &[], Dimension::Volume, 0.001),
+    unit("gal", &[], &["gallon"], Dimension::Volume, GALLON),
+    unit("qt", &[], &["quart"], Dimension::Volume, GALLON / 4.0),
+    unit("pt", &[], &["pint"], Dimension::Volume, GALLON / 8.0),
+    unit("cup", &[], &["cup"], Dimension::Volume, GALLON / 16.0),
+    unit(
+        "fl oz",
+        &["floz"],
+        &["fluid ounce"],
+        Dimension::Volume,
+        GALLON / 128.0,
+    ),
+    unit(
+        "tbsp",
+        &[],
+        &["tablespoon"],
+        Dimension::Volume,
+        GALLON / 256.0,
+    ),
+    unit("tsp", &[], &["teaspoon"], Dimension::Volume, GALLON / 768.0),
+    unit(
+        "in³",
+        &[],
+        &["inch³", "inches³"],
+        Dimension::Volume,
+        0.016_387_064,
+    ),
+    unit(
+        "ft³",
+        &[],
+        &["foot³", "feet³"],
+        Dimension::Volume,
+        28.316_846_592,
+    ),
+    // Data size
+    metric("B", &[], &["byte"], Dimension::Data, 1.0, Prefixes::Data, 1),
+    metric(
+        "bit",
+        &["b"],
+        &["bit"],
+        Dimension::Data,
+        0.125,
+        Prefixes::Data,
+        1,
+    ),
+    // Time
+    metric(
+        "s",
+        &["sec"],
+        &["second"],
+        Dimension::Time,
+        1.0,
+        Prefixes::Si,
+        1,
+    ),
+    unit("min", &["m", "mins"], &["minute"], Dimension::Time, 60.0),
+    unit("h", &["hr", "hrs"], &["hour"], Dimension::Time, 3600.0),
+    unit("d", &[], &["day"], Dimension::Time, 86_400.0),
+    unit("wk", &["w"], &["week"], Dimension::Time, 7.0 * 86_400.0),
+    unit("mo", &[], &["month"], Dimension::Time, YEAR / 12.0),
+    unit("yr", &["y"], &["year"], Dimension::Time, YEAR),
+    // Speed (any length over any time also works)
+    unit("mph", &[], &[], Dimension::Speed, MILE / 3600.0),
+    unit("kph", &["kmh"], &[], Dimension::Speed, 1000.0 / 3600.0),
+    unit("kn", &["kt"], &["knot"], Dimension::Speed, 1852.0 / 3600.0),
+    // Area
+    metric(
+        "m²",
+        &[],
+        &["meter²", "metre²"],
+        Dimension::Area,
+        1.0,
+        Prefixes::Si,
+        2,
+    ),
+    unit("ha", &[], &["hectare"], Dimension::Area, 10_000.0),
+    unit("ac", &[], &["acre"], Dimension::Area, 4_046.856_422_4),
+    unit(
+        "in²",
+        &[],
+        &["inch²", "inches²"],
+        Dimension::Area,
+        INCH * INCH,
+    ),
+    unit(
+        "ft²",
+        &["sqft"],
+        &["foot²", "feet²"],
+        Dimension::Area,
+        FOOT * FOOT,
+    ),
+    unit("yd²", &[], &["yard²"], Dimension::Area, 9.0 * FOOT * FOOT),
+    unit("mi²", &[], &["mile²"], Dimension::Area, MILE * MILE),
+];
+
+/// SI prefixes: symbol, name and factor
+const SI_PREFIXES: &[(&str, &str, f64)] = &[
+    ("T", "tera", 1e12),
+    ("G", "giga", 1e9),
+    ("M", "mega", 1e6),
+    ("k", "kilo", 1e3),
+    ("h", "hecto", 1e2),
+    ("da", "deca", 1e1),
+    ("d", "deci", 1e-1),
+    ("c", "centi", 1e-2),
+    ("m", "milli", 1e-3),
+    ("µ", "micro", 1e-6),
+    ("μ", "micro", 1e-6),
+    ("u", "micro", 1e-6),
+    ("n", "nano", 1e-9),
+    ("p", "pico", 1e-12),
+];
+
+/// Prefixes for data sizes: decimal ones (with `K` as a common spelling
+/// of kilo) and IEC binary ones
+const DATA_PREFIXES: &[(&str, &str, f64)] = &[
+    ("k", "kilo", 1e3),
+    ("K", "kilo", 1e3),
+    ("M", "mega", 1e6),
+    ("G", "giga", 1e9),
+    ("T", "tera", 1e12),
+    ("P", "peta", 1e15),
+    ("E", "exa", 1e18),
+    ("Ki", "kibi", 1024.0),
+    ("Mi", "mebi", 1_048_576.0),
+    ("Gi", "gibi", 1_073_741_824.0),
+    ("Ti", "tebi", 1_099_511_627_776.0),
+    ("Pi", "pebi", 1_125_899_906_842_624.0),
+    ("Ei", "exbi", 1_152_921_504_606_846_976.0),
+];
+
+/// A unit as written in a query, with any prefix applied
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unit {
+    /// Symbol shown in answers, such as `km` or `GiB`
+    pub symbol: String,
+
+    pub dimension: Dimension,
+
+    /// Size of the unit in the dimension's base unit
+    pub factor: f64,
+
+    /// Added after scaling to reach the base unit
+    pub offset: f64,
+}
+
+impl Unit {
+    fn from_def(def: &UnitDef, prefix: Option<(&str, f64)>) -> Unit {
+        let (prefix_symbol, prefix_factor) = prefix.unwrap_or(("", 1.0));
+        Unit {
+            symbol: format!("{}{}", prefix_symbol, def.symbol),
+            dimension: def.dimension,
+            factor: def.factor * prefix_factor.powi(def.power),
+            offset: def.offset,
+        }
+    }
+
+    /// Converts a value in this unit to the base unit
+    fn in_base(&self, value: f64) -> f64 {
+        value * self.factor + self.offset
+    }
+
+    /// Converts a value in the base unit to this unit
+    fn in_unit(&self, value: f64) -> f64 {
+        (value - self.offset) / self.factor
+    }
+
+    /// Finds the unit written as `text`
+    ///
+    /// When a symbol could mean several units, one of dimension `prefer`
+    /// is chosen if there is one, so `m` can be a minute in `2h30m`.
+    pub fn parse(text: &str, prefer: Option<Dimension>) -> Option<Unit> {
+        let text = normalize(text);
+        if text.is_empty() {
+            return None;
+        }
+
+        let candidates = matching_units(&text);
+        if let Some(unit) = prefer
+            .and_then(|dimension| candidates.iter().find(|u| u.dimension == dimension))
+            .or(candidates.first())
+        {
+            return Some(unit.clone());
+        }
+
+        // A length over a time is a speed
+        let (length, time) = text.split_once('/')?;
+        let length = Unit::parse(length, Some(Dimension::Length))?;
+        let time = Unit::parse(time, Some(Dimension::Time))?;
+        if length.dimension != Dimension::Length || time.dimension != Dimension::Time {
+            return None;
+        }
+        Some(Unit {
+            symbol: format!("{}/{}", length.symbol, time.symbol),
+            dimension: Dimension::Speed,
+            factor: length.factor / time.factor,
+            offset: 0.0,
+        })
+    }
+}
+
+/// Rewrites the ways of writing squares, cubes and "per" into one form
+fn normalize(text: &str) -> String {
+    let mut text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    text = text.replace("^2", "²").replace("^3", "³");
+    text = text.replace(" per ", "/");
+
+    let lower = text.to_lowercase();
+    for (words, power) in [
+        ("square ", "²"),
+        ("sq ", "²"),
+        ("sq.", "²"),
+        ("cubic ", "³"),
+        ("cu ", "³"),
+    ] {
+        if lower.starts_with(words) {
+            text = format!("{}{}", text[words.len()..].trim_start(), power);
+            break;
+        }
+    }
+
+    // `m2` and `ft3` are squares and cubes
+    if let Some(stripped) = text.strip_suffix('2').or_else(|| text.strip_suffix('3')) {
+        if stripped.ends_with(char::is_alphabetic) {
+            let power = if text.ends_with('2') { "²" } else { "³" };
+            text = format!("{}{}", stripped, power);
+        }
+    }
+    text
+}
+
+/// Returns every unit `text` could mean, best match first
+///
+/// Symbols are matched exactly, then names ignoring case and plurals,
+/// then symbols ignoring case.
+fn matching_units(text: &str) -> Vec<Unit> {
+    let lower = text.to_lowercase();
+    let stem = lower.trim_end_matches(['²', '³']);
+    let power = &lower[stem.len()..];
+    let singular = stem
+        .strip_suffix("es")
+        .filter(|s| s.ends_with("ch"))
+        .or_else(|| stem.strip_suffix('s'))
+        .map(|s| format!("{}{}", s, power))
+        .unwrap_or_else(|| lower.clone());
+
+    let by_symbol = |ignore_case: bool| {
+        let mut found = Vec::new();
+        for def in UNITS {
+            let symbols = std::iter::once(&def.symbol).chain(def.aliases.iter());
+            for symbol in symbols {
+                let matches = |candidate: &str| {
+                    if ignore_case {
+                        candidate.eq_ignore_ascii_case(text)
+                    } else {
+                        candidate == text
+                    }
+                };
+                if matches(symbol) {
+                    found.push(Unit::from_def(def, None));
+                }
+                for &(prefix, _, factor) in prefixes(def) {
+                    if text.len() > prefix.len() && matches(&format!("{}{}", prefix, symbol)) {
+                        found.push(Unit::from_def(def, Some((prefix, factor))));
+                    }
+                }
+            }
+        }
+        found
+    };
+
+    let mut found = by_symbol(false);
+    for def in UNITS {
+        for name in def.names {
+            if *name == lower || *name == singular {
+                found.push(Unit::from_def(def, None));
+            }
+            for &(prefix, prefix_name, factor) in prefixes(def) {
+                let full = format!("{}{}", prefix_name, name);
+                if full == lower || full == singular {
+                    found.push(Unit::from_def(def, Some((prefix, factor))));
+                }
+            }
+        }
+    }
+    if found.is_empty() {
+        found = by_symbol(true);
+    }
+    found
+}
+
+fn prefixes(def: &UnitDef) -> &'static [(&'static str, &'static str, f64)] {
+    match def.prefixes {
+        Prefixes::None => &[],
+        Prefixes::Si => SI_PREFIXES,
+        Prefixes::Data => DATA_PREFIXES,
+    }
+}
+
+/// A quantity and the unit to express it in
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conversion {
+    /// The quantity, possibly in several parts as in `5 ft 11 in`
+    pub parts: Vec<(f64, Unit)>,
+
+    /// The unit to convert to
+    pub target: Unit,
+}
+
+impl Conversion {
+    /// Parses a query such as `5 km to mi`
+    ///
+    /// Returns None for anything that is not a conversion between known
+    /// units, so the query can be left to other providers.
+    pub fn parse(input: &str) -> Option<Conversion> {
+        let words: Vec<&str> = input.split_whitespace().collect();
+
+        // The last connector with something on both sides splits the
+        // query, so `5 in to cm` and `5 ft in in` both work
+        let split = (1..words.len().saturating_sub(1))
+            .rev()
+            .find(|&i| CONNECTORS.contains(&words[i].to_lowercase().as_str()))?;
+        let quantity = words[..split].join(" ");
+        let target = words[split + 1..].join(" ");
+
+        let parts = parse_quantity(&quantity)?;
+        let target = Unit::parse(&target, Some(parts[0].1.dimension))?;
+        Some(Conversion { parts, target })
+    }
+
+    /// Works out the quantity in the target unit
+    pub fn convert(&self) -> Result<f64> {
+        let mut base = 0.0;
+        for (value, unit) in &self.parts {
+            if unit.dimension != self.target.dimension {
+                return Err(RustleError::conversion_error(format!(
+                    "{} is a {}, {} is a {}",
+                    unit.symbol,
+                    unit.dimension.as_str(),
+                    self.target.symbol,
+                    self.target.dimension.as_str()
+                )));
+            }
+            if unit.offset != 0.0 && self.parts.len() > 1 {
+                return Err(RustleError::conversion_error(
+                    "temperatures cannot be added up",
+                ));
+            }
+            base += unit.in_base(*value);
+        }
+        Ok(self.target.in_unit(base))
+    }
+
+    /// Describes the quantity, as in `2 h 30 min`
+    pub fn describe_quantity(&self) -> String {
+        self.parts
+            .iter()
+            .map(|(value, unit)| format!("{} {}", Number::Real(*value), unit.symbol))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Parses one or more number-unit pairs, such as `5 km` or `2h30m`
+///
+/// Units after the first prefer the first one's dimension.
+fn parse_quantity(text: &str) -> Option<Vec<(f64, Unit)>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut parts: Vec<(f64, Unit)> = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        while chars.get(i).is_some_and(|c| c.is_whitespace()) {
+            i += 1;
+        }
+        if i >= chars.len() {
+            break;
+        }
+
+        let (value, len) = number(&chars[i..], parts.is_empty())?;
+        i += len;
+
+        let start = i;
+        while i < chars.len() && !starts_number(&chars, i) {
+            i += 1;
+        }
+        let unit_text: String = chars[start..i].iter().collect();
+        let prefer = parts.first().map(|(_, unit)| unit.dimension);
+        let unit = Unit::parse(unit_text.trim(), prefer)?;
+        parts.push((value, unit));
+    }
+
+    (!parts.is_empty()).then_some(parts)
+}
+
+/// Reads a decimal number, returning it and how many characters it used
+fn number(chars: &[char], allow_sign: bool) -> Option<(f64, usize)> {
+    let mut len = 0;
+    if allow_sign && matches!(chars.first(), Some('-' | '+' | '−')) {
+        len += 1;
+    }
+    let digits_start = len;
+    while chars
+        .get(len)
+        .is_some_and(|c| c.is_ascii_digit() || *c == '.')
+    {
+        len += 1;
+    }
+    if len == digits_start {
+        return None;
+    }
+
+    // An exponent needs digits after it
+    if matches!(chars.get(len), Some('e' | 'E')) {
+        let sign = usize::from(matches!(chars.get(len + 1), Some('+' | '-')));
+        if chars
+            .get(len + 1 + sign)
+            .is_some_and(|c| c.is_ascii_digit())
+        {
+            len += 1 + sign;
+            while chars.get(len).is_some_and(|c| c.is_ascii_digit()) {
+                len += 1;
+            }
+        }
+    }
+
+    let text: String = chars[..len].iter().collect();
+    let value = text.replace('−', "-").parse().ok()?;
+    Some((value, len))
+}
+
+/// Returns true if a number starts at `chars[i]`
+///
+/// A lone 2 or 3 right after a unit is a square or cube, as in `m2`.
+fn starts_number(chars: &[char], i: usize) -> bool {
+    let c = chars[i];
+    if !c.is_ascii_digit() && c != '.' {
+        return false;
+    }
+    let previous = i.checked_sub(1).map(|p| chars[p]);
+    let next = chars.get(i + 1);
+    let is_power = matches!(c, '2' | '3')
+        && previous.is_some_and(|p| p.is_alphabetic() || p == '^')
+        && !next.is_some_and(|n| n.is_alphanumeric() || *n == '.');
+    !is_power
+}
+
+/// Shows conversions between units
+pub struct UnitProvider;
+
+impl Provider for UnitProvider {
+    fn id(&self) -> &'static str {
+        "units"
+    }
+
+    fn title(&self) -> &str {
+        "Conversion"
+    }
+
+    fn priority(&self) -> i32 {
+        1
+    }
+
+    /// Conversions are read as typed
+    fn wants_filters(&self) -> bool {
+        false
+    }
+
+    fn query(
+        &self,
+        query: &ProviderQuery,
+        _cancel: &CancelToken,
+        emit: &(dyn Fn(Vec<Section>) + Sync),
+    ) -> bool {
+        let Some(conversion) = Conversion::parse(query.text) else {
+            return true;
+        };
+
+        let quantity = conversion.describe_quantity();
+        let (name, description) = match conversion.convert() {
+            Ok(value) => (
+                format!("{} {}", Number::Real(value), conversion.target.symbol),
+                format!("{} =", quantity),
+            ),
+            Err(e) => (e.to_string(), quantity),
+        };
+        emit(vec![self.section(vec![SearchResult {
+            name,
+            path: PathBuf::new(),
+            result_type: ResultType::Answer,
+            score: 0,
+            description,
+            provider: self.id(),
+            shortcut: None,
+        }])]);
+        true
+    }
+
+    /// Copies the converted number; error messages have nothing to copy
+    fn actions(&self, result: &SearchResult) -> Vec<Action> {
+        match result.name.split(' ').next() {
+            Some(value) if value.parse::<f64>().is_ok() => vec![Action::Copy(value.to_string())],
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::run_provider;
+
+    /// Converts and formats the answer the way the provider shows it
+    fn convert(query: &str) -> String {
+        let conversion = Conversion::parse(query).unwrap();
+        let value = conversion.convert().unwrap();
+        format!("{} {}", Number::Real(value), conversion.target.symbol)
+    }
+
+    #[test]
+    fn test_examples() {
+        assert_eq!(convert("5 km to mi"), "3.10685596119 mi");
+        assert_eq!(convert("72 f in c"), "22.2222222222 °C");
+        assert_eq!(convert("3.5 GiB in MB"), "3758.096384 MB");
+        assert_eq!(convert("2h30m in minutes"), "150 min");
+    }
+
+    #[test]
+    fn test_length_mass_and_temperature() {
+        assert_eq!(convert("1 mile in feet"), "5280 ft");
+        assert_eq!(convert("5 ft 11 in to cm"), "180.34 cm");
+        assert_eq!(convert("254 mm in inches"), "10 in");
+        assert_eq!(convert("1 nautical mile to m"), "1852 m");
+        assert_eq!(convert("10 kg to lb"), "22.0462262185 lb");
+        assert_eq!(convert("16 oz in g"), "453.59237 g");
+        assert_eq!(convert("2 stone to kilograms"), "12.70058636 kg");
+        assert_eq!(convert("-40 C to F"), "-40 °F");
+        assert_eq!(convert("0 kelvin in celsius"), "-273.15 °C");
+        assert_eq!(convert("212 degrees fahrenheit to K"), "373.15 K");
+    }
+
+    #[test]
+    fn test_volume_data_and_time() {
+        assert_eq!(convert("1 gal to L"), "3.785411784 L");
+        assert_eq!(convert("500 mL in cups"), "2.11337641887 cup");
+        assert_eq!(convert("1 m3 to liters"), "1000 L");
+        assert_eq!(convert("2 tbsp to tsp"), "6 tsp");
+        assert_eq!(convert("1 MiB to KiB"), "1024 KiB");
+        assert_eq!(convert("1 GB in MiB"), "953.674316406 MiB");
+        assert_eq!(convert("100 Mb to MB"), "12.5 MB");
+        assert_eq!(convert("1 byte in bits"), "8 bit");
+        assert_eq!(convert("90 min in h"), "1.5 h");
+        assert_eq!(convert("1 week to hours"), "168 h");
+        assert_eq!(convert("1500 ms to s"), "1.5 s");
+        assert_eq!(convert("1 year in days"), "365.2425 d");
+    }
+
+    #[test]
+    fn test_speed_and_area() {
+        assert_eq!(convert("100 km/h to mph"), "62.1371192237 mph");
+        assert_eq!(convert("10 m/s in km/h"), "36 km/h");
+        assert_eq!(convert("60 miles per hour to m/s"), "26.8224 m/s");
+        assert_eq!(convert("20 knots to kph"), "37.04 kph");
+        assert_eq!(convert("1 km2 in ha"), "100 ha");
+        assert_eq!(convert("1 acre to m^2"), "4046.8564224 m²");
+        assert_eq!(convert("100 sq ft to square meters"), "9.290304 m²");
+        assert_eq!(convert("1 m² to cm²"), "10000 cm²");
+    }
+
+    #[test]
+    fn test_unit_lookup() {
+        let symbol = |text: &str| Unit::parse(text, None).map(|u| u.symbol);
+        assert_eq!(symbol("kilometres").as_deref(), Some("km"));
+        assert_eq!(symbol("µm").as_deref(), Some("µm"));
+        assert_eq!(symbol("Megabytes").as_deref(), Some("MB"));
+        assert_eq!(symbol("gibibyte").as_deref(), Some("GiB"));
+        assert_eq!(symbol("gb").as_deref(), Some("GB"));
+        assert_eq!(symbol("Gb").as_deref(), Some("Gbit"));
+        assert_eq!(symbol("m").as_deref(), Some("m"));
+        assert_eq!(
+            Unit::parse("m", Some(Dimension::Time)).map(|u| u.symbol),
+            Some("min".to_string())
+        );
+        assert_eq!(symbol("furlong"), None);
+    }
+
+    #[test]
+    fn test_mismatches_and_non_conversions() {
+        let error = Conversion::parse("5 km to kg")
+            .unwrap()
+            .convert()
+            .unwrap_err();
+        assert!(error.to_string().contains("km is a length, kg is a mass"));
+        assert!(Conversion::parse("10 c 5 f to k")
+            .unwrap()
+            .convert()
+            .is_err());
+
+        for query in [
+            "notepad",
+            "5 km",
+            "km to mi",
+            "5 furlongs to m",
+            "how to cook",
+            "report in docs",
+        ] {
+            assert!(Conversion::parse(query).is_none(), "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_provider_copies_value() {
+        let results = run_provider(&UnitProvider, "5 km to m");
+        let answer = &results[0];
+        assert_eq!(answer.name, "5000 m");
+        assert_eq!(answer.description, "5 km =");
+        assert_eq!(
+            UnitProvider.actions(answer),
+            [Action::Copy("5000".to_string())]
+        );
+    }
+}
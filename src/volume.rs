@@ -0,0 +1,158 @@
+//! Raw NTFS volume access for Rustle
+//!
+//! This module opens a volume device (e.g. `\\.\D:`), locates the Master
+//! File Table from the boot sector and streams every file record through
+//! the parser in `mft.rs`. Reading a volume this way needs administrator
+//! rights; callers fall back to walking directories when it fails.
+
+#![allow(dead_code)]
+
+use crate::error::{Result, RustleError};
+use crate::mft::{self, BootSector, MftTree, VolumeEntry};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Component, Path, Prefix};
+
+/// Bytes read from the volume per request while scanning the MFT
+const READ_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// An open NTFS volume
+pub struct NtfsVolume<R> {
+    /// Reader positioned anywhere on the raw volume
+    reader: R,
+
+    /// Layout read from the boot sector
+    boot: BootSector,
+}
+
+impl NtfsVolume<File> {
+    /// Opens the raw volume for a drive letter
+    pub fn open_drive(letter: char) -> Result<Self> {
+        let device = format!(r"\\.\{}:", letter.to_ascii_uppercase());
+        let file = File::open(&device)
+            .map_err(|e| RustleError::directory_access(std::path::PathBuf::from(&device), e))?;
+        Self::new(file)
+    }
+}
+
+impl<R: Read + Seek> NtfsVolume<R> {
+    /// Reads the boot sector and prepares to scan the MFT
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut sector = vec![0u8; 512];
+        reader.seek(SeekFrom::Start(0))?;
+        reader.read_exact(&mut sector)?;
+        let boot = mft::parse_boot_sector(&sector)?;
+        Ok(Self { reader, boot })
+    }
+
+    /// Returns the volume layout
+    pub fn boot_sector(&self) -> &BootSector {
+        &self.boot
+    }
+
+    /// Reads every MFT record and links them into a tree
+    ///
+    /// Records that fail to parse (torn writes, corruption) are skipped.
+    /// The location of the MFT comes from the data runs of record 0,
+    /// the `$MFT` file itself.
+    pub fn read_tree(&mut self) -> Result<MftTree> {
+        let record_size = self.boot.record_size as u64;
+        let cluster_size = self.boot.bytes_per_cluster;
+
+        // Record 0 describes where the rest of the MFT lives
+        let mut first = self.read_at(self.boot.mft_offset(), cluster_size.max(record_size))?;
+        let mft_record = mft::parse_record(
+            0,
+            &mut first[..record_size as usize],
+            self.boot.bytes_per_sector,
+        )?
+        .ok_or_else(|| RustleError::mft_parse("$MFT record is not in use"))?;
+
+        let total_records = mft_record.data_size.unwrap_or(0) / record_size;
+        let chunk_size = (READ_CHUNK_SIZE / cluster_size).max(1) * cluster_size;
+
+        let mut tree = MftTree::new();
+        let mut record_number = 0u64;
+
+        for run in &mft_record.data_runs {
+            let run_bytes = run.length * cluster_size;
+            let Some(lcn) = run.lcn else {
+                // Sparse runs hold no records on disk
+                record_number += run_bytes / record_size;
+                continue;
+            };
+
+            let mut done = 0;
+            while done < run_bytes && record_number < total_records {
+                let len = chunk_size.min(run_bytes - done);
+                let mut chunk = self.read_at(lcn * cluster_size + done, len)?;
+
+                for record in chunk.chunks_exact_mut(record_size as usize) {
+                    if record_number >= total_records {
+                        break;
+                    }
+                    match mft::parse_record(record_number, record, self.boot.bytes_per_sector) {
+                        Ok(Some(parsed)) => tree.insert(parsed),
+                        Ok(None) => {}
+                        Err(e) => log::debug!("Skipping MFT record {}: {}", record_number, e),
+                    }
+                    record_number += 1;
+                }
+
+                done += len;
+            }
+        }
+
+        log::debug!("Read {} MFT records", record_number);
+        Ok(tree)
+    }
+
+    /// Reads `len` bytes at `offset`; both must be sector-aligned
+    fn read_at(&mut self, offset: u64, len: u64) -> Result<Vec<u8>> {
+        let mut buf = vec![0u8; len as usize];
+        self.reader.seek(SeekFrom::Start(offset))?;
+        self.reader.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+/// Returns the drive letter if `path` is the root of a drive (e.g. `D:\`)
+pub fn drive_root_letter(path: &Path) -> Option<char> {
+    let mut components = path.components();
+    let letter = match components.next()? {
+        Component::Prefix(prefix) => match prefix.kind() {
+            Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => letter as char,
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    match (components.next(), components.next()) {
+        (Some(Component::RootDir), None) => Some(letter),
+        _ => None,
+    }
+}
+
+/// Checks whether the MFT of a drive can be read
+pub fn can_read_volume(letter: char) -> bool {
+    NtfsVolume::open_drive(letter).is_ok()
+}
+
+/// Reads every file and folder on the volume mounted at `root`
+pub fn read_volume_entries(root: &Path) -> Result<Vec<VolumeEntry>> {
+    let letter = drive_root_letter(root).ok_or_else(|| {
+        RustleError::InvalidPath(format!("{} is not a drive root", root.display()))
+    })?;
+
+    let started = std::time::Instant::now();
+    let mut volume = NtfsVolume::open_drive(letter)?;
+    let entries = volume.read_tree()?.into_entries();
+
+    log::info!(
+        "Read {} entries from the MFT of {}: in {:?}",
+        entries.len(),
+        letter,
+        started.elapsed()
+    );
+    Ok(entries)
+}
@@ -0,0 +1,1026 @@
+//! File system change watching for Rustle
+//!
+//! This module keeps the persistent file index up to date while Rustle is
+//! running. Change sources implement the `ChangeWatcher` trait: a native
+//! backend built on `ReadDirectoryChangesW`, and a portable polling backend
+//! that diffs periodic snapshots of the search roots. A background thread
+//! drains whichever backend is active and applies the events to the index.
+
+#![allow(dead_code)]
+
+use crate::error::Result;
+use crate::fulltext::{read_documents, FullTextIndex};
+use crate::ignore::{is_ignore_file_name, IgnoreSet};
+use crate::index::{walk_root, FileIndex, IndexEntry, SubtreeScan};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How often the polling backend rescans the search roots
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long the watcher thread waits for events before checking for shutdown
+const EVENT_WAIT: Duration = Duration::from_secs(1);
+
+/// Minimum time between saves of a changed index
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A single change to the file system
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileEvent {
+    /// A file or folder was created (or moved in from outside the roots)
+    Created(PathBuf),
+
+    /// A file or folder was deleted (or moved out of the roots)
+    Deleted(PathBuf),
+
+    /// A file's contents or metadata changed
+    Modified(PathBuf),
+
+    /// A file or folder was renamed or moved within the roots
+    Renamed { from: PathBuf, to: PathBuf },
+
+    /// Changes were lost (e.g. buffer overflow); the directory must be re-walked
+    Rescan(PathBuf),
+}
+
+impl FileEvent {
+    /// Applies this event to a file index
+    ///
+    /// Any rescans run while the index is borrowed; `IndexWatcher` instead
+    /// runs them with no lock held.
+    pub fn apply(&self, index: &mut FileIndex) {
+        self.apply_changes(index);
+        for dir in self.rescan_dirs(index) {
+            index.rescan_path(&dir);
+        }
+    }
+
+    /// Applies the parts of this event that need no rescanning
+    fn apply_changes(&self, index: &mut FileIndex) {
+        match self {
+            FileEvent::Created(path) => index.add_path(path),
+            FileEvent::Deleted(path) => index.remove_path(path),
+            FileEvent::Modified(path) => index.update_path(path),
+            FileEvent::Renamed { from, to } => index.rename_path(from, to),
+            FileEvent::Rescan(_) => {}
+        }
+    }
+
+    /// Returns the directories this event requires rescanning
+    fn rescan_dirs(&self, index: &FileIndex) -> Vec<PathBuf> {
+        let mut dirs = Vec::new();
+        if let FileEvent::Rescan(path) = self {
+            dirs.push(path.clone());
+        }
+
+        // A changed ignore file can hide or reveal anything next to it
+        if index.ignore().uses_ignore_files() {
+            for path in self.paths() {
+                let is_ignore_file = path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(is_ignore_file_name);
+                if let (true, Some(dir)) = (is_ignore_file, path.parent()) {
+                    dirs.push(dir.to_path_buf());
+                }
+            }
+        }
+        dirs
+    }
+
+    /// Returns the paths this event touches
+    pub fn paths(&self) -> Vec<&Path> {
+        match self {
+            FileEvent::Created(path)
+            | FileEvent::Deleted(path)
+            | FileEvent::Modified(path)
+            | FileEvent::Rescan(path) => vec![path],
+            FileEvent::Renamed { from, to } => vec![from, to],
+        }
+    }
+}
+
+/// A source of file system change events
+pub trait ChangeWatcher: Send {
+    /// Short name of the backend, for logging
+    fn name(&self) -> &'static str;
+
+    /// Waits up to `timeout` and returns the changes seen since the last call
+    fn next_events(&mut self, timeout: Duration) -> Result<Vec<FileEvent>>;
+}
+
+/// Portable watcher that rescans the roots and diffs the results
+///
+/// Works on any file system, including network shares that do not
+/// deliver change notifications, at the cost of periodic walks.
+pub struct PollingWatcher {
+    /// Roots to rescan
+    roots: Vec<PathBuf>,
+
+    /// Rules deciding which files and folders are left out
+    ignore: IgnoreSet,
+
+    /// Entries found by the previous scan, keyed by path
+    snapshot: HashMap<PathBuf, IndexEntry>,
+
+    /// Time between scans
+    interval: Duration,
+
+    /// When the last scan finished
+    last_scan: Instant,
+}
+
+impl PollingWatcher {
+    /// Creates a polling watcher and takes the initial snapshot
+    pub fn new(roots: &[PathBuf], ignore: IgnoreSet) -> Self {
+        Self::with_interval(roots, ignore, POLL_INTERVAL)
+    }
+
+    /// Creates a polling watcher with a custom rescan interval
+    pub fn with_interval(roots: &[PathBuf], ignore: IgnoreSet, interval: Duration) -> Self {
+        Self {
+            roots: roots.to_vec(),
+            snapshot: take_snapshot(roots, &ignore),
+            ignore,
+            interval,
+            last_scan: Instant::now(),
+        }
+    }
+
+    /// Rescans the roots immediately and returns what changed
+    pub fn scan(&mut self) -> Vec<FileEvent> {
+        let snapshot = take_snapshot(&self.roots, &self.ignore);
+        let events = diff_snapshots(&self.snapshot, &snapshot);
+        self.snapshot = snapshot;
+        self.last_scan = Instant::now();
+        events
+    }
+}
+
+impl ChangeWatcher for PollingWatcher {
+    fn name(&self) -> &'static str {
+        "polling"
+    }
+
+    fn next_events(&mut self, timeout: Duration) -> Result<Vec<FileEvent>> {
+        let due = self.interval.saturating_sub(self.last_scan.elapsed());
+        if due > timeout {
+            std::thread::sleep(timeout);
+            return Ok(Vec::new());
+        }
+
+        std::thread::sleep(due);
+        Ok(self.scan())
+    }
+}
+
+/// Walks all roots and returns their entries keyed by path
+fn take_snapshot(roots: &[PathBuf], ignore: &IgnoreSet) -> HashMap<PathBuf, IndexEntry> {
+    roots
+        .iter()
+        .flat_map(|root| walk_root(root, ignore))
+        .map(|entry| (entry.path.clone(), entry))
+        .collect()
+}
+
+/// Compares two snapshots and returns the events that turn `old` into `new`
+///
+/// A deletion and a creation are reported as a rename when they are the
+/// only pair with the same kind, size and modification time, which is
+/// what a rename or move within the roots looks like from the outside.
+pub fn diff_snapshots(
+    old: &HashMap<PathBuf, IndexEntry>,
+    new: &HashMap<PathBuf, IndexEntry>,
+) -> Vec<FileEvent> {
+    let mut deleted: Vec<&IndexEntry> = old
+        .values()
+        .filter(|e| !new.contains_key(&e.path))
+        .collect();
+    let mut created: Vec<&IndexEntry> = new
+        .values()
+        .filter(|e| !old.contains_key(&e.path))
+        .collect();
+
+    // Only report the top-most path of a removed or added subtree
+    let deleted_paths: HashSet<&Path> = deleted.iter().map(|e| e.path.as_path()).collect();
+    deleted.retain(|e| !has_ancestor_in(&e.path, &deleted_paths));
+    let created_paths: HashSet<&Path> = created.iter().map(|e| e.path.as_path()).collect();
+    created.retain(|e| !has_ancestor_in(&e.path, &created_paths));
+
+    deleted.sort_by(|a, b| a.path.cmp(&b.path));
+    created.sort_by(|a, b| a.path.cmp(&b.path));
+
+    // Group both sides by signature once, so matching is a single pass
+    let signature = |e: &IndexEntry| (e.is_dir, e.size, e.modified);
+    let mut deleted_by_signature: HashMap<_, Vec<&IndexEntry>> = HashMap::new();
+    for &entry in &deleted {
+        deleted_by_signature
+            .entry(signature(entry))
+            .or_default()
+            .push(entry);
+    }
+    let mut created_counts: HashMap<_, usize> = HashMap::new();
+    for &entry in &created {
+        *created_counts.entry(signature(entry)).or_default() += 1;
+    }
+
+    let mut events = Vec::new();
+    let mut renamed_from: HashSet<&Path> = HashSet::new();
+    let mut unmatched_created = Vec::new();
+    for entry in created {
+        let key = signature(entry);
+        match deleted_by_signature.get(&key).map(Vec::as_slice) {
+            Some([from]) if created_counts[&key] == 1 => {
+                renamed_from.insert(&from.path);
+                events.push(FileEvent::Renamed {
+                    from: from.path.clone(),
+                    to: entry.path.clone(),
+                });
+            }
+            _ => unmatched_created.push(entry),
+        }
+    }
+
+    events.extend(
+        deleted
+            .into_iter()
+            .filter(|e| !renamed_from.contains(e.path.as_path()))
+            .map(|e| FileEvent::Deleted(e.path.clone())),
+    );
+    events.extend(
+        unmatched_created
+            .into_iter()
+            .map(|e| FileEvent::Created(e.path.clone())),
+    );
+
+    let mut modified: Vec<&IndexEntry> = new
+        .values()
+        .filter(|e| {
+            old.get(&e.path)
+                .is_some_and(|o| o.size != e.size || o.modified != e.modified)
+        })
+        .collect();
+    modified.sort_by(|a, b| a.path.cmp(&b.path));
+    events.extend(
+        modified
+            .into_iter()
+            .map(|e| FileEvent::Modified(e.path.clone())),
+    );
+
+    events
+}
+
+/// Checks whether any strict ancestor of `path` is in `paths`
+fn has_ancestor_in(path: &Path, paths: &HashSet<&Path>) -> bool {
+    path.ancestors().skip(1).any(|a| paths.contains(a))
+}
+
+/// Parses a `FILE_NOTIFY_INFORMATION` buffer filled by `ReadDirectoryChangesW`
+///
+/// Each record holds the next-entry offset, the action and a UTF-16 file
+/// name relative to the watched directory. Old/new name records are
+/// paired into a single rename event.
+pub fn parse_notify_buffer(root: &Path, buffer: &[u8]) -> Vec<FileEvent> {
+    const ACTION_ADDED: u32 = 1;
+    const ACTION_REMOVED: u32 = 2;
+    const ACTION_MODIFIED: u32 = 3;
+    const ACTION_RENAMED_OLD_NAME: u32 = 4;
+    const ACTION_RENAMED_NEW_NAME: u32 = 5;
+
+    let read_u32 = |offset: usize| -> Option<u32> {
+        buffer
+            .get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+
+    let mut events = Vec::new();
+    let mut pending_old_name: Option<PathBuf> = None;
+    let mut offset = 0;
+
+    while let (Some(next), Some(action), Some(name_len)) =
+        (read_u32(offset), read_u32(offset + 4), read_u32(offset + 8))
+    {
+        let name_start = offset + 12;
+        let Some(name_bytes) = buffer.get(name_start..name_start + name_len as usize) else {
+            break;
+        };
+        let name_utf16: Vec<u16> = name_bytes
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        let path = root.join(String::from_utf16_lossy(&name_utf16));
+
+        match action {
+            ACTION_ADDED => events.push(FileEvent::Created(path)),
+            ACTION_REMOVED => events.push(FileEvent::Deleted(path)),
+            ACTION_MODIFIED => events.push(FileEvent::Modified(path)),
+            ACTION_RENAMED_OLD_NAME => pending_old_name = Some(path),
+            ACTION_RENAMED_NEW_NAME => match pending_old_name.take() {
+                Some(from) => events.push(FileEvent::Renamed { from, to: path }),
+                None => events.push(FileEvent::Created(path)),
+            },
+            _ => {}
+        }
+
+        if next == 0 {
+            break;
+        }
+        offset += next as usize;
+    }
+
+    // An old name without a matching new name was moved out of the root
+    if let Some(from) = pending_old_name {
+        events.push(FileEvent::Deleted(from));
+    }
+
+    events
+}
+
+#[cfg(windows)]
+pub use native::NativeWatcher;
+
+#[cfg(windows)]
+mod native {
+    use super::{parse_notify_buffer, ChangeWatcher, FileEvent};
+    use crate::error::Result;
+    use crate::utils::to_wide_string;
+    use std::os::windows::io::AsRawHandle;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+    use std::sync::Arc;
+    use std::thread::JoinHandle;
+    use std::time::{Duration, Instant};
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::{CloseHandle, HANDLE, TRUE};
+    use windows::Win32::Storage::FileSystem::{
+        CreateFileW, ReadDirectoryChangesW, FILE_FLAG_BACKUP_SEMANTICS, FILE_LIST_DIRECTORY,
+        FILE_NOTIFY_CHANGE_DIR_NAME, FILE_NOTIFY_CHANGE_FILE_NAME, FILE_NOTIFY_CHANGE_LAST_WRITE,
+        FILE_NOTIFY_CHANGE_SIZE, FILE_SHARE_DELETE, FILE_SHARE_READ, FILE_SHARE_WRITE,
+        OPEN_EXISTING,
+    };
+    use windows::Win32::System::IO::CancelSynchronousIo;
+
+    /// Size of the notification buffer for each watched directory
+    const NOTIFY_BUFFER_SIZE: usize = 64 * 1024;
+
+    /// How long dropping the watcher keeps trying to stop a reader thread
+    const STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
+    /// Time between attempts to cancel a reader's pending call
+    const CANCEL_RETRY: Duration = Duration::from_millis(10);
+
+    /// Directory handle that can be moved to a reader thread
+    struct DirHandle(isize);
+
+    impl DirHandle {
+        fn handle(&self) -> HANDLE {
+            HANDLE(self.0 as *mut core::ffi::c_void)
+        }
+    }
+
+    /// A directory being watched and the thread blocked reading it
+    struct Reader {
+        handle: isize,
+        thread: JoinHandle<()>,
+    }
+
+    /// Watcher backed by `ReadDirectoryChangesW`
+    ///
+    /// Runs one blocking reader thread per root and forwards parsed
+    /// events over a channel.
+    pub struct NativeWatcher {
+        receiver: Receiver<FileEvent>,
+        readers: Vec<Reader>,
+        stop: Arc<AtomicBool>,
+    }
+
+    impl NativeWatcher {
+        /// Opens every root for change notification
+        ///
+        /// Fails if none of the roots could be opened.
+        pub fn new(roots: &[PathBuf]) -> Result<Self> {
+            let (sender, receiver) = mpsc::channel();
+            let stop = Arc::new(AtomicBool::new(false));
+            let mut readers = Vec::new();
+            let mut last_error = None;
+
+            for root in roots {
+                match open_directory(root) {
+                    Ok(handle) => {
+                        let raw = handle.0;
+                        let thread =
+                            spawn_reader(root.clone(), handle, sender.clone(), stop.clone());
+                        readers.push(Reader {
+                            handle: raw,
+                            thread,
+                        });
+                    }
+                    Err(e) => {
+                        log::debug!("Cannot watch {}: {}", root.display(), e);
+                        last_error = Some(e);
+                    }
+                }
+            }
+
+            match (readers.is_empty(), last_error) {
+                (true, Some(e)) => Err(e),
+                _ => Ok(Self {
+                    receiver,
+                    readers,
+                    stop,
+                }),
+            }
+        }
+    }
+
+    impl ChangeWatcher for NativeWatcher {
+        fn name(&self) -> &'static str {
+            "native"
+        }
+
+        fn next_events(&mut self, timeout: Duration) -> Result<Vec<FileEvent>> {
+            let mut events = match self.receiver.recv_timeout(timeout) {
+                Ok(event) => vec![event],
+                Err(RecvTimeoutError::Timeout) => return Ok(Vec::new()),
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(crate::error::RustleError::index_error(
+                        "all change notification readers stopped",
+                    ))
+                }
+            };
+            events.extend(self.receiver.try_iter());
+            Ok(events)
+        }
+    }
+
+    impl Drop for NativeWatcher {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::SeqCst);
+            for reader in self.readers.drain(..) {
+                if !stop_reader(&reader.thread) {
+                    // Closing the handle under a reader that is still
+                    // running could let it read from a reused handle value
+                    log::warn!("A change notification reader did not stop");
+                    continue;
+                }
+                let _ = reader.thread.join();
+                unsafe {
+                    let _ = CloseHandle(DirHandle(reader.handle).handle());
+                }
+            }
+        }
+    }
+
+    /// Cancels a reader's pending `ReadDirectoryChangesW` call until the
+    /// thread sees the stop flag and exits
+    ///
+    /// Cancelling does nothing while the thread is between calls, so it is
+    /// retried until the thread finishes or the timeout passes. Returns
+    /// false if the thread is still running.
+    fn stop_reader(thread: &JoinHandle<()>) -> bool {
+        let deadline = Instant::now() + STOP_TIMEOUT;
+        let thread_handle = HANDLE(thread.as_raw_handle());
+        while !thread.is_finished() {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            unsafe {
+                let _ = CancelSynchronousIo(thread_handle);
+            }
+            std::thread::sleep(CANCEL_RETRY);
+        }
+        true
+    }
+
+    fn open_directory(path: &Path) -> Result<DirHandle> {
+        let path_wide = to_wide_string(&path.to_string_lossy());
+        let handle = unsafe {
+            CreateFileW(
+                PCWSTR(path_wide.as_ptr()),
+                FILE_LIST_DIRECTORY.0,
+                FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+                None,
+                OPEN_EXISTING,
+                FILE_FLAG_BACKUP_SEMANTICS,
+                HANDLE::default(),
+            )?
+        };
+        Ok(DirHandle(handle.0 as isize))
+    }
+
+    fn spawn_reader(
+        root: PathBuf,
+        handle: DirHandle,
+        sender: Sender<FileEvent>,
+        stop: Arc<AtomicBool>,
+    ) -> JoinHandle<()> {
+        std::thread::spawn(move || {
+            // u32-backed buffer keeps the records DWORD-aligned as required
+            let mut buffer = vec![0u32; NOTIFY_BUFFER_SIZE / 4];
+
+            while !stop.load(Ordering::SeqCst) {
+                let mut bytes_returned = 0u32;
+                let result = unsafe {
+                    ReadDirectoryChangesW(
+                        handle.handle(),
+                        buffer.as_mut_ptr() as *mut core::ffi::c_void,
+                        NOTIFY_BUFFER_SIZE as u32,
+                        TRUE,
+                        FILE_NOTIFY_CHANGE_FILE_NAME
+                            | FILE_NOTIFY_CHANGE_DIR_NAME
+                            | FILE_NOTIFY_CHANGE_SIZE
+                            | FILE_NOTIFY_CHANGE_LAST_WRITE,
+                        Some(&mut bytes_returned as *mut u32),
+                        None,
+                        None,
+                    )
+                };
+
+                if let Err(e) = result {
+                    if !stop.load(Ordering::SeqCst) {
+                        log::warn!("Stopped watching {}: {}", root.display(), e);
+                    }
+                    break;
+                }
+
+                let events = if bytes_returned == 0 {
+                    // The buffer overflowed and changes were dropped
+                    vec![FileEvent::Rescan(root.clone())]
+                } else {
+                    let bytes = unsafe {
+                        std::slice::from_raw_parts(
+                            buffer.as_ptr() as *const u8,
+                            bytes_returned as usize,
+                        )
+                    };
+                    parse_notify_buffer(&root, bytes)
+                };
+
+                for event in events {
+                    if sender.send(event).is_err() {
+                        return;
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Creates the best available watcher for the given roots
+///
+/// Prefers native change notifications and falls back to polling. Native
+/// events are not filtered here; the index drops ignored paths itself.
+pub fn default_watcher(roots: &[PathBuf], ignore: &IgnoreSet) -> Box<dyn ChangeWatcher> {
+    #[cfg(windows)]
+    {
+        match NativeWatcher::new(roots) {
+            Ok(watcher) => return Box::new(watcher),
+            Err(e) => log::warn!("Native change notifications unavailable: {}", e),
+        }
+    }
+
+    Box::new(PollingWatcher::new(roots, ignore.clone()))
+}
+
+/// Handle to the background thread that keeps the file index current
+///
+/// The thread is stopped when the handle is dropped.
+pub struct IndexWatcher {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl IndexWatcher {
+    /// Starts applying events from `watcher` to `index`
+    ///
+    /// An index loaded from disk is first reconciled by rescanning the
+    /// directories changed since it was saved. If `full_text` is given, the documents touched by each event are
+    /// re-synced with the file index afterwards. If `save_path` is given,
+    /// the index is saved there after changes, at most once per
+    /// `SAVE_INTERVAL`, and again on shutdown; the full-text index is then
+    /// saved to its default location alongside it.
+    pub fn spawn(
+        index: Arc<RwLock<FileIndex>>,
+        full_text: Option<Arc<RwLock<FullTextIndex>>>,
+        mut watcher: Box<dyn ChangeWatcher>,
+        save_path: Option<PathBuf>,
+    ) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+
+        let thread = std::thread::spawn(move || {
+            log::info!("Watching for file changes ({} backend)", watcher.name());
+            let mut dirty = false;
+            let mut last_save = Instant::now();
+
+            let startup = startup_events(&index);
+            if !startup.is_empty() {
+                log::info!(
+                    "Reconciling loaded file index: {} directories changed",
+                    startup.len()
+                );
+                apply_events(&index, full_text.as_deref(), &startup);
+                dirty = true;
+            }
+
+            while !thread_stop.load(Ordering::SeqCst) {
+                match watcher.next_events(EVENT_WAIT) {
+                    Ok(events) if !events.is_empty() => {
+                        log::debug!("Applying {} file changes to index", events.len());
+                        apply_events(&index, full_text.as_deref(), &events);
+                        dirty = true;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        log::warn!("File watcher stopped: {}", e);
+                        break;
+                    }
+                }
+
+                if dirty && last_save.elapsed() >= SAVE_INTERVAL {
+                    save_index(&index, full_text.as_deref(), save_path.as_deref());
+                    dirty = false;
+                    last_save = Instant::now();
+                }
+            }
+
+            if dirty {
+                save_index(&index, full_text.as_deref(), save_path.as_deref());
+            }
+        });
+
+        Self {
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for IndexWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Returns the events that bring an index loaded from disk up to date
+///
+/// Directories are checked for changes with no lock held.
+fn startup_events(index: &RwLock<FileIndex>) -> Vec<FileEvent> {
+    let Some(check) = index.read().unwrap().reconcile_check() else {
+        return Vec::new();
+    };
+    check
+        .changed_dirs()
+        .into_iter()
+        .map(FileEvent::Rescan)
+        .collect()
+}
+
+/// Applies events to the file index, then re-syncs the documents they touch
+///
+/// Rescans are planned under a read lock and run with no lock held, so
+/// only splicing in their results shuts out searches. Outer directories
+/// are spliced first, so rescans of directories nested inside them win.
+fn apply_events(
+    index: &RwLock<FileIndex>,
+    full_text: Option<&RwLock<FullTextIndex>>,
+    events: &[FileEvent],
+) {
+    let mut scans: Vec<SubtreeScan> = {
+        let index = index.read().unwrap();
+        events
+            .iter()
+            .flat_map(|event| event.rescan_dirs(&index))
+            .map(|dir| index.plan_rescan(&dir))
+            .collect()
+    };
+    scans.sort_by(|a, b| a.path().cmp(b.path()));
+    scans.dedup_by(|a, b| a.path() == b.path());
+    let scanned: Vec<(PathBuf, Vec<IndexEntry>)> = scans
+        .iter()
+        .map(|scan| (scan.path().to_path_buf(), scan.run()))
+        .collect();
+
+    {
+        let mut index = index.write().unwrap();
+        for event in events {
+            event.apply_changes(&mut index);
+        }
+        for (dir, entries) in scanned {
+            index.replace_subtree(&dir, entries);
+        }
+    }
+
+    if let Some(full_text) = full_text {
+        for path in events.iter().flat_map(FileEvent::paths) {
+            sync_full_text(index, full_text, path);
+        }
+    }
+}
+
+/// Brings the documents at or below `path` in line with the file index
+///
+/// Documents are read and tokenized with no lock held, so searches are
+/// not blocked meanwhile; the write lock is only taken to remove stale
+/// documents and to add each batch of new ones.
+fn sync_full_text(index: &RwLock<FileIndex>, full_text: &RwLock<FullTextIndex>, path: &Path) {
+    let plan = {
+        let index = index.read().unwrap();
+        let full_text = full_text.read().unwrap();
+        full_text.plan_sync(index.subtree(path), Some(path))
+    };
+    full_text.write().unwrap().remove_stale(&plan);
+
+    for batch in plan.batches() {
+        if full_text.read().unwrap().is_full() {
+            log::info!("Full-text index reached its size limit; some files are left out");
+            break;
+        }
+        let documents = read_documents(batch, plan.max_file_size());
+        full_text.write().unwrap().add_documents(documents);
+    }
+}
+
+fn save_index(
+    index: &RwLock<FileIndex>,
+    full_text: Option<&RwLock<FullTextIndex>>,
+    path: Option<&Path>,
+) {
+    let Some(path) = path else {
+        return;
+    };
+    if let Err(e) = index.read().unwrap().save(path) {
+        log::warn!("Failed to save file index: {}", e);
+    }
+    if let (Some(full_text), Some(full_text_path)) = (full_text, FullTextIndex::default_path()) {
+        if let Err(e) = full_text.read().unwrap().save(&full_text_path) {
+            log::warn!("Failed to save full-text index: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn names(index: &FileIndex) -> Vec<String> {
+        index.entries().iter().map(|e| e.name.clone()).collect()
+    }
+
+    #[test]
+    fn test_polling_detects_create_and_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("old.txt"), b"old").unwrap();
+        let mut watcher = PollingWatcher::new(&[dir.path().to_path_buf()], IgnoreSet::default());
+
+        fs::remove_file(dir.path().join("old.txt")).unwrap();
+        fs::write(dir.path().join("new.txt"), b"a longer body").unwrap();
+
+        let events = watcher.scan();
+        assert!(events.contains(&FileEvent::Deleted(dir.path().join("old.txt"))));
+        assert!(events.contains(&FileEvent::Created(dir.path().join("new.txt"))));
+    }
+
+    #[test]
+    fn test_polling_detects_rename() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("docs")).unwrap();
+        fs::write(dir.path().join("docs").join("a.txt"), b"a").unwrap();
+        let mut watcher = PollingWatcher::new(&[dir.path().to_path_buf()], IgnoreSet::default());
+
+        fs::rename(dir.path().join("docs"), dir.path().join("papers")).unwrap();
+
+        let events = watcher.scan();
+        assert!(events.contains(&FileEvent::Renamed {
+            from: dir.path().join("docs"),
+            to: dir.path().join("papers"),
+        }));
+        assert!(!events
+            .iter()
+            .any(|e| matches!(e, FileEvent::Created(_) | FileEvent::Deleted(_))));
+    }
+
+    #[test]
+    fn test_polling_reports_only_subtree_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut watcher = PollingWatcher::new(&[dir.path().to_path_buf()], IgnoreSet::default());
+
+        fs::create_dir_all(dir.path().join("music").join("albums")).unwrap();
+        fs::write(
+            dir.path().join("music").join("albums").join("song.mp3"),
+            b"x",
+        )
+        .unwrap();
+
+        let created: Vec<FileEvent> = watcher
+            .scan()
+            .into_iter()
+            .filter(|e| matches!(e, FileEvent::Created(_)))
+            .collect();
+        assert_eq!(created, vec![FileEvent::Created(dir.path().join("music"))]);
+    }
+
+    #[test]
+    fn test_polling_sees_every_level() {
+        let dir = tempfile::tempdir().unwrap();
+        let deep = dir.path().join("a").join("b").join("c").join("d").join("e");
+        fs::create_dir_all(&deep).unwrap();
+        let mut watcher = PollingWatcher::new(&[dir.path().to_path_buf()], IgnoreSet::default());
+
+        fs::write(deep.join("buried.txt"), b"x").unwrap();
+        assert!(watcher
+            .scan()
+            .contains(&FileEvent::Created(deep.join("buried.txt"))));
+    }
+
+    #[test]
+    fn test_diff_matches_renames_among_many_creations() {
+        let entry = |path: String, is_dir: bool, size: u64| {
+            let path = PathBuf::from(path);
+            let entry = IndexEntry {
+                name: path.file_name().unwrap().to_string_lossy().into_owned(),
+                path: path.clone(),
+                is_dir,
+                size,
+                modified: 1_700_000_000,
+            };
+            (path, entry)
+        };
+
+        let mut old = HashMap::from([
+            entry("/r/report.txt".to_string(), false, 7),
+            entry("/r/a.txt".to_string(), false, 1),
+            entry("/r/b.txt".to_string(), false, 1),
+        ]);
+        let mut new = HashMap::from([entry("/r/summary.txt".to_string(), false, 7)]);
+        // An unpacked archive: one new folder holding many files
+        new.extend([entry("/r/unpacked".to_string(), true, 0)]);
+        for i in 0..20_000 {
+            new.extend([entry(format!("/r/unpacked/{}.txt", i), false, 1)]);
+        }
+        // Two files of one signature deleted, two created: not a rename
+        old.extend([entry("/r/c.txt".to_string(), false, 2)]);
+        new.extend([
+            entry("/r/d.txt".to_string(), false, 1),
+            entry("/r/e.txt".to_string(), false, 1),
+        ]);
+
+        let events = diff_snapshots(&old, &new);
+        assert_eq!(
+            events,
+            [
+                FileEvent::Renamed {
+                    from: PathBuf::from("/r/report.txt"),
+                    to: PathBuf::from("/r/summary.txt"),
+                },
+                FileEvent::Deleted(PathBuf::from("/r/a.txt")),
+                FileEvent::Deleted(PathBuf::from("/r/b.txt")),
+                FileEvent::Deleted(PathBuf::from("/r/c.txt")),
+                FileEvent::Created(PathBuf::from("/r/d.txt")),
+                FileEvent::Created(PathBuf::from("/r/e.txt")),
+                FileEvent::Created(PathBuf::from("/r/unpacked")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_events_keep_index_in_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let roots = vec![dir.path().to_path_buf()];
+        fs::create_dir(dir.path().join("docs")).unwrap();
+        fs::write(dir.path().join("docs").join("report.txt"), b"q1").unwrap();
+
+        let mut index = FileIndex::build(&roots, IgnoreSet::default());
+        let mut watcher = PollingWatcher::new(&roots, IgnoreSet::default());
+
+        fs::rename(dir.path().join("docs"), dir.path().join("archive")).unwrap();
+        fs::write(dir.path().join("todo.txt"), b"things").unwrap();
+        fs::create_dir(dir.path().join("node_modules")).unwrap();
+
+        for event in watcher.scan() {
+            event.apply(&mut index);
+        }
+
+        let rebuilt = FileIndex::build(&roots, IgnoreSet::default());
+        assert_eq!(index.entries(), rebuilt.entries());
+        assert!(names(&index).contains(&"archive".to_string()));
+        assert!(!names(&index).contains(&"docs".to_string()));
+        assert!(!names(&index).contains(&"node_modules".to_string()));
+    }
+
+    #[test]
+    fn test_events_keep_full_text_in_sync() {
+        use crate::config::FullTextConfig;
+        use crate::fulltext::FullTextQuery;
+
+        let dir = tempfile::tempdir().unwrap();
+        let roots = vec![dir.path().to_path_buf()];
+        let report = dir.path().join("report.txt");
+        fs::write(&report, "quarterly report").unwrap();
+        let file_index = FileIndex::build(&roots, IgnoreSet::default());
+        let full_text = FullTextIndex::build(&file_index, &FullTextConfig::default());
+        let (index, full_text) = (RwLock::new(file_index), RwLock::new(full_text));
+
+        fs::write(&report, "annual review of the year").unwrap();
+        fs::write(dir.path().join("minutes.txt"), "quarterly minutes").unwrap();
+        apply_events(
+            &index,
+            Some(&full_text),
+            &[
+                FileEvent::Modified(report.clone()),
+                FileEvent::Created(dir.path().join("minutes.txt")),
+            ],
+        );
+
+        let full_text = full_text.read().unwrap();
+        let found = |text: &str| -> Vec<PathBuf> {
+            full_text
+                .search(&FullTextQuery::parse(text))
+                .into_iter()
+                .map(|m| m.document.path)
+                .collect()
+        };
+        assert_eq!(found("annual review"), [report]);
+        assert_eq!(found("quarterly"), [dir.path().join("minutes.txt")]);
+    }
+
+    #[test]
+    fn test_loaded_index_is_reconciled_on_start() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = tempfile::tempdir().unwrap();
+        let roots = vec![dir.path().to_path_buf()];
+        let index_path = data.path().join("index.bin");
+        fs::create_dir(dir.path().join("docs")).unwrap();
+        fs::write(dir.path().join("docs").join("report.txt"), b"q1").unwrap();
+        FileIndex::build(&roots, IgnoreSet::default())
+            .save(&index_path)
+            .unwrap();
+
+        // Changes made while Rustle was not running
+        fs::remove_file(dir.path().join("docs").join("report.txt")).unwrap();
+        fs::write(dir.path().join("docs").join("summary.txt"), b"q2").unwrap();
+
+        let loaded = FileIndex::load_or_build(&index_path, &roots, IgnoreSet::default());
+        assert!(loaded.is_loaded());
+        assert!(names(&loaded).contains(&"report.txt".to_string()));
+
+        let index = Arc::new(RwLock::new(loaded));
+        let watcher =
+            PollingWatcher::with_interval(&roots, IgnoreSet::default(), Duration::from_secs(3600));
+        // Dropping the handle waits for the thread, which reconciles first
+        drop(IndexWatcher::spawn(
+            index.clone(),
+            None,
+            Box::new(watcher),
+            None,
+        ));
+
+        let index = index.read().unwrap();
+        let rebuilt = FileIndex::build(&roots, IgnoreSet::default());
+        assert_eq!(index.entries(), rebuilt.entries());
+        assert!(names(&index).contains(&"summary.txt".to_string()));
+        assert!(!names(&index).contains(&"report.txt".to_string()));
+    }
+
+    #[test]
+    fn test_parse_notify_buffer() {
+        fn record(action: u32, name: &str, last: bool) -> Vec<u8> {
+            let name: Vec<u8> = name.encode_utf16().flat_map(|c| c.to_le_bytes()).collect();
+            let len = (12 + name.len() + 3) & !3;
+            let mut bytes = Vec::new();
+            bytes.extend_from_slice(&(if last { 0 } else { len as u32 }).to_le_bytes());
+            bytes.extend_from_slice(&action.to_le_bytes());
+            bytes.extend_from_slice(&(name.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&name);
+            bytes.resize(len, 0);
+            bytes
+        }
+
+        let root = Path::new("root");
+        let mut buffer = record(1, "new.txt", false);
+        buffer.extend(record(4, "before.txt", false));
+        buffer.extend(record(5, "after.txt", false));
+        buffer.extend(record(2, "gone.txt", true));
+
+        assert_eq!(
+            parse_notify_buffer(root, &buffer),
+            vec![
+                FileEvent::Created(root.join("new.txt")),
+                FileEvent::Renamed {
+                    from: root.join("before.txt"),
+                    to: root.join("after.txt"),
+                },
+                FileEvent::Deleted(root.join("gone.txt")),
+            ]
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! Web search shortcuts and URLs for Rustle
+//!
+//! A query starting with a configured keyword, such as `g rust lifetimes`,
+//! offers to search that site: the rest of the query is URL-encoded into
+//! the shortcut's URL template. URLs and domain-like input such as
+//! `docs.rs/serde` are offered to be opened in the browser directly.
+
+#![allow(dead_code)]
+
+use crate::config::{WebSearchConfig, WebShortcut};
+use crate::provider::{Action, Provider, ProviderQuery};
+use crate::search::{CancelToken, ResultType, SearchResult, Section};
+use std::path::PathBuf;
+
+/// Top-level domains recognised without a scheme, `www.` or path
+///
+/// Domains that are also common file extensions (`.rs`, `.py`, `.ai`,
+/// `.in`, `.pl`) are left out so file names are not mistaken for sites; they are still
+/// recognised with a path, as in `docs.rs/serde`.
+const KNOWN_TLDS: &[&str] = &[
+    "com", "org", "net", "edu", "gov", "mil", "int", "io", "dev", "app", "co", "me", "info", "biz",
+    "xyz", "tech", "site", "online", "store", "blog", "cloud", "page", "us", "uk", "de", "fr",
+    "jp", "cn", "ru", "br", "ca", "au", "nl", "eu", "ch", "se", "no", "es", "it", "be", "at", "dk",
+    "fi", "ie", "nz", "kr", "tw", "hk", "sg", "za", "mx", "ar", "tv", "gg", "fm", "ly",
+];
+
+/// Percent-encodes text for use in a URL query string
+///
+/// Everything except letters, digits and `-._~` is encoded as UTF-8 bytes,
+/// so spaces become `%20`.
+pub fn encode_query(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Fills a shortcut's URL template with the encoded search text
+pub fn build_url(template: &str, query: &str) -> String {
+    template.replace("{query}", &encode_query(query))
+}
+
+/// Returns the URL to open if `text` is a URL or looks like one
+///
+/// Accepts `http`, `https` and `ftp` URLs, `localhost` and IPv4 addresses
+/// with a port or path, and domain names that start with `www.`, have a
+/// port or path, or end in a well-known top-level domain. A scheme is
+/// added where missing.
+pub fn detect_url(text: &str) -> Option<String> {
+    let text = text.trim();
+    if text.is_empty() || text.contains(char::is_whitespace) {
+        return None;
+    }
+
+    let lower = text.to_lowercase();
+    if let Some(scheme_end) = lower.find("://") {
+        let scheme = &lower[..scheme_end];
+        let has_host = text.len() > scheme_end + 3;
+        return (matches!(scheme, "http" | "https" | "ftp") && has_host).then(|| text.to_string());
+    }
+
+    let end = lower.find(['/', '?', '#']).unwrap_or(lower.len());
+    let (authority, rest) = lower.split_at(end);
+    let (host, port) = match authority.split_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (authority, None),
+    };
+    if port.is_some_and(|p| p.is_empty() || p.len() > 5 || !p.chars().all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+
+    let explicit = port.is_some() || !rest.is_empty();
+    let scheme = if host == "localhost" || is_ipv4(host) {
+        if !explicit && host == "localhost" {
+            return None;
+        }
+        "http"
+    } else {
+        let tld = host.rsplit('.').next().unwrap_or_default();
+        let recognised = explicit || host.starts_with("www.") || KNOWN_TLDS.contains(&tld);
+        if !is_domain_name(host) || !recognised {
+            return None;
+        }
+        "https"
+    };
+    Some(format!("{}://{}", scheme, text))
+}
+
+/// Returns true for a dotted name ending in an alphabetic top-level domain
+fn is_domain_name(host: &str) -> bool {
+    let labels: Vec<&str> = host.split('.').collect();
+    let valid_label = |label: &&str| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    };
+    let tld = labels.last().copied().unwrap_or_default();
+    labels.len() >= 2
+        && labels.iter().all(valid_label)
+        && tld.len() >= 2
+        && tld.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+/// Returns true for an address such as `192.168.1.10`
+fn is_ipv4(host: &str) -> bool {
+    let parts: Vec<&str> = host.split('.').collect();
+    parts.len() == 4
+        && parts
+            .iter()
+            .all(|p| !p.is_empty() && p.len() <= 3 && p.chars().all(|c| c.is_ascii_digit()))
+        && parts.iter().all(|p| p.parse::<u8>().is_ok())
+}
+
+/// Offers web searches for keyword shortcuts and opens URLs
+pub struct WebProvider {
+    config: WebSearchConfig,
+}
+
+impl WebProvider {
+    pub fn new(config: WebSearchConfig) -> Self {
+        Self { config }
+    }
+
+    /// Returns the shortcut the query starts with and the text to search
+    fn shortcut<'a>(&self, text: &'a str) -> Option<(&WebShortcut, &'a str)> {
+        let (keyword, search) = text.split_once(char::is_whitespace)?;
+        let search = search.trim();
+        if search.is_empty() {
+            return None;
+        }
+        self.config
+            .shortcuts
+            .iter()
+            .find(|s| s.keyword.eq_ignore_ascii_case(keyword))
+            .map(|shortcut| (shortcut, search))
+    }
+}
+
+impl Provider for WebProvider {
+    fn id(&self) -> &'static str {
+        "web"
+    }
+
+    fn title(&self) -> &str {
+        "Web"
+    }
+
+    fn priority(&self) -> i32 {
+        5
+    }
+
+    /// Searches are sent to the web as typed
+    fn wants_filters(&self) -> bool {
+        false
+    }
+
+    fn query(
+        &self,
+        query: &ProviderQuery,
+        _cancel: &CancelToken,
+        emit: &(dyn Fn(Vec<Section>) + Sync),
+    ) -> bool {
+        let text = query.text.trim();
+        let mut results = Vec::new();
+        let mut push = |name: String, url: String| {
+            results.push(SearchResult {
+                name,
+                path: PathBuf::from(&url),
+                result_type: ResultType::Web,
+                score: 0,
+                description: url,
+                provider: self.id(),
+                shortcut: None,
+            })
+        };
+
+        if self.config.enabled {
+            if let Some((shortcut, search)) = self.shortcut(text) {
+                push(
+                    format!("Search {} for “{}”", shortcut.name, search),
+                    build_url(&shortcut.url, search),
+                );
+            }
+        }
+        if self.config.detect_urls {
+            if let Some(url) = detect_url(text) {
+                push(format!("Open {}", text), url);
+            }
+        }
+
+        if !results.is_empty() {
+            emit(vec![self.section(results)]);
+        }
+        true
+    }
+
+    /// Opens the page, or copies its URL
+    fn actions(&self, result: &SearchResult) -> Vec<Action> {
+        vec![
+            Action::Open(result.path.clone()),
+            Action::Copy(result.description.clone()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::run_provider;
+
+    #[test]
+    fn test_build_url() {
+        assert_eq!(encode_query("rust lifetimes"), "rust%20lifetimes");
+        assert_eq!(encode_query("a+b=c&d"), "a%2Bb%3Dc%26d");
+        assert_eq!(encode_query("café ~_-."), "caf%C3%A9%20~_-.");
+        assert_eq!(
+            build_url("https://github.com/search?q={query}", "tokio #1 bug"),
+            "https://github.com/search?q=tokio%20%231%20bug"
+        );
+        assert_eq!(
+            build_url("https://example.com/", "ignored"),
+            "https://example.com/"
+        );
+    }
+
+    #[test]
+    fn test_detect_url() {
+        let cases = [
+            ("https://example.com/a?b=c", "https://example.com/a?b=c"),
+            ("HTTP://Example.com", "HTTP://Example.com"),
+            ("docs.rs/serde", "https://docs.rs/serde"),
+            ("example.ai/docs", "https://example.ai/docs"),
+            ("example.com", "https://example.com"),
+            ("www.example.xyz", "https://www.example.xyz"),
+            ("news.ycombinator.com", "https://news.ycombinator.com"),
+            ("example.rs:8443", "https://example.rs:8443"),
+            ("localhost:3000/api", "http://localhost:3000/api"),
+            ("192.168.1.10", "http://192.168.1.10"),
+        ];
+        for (input, url) in cases {
+            assert_eq!(detect_url(input).as_deref(), Some(url), "{}", input);
+        }
+
+        for input in [
+            "notepad",
+            "main.rs",
+            "report.pdf",
+            "node.js",
+            "src/main.rs",
+            "logo.ai",
+            "Makefile.in",
+            "build.pl",
+            "v1.2.3",
+            "1.5",
+            "localhost",
+            "john@example.com",
+            "example.com/path with spaces",
+            "file:///C:/Windows",
+            r"C:\Users\me",
+            "256.1.1.1",
+            "-bad.com",
+        ] {
+            assert_eq!(detect_url(input), None, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_shortcuts() {
+        let provider = WebProvider::new(WebSearchConfig::default());
+
+        let found = run_provider(&provider, "GH  tokio runtime");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "Search GitHub for “tokio runtime”");
+        assert_eq!(
+            found[0].description,
+            "https://github.com/search?q=tokio%20runtime"
+        );
+        assert_eq!(
+            provider.actions(&found[0])[0],
+            Action::Open(PathBuf::from("https://github.com/search?q=tokio%20runtime"))
+        );
+
+        assert!(run_provider(&provider, "g").is_empty());
+        assert!(run_provider(&provider, "gimp editor").is_empty());
+
+        let found = run_provider(&provider, "crates.io");
+        assert_eq!(found[0].name, "Open crates.io");
+        assert_eq!(found[0].description, "https://crates.io");
+
+        let disabled = WebProvider::new(WebSearchConfig {
+            enabled: false,
+            detect_urls: false,
+            ..WebSearchConfig::default()
+        });
+        assert!(run_provider(&disabled, "g rust").is_empty());
+        assert!(run_provider(&disabled, "example.com").is_empty());
+    }
+}
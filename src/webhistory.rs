@@ -0,0 +1,612 @@
+//! Browser history for Rustle
+//!
+//! This opt-in provider finds pages visited in Chromium-based browsers and
+//! Firefox. Browsers keep their history database open while they run, so
+//! each profile's `History` or `places.sqlite` file is copied first and
+//! only the copy is read, then deleted. Pages are ranked by how well their
+//! title or URL matches, boosted by how often and how recently they were
+//! visited. Profiles set never to remember history are skipped, as are
+//! sites excluded in the configuration; private windows leave nothing
+//! behind to find.
+
+#![allow(dead_code)]
+
+use crate::browsers::{self, Engine, FileStamp, Profile};
+use crate::cliphistory::format_age;
+use crate::config::{BrowserHistoryConfig, Config};
+use crate::error::Result;
+use crate::json::{self, Json};
+use crate::provider::{Action, Provider, ProviderQuery};
+use crate::query;
+use crate::search::{name_score, CancelToken, ResultType, SearchResult, Section};
+use crate::sqlite::Database;
+use crate::utils::normalize_for_search;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Seconds from 1601-01-01, where Chromium counts time from, to the Unix
+/// epoch
+const WEBKIT_EPOCH_OFFSET: i64 = 11_644_473_600;
+
+/// Folder under the data directory that history databases are copied to
+const COPY_DIR_NAME: &str = "browser-history";
+
+/// Shortest time between checks of a profile for new history; browsers
+/// write to it with nearly every page load
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Ranking boost per logarithm of a page's visit count
+const VISIT_WEIGHT: f64 = 40.0;
+
+/// Days after which a page's visit boost has halved
+const RECENCY_HALF_LIFE_DAYS: f64 = 14.0;
+
+/// A visited page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page {
+    pub title: String,
+    pub url: String,
+    pub visit_count: u64,
+
+    /// Time of the last visit, in seconds since the Unix epoch
+    pub last_visit: u64,
+}
+
+/// Returns the host of a URL without `www.`, e.g. `docs.rs`
+pub fn domain(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    let host = match host.rfind(':') {
+        Some(i) if !host.ends_with(']') => &host[..i],
+        _ => host,
+    };
+    host.strip_prefix("www.").unwrap_or(host)
+}
+
+/// Returns true if `host` is one of `excluded` or a subdomain of one
+fn is_excluded(host: &str, excluded: &[String]) -> bool {
+    let host = host.to_lowercase();
+    excluded.iter().any(|domain| {
+        let domain = domain.trim().trim_start_matches('.').to_lowercase();
+        !domain.is_empty()
+            && (host == domain
+                || host
+                    .strip_suffix(domain.as_str())
+                    .is_some_and(|sub| sub.ends_with('.')))
+    })
+}
+
+/// Returns false for history entries that are not pages to go back to
+fn is_page(url: &str) -> bool {
+    let scheme = url.split(':').next().unwrap_or_default().to_lowercase();
+    matches!(scheme.as_str(), "http" | "https" | "file" | "ftp")
+}
+
+/// Reads the pages of a Chromium `History` database visited at or after
+/// `since`, in seconds since the Unix epoch
+///
+/// Hidden entries, such as subframes and redirects, are left out.
+pub fn parse_chromium_history(db: &Database, since: u64) -> Result<Vec<Page>> {
+    let urls = db.table("urls")?;
+    let pages = urls
+        .iter()
+        .filter(|row| row.integer("hidden").unwrap_or(0) == 0)
+        .filter_map(|row| {
+            let last_visit = row.integer("last_visit_time")? / 1_000_000 - WEBKIT_EPOCH_OFFSET;
+            page(
+                row.text("url")?,
+                row.text("title").unwrap_or_default(),
+                row.integer("visit_count").unwrap_or(0),
+                last_visit,
+                since,
+            )
+        })
+        .collect();
+    Ok(pages)
+}
+
+/// Reads the pages of a Firefox `places.sqlite` database visited at or
+/// after `since`, in seconds since the Unix epoch
+///
+/// Hidden entries and places that were bookmarked but never visited are
+/// left out.
+pub fn parse_firefox_history(db: &Database, since: u64) -> Result<Vec<Page>> {
+    let places = db.table("moz_places")?;
+    let pages = places
+        .iter()
+        .filter(|row| row.integer("hidden").unwrap_or(0) == 0)
+        .filter_map(|row| {
+            page(
+                row.text("url")?,
+                row.text("title").unwrap_or_default(),
+                row.integer("visit_count").unwrap_or(0),
+                row.integer("last_visit_date")? / 1_000_000,
+                since,
+            )
+        })
+        .collect();
+    Ok(pages)
+}
+
+fn page(url: &str, title: &str, visit_count: i64, last_visit: i64, since: u64) -> Option<Page> {
+    let last_visit = u64::try_from(last_visit).ok()?;
+    if last_visit < since || visit_count <= 0 || !is_page(url) {
+        return None;
+    }
+    Some(Page {
+        title: title.to_string(),
+        url: url.to_string(),
+        visit_count: visit_count as u64,
+        last_visit,
+    })
+}
+
+/// Returns true if a Chromium profile's `Preferences` turn off saving
+/// history
+fn chromium_history_disabled(preferences: &str) -> bool {
+    let Ok(preferences) = json::parse(preferences) else {
+        return false;
+    };
+    preferences
+        .get("history")
+        .and_then(|h| h.get("saving_disabled"))
+        == Some(&Json::Bool(true))
+}
+
+/// Returns true if a Firefox profile's `prefs.js` says never to remember
+/// history, or to always browse privately
+fn firefox_history_disabled(prefs: &str) -> bool {
+    let pref = |name: &str| {
+        let key = format!("user_pref(\"{}\",", name);
+        prefs.lines().find_map(|line| {
+            let value = line.trim().strip_prefix(&key)?;
+            Some(value.trim_end_matches([';', ')', ' ']).trim() == "true")
+        })
+    };
+    pref("places.history.enabled") == Some(false)
+        || pref("browser.privatebrowsing.autostart") == Some(true)
+}
+
+/// Returns true if a profile is set never to remember history
+pub fn history_disabled(profile: &Profile) -> bool {
+    match profile.engine {
+        Engine::Chromium => std::fs::read_to_string(profile.dir.join("Preferences"))
+            .is_ok_and(|prefs| chromium_history_disabled(&prefs)),
+        Engine::Firefox => std::fs::read_to_string(profile.dir.join("prefs.js"))
+            .is_ok_and(|prefs| firefox_history_disabled(&prefs)),
+    }
+}
+
+/// Returns the database a profile keeps its history in
+fn history_file(profile: &Profile) -> PathBuf {
+    match profile.engine {
+        Engine::Chromium => profile.dir.join("History"),
+        Engine::Firefox => profile.dir.join("places.sqlite"),
+    }
+}
+
+/// Copies a database, and its `-wal` file if it has one, into `dir`
+///
+/// Returns the path of the copy.
+fn copy_database(source: &Path, dir: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let name = source.file_name().unwrap_or_default();
+    let copy = dir.join(name);
+    std::fs::copy(source, &copy)?;
+
+    let mut wal_name = name.to_owned();
+    wal_name.push("-wal");
+    let wal = source.with_file_name(&wal_name);
+    if wal.is_file() {
+        std::fs::copy(&wal, dir.join(&wal_name))?;
+    } else {
+        let _ = std::fs::remove_file(dir.join(&wal_name));
+    }
+    Ok(copy)
+}
+
+/// Reads the history of one profile from a copy made in `copy_dir`,
+/// which is deleted again afterwards
+pub fn read_profile(profile: &Profile, copy_dir: &Path, since: u64) -> Result<Vec<Page>> {
+    let copy = copy_database(&history_file(profile), copy_dir);
+    let pages = copy.and_then(|copy| {
+        let db = Database::open(&copy)?;
+        match profile.engine {
+            Engine::Chromium => parse_chromium_history(&db, since),
+            Engine::Firefox => parse_firefox_history(&db, since),
+        }
+    });
+    let _ = std::fs::remove_dir_all(copy_dir);
+    pages
+}
+
+/// Ranking boost from a page's visits, fading as its last visit ages
+fn visit_boost(page: &Page, now: u64) -> i64 {
+    let age_days = now.saturating_sub(page.last_visit) as f64 / 86_400.0;
+    let recency = 0.5f64.powf(age_days / RECENCY_HALF_LIFE_DAYS);
+    (VISIT_WEIGHT * (page.visit_count as f64).ln_1p() * recency).round() as i64
+}
+
+/// The history of one profile, as of when it was last read
+struct LoadedProfile {
+    profile: Profile,
+    stamp: Option<FileStamp>,
+    checked: Option<Instant>,
+    pages: Arc<Vec<Page>>,
+}
+
+/// Finds visited pages in every browser profile
+///
+/// Copying and parsing a history database can take a while, so profiles
+/// are read again on a background thread and queries search whatever was
+/// read last.
+pub struct BrowserHistoryProvider {
+    config: BrowserHistoryConfig,
+    profiles: Arc<Mutex<Vec<LoadedProfile>>>,
+
+    /// Thread reading profiles again, if one was started
+    refresh: Mutex<Option<JoinHandle<()>>>,
+
+    /// Where history databases are copied to be read
+    copy_dir: PathBuf,
+
+    matcher: SkimMatcherV2,
+}
+
+impl BrowserHistoryProvider {
+    /// Creates the provider over the profiles of the installed browsers
+    /// and starts reading them
+    pub fn new(config: BrowserHistoryConfig) -> Self {
+        let copy_dir = Config::data_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join(COPY_DIR_NAME);
+        let provider = Self::with_profiles(config, browsers::find_profiles(), copy_dir);
+        provider.start_refresh();
+        provider
+    }
+
+    /// Creates the provider over the given profiles, without reading them
+    pub fn with_profiles(
+        config: BrowserHistoryConfig,
+        profiles: Vec<Profile>,
+        copy_dir: PathBuf,
+    ) -> Self {
+        let profiles = profiles
+            .into_iter()
+            .map(|profile| LoadedProfile {
+                profile,
+                stamp: None,
+                checked: None,
+                pages: Arc::default(),
+            })
+            .collect();
+        Self {
+            config,
+            profiles: Arc::new(Mutex::new(profiles)),
+            refresh: Mutex::new(None),
+            copy_dir,
+            matcher: SkimMatcherV2::default().smart_case(),
+        }
+    }
+
+    /// Starts reading again, in the background, the profiles not checked
+    /// within the refresh interval
+    ///
+    /// Does nothing while an earlier refresh is still running. Profiles
+    /// whose history file is unchanged are not read.
+    fn start_refresh(&self) {
+        let mut refresh = self.refresh.lock().unwrap();
+        if refresh.as_ref().is_some_and(|thread| !thread.is_finished()) {
+            return;
+        }
+
+        let due: Vec<(usize, Profile, Option<FileStamp>)> = {
+            let mut profiles = self.profiles.lock().unwrap();
+            profiles
+                .iter_mut()
+                .enumerate()
+                .filter(|(_, loaded)| {
+                    loaded
+                        .checked
+                        .is_none_or(|t| t.elapsed() >= REFRESH_INTERVAL)
+                })
+                .map(|(i, loaded)| {
+                    loaded.checked = Some(Instant::now());
+                    (i, loaded.profile.clone(), loaded.stamp)
+                })
+                .collect()
+        };
+        if due.is_empty() {
+            return;
+        }
+
+        let max_age = self.config.max_age_days.saturating_mul(86_400);
+        let profiles = self.profiles.clone();
+        let copy_dir = self.copy_dir.clone();
+        *refresh = Some(std::thread::spawn(move || {
+            let since = query::now_secs().saturating_sub(max_age);
+            for (i, profile, old_stamp) in due {
+                let stamp = FileStamp::of(&history_file(&profile));
+                if old_stamp == Some(stamp) {
+                    continue;
+                }
+                let pages = if !stamp.exists() || history_disabled(&profile) {
+                    Vec::new()
+                } else {
+                    read_profile(&profile, &copy_dir, since).unwrap_or_else(|e| {
+                        log::warn!(
+                            "Failed to read history from {}: {}",
+                            profile.dir.display(),
+                            e
+                        );
+                        Vec::new()
+                    })
+                };
+
+                let mut profiles = profiles.lock().unwrap();
+                profiles[i].stamp = Some(stamp);
+                profiles[i].pages = Arc::new(pages);
+            }
+        }));
+    }
+
+    /// Waits for a running refresh to finish
+    fn wait_for_refresh(&self) {
+        if let Some(thread) = self.refresh.lock().unwrap().take() {
+            let _ = thread.join();
+        }
+    }
+
+    /// Scores a page by its title, falling back to its URL
+    fn score(&self, page: &Page, normalized_query: &str, query_lower: &str) -> Option<i64> {
+        name_score(&self.matcher, &page.title, normalized_query, query_lower).or_else(|| {
+            let url = page.url.split_once("://").map_or(&*page.url, |(_, u)| u);
+            self.matcher.fuzzy_match(url, query_lower).map(|s| s / 2)
+        })
+    }
+}
+
+impl Provider for BrowserHistoryProvider {
+    fn id(&self) -> &'static str {
+        "browser-history"
+    }
+
+    fn title(&self) -> &str {
+        "Browser History"
+    }
+
+    fn priority(&self) -> i32 {
+        13
+    }
+
+    fn query(
+        &self,
+        query: &ProviderQuery,
+        cancel: &CancelToken,
+        emit: &(dyn Fn(Vec<Section>) + Sync),
+    ) -> bool {
+        let query = query.parsed;
+        if query.text.is_empty() || query.has_filters() {
+            return true;
+        }
+
+        let text = query.text.as_str();
+        let normalized_query = normalize_for_search(text);
+        let query_lower = text.to_lowercase();
+        let now = query::now_secs();
+
+        self.start_refresh();
+        let pages: Vec<Arc<Vec<Page>>> = self
+            .profiles
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|loaded| loaded.pages.clone())
+            .collect();
+
+        let mut seen = HashSet::new();
+        let mut results = Vec::new();
+        for page in pages.iter().flat_map(|pages| pages.iter()) {
+            if cancel.is_cancelled() {
+                return false;
+            }
+            let domain = domain(&page.url);
+            if is_excluded(domain, &self.config.excluded_domains) || !seen.insert(&page.url) {
+                continue;
+            }
+            let Some(score) = self.score(page, &normalized_query, &query_lower) else {
+                continue;
+            };
+            let name = if page.title.is_empty() {
+                page.url.split_once("://").map_or(&*page.url, |(_, u)| u)
+            } else {
+                &page.title
+            };
+            results.push(SearchResult {
+                name: name.to_string(),
+                path: PathBuf::from(&page.url),
+                result_type: ResultType::Web,
+                score: score + visit_boost(page, now),
+                description: format!("{} · {}", domain, format_age(page.last_visit, now)),
+                provider: self.id(),
+                shortcut: None,
+            });
+        }
+
+        results.sort_unstable_by_key(|r| std::cmp::Reverse(r.score));
+        results.truncate(self.config.max_results);
+        if !results.is_empty() {
+            emit(vec![self.section(results)]);
+        }
+        true
+    }
+
+    /// Opens the page, or copies its URL
+    fn actions(&self, result: &SearchResult) -> Vec<Action> {
+        vec![
+            Action::Open(result.path.clone()),
+            Action::Copy(result.path.to_string_lossy().into_owned()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::run_provider;
+    use std::fs;
+
+    const CHROMIUM_HISTORY: &[u8] = include_bytes!("../tests/fixtures/browsers/History");
+    const FIREFOX_PLACES: &[u8] = include_bytes!("../tests/fixtures/browsers/places.sqlite");
+
+    /// When the fixture profiles were last used: 2026-10-17 12:00 UTC
+    const NOW: u64 = 1_792_238_400;
+    const DAY: u64 = 86_400;
+
+    #[test]
+    fn test_parse_chromium_history() {
+        let db = Database::from_bytes(CHROMIUM_HISTORY.to_vec(), None).unwrap();
+        let pages = parse_chromium_history(&db, NOW - 90 * DAY).unwrap();
+        let urls: Vec<&str> = pages.iter().map(|p| p.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            [
+                "https://wiki.example.com/Onboarding",
+                "https://www.example.com/blog/rust-2026",
+                "https://mail.example.com/inbox",
+                "https://docs.example.com/untitled",
+            ]
+        );
+        assert_eq!(pages[0].title, "Onboarding – Team Wiki");
+        assert_eq!(pages[0].visit_count, 25);
+        assert_eq!(pages[0].last_visit, NOW - 3 * DAY);
+
+        let all = parse_chromium_history(&db, 0).unwrap();
+        assert!(all.iter().any(|p| p.title == "Ancient archive"));
+    }
+
+    #[test]
+    fn test_parse_firefox_history() {
+        let db = Database::from_bytes(FIREFOX_PLACES.to_vec(), None).unwrap();
+        let pages = parse_firefox_history(&db, NOW - 90 * DAY).unwrap();
+        let titles: Vec<&str> = pages.iter().map(|p| p.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            [
+                "Intranet home",
+                "Build dashboard",
+                "API reference – Example",
+                "Rust Blog",
+                "A story we read yesterday",
+                "Inbox (3) - Mail",
+            ]
+        );
+        assert_eq!(pages[4].last_visit, NOW - DAY / 2);
+        assert_eq!(pages[5].visit_count, 120);
+    }
+
+    #[test]
+    fn test_domains_and_privacy() {
+        assert_eq!(domain("https://www.example.com/a?b"), "example.com");
+        assert_eq!(domain("http://user@host.example:8080/x"), "host.example");
+        assert_eq!(domain("file:///C:/notes.html"), "");
+
+        let excluded = vec!["bank.example".to_string()];
+        assert!(is_excluded("bank.example", &excluded));
+        assert!(is_excluded("online.Bank.example", &excluded));
+        assert!(!is_excluded("notbank.example", &excluded));
+
+        assert!(chromium_history_disabled(
+            r#"{"history": {"saving_disabled": true}}"#
+        ));
+        assert!(!chromium_history_disabled(r#"{"history": {}}"#));
+        assert!(firefox_history_disabled(
+            "user_pref(\"places.history.enabled\", false);\n"
+        ));
+        assert!(firefox_history_disabled(
+            "user_pref(\"browser.privatebrowsing.autostart\", true);\n"
+        ));
+        assert!(!firefox_history_disabled(
+            "user_pref(\"places.history.enabled\", true);\n"
+        ));
+    }
+
+    #[test]
+    fn test_searches_copies_of_profiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let chrome = dir.path().join("Default");
+        let firefox = dir.path().join("abcd.default-release");
+        fs::create_dir_all(&chrome).unwrap();
+        fs::create_dir_all(&firefox).unwrap();
+        fs::write(chrome.join("History"), CHROMIUM_HISTORY).unwrap();
+        fs::write(chrome.join("Preferences"), "{}").unwrap();
+        fs::write(firefox.join("places.sqlite"), FIREFOX_PLACES).unwrap();
+
+        let profiles = vec![
+            Profile {
+                browser: "Chrome",
+                engine: Engine::Chromium,
+                dir: chrome.clone(),
+            },
+            Profile {
+                browser: "Firefox",
+                engine: Engine::Firefox,
+                dir: firefox.clone(),
+            },
+        ];
+        let config = BrowserHistoryConfig {
+            enabled: true,
+            // The fixtures' visits must not age out as the tests get older
+            max_age_days: 100_000,
+            excluded_domains: vec!["news.example.org".to_string()],
+            ..BrowserHistoryConfig::default()
+        };
+        let copy_dir = dir.path().join("copies");
+        let provider = BrowserHistoryProvider::with_profiles(
+            config.clone(),
+            profiles.clone(),
+            copy_dir.clone(),
+        );
+        provider.start_refresh();
+        provider.wait_for_refresh();
+
+        let found = run_provider(&provider, "onboarding");
+        assert_eq!(found[0].name, "Onboarding – Team Wiki");
+        assert!(found[0].description.starts_with("wiki.example.com · "));
+        assert_eq!(
+            provider.actions(&found[0])[0],
+            Action::Open(PathBuf::from("https://wiki.example.com/Onboarding"))
+        );
+        assert!(!copy_dir.exists());
+
+        // A page visited in both browsers is shown once
+        let found = run_provider(&provider, "inbox");
+        assert_eq!(found[0].name, "Inbox (12) - Mail");
+        assert_eq!(found.len(), 1);
+
+        assert_eq!(
+            run_provider(&provider, "docs.example.com/untitled")[0].name,
+            "docs.example.com/untitled"
+        );
+        assert!(run_provider(&provider, "story").is_empty());
+        assert!(run_provider(&provider, "pixel").is_empty());
+
+        // Profiles that never remember history are not read
+        fs::write(
+            firefox.join("prefs.js"),
+            "user_pref(\"places.history.enabled\", false);\n",
+        )
+        .unwrap();
+        let provider = BrowserHistoryProvider::with_profiles(config, profiles, copy_dir);
+        provider.start_refresh();
+        provider.wait_for_refresh();
+        assert!(run_provider(&provider, "build dashboard").is_empty());
+        assert_eq!(run_provider(&provider, "onboarding").len(), 1);
+    }
+}
@@ -6,21 +6,22 @@
 
 #![allow(dead_code)]
 
-use crate::clipboard::{copy_to_clipboard, paste_from_clipboard};
+use crate::clipboard::{self, copy_to_clipboard, paste_from_clipboard};
 use crate::config::AppearanceConfig;
 use crate::error::{Result, RustleError};
 use crate::hotkey::HotkeyManager;
 use crate::icons::{draw_icon, extract_icon, IconHandle};
-use crate::launcher;
-use crate::search::{FlatResult, GroupedResults, ResultType, SearchEngine, SearchResult};
+use crate::provider::Action;
+use crate::search::{
+    FlatResult, GroupedResults, ResultType, SearchEngine, SearchResponse, SearchResult, SearchWorker,
+};
 use crate::utils::{to_wide_string, truncate_with_ellipsis};
-use std::cell::RefCell;
 use std::collections::HashMap;
 use std::mem;
 use std::path::PathBuf;
 use std::sync::Arc;
 use windows::core::PCWSTR;
-use windows::Win32::Foundation::{COLORREF, HWND, LPARAM, LRESULT, RECT, SIZE, WPARAM};
+use windows::Win32::Foundation::{COLORREF, HWND, LPARAM, LRESULT, POINT, RECT, SIZE, WPARAM};
 use windows::Win32::Graphics::Dwm::{
     DwmSetWindowAttribute, DWMWA_WINDOW_CORNER_PREFERENCE, DWMWCP_ROUND,
     DWM_WINDOW_CORNER_PREFERENCE,
@@ -31,12 +32,15 @@ use windows::Win32::Graphics::Gdi::{
     GetStockObject, GetTextExtentPoint32W, InvalidateRect, RoundRect, SelectClipRgn, SelectObject,
     SetBkMode, SetTextColor, DT_END_ELLIPSIS, DT_LEFT, DT_SINGLELINE, DT_VCENTER, FONT_CHARSET,
     FONT_CLIP_PRECISION, FONT_OUTPUT_PRECISION, FONT_QUALITY, HBRUSH, HFONT, LOGFONTW, NULL_BRUSH,
-    PAINTSTRUCT, PS_SOLID, SRCCOPY, TRANSPARENT, GetDC, ReleaseDC,
+    PAINTSTRUCT, PS_SOLID, SRCCOPY, TRANSPARENT, GetDC, ReleaseDC, ClientToScreen,
+};
+use windows::Win32::System::DataExchange::{
+    AddClipboardFormatListener, RemoveClipboardFormatListener,
 };
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
 use windows::Win32::UI::Input::KeyboardAndMouse::{
     GetKeyState, ReleaseCapture, SetCapture, SetFocus, VIRTUAL_KEY, VK_A, VK_BACK, VK_C, VK_CONTROL,
-    VK_DELETE, VK_DOWN, VK_ESCAPE, VK_LEFT, VK_RETURN, VK_RIGHT, VK_UP, VK_V,
+    VK_DELETE, VK_DOWN, VK_ESCAPE, VK_K, VK_LEFT, VK_RETURN, VK_RIGHT, VK_TAB, VK_UP, VK_V,
 };
 use windows::Win32::UI::Shell::{
     Shell_NotifyIconW, NIF_ICON, NIF_MESSAGE, NIF_TIP, NIM_ADD, NIM_DELETE,
@@ -44,15 +48,16 @@ use windows::Win32::UI::Shell::{
 };
 use windows::Win32::UI::WindowsAndMessaging::{
     CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, GetWindowLongPtrW, KillTimer,
-    LoadCursorW, PostQuitMessage, RegisterClassExW, SetCursor, SetForegroundWindow,
+    LoadCursorW, PostMessageW, PostQuitMessage, RegisterClassExW, SetCursor, SetForegroundWindow,
     SetLayeredWindowAttributes, SetTimer, SetWindowLongPtrW, SetWindowPos, ShowWindow,
     TranslateMessage, CS_HREDRAW, CS_VREDRAW, GWLP_USERDATA, HMENU, HWND_TOPMOST, IDC_ARROW,
     IDC_IBEAM, LWA_ALPHA, MSG, SWP_NOMOVE, SWP_NOSIZE, SWP_SHOWWINDOW, SW_HIDE, SW_SHOW, WM_CHAR,
     WM_CLOSE, WM_CREATE, WM_DESTROY, WM_ERASEBKGND, WM_HOTKEY, WM_KEYDOWN, WM_LBUTTONDBLCLK,
     WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_PAINT, WM_TIMER, WNDCLASSEXW,
-    WS_EX_LAYERED, WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_POPUP,
+    WS_EX_LAYERED, WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_POPUP, WM_MOUSEHWHEEL,
     AppendMenuW, CreatePopupMenu, IDI_APPLICATION, LoadIconW, MF_STRING, TPM_BOTTOMALIGN,
-    TPM_RIGHTALIGN, TrackPopupMenu, WM_COMMAND, WM_RBUTTONUP, WM_USER,
+    TPM_RIGHTALIGN, TrackPopupMenu, WM_COMMAND, WM_RBUTTONUP, WM_USER, DestroyMenu,
+    GetCursorPos, TPM_LEFTALIGN, TPM_TOPALIGN, WM_CLIPBOARDUPDATE, GetForegroundWindow, IsWindow,
 };
 
 /// Window class name
@@ -63,6 +68,12 @@ const WM_TRAYICON: u32 = WM_USER + 1;
 const ID_TRAY_SHOW: usize = 1001;
 const ID_TRAY_EXIT: usize = 1002;
 
+/// Menu IDs of a result's actions start here, one per action
+const ID_ACTION_FIRST: usize = 2000;
+
+/// Posted by the search worker when new results are ready
+const WM_SEARCH_RESULTS: u32 = WM_USER + 2;
+
 /// Timer ID for cursor blinking
 const CURSOR_TIMER_ID: usize = 1;
 
@@ -81,8 +92,8 @@ const INPUT_HEIGHT: i32 = 56; // Taller input for prominence
 const SECTION_HEADER_HEIGHT: i32 = 32;
 const ITEM_HEIGHT: i32 = 56; // Taller items for better touch/click
 const PADDING: i32 = 16; // More generous padding
-const COLUMN_WIDTH: i32 = 250; // Adjusted for 3 columns in 800px
 const COLUMN_GAP: i32 = 12; // Larger gap between columns
+const MIN_COLUMN_WIDTH: i32 = 220; // Narrower columns scroll sideways instead
 const ICON_SIZE: i32 = 36; // Slightly larger icons
 const ICON_TEXT_GAP: i32 = 14; // Better spacing
 const RESULTS_AREA_HEIGHT: i32 = 400; // Compact results area
@@ -119,6 +130,10 @@ struct Colors {
     icon_app: u32,
     icon_file: u32,
     icon_folder: u32,
+    icon_answer: u32,
+    icon_web: u32,
+    icon_command: u32,
+    icon_text: u32,
 
     // Badges/Tags
     badge_bg: u32,
@@ -157,6 +172,10 @@ impl Default for Colors {
             icon_app: 0xFF60A5FA,    // Blue for apps
             icon_file: 0xFF34D399,   // Green for files
             icon_folder: 0xFFFBBF24, // Yellow/gold for folders
+            icon_answer: 0xFFA78BFA, // Violet for answers
+            icon_web: 0xFF22D3EE,    // Cyan for web pages
+            icon_command: 0xFFF472B6, // Pink for shell commands
+            icon_text: 0xFF94A3B8,    // Slate for clipboard text
 
             // Badge styling
             badge_bg: 0xFF2A2A2A,
@@ -167,10 +186,14 @@ impl Default for Colors {
 
 /// Window state
 struct WindowState {
-    search_engine: Arc<RefCell<SearchEngine>>,
+    search_engine: Arc<SearchEngine>,
+    search_worker: SearchWorker,
     query: String,
     grouped_results: GroupedResults,
     flat_results: Vec<FlatResult>,
+    query_error: Option<String>, // Why the current query could not be run
+    searching: bool,             // Whether more results are still streaming in
+    results_generation: u64,     // Search generation the shown results belong to
     selected_index: usize,
     hovered_index: Option<usize>, // Currently hovered result index
     visible: bool,
@@ -191,14 +214,42 @@ struct WindowState {
     hwnd: HWND,
     base_height: i32,                         // Store base window height for reset
     icon_cache: HashMap<PathBuf, IconHandle>, // Cache of extracted icons
-    scroll_apps: i32,                         // Scroll offset for Applications column
-    scroll_folders: i32,                      // Scroll offset for Folders column
-    scroll_files: i32,                        // Scroll offset for Files column
+    scroll_offsets: HashMap<String, i32>,     // Scroll offset of each column, by section title
+    first_column: usize,                      // Leftmost column in view when they don't all fit
+    action_menu: Option<(SearchResult, Vec<Action>)>, // Result whose actions menu is open
+    previous_foreground: Option<HWND>, // Window that had focus before the window was shown
 }
 
 impl WindowState {
+    /// Hands the current query to the background search worker
     fn perform_search(&mut self) {
-        self.grouped_results = self.search_engine.borrow().search(&self.query);
+        self.search_worker.submit(&self.query);
+        self.searching = true;
+    }
+
+    /// Shows results posted back by the search worker
+    ///
+    /// Partial updates for the same search keep the selected item selected
+    /// if it is still among the results.
+    fn apply_search_response(&mut self, response: SearchResponse) {
+        let previous_selection = if response.generation == self.results_generation {
+            self.get_selected_result().map(|r| r.path.clone())
+        } else {
+            None
+        };
+        self.results_generation = response.generation;
+        self.searching = !response.complete;
+
+        match response.results {
+            Ok(grouped) => {
+                self.grouped_results = grouped;
+                self.query_error = None;
+            }
+            Err(e) => {
+                self.grouped_results = GroupedResults::default();
+                self.query_error = Some(e.to_string());
+            }
+        }
         self.flat_results = self.grouped_results.flatten_with_sections();
 
         // Extract icons for applications
@@ -206,12 +257,19 @@ impl WindowState {
             self.extract_icons_for_results();
         }
 
-        // Find first selectable item
-        self.selected_index = self
-            .flat_results
-            .iter()
-            .position(|r| r.is_selectable())
-            .unwrap_or(0);
+        // Keep the previous selection, or find the first selectable item
+        let kept = previous_selection.and_then(|path| {
+            self.flat_results
+                .iter()
+                .position(|r| matches!(r, FlatResult::Item(item) if item.path == path))
+        });
+        self.selected_index = kept.unwrap_or_else(|| {
+            self.flat_results
+                .iter()
+                .position(|r| r.is_selectable())
+                .unwrap_or(0)
+        });
+        self.scroll_to_selection();
     }
 
     fn select_previous(&mut self) {
@@ -230,6 +288,7 @@ impl WindowState {
                 break;
             }
         }
+        self.scroll_to_selection();
     }
 
     fn select_next(&mut self) {
@@ -248,6 +307,7 @@ impl WindowState {
                 break;
             }
         }
+        self.scroll_to_selection();
     }
 
     fn get_selected_result(&self) -> Option<&SearchResult> {
@@ -258,11 +318,26 @@ impl WindowState {
         }
     }
 
-    fn launch_selected(&self) -> Result<()> {
-        if let Some(result) = self.get_selected_result() {
-            launcher::launch(&result.path)?;
-        }
-        Ok(())
+    /// Replaces the query with the completion of the selected result
+    ///
+    /// Returns false if the selected result cannot complete the query.
+    fn complete_selected(&mut self) -> bool {
+        let completion = self
+            .get_selected_result()
+            .and_then(|result| self.search_engine.complete(&self.query, result));
+        let Some(completion) = completion else {
+            return false;
+        };
+        self.set_query(completion);
+        true
+    }
+
+    /// Replaces the query, with the cursor at its end
+    fn set_query(&mut self, query: String) {
+        self.query = query;
+        self.cursor_position = self.query.len();
+        self.selection_start = None;
+        self.selection_end = None;
     }
 
     /// Finds which result item was clicked based on X and Y coordinates (column-aware)
@@ -270,60 +345,26 @@ impl WindowState {
         let results_top = PADDING + INPUT_HEIGHT + 8;
         let column_content_top = results_top + SECTION_HEADER_HEIGHT;
 
-        // Determine which column
-        let (result_type, column_x) = if x < PADDING + COLUMN_WIDTH {
-            (
-                ResultType::Application,
-                self.get_column_x(ResultType::Application),
-            )
-        } else if x < PADDING + COLUMN_WIDTH * 2 + COLUMN_GAP {
-            (ResultType::Folder, self.get_column_x(ResultType::Folder))
-        } else {
-            (ResultType::File, self.get_column_x(ResultType::File))
-        };
-
         if y < column_content_top || y >= column_content_top + RESULTS_AREA_HEIGHT {
             return None;
         }
 
-        // Check if click is within column bounds
-        if x < column_x || x >= column_x + COLUMN_WIDTH {
-            return None;
-        }
-
-        let scroll_offset = self.get_scroll_offset(result_type);
-        let relative_y = y - column_content_top + scroll_offset;
-
-        // Find which item in this column
-        let results = self.grouped_results.get_by_type(result_type);
+        // Determine which column, and which item in it
+        let column = self.column_at(x)?;
+        let section = &self.grouped_results.sections[column];
+        let relative_y = y - column_content_top + self.get_scroll_offset(&section.title);
         let item_index = (relative_y / ITEM_HEIGHT) as usize;
 
-        if item_index < results.len() {
-            // Find the global index in flat_results
-            self.flat_results.iter().position(|r| {
-                if let FlatResult::Item(r) = r {
-                    r.path == results[item_index].path && r.result_type == result_type
-                } else {
-                    false
-                }
-            })
-        } else {
-            None
-        }
-    }
-
-    /// Launches a result by index
-    fn launch_result(&self, index: usize) -> Result<()> {
-        if let Some(FlatResult::Item(result)) = self.flat_results.get(index) {
-            launcher::launch(&result.path)?;
-        }
-        Ok(())
+        self.grouped_results.flat_index(column, item_index)
     }
 
     fn clear(&mut self) {
+        self.search_worker.cancel();
+        self.searching = false;
         self.query.clear();
         self.grouped_results = GroupedResults::default();
         self.flat_results.clear();
+        self.query_error = None;
         self.selected_index = 0;
         self.hovered_index = None;
         self.cursor_position = 0;
@@ -332,9 +373,8 @@ impl WindowState {
         // Clear icon cache when clearing results
         self.icon_cache.clear();
         // Reset scroll positions
-        self.scroll_apps = 0;
-        self.scroll_folders = 0;
-        self.scroll_files = 0;
+        self.scroll_offsets.clear();
+        self.first_column = 0;
     }
 
     /// Extracts icons for application results
@@ -402,31 +442,87 @@ impl WindowState {
         base + SECTION_HEADER_HEIGHT + RESULTS_AREA_HEIGHT + PADDING
     }
 
-    /// Gets scroll offset for a specific result type
-    fn get_scroll_offset(&self, result_type: ResultType) -> i32 {
-        match result_type {
-            ResultType::Application => self.scroll_apps,
-            ResultType::Folder => self.scroll_folders,
-            ResultType::File => self.scroll_files,
-        }
+    /// Gets scroll offset for the column of a section
+    fn get_scroll_offset(&self, title: &str) -> i32 {
+        self.scroll_offsets.get(title).copied().unwrap_or(0)
+    }
+
+    /// Sets scroll offset for the column of a section
+    fn set_scroll_offset(&mut self, title: &str, offset: i32) {
+        self.scroll_offsets.insert(title.to_string(), offset.max(0));
+    }
+
+    /// Gets how many columns are in view: as many as fit at their minimum width
+    fn visible_columns(&self) -> usize {
+        let fit = (WINDOW_WIDTH - PADDING * 2 + COLUMN_GAP) / (MIN_COLUMN_WIDTH + COLUMN_GAP);
+        self.grouped_results
+            .sections
+            .len()
+            .clamp(1, fit.max(1) as usize)
+    }
+
+    /// Gets the width of each column (the columns in view share the window evenly)
+    fn column_width(&self) -> i32 {
+        let columns = self.visible_columns() as i32;
+        (WINDOW_WIDTH - PADDING * 2 - COLUMN_GAP * (columns - 1)) / columns
+    }
+
+    /// Gets column X position for a section index (outside the window if
+    /// the column is scrolled out of view)
+    fn get_column_x(&self, column: usize) -> i32 {
+        let position = column as i32 - self.first_column as i32;
+        PADDING + (self.column_width() + COLUMN_GAP) * position
+    }
+
+    /// Whether the column of a section is scrolled into view
+    fn is_column_visible(&self, column: usize) -> bool {
+        column >= self.first_column && column < self.first_column + self.visible_columns()
     }
 
-    /// Sets scroll offset for a specific result type
-    fn set_scroll_offset(&mut self, result_type: ResultType, offset: i32) {
-        match result_type {
-            ResultType::Application => self.scroll_apps = offset.max(0),
-            ResultType::Folder => self.scroll_folders = offset.max(0),
-            ResultType::File => self.scroll_files = offset.max(0),
+    /// Finds the section whose column contains an X position
+    fn column_at(&self, x: i32) -> Option<usize> {
+        if x < PADDING {
+            return None;
         }
+        let position = (x - PADDING) / (self.column_width() + COLUMN_GAP);
+        let column = self.first_column + position as usize;
+        if column >= self.grouped_results.sections.len()
+            || !self.is_column_visible(column)
+            || x >= self.get_column_x(column) + self.column_width()
+        {
+            return None;
+        }
+        Some(column)
     }
 
-    /// Gets column X position for a result type
-    fn get_column_x(&self, result_type: ResultType) -> i32 {
-        match result_type {
-            ResultType::Application => PADDING,
-            ResultType::Folder => PADDING + COLUMN_WIDTH + COLUMN_GAP,
-            ResultType::File => PADDING + (COLUMN_WIDTH + COLUMN_GAP) * 2,
+    /// Scrolls the columns sideways by `delta` columns, without leaving
+    /// empty space after the last one
+    fn scroll_columns(&mut self, delta: i32) {
+        let last = self
+            .grouped_results
+            .sections
+            .len()
+            .saturating_sub(self.visible_columns());
+        self.first_column = (self.first_column as i32 + delta).clamp(0, last as i32) as usize;
+    }
+
+    /// Scrolls the columns sideways until the selected item's column is in view
+    fn scroll_to_selection(&mut self) {
+        let column = self
+            .flat_results
+            .iter()
+            .take(self.selected_index + 1)
+            .filter(|r| matches!(r, FlatResult::SectionHeader(_)))
+            .count()
+            .saturating_sub(1);
+        let visible = self.visible_columns();
+        if column < self.first_column {
+            self.first_column = column;
+        } else if column >= self.first_column + visible {
+            self.first_column = column + 1 - visible;
         }
+        // The sections may have changed, so keep the window filled
+        self.scroll_columns(0);
     }
 }
 
@@ -513,11 +609,27 @@ pub fn create_and_run(search_engine: SearchEngine, appearance: AppearanceConfig)
 
         let base_height = INPUT_HEIGHT + PADDING * 2;
 
+        // Searches run on a worker thread, which posts back when done
+        let search_engine = Arc::new(search_engine);
+        let hwnd_raw = hwnd.0 as isize;
+        let search_worker = SearchWorker::for_engine(search_engine.clone(), move || {
+            let _ = PostMessageW(
+                HWND(hwnd_raw as *mut _),
+                WM_SEARCH_RESULTS,
+                WPARAM(0),
+                LPARAM(0),
+            );
+        });
+
         let state = Box::new(WindowState {
-            search_engine: Arc::new(RefCell::new(search_engine)),
+            search_engine,
+            search_worker,
             query: String::new(),
             grouped_results: GroupedResults::default(),
             flat_results: Vec::new(),
+            query_error: None,
+            searching: false,
+            results_generation: 0,
             selected_index: 0,
             hovered_index: None,
             visible: false,
@@ -538,9 +650,10 @@ pub fn create_and_run(search_engine: SearchEngine, appearance: AppearanceConfig)
             hwnd,
             base_height,
             icon_cache: HashMap::new(),
-            scroll_apps: 0,
-            scroll_folders: 0,
-            scroll_files: 0,
+            scroll_offsets: HashMap::new(),
+            first_column: 0,
+            action_menu: None,
+            previous_foreground: None,
         });
 
         SetWindowLongPtrW(hwnd, GWLP_USERDATA, Box::into_raw(state) as isize);
@@ -554,6 +667,11 @@ pub fn create_and_run(search_engine: SearchEngine, appearance: AppearanceConfig)
             }
             state.hotkey_manager = Some(hotkey_manager);
             
+            // Record what gets copied, for the clipboard history
+            if let Err(e) = AddClipboardFormatListener(hwnd) {
+                log::warn!("Failed to listen for clipboard changes: {}", e);
+            }
+
             // Initialize tray icon and show window
             init_tray_icon(hwnd);
             show_window(hwnd, state);
@@ -635,15 +753,50 @@ unsafe extern "system" fn window_proc(
                 ID_TRAY_EXIT => {
                     PostQuitMessage(0);
                 }
+                _ if id >= ID_ACTION_FIRST => {
+                    let state = get_window_state(hwnd);
+                    if let Some(state) = state {
+                        run_menu_action(hwnd, state, id - ID_ACTION_FIRST);
+                    }
+                }
                 _ => {}
             }
             LRESULT(0)
         }
 
+        WM_CLIPBOARDUPDATE => {
+            let state = get_window_state(hwnd);
+            if let Some(state) = state {
+                match clipboard::read_change(Some(hwnd)) {
+                    Ok(change) => state.search_engine.record_clipboard(change),
+                    Err(e) => log::debug!("Could not read clipboard change: {}", e),
+                }
+            }
+            LRESULT(0)
+        }
+
+        WM_RBUTTONUP => {
+            let state = get_window_state(hwnd);
+            if let Some(state) = state {
+                let x = (lparam.0 & 0xFFFF) as i32;
+                let y = ((lparam.0 >> 16) & 0xFFFF) as i32;
+                if let Some(clicked_index) = state.find_clicked_result_index(x, y) {
+                    state.selected_index = clicked_index;
+                    let _ = InvalidateRect(hwnd, None, false);
+
+                    let mut pt = POINT::default();
+                    let _ = GetCursorPos(&mut pt);
+                    show_action_menu(hwnd, state, pt);
+                }
+            }
+            LRESULT(0)
+        }
+
         WM_ERASEBKGND => LRESULT(1),
 
         WM_DESTROY => {
             remove_tray_icon(hwnd);
+            let _ = RemoveClipboardFormatListener(hwnd);
             let _ = KillTimer(hwnd, CURSOR_TIMER_ID);
             let _ = KillTimer(hwnd, SEARCH_TIMER_ID);
 
@@ -682,6 +835,16 @@ unsafe extern "system" fn window_proc(
                     let _ = KillTimer(hwnd, SEARCH_TIMER_ID);
                     state.search_pending = false;
                     state.perform_search();
+                }
+            }
+            LRESULT(0)
+        }
+
+        WM_SEARCH_RESULTS => {
+            let state = get_window_state(hwnd);
+            if let Some(state) = state {
+                if let Some(response) = state.search_worker.take_response() {
+                    state.apply_search_response(response);
                     update_window_size(hwnd, state);
                     let _ = InvalidateRect(hwnd, None, false);
                 }
@@ -726,10 +889,13 @@ unsafe extern "system" fn window_proc(
                         let _ = InvalidateRect(hwnd, None, false);
                     }
                     VK_RETURN => {
-                        if let Err(e) = state.launch_selected() {
-                            log::error!("Failed to launch: {}", e);
-                        } else {
-                            hide_window(hwnd, state);
+                        let index = state.selected_index;
+                        activate_result(hwnd, state, index);
+                    }
+                    VK_TAB => {
+                        if state.complete_selected() {
+                            schedule_search(hwnd, state);
+                            let _ = InvalidateRect(hwnd, None, false);
                         }
                     }
                     VK_LEFT => {
@@ -774,6 +940,17 @@ unsafe extern "system" fn window_proc(
                         schedule_search(hwnd, state);
                         let _ = InvalidateRect(hwnd, None, false);
                     }
+                    VK_K => {
+                        if ctrl_pressed {
+                            // Ctrl+K: Show the selected result's actions below the input
+                            let mut pt = POINT {
+                                x: PADDING + 48,
+                                y: PADDING + INPUT_HEIGHT,
+                            };
+                            let _ = ClientToScreen(hwnd, &mut pt);
+                            show_action_menu(hwnd, state, pt);
+                        }
+                    }
                     VK_A => {
                         if ctrl_pressed {
                             // Ctrl+A: Select all
@@ -832,7 +1009,7 @@ unsafe extern "system" fn window_proc(
                     // Ctrl key combinations handled in WM_KEYDOWN
                     // Skip character insertion for Ctrl+key combinations
                 } else if c.is_alphanumeric() || c.is_whitespace() || c.is_ascii_punctuation() {
-                    if c != '\r' && c != '\n' && c != '\x08' {
+                    if c != '\r' && c != '\n' && c != '\t' && c != '\x08' {
                         // Delete selection if any
                         if state.has_selection() {
                             state.delete_selection();
@@ -984,11 +1161,7 @@ unsafe extern "system" fn window_proc(
 
                         if is_double_click {
                             // Double-click - launch it
-                            if let Err(e) = state.launch_result(clicked_index) {
-                                log::error!("Failed to launch: {}", e);
-                            } else {
-                                hide_window(hwnd, state);
-                            }
+                            activate_result(hwnd, state, clicked_index);
                             // Reset double-click tracking
                             state.last_click_index = None;
                             state.last_click_time = None;
@@ -1013,16 +1186,23 @@ unsafe extern "system" fn window_proc(
                 let x = (lparam.0 & 0xFFFF) as i32;
                 let y = ((lparam.0 >> 16) & 0xFFFF) as i32;
                 if let Some(clicked_index) = state.find_clicked_result_index(x, y) {
-                    if let Err(e) = state.launch_result(clicked_index) {
-                        log::error!("Failed to launch: {}", e);
-                    } else {
-                        hide_window(hwnd, state);
-                    }
+                    activate_result(hwnd, state, clicked_index);
                 }
             }
             LRESULT(0)
         }
 
+        WM_MOUSEHWHEEL => {
+            let state = get_window_state(hwnd);
+            if let Some(state) = state {
+                // Tilting the wheel right (positive delta) shows later columns
+                let delta = (wparam.0 >> 16) as i16 as i32;
+                state.scroll_columns(delta.signum());
+                let _ = InvalidateRect(hwnd, None, false);
+            }
+            LRESULT(0)
+        }
+
         WM_MOUSEWHEEL => {
             let state = get_window_state(hwnd);
             if let Some(state) = state {
@@ -1030,29 +1210,32 @@ unsafe extern "system" fn window_proc(
                 let y = ((lparam.0 >> 16) & 0xFFFF) as i32;
                 let delta = (wparam.0 >> 16) as i16 as i32; // Wheel delta
 
+                // Shift+wheel scrolls the columns sideways (0x0004 = MK_SHIFT)
+                if wparam.0 & 0x0004 != 0 {
+                    state.scroll_columns(-delta.signum());
+                    let _ = InvalidateRect(hwnd, None, false);
+                    return LRESULT(0);
+                }
+
                 // Determine which column the mouse is over
                 let results_top = PADDING + INPUT_HEIGHT + 8;
                 let column_content_top = results_top + SECTION_HEADER_HEIGHT;
 
-                if y >= column_content_top && y < column_content_top + RESULTS_AREA_HEIGHT {
-                    let result_type = if x < PADDING + COLUMN_WIDTH {
-                        ResultType::Application
-                    } else if x < PADDING + COLUMN_WIDTH * 2 + COLUMN_GAP {
-                        ResultType::Folder
-                    } else {
-                        ResultType::File
-                    };
+                let in_results =
+                    y >= column_content_top && y < column_content_top + RESULTS_AREA_HEIGHT;
+                if let Some(column) = state.column_at(x).filter(|_| in_results) {
+                    let section = &state.grouped_results.sections[column];
+                    let title = section.title.clone();
 
                     // Scroll the column (negative delta = scroll up, positive = scroll down)
                     let scroll_delta = -delta / 40; // Convert wheel units to pixels
-                    let current_scroll = state.get_scroll_offset(result_type);
+                    let current_scroll = state.get_scroll_offset(&title);
                     let max_scroll = {
-                        let results = state.grouped_results.get_by_type(result_type);
-                        let total_height = results.len() as i32 * ITEM_HEIGHT;
+                        let total_height = section.results.len() as i32 * ITEM_HEIGHT;
                         (total_height - RESULTS_AREA_HEIGHT).max(0)
                     };
                     let new_scroll = (current_scroll + scroll_delta).max(0).min(max_scroll);
-                    state.set_scroll_offset(result_type, new_scroll);
+                    state.set_scroll_offset(&title, new_scroll);
                     let _ = InvalidateRect(hwnd, None, false);
                 }
             }
@@ -1075,6 +1258,11 @@ unsafe fn show_window(hwnd: HWND, state: &mut WindowState) {
     state.visible = true;
     state.cursor_visible = true;
 
+    // Remember where focus was, to hand it back on hiding
+    let foreground = GetForegroundWindow();
+    state.previous_foreground =
+        (!foreground.is_invalid() && foreground != hwnd).then_some(foreground);
+
     let _ = SetTimer(hwnd, CURSOR_TIMER_ID, CURSOR_BLINK_MS, None);
 
     // Show and activate window properly
@@ -1096,7 +1284,10 @@ unsafe fn show_window(hwnd: HWND, state: &mut WindowState) {
     let _ = InvalidateRect(hwnd, None, false);
 }
 
-unsafe fn hide_window(hwnd: HWND, state: &mut WindowState) {
+/// Hides the window and hands focus back to the window that had it before
+///
+/// Returns that window, if focus was handed back to it.
+unsafe fn hide_window(hwnd: HWND, state: &mut WindowState) -> Option<HWND> {
     state.visible = false;
     state.clear();
     state.search_pending = false;
@@ -1104,6 +1295,9 @@ unsafe fn hide_window(hwnd: HWND, state: &mut WindowState) {
     let _ = KillTimer(hwnd, CURSOR_TIMER_ID);
     let _ = KillTimer(hwnd, SEARCH_TIMER_ID);
 
+    // Focus only goes back if it has not moved elsewhere meanwhile
+    let had_focus = GetForegroundWindow() == hwnd;
+
     // Reset window to base height
     let _ = SetWindowPos(
         hwnd,
@@ -1116,6 +1310,97 @@ unsafe fn hide_window(hwnd: HWND, state: &mut WindowState) {
     );
 
     let _ = ShowWindow(hwnd, SW_HIDE);
+
+    // Rustle handled the last input, so it may still pass focus on
+    let previous = state.previous_foreground.take()?;
+    if !had_focus || !IsWindow(previous).as_bool() {
+        return None;
+    }
+    let _ = SetForegroundWindow(previous);
+    Some(previous)
+}
+
+/// Pops up a menu of the selected result's actions at `pt` (screen
+/// coordinates); the chosen one arrives as a WM_COMMAND
+unsafe fn show_action_menu(hwnd: HWND, state: &mut WindowState, pt: POINT) {
+    let Some(result) = state.get_selected_result().cloned() else {
+        return;
+    };
+    let actions = state.search_engine.actions(&result);
+    if actions.is_empty() {
+        return;
+    }
+
+    let Ok(hmenu) = CreatePopupMenu() else {
+        return;
+    };
+    for (i, action) in actions.iter().enumerate() {
+        let label = to_wide_string(action.label());
+        let _ = AppendMenuW(hmenu, MF_STRING, ID_ACTION_FIRST + i, PCWSTR(label.as_ptr()));
+    }
+    state.action_menu = Some((result, actions));
+
+    let _ = SetForegroundWindow(hwnd);
+    let _ = TrackPopupMenu(hmenu, TPM_LEFTALIGN | TPM_TOPALIGN, pt.x, pt.y, 0, hwnd, None);
+    let _ = DestroyMenu(hmenu);
+}
+
+/// Carries out the action picked from the actions menu
+unsafe fn run_menu_action(hwnd: HWND, state: &mut WindowState, index: usize) {
+    let Some((result, actions)) = state.action_menu.take() else {
+        return;
+    };
+    if let Some(action) = actions.get(index) {
+        run_result_action(hwnd, state, &result, action);
+    }
+}
+
+/// Carries out the default action of the result at `index`
+///
+/// The window is hidden if there is no result there.
+unsafe fn activate_result(hwnd: HWND, state: &mut WindowState, index: usize) {
+    let Some(FlatResult::Item(result)) = state.flat_results.get(index).cloned() else {
+        hide_window(hwnd, state);
+        return;
+    };
+    match state.search_engine.actions(&result).first() {
+        Some(action) => run_result_action(hwnd, state, &result, action),
+        None => {
+            hide_window(hwnd, state);
+        }
+    }
+}
+
+/// Carries out an action of a result, then refreshes the results if the
+/// window stays open or hides it
+unsafe fn run_result_action(
+    hwnd: HWND,
+    state: &mut WindowState,
+    result: &SearchResult,
+    action: &Action,
+) {
+    if let Action::SetQuery(query) = action {
+        state.set_query(query.clone());
+        schedule_search(hwnd, state);
+        let _ = InvalidateRect(hwnd, None, false);
+        return;
+    }
+    match state.search_engine.run_action(result, action) {
+        Ok(true) => {
+            state.perform_search();
+            let _ = InvalidateRect(hwnd, None, false);
+        }
+        Ok(false) => {
+            let previous = hide_window(hwnd, state);
+            if let Action::Paste(_) = action {
+                match previous {
+                    Some(target) => clipboard::paste_into(target),
+                    None => log::warn!("Not pasting: no window had focus before Rustle"),
+                }
+            }
+        }
+        Err(e) => log::error!("Failed to {}: {}", action.label().to_lowercase(), e),
+    }
 }
 
 unsafe fn schedule_search(hwnd: HWND, state: &mut WindowState) {
@@ -1124,8 +1409,11 @@ unsafe fn schedule_search(hwnd: HWND, state: &mut WindowState) {
     }
 
     if state.query.is_empty() {
+        state.search_worker.cancel();
+        state.searching = false;
         state.grouped_results = GroupedResults::default();
         state.flat_results.clear();
+        state.query_error = None;
         state.selected_index = 0;
         update_window_size(hwnd, state);
         return;
@@ -1212,14 +1500,19 @@ unsafe fn paint_window(hwnd: HWND, state: &WindowState) {
 
     let _ = DeleteObject(input_pen);
 
-    // Hint text (Esc to close)
+    // Hint text (Esc to close, or a searching indicator while results stream in)
     SelectObject(hdc, state.font_secondary);
     SetTextColor(hdc, COLORREF(colors.text_muted & 0x00FFFFFF));
     let mut hint_rect = input_rect;
     hint_rect.right -= 16; // Padding from right
+    let hint = if state.searching {
+        "Searching…"
+    } else {
+        "Esc to close"
+    };
     DrawTextW(
         hdc,
-        &mut to_wide_chars("Esc to close"),
+        &mut to_wide_chars(hint),
         &mut hint_rect,
         windows::Win32::Graphics::Gdi::DT_RIGHT | DT_SINGLELINE | DT_VCENTER,
     );
@@ -1340,15 +1633,26 @@ unsafe fn paint_window(hwnd: HWND, state: &WindowState) {
     let results_top = input_rect.bottom + 8;
 
     if !state.flat_results.is_empty() {
-        // Render each column
-        let column_types = [
-            ResultType::Application,
-            ResultType::Folder,
-            ResultType::File,
-        ];
-        for result_type in &column_types {
-            let column_x = state.get_column_x(*result_type);
-            let scroll_offset = state.get_scroll_offset(*result_type);
+        // Render one column per section
+        let column_width = state.column_width();
+        let sections = &state.grouped_results.sections;
+        for (column, section) in sections.iter().enumerate() {
+            if !state.is_column_visible(column) {
+                continue;
+            }
+            let column_x = state.get_column_x(column);
+            let scroll_offset = state.get_scroll_offset(&section.title);
+
+            // Arrows on the outer headers show that more columns are scrolled out of view
+            let mut title = section.title.to_uppercase();
+            if column == state.first_column && column > 0 {
+                title = format!("‹ {}", title);
+            }
+            if column + 1 == state.first_column + state.visible_columns()
+                && column + 1 < sections.len()
+            {
+                title.push_str(" ›");
+            }
 
             // Column header
             SelectObject(hdc, state.font_section);
@@ -1356,13 +1660,13 @@ unsafe fn paint_window(hwnd: HWND, state: &WindowState) {
             let header_rect = RECT {
                 left: column_x,
                 top: results_top,
-                right: column_x + COLUMN_WIDTH,
+                right: column_x + column_width,
                 bottom: results_top + SECTION_HEADER_HEIGHT,
             };
             let mut header_rect_mut = header_rect;
             DrawTextW(
                 hdc,
-                &mut to_wide_chars(result_type.section_header()),
+                &mut to_wide_chars(&title),
                 &mut header_rect_mut,
                 DT_LEFT | DT_SINGLELINE | DT_VCENTER,
             );
@@ -1372,7 +1676,7 @@ unsafe fn paint_window(hwnd: HWND, state: &WindowState) {
             let column_clip = RECT {
                 left: column_x,
                 top: column_content_top,
-                right: column_x + COLUMN_WIDTH,
+                right: column_x + column_width,
                 bottom: column_content_top + RESULTS_AREA_HEIGHT,
             };
 
@@ -1385,36 +1689,19 @@ unsafe fn paint_window(hwnd: HWND, state: &WindowState) {
             );
             let _ = SelectClipRgn(hdc, clip_region);
 
-            // Get results for this column
-            let column_results: Vec<_> = state
-                .grouped_results
-                .get_by_type(*result_type)
-                .iter()
-                .collect();
-
             let mut y = column_content_top - scroll_offset;
-            for (_idx, result) in column_results.iter().enumerate() {
+            for (idx, result) in section.results.iter().enumerate() {
                 let item_rect = RECT {
                     left: column_x + 8,
                     top: y,
-                    right: column_x + COLUMN_WIDTH - 8,
+                    right: column_x + column_width - 8,
                     bottom: y + ITEM_HEIGHT,
                 };
 
                 // Only draw if visible in clip region
                 if item_rect.bottom >= column_clip.top && item_rect.top <= column_clip.bottom {
                     // Calculate global index for hover/selection
-                    let global_idx = state
-                        .flat_results
-                        .iter()
-                        .position(|r| {
-                            if let FlatResult::Item(r) = r {
-                                r.path == result.path && r.result_type == *result_type
-                            } else {
-                                false
-                            }
-                        })
-                        .unwrap_or(0);
+                    let global_idx = state.grouped_results.flat_index(column, idx).unwrap_or(0);
 
                     // Hover highlight
                     let is_hovered = state.hovered_index == Some(global_idx)
@@ -1525,6 +1812,54 @@ unsafe fn paint_window(hwnd: HWND, state: &WindowState) {
                                 DT_LEFT | DT_SINGLELINE | DT_VCENTER,
                             );
                         }
+                        ResultType::Answer => {
+                            let icon_color = colors.icon_answer;
+                            SelectObject(hdc, state.font_main);
+                            SetTextColor(hdc, COLORREF(icon_color & 0x00FFFFFF));
+                            let mut icon_item_rect_mut = icon_item_rect;
+                            DrawTextW(
+                                hdc,
+                                &mut to_wide_chars("🧮"),
+                                &mut icon_item_rect_mut,
+                                DT_LEFT | DT_SINGLELINE | DT_VCENTER,
+                            );
+                        }
+                        ResultType::Web => {
+                            let icon_color = colors.icon_web;
+                            SelectObject(hdc, state.font_main);
+                            SetTextColor(hdc, COLORREF(icon_color & 0x00FFFFFF));
+                            let mut icon_item_rect_mut = icon_item_rect;
+                            DrawTextW(
+                                hdc,
+                                &mut to_wide_chars("🌐"),
+                                &mut icon_item_rect_mut,
+                                DT_LEFT | DT_SINGLELINE | DT_VCENTER,
+                            );
+                        }
+                        ResultType::Command => {
+                            let icon_color = colors.icon_command;
+                            SelectObject(hdc, state.font_main);
+                            SetTextColor(hdc, COLORREF(icon_color & 0x00FFFFFF));
+                            let mut icon_item_rect_mut = icon_item_rect;
+                            DrawTextW(
+                                hdc,
+                                &mut to_wide_chars("❯"),
+                                &mut icon_item_rect_mut,
+                                DT_LEFT | DT_SINGLELINE | DT_VCENTER,
+                            );
+                        }
+                        ResultType::Text => {
+                            let icon_color = colors.icon_text;
+                            SelectObject(hdc, state.font_main);
+                            SetTextColor(hdc, COLORREF(icon_color & 0x00FFFFFF));
+                            let mut icon_item_rect_mut = icon_item_rect;
+                            DrawTextW(
+                                hdc,
+                                &mut to_wide_chars("📋"),
+                                &mut icon_item_rect_mut,
+                                DT_LEFT | DT_SINGLELINE | DT_VCENTER,
+                            );
+                        }
                     }
 
                     // Name (with reduced gap from icon)
@@ -1575,7 +1910,7 @@ unsafe fn paint_window(hwnd: HWND, state: &WindowState) {
             let _ = DeleteObject(clip_region);
         }
     } else if !state.query.is_empty() {
-        // No results message - Centered and clear
+        // No results message (or why the query is invalid) - Centered and clear
         SelectObject(hdc, state.font_secondary);
         SetTextColor(hdc, COLORREF(colors.text_muted & 0x00FFFFFF));
 
@@ -1589,11 +1924,16 @@ unsafe fn paint_window(hwnd: HWND, state: &WindowState) {
 
         // Draw formatted no results message centered
         use windows::Win32::Graphics::Gdi::DT_CENTER;
+        let message = match &state.query_error {
+            Some(error) => error.as_str(),
+            None if state.searching => "Searching…",
+            None => "No results found",
+        };
         DrawTextW(
             hdc,
-            &mut to_wide_chars("No results found"),
+            &mut to_wide_chars(message),
             &mut no_results_rect_mut,
-            DT_CENTER | DT_SINGLELINE | DT_VCENTER,
+            DT_CENTER | DT_SINGLELINE | DT_VCENTER | DT_END_ELLIPSIS,
         );
     }
 
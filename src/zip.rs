@@ -0,0 +1,215 @@
+//! Minimal ZIP archive reader for Rustle
+//!
+//! Office Open XML documents (.docx, .xlsx, .pptx) are zip packages of XML
+//! parts. This reader understands just enough of the format to list the
+//! entries of an archive held in memory and read stored or deflated ones;
+//! ZIP64, encryption and multi-disk archives are rejected.
+
+#![allow(dead_code)]
+
+use crate::error::{Result, RustleError};
+use crate::inflate;
+
+/// Signature of the end of central directory record
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+
+/// Signature of a central directory file header
+const CENTRAL_FILE_HEADER: u32 = 0x0201_4b50;
+
+/// Signature of a local file header
+const LOCAL_FILE_HEADER: u32 = 0x0403_4b50;
+
+/// Size of the end of central directory record without its comment
+const END_RECORD_LEN: usize = 22;
+
+/// Longest archive comment, which bounds the search for the end record
+const MAX_COMMENT_LEN: usize = u16::MAX as usize;
+
+/// Size of a central directory file header without its variable fields
+const CENTRAL_HEADER_LEN: usize = 46;
+
+/// Size of a local file header without its variable fields
+const LOCAL_HEADER_LEN: usize = 30;
+
+/// Compression method of entries stored as is
+const METHOD_STORED: u16 = 0;
+
+/// Compression method of deflated entries
+const METHOD_DEFLATED: u16 = 8;
+
+/// Flag bit marking an encrypted entry
+const FLAG_ENCRYPTED: u16 = 0x0001;
+
+/// One file inside an archive
+#[derive(Debug, Clone)]
+struct ZipEntry {
+    name: String,
+    method: u16,
+    flags: u16,
+    compressed_size: usize,
+    uncompressed_size: usize,
+    local_header_offset: usize,
+}
+
+/// A zip archive held in memory
+pub struct ZipArchive<'a> {
+    data: &'a [u8],
+    entries: Vec<ZipEntry>,
+}
+
+impl<'a> ZipArchive<'a> {
+    /// Reads the central directory of an archive
+    pub fn new(data: &'a [u8]) -> Result<Self> {
+        let end = find_end_record(data)?;
+        let entry_count = read_u16(data, end + 10)? as usize;
+        let directory_len = read_u32(data, end + 12)? as usize;
+        let directory_offset = read_u32(data, end + 16)? as usize;
+        if directory_offset == u32::MAX as usize || entry_count == u16::MAX as usize {
+            return Err(corrupt("ZIP64 archives are not supported"));
+        }
+        if directory_offset.saturating_add(directory_len) > end {
+            return Err(corrupt("central directory runs past its end"));
+        }
+
+        let mut entries = Vec::with_capacity(entry_count);
+        let mut pos = directory_offset;
+        for _ in 0..entry_count {
+            if read_u32(data, pos)? != CENTRAL_FILE_HEADER {
+                return Err(corrupt("bad central directory header"));
+            }
+            let name_len = read_u16(data, pos + 28)? as usize;
+            let extra_len = read_u16(data, pos + 30)? as usize;
+            let comment_len = read_u16(data, pos + 32)? as usize;
+            let name_start = pos + CENTRAL_HEADER_LEN;
+            let name = data
+                .get(name_start..name_start + name_len)
+                .ok_or_else(|| corrupt("entry name runs past the end"))?;
+
+            entries.push(ZipEntry {
+                name: String::from_utf8_lossy(name).replace('\\', "/"),
+                flags: read_u16(data, pos + 8)?,
+                method: read_u16(data, pos + 10)?,
+                compressed_size: read_u32(data, pos + 20)? as usize,
+                uncompressed_size: read_u32(data, pos + 24)? as usize,
+                local_header_offset: read_u32(data, pos + 42)? as usize,
+            });
+            pos = name_start + name_len + extra_len + comment_len;
+        }
+
+        Ok(Self { data, entries })
+    }
+
+    /// Returns the names of all entries, in directory order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|e| e.name.as_str())
+    }
+
+    /// Returns true if the archive has an entry called `name`
+    pub fn contains(&self, name: &str) -> bool {
+        self.entry(name).is_some()
+    }
+
+    /// Reads and decompresses the entry called `name`
+    ///
+    /// Names are matched case-insensitively, as Office does. Fails if the
+    /// entry is missing, encrypted, uses an unsupported compression method
+    /// or is larger than `max_size` once decompressed.
+    pub fn read(&self, name: &str, max_size: usize) -> Result<Vec<u8>> {
+        let entry = self
+            .entry(name)
+            .ok_or_else(|| RustleError::extract_error(format!("missing zip entry {}", name)))?;
+        if entry.flags & FLAG_ENCRYPTED != 0 {
+            return Err(corrupt("encrypted entries are not supported"));
+        }
+        if entry.uncompressed_size > max_size {
+            return Err(RustleError::extract_error(format!(
+                "zip entry {} is larger than {} bytes",
+                name, max_size
+            )));
+        }
+
+        let pos = entry.local_header_offset;
+        if read_u32(self.data, pos)? != LOCAL_FILE_HEADER {
+            return Err(corrupt("bad local file header"));
+        }
+        // The local header repeats the name and may carry a different extra field
+        let name_len = read_u16(self.data, pos + 26)? as usize;
+        let extra_len = read_u16(self.data, pos + 28)? as usize;
+        let start = pos + LOCAL_HEADER_LEN + name_len + extra_len;
+        let compressed = self
+            .data
+            .get(start..start.saturating_add(entry.compressed_size))
+            .ok_or_else(|| corrupt("entry data runs past the end"))?;
+
+        match entry.method {
+            METHOD_STORED => Ok(compressed.to_vec()),
+            METHOD_DEFLATED => inflate::inflate(compressed, max_size),
+            method => Err(RustleError::extract_error(format!(
+                "unsupported zip compression method {}",
+                method
+            ))),
+        }
+    }
+
+    fn entry(&self, name: &str) -> Option<&ZipEntry> {
+        self.entries
+            .iter()
+            .find(|e| e.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Finds the end of central directory record, which sits before the comment
+fn find_end_record(data: &[u8]) -> Result<usize> {
+    if data.len() < END_RECORD_LEN {
+        return Err(corrupt("file is too small"));
+    }
+    let last = data.len() - END_RECORD_LEN;
+    let first = last.saturating_sub(MAX_COMMENT_LEN);
+    (first..=last)
+        .rev()
+        .find(|&pos| read_u32(data, pos).ok() == Some(END_OF_CENTRAL_DIRECTORY))
+        .ok_or_else(|| corrupt("end of central directory not found"))
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Result<u16> {
+    data.get(pos..pos + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| corrupt("unexpected end of archive"))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Result<u32> {
+    data.get(pos..pos + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| corrupt("unexpected end of archive"))
+}
+
+fn corrupt(msg: &str) -> RustleError {
+    RustleError::extract_error(format!("invalid zip archive: {}", msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_stored_and_deflated_entries() {
+        let data = include_bytes!("../tests/fixtures/extract/sample.zip");
+        let archive = ZipArchive::new(data).unwrap();
+
+        let names: Vec<&str> = archive.names().collect();
+        assert_eq!(names, ["stored.txt", "dir/deflated.txt"]);
+        assert_eq!(archive.read("stored.txt", 1024).unwrap(), b"kept as is");
+        assert_eq!(
+            archive.read("DIR/Deflated.txt", 1024).unwrap(),
+            "squeezed ".repeat(50).as_bytes()
+        );
+        assert!(archive.read("dir/deflated.txt", 16).is_err());
+        assert!(archive.read("missing.txt", 1024).is_err());
+    }
+
+    #[test]
+    fn test_rejects_non_archives() {
+        assert!(ZipArchive::new(b"plain text, not a zip archive").is_err());
+        assert!(ZipArchive::new(b"").is_err());
+    }
+}